@@ -1,48 +1,48 @@
+#[cfg(feature = "signer")]
 use std::{env, path::PathBuf};
 
+// without the signer there are no bindings to generate
+#[cfg(not(feature = "signer"))]
+fn main() {}
+
 // We will build the `lighter-signer` bindings here
 // instead of mapping them manually, since we have the header files from v0.1.3
+#[cfg(feature = "signer")]
 fn main() {
     let dir = env::var("CARGO_MANIFEST_DIR").unwrap();
 
-    // header file
+    // header file and lib, which is loaded at runtime so that a missing one only fails the
+    // signing calls
     #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
-    let header = format!("{dir}/libs/linux/amd64/liblighter-signer.h");
+    let (header, lib) = (
+        format!("{dir}/libs/linux/amd64/liblighter-signer.h"),
+        format!("{dir}/libs/linux/amd64/liblighter-signer.so"),
+    );
     #[cfg(all(target_os = "linux", target_arch = "aarch64"))]
-    let header = format!("{dir}/libs/linux/arm64/liblighter-signer.h");
+    let (header, lib) = (
+        format!("{dir}/libs/linux/arm64/liblighter-signer.h"),
+        format!("{dir}/libs/linux/arm64/liblighter-signer.so"),
+    );
     #[cfg(all(target_os = "macos", target_arch = "aarch64"))]
-    let header = format!("{dir}/libs/darwin/arm64/liblighter-signer.h");
+    let (header, lib) = (
+        format!("{dir}/libs/darwin/arm64/liblighter-signer.h"),
+        format!("{dir}/libs/darwin/arm64/liblighter-signer.dylib"),
+    );
     #[cfg(all(target_os = "windows", target_arch = "x86_64"))]
-    let header = format!("{dir}/libs/windows/amd64/liblighter-signer.h");
+    let (header, lib) = (
+        format!("{dir}/libs/windows/amd64/liblighter-signer.h"),
+        format!("{dir}/libs/windows/amd64/liblighter-signer.dll"),
+    );
 
-    // tell the linker where to look for for the lib
-    #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
-    {
-        println!("cargo:rustc-link-search=native={dir}/libs/linux/amd64");
-        println!("cargo:rustc-link-lib=dylib=lighter-signer");
-        println!("cargo:rustc-link-arg=-Wl,-rpath,{dir}/libs/linux/amd64");
-    }
-    #[cfg(all(target_os = "linux", target_arch = "aarch64"))]
-    {
-        println!("cargo:rustc-link-search=native={dir}/libs/linux/arm64");
-        println!("cargo:rustc-link-lib=dylib=lighter-signer");
-        println!("cargo:rustc-link-arg=-Wl,-rpath,{dir}/libs/linux/arm64");
-    }
-    #[cfg(all(target_os = "macos", target_arch = "aarch64"))]
-    {
-        println!("cargo:rustc-link-search=native={dir}/libs/darwin/arm64");
-        println!("cargo:rustc-link-lib=dylib=lighter-signer");
-    }
-    #[cfg(all(target_os = "windows", target_arch = "x86_64"))]
-    {
-        println!("cargo:rustc-link-search=native={dir}/libs/windows/amd64");
-        println!("cargo:rustc-link-lib=dylib=lighter-signer");
-    }
+    // default path of the lib, see `signer::ffi::library`
+    println!("cargo:rustc-env=LIGHTER_SIGNER_DEFAULT_LIB={lib}");
 
     let out_path = PathBuf::from(env::var("OUT_DIR").unwrap());
     bindgen::Builder::default()
         .header(header)
         .generate_comments(true)
+        .dynamic_library_name("LighterSigner")
+        .dynamic_link_require_all(true)
         .parse_callbacks(Box::new(bindgen::CargoCallbacks::new()))
         .generate()
         .expect("Unable to generate bindings")
@@ -2,19 +2,310 @@
 use crate::{
     apis::{self, configuration::Configuration},
     config::LighterConfig,
-    error::Result,
+    error::{LighterError, LighterErrorCode, Result},
     models::{
-        AccountApiKeys, AccountLimits, AccountMetadatas, AccountPnL, DetailedAccounts, L1Metadata,
-        LiquidationInfos, PositionFundings, RespChangeAccountTier,
-        RespPublicPoolsMetadata, SubAccounts,
+        AccountApiKeys, AccountLimits, AccountMetadatas, AccountPnL, AccountPosition, ApiKey,
+        DetailedAccount, DetailedAccounts, L1Metadata, LiquidationInfos, PnLEntry,
+        PositionFundings, PublicPoolMetadata, RespChangeAccountTier, RespPublicPoolsMetadata,
+        SubAccounts,
+    },
+    request::RequestOptions,
+    AccountIndex, MarketIndex,
+};
+
+#[cfg(feature = "signer")]
+use std::{collections::HashSet, sync::Arc, time::Duration};
+
+#[cfg(feature = "signer")]
+use crate::{
+    api::info,
+    client::{MetadataCache, TxClient},
+    models::RespSendTx,
+    signer::{
+        data::{
+            SignBurnSharesData, SignCreatePublicPoolData, SignMintSharesData,
+            SignUpdateLeverageData, SignUpdateMarginData, SignUpdatePublicPoolData,
+        },
+        FFISigner,
     },
-    signer::FFISigner,
 };
 
 #[derive(Debug)]
 pub struct AccountApi {
     config: apis::configuration::Configuration,
-    signer: FFISigner,
+    #[cfg(feature = "signer")]
+    signer: Option<FFISigner>, // creates the auth tokens
+    #[cfg(feature = "signer")]
+    tx_client: Option<Arc<TxClient>>, // needed only for the operations that require signing
+    #[cfg(feature = "signer")]
+    metadata: Arc<MetadataCache>, // checks the leverage
+}
+
+/// Outcome of [`AccountApi::create_subaccount`]
+#[cfg(feature = "signer")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubAccountCreated {
+    pub tx_hash: String,
+    /// Index of the new sub account, `None` if it wasn't listed yet after the retries (the tx
+    /// may still be executed later)
+    pub account_index: Option<i64>,
+}
+
+/// Side of an open position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PositionSide {
+    Long,
+    Short,
+}
+
+/// Position of an account on a market, with the decimals of the [`AccountPosition`] parsed
+#[derive(Debug, Clone, PartialEq)]
+pub struct Position {
+    pub market_index: i32,
+    pub symbol: String,
+    /// `None` when there is no open position (zero size)
+    pub side: Option<PositionSide>,
+    /// Size of the position, always non-negative, see [`Position::net_size`]
+    pub size: f64,
+    pub avg_entry_price: f64,
+    pub position_value: f64,
+    pub unrealized_pnl: f64,
+    pub realized_pnl: f64,
+    pub liquidation_price: f64,
+    pub initial_margin_fraction: f64,
+    pub margin_mode: MarginMode,
+    pub allocated_margin: f64,
+    pub total_funding_paid_out: Option<f64>,
+    pub open_order_count: i64,
+}
+
+impl Position {
+    /// Size of the position, negative when short
+    pub fn net_size(&self) -> f64 {
+        match self.side {
+            Some(PositionSide::Short) => -self.size,
+            _ => self.size,
+        }
+    }
+}
+
+impl TryFrom<&AccountPosition> for Position {
+    type Error = LighterError;
+
+    fn try_from(position: &AccountPosition) -> Result<Self> {
+        let parse = |value: &str| {
+            value
+                .parse::<f64>()
+                .map_err(|_| LighterError::Generic(format!("Invalid position {position:?}")))
+        };
+
+        let size = parse(&position.position)?;
+        let side = match position.sign {
+            _ if size == 0.0 => None,
+            1 => Some(PositionSide::Long),
+            -1 => Some(PositionSide::Short),
+            sign => {
+                return Err(LighterError::Generic(format!(
+                    "Invalid position sign {sign}, expected 1 or -1"
+                )))
+            }
+        };
+
+        Ok(Self {
+            market_index: position.market_id,
+            symbol: position.symbol.clone(),
+            side,
+            size: size.abs(),
+            avg_entry_price: parse(&position.avg_entry_price)?,
+            position_value: parse(&position.position_value)?,
+            unrealized_pnl: parse(&position.unrealized_pnl)?,
+            realized_pnl: parse(&position.realized_pnl)?,
+            liquidation_price: parse(&position.liquidation_price)?,
+            initial_margin_fraction: parse(&position.initial_margin_fraction)?,
+            margin_mode: MarginMode::try_from(position.margin_mode)?,
+            allocated_margin: parse(&position.allocated_margin)?,
+            total_funding_paid_out: position
+                .total_funding_paid_out
+                .as_deref()
+                .map(parse)
+                .transpose()?,
+            open_order_count: position.open_order_count,
+        })
+    }
+}
+
+impl DetailedAccount {
+    /// Returns the position of the account on the market, `None` if it never had one there
+    pub fn position(&self, market_index: impl Into<MarketIndex>) -> Result<Option<Position>> {
+        let market_index = market_index.into().0;
+        self.positions
+            .iter()
+            .find(|v| v.market_id == market_index)
+            .map(Position::try_from)
+            .transpose()
+    }
+
+    /// Returns the positions of the account on all the markets
+    pub fn typed_positions(&self) -> Result<Vec<Position>> {
+        self.positions.iter().map(Position::try_from).collect()
+    }
+
+    /// Returns the number of open orders of the account on the market
+    pub fn open_order_count(&self, market_index: impl Into<MarketIndex>) -> i64 {
+        let market_index = market_index.into().0;
+        self.positions
+            .iter()
+            .find(|v| v.market_id == market_index)
+            .map_or(0, |v| v.open_order_count)
+    }
+}
+
+/// Current state of a public pool, see [`AccountApi::public_pool`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PublicPoolState {
+    pub pool_index: i64,
+    pub name: String,
+    pub status: PoolStatus,
+    pub operator_fee: f64,
+    pub total_shares: i64,
+    pub total_asset_value: f64,
+}
+
+impl TryFrom<&PublicPoolMetadata> for PublicPoolState {
+    type Error = LighterError;
+
+    fn try_from(pool: &PublicPoolMetadata) -> Result<Self> {
+        let parse = |value: &str| {
+            value
+                .parse()
+                .map_err(|_| LighterError::Generic(format!("Invalid public pool {pool:?}")))
+        };
+        Ok(Self {
+            pool_index: pool.account_index,
+            name: pool.name.clone(),
+            status: PoolStatus::try_from(pool.status)?,
+            operator_fee: parse(&pool.operator_fee)?,
+            total_shares: pool.total_shares,
+            total_asset_value: parse(&pool.total_asset_value)?,
+        })
+    }
+}
+
+/// `api_key_index` of [`AccountApi::apikeys`] listing all the API keys of the account
+pub const ALL_API_KEYS: i32 = 255;
+
+/// API key registered for an account, see [`AccountApi::all_api_keys`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiKeyInfo {
+    pub account_index: i64,
+    pub api_key_index: i32,
+    pub public_key: String,
+    /// Next nonce of the txs signed with the key
+    pub nonce: i64,
+}
+
+impl From<ApiKey> for ApiKeyInfo {
+    fn from(val: ApiKey) -> Self {
+        Self {
+            account_index: val.account_index,
+            api_key_index: val.api_key_index,
+            public_key: val.public_key,
+            nonce: val.nonce,
+        }
+    }
+}
+
+#[cfg(feature = "signer")]
+fn check_share_amount(share_amount: i64) -> Result<()> {
+    if share_amount <= 0 {
+        return Err(LighterError::Generic(format!(
+            "Invalid share amount {share_amount}, expected greater than 0"
+        )));
+    }
+    Ok(())
+}
+
+/// Margin mode of a position, see [`AccountApi::set_leverage`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarginMode {
+    Cross = 0,
+    Isolated = 1,
+}
+
+impl From<MarginMode> for i32 {
+    fn from(val: MarginMode) -> Self {
+        val as i32
+    }
+}
+
+impl TryFrom<i32> for MarginMode {
+    type Error = LighterError;
+
+    fn try_from(value: i32) -> Result<Self> {
+        match value {
+            0 => Ok(Self::Cross),
+            1 => Ok(Self::Isolated),
+            _ => Err(LighterError::Generic(format!(
+                "Invalid margin mode {value}, expected 0 (cross) or 1 (isolated)"
+            ))),
+        }
+    }
+}
+
+/// Whether [`AccountApi::update_margin`] adds collateral to an isolated position or removes it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarginDirection {
+    Remove = 0,
+    Add = 1,
+}
+
+impl From<MarginDirection> for i32 {
+    fn from(val: MarginDirection) -> Self {
+        val as i32
+    }
+}
+
+impl TryFrom<i32> for MarginDirection {
+    type Error = LighterError;
+
+    fn try_from(value: i32) -> Result<Self> {
+        match value {
+            0 => Ok(Self::Remove),
+            1 => Ok(Self::Add),
+            _ => Err(LighterError::Generic(format!(
+                "Invalid margin direction {value}, expected 0 (remove) or 1 (add)"
+            ))),
+        }
+    }
+}
+
+/// Status of a public pool, see [`AccountApi::update_public_pool`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PoolStatus {
+    /// Open to the deposits, shares can be minted
+    Active = 0,
+    /// Closed by the operator: no shares can be minted anymore, they can still be burned
+    Frozen = 1,
+}
+
+impl From<PoolStatus> for i32 {
+    fn from(val: PoolStatus) -> Self {
+        val as i32
+    }
+}
+
+impl TryFrom<i32> for PoolStatus {
+    type Error = LighterError;
+
+    fn try_from(value: i32) -> Result<Self> {
+        match value {
+            0 => Ok(Self::Active),
+            1 => Ok(Self::Frozen),
+            _ => Err(LighterError::Generic(format!(
+                "Invalid public pool status {value}, expected 0 (active) or 1 (frozen)"
+            ))),
+        }
+    }
 }
 
 #[derive(Debug, strum::Display)]
@@ -44,7 +335,38 @@ pub enum AccountTier {
     Premium,
 }
 
-#[derive(Debug, strum::Display)]
+/// Outcome of [`AccountApi::change_account_tier`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccountTierChange {
+    /// The account is now of the requested tier
+    Changed,
+    /// The switch is not allowed (e.g. while positions are open, or too soon after the last
+    /// one), `code` being the one of the API
+    Rejected { code: i32, message: String },
+}
+
+impl AccountTierChange {
+    pub const CHANGED: i32 = 200;
+
+    pub fn is_changed(&self) -> bool {
+        matches!(self, Self::Changed)
+    }
+}
+
+impl From<RespChangeAccountTier> for AccountTierChange {
+    fn from(resp: RespChangeAccountTier) -> Self {
+        match resp.code {
+            Self::CHANGED => Self::Changed,
+            code => Self::Rejected {
+                code,
+                message: resp.message.unwrap_or_default(),
+            },
+        }
+    }
+}
+
+/// Resolution of the PnL chart, the API serves every one of them but `ThirtyMinutes`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::Display)]
 pub enum PnlResolution {
     #[strum(to_string = "1m")]
     OneMinute,
@@ -52,7 +374,7 @@ pub enum PnlResolution {
     FiveMinutes,
     #[strum(to_string = "15m")]
     FifteenMinutes,
-    #[strum(to_string = "30")]
+    #[strum(to_string = "30m")]
     ThirtyMinutes,
     #[strum(to_string = "1h")]
     OneHour,
@@ -62,6 +384,142 @@ pub enum PnlResolution {
     OneDay,
 }
 
+/// Highest start and end timestamp of [`AccountApi::pnl`], in milliseconds
+pub const MAX_PNL_TIMESTAMP: i64 = 5_000_000_000_000;
+
+fn check_pnl_range(
+    resolution: PnlResolution,
+    start_timestamp: i64,
+    end_timestamp: i64,
+) -> Result<()> {
+    if resolution == PnlResolution::ThirtyMinutes {
+        return Err(LighterError::Generic(format!(
+            "Invalid PnL resolution {resolution}, must be one of 1m, 5m, 15m, 1h, 4h or 1d"
+        )));
+    }
+    for timestamp in [start_timestamp, end_timestamp] {
+        if !(0..=MAX_PNL_TIMESTAMP).contains(&timestamp) {
+            return Err(LighterError::Generic(format!(
+                "Invalid PnL timestamp {timestamp}, must be between 0 and {MAX_PNL_TIMESTAMP}"
+            )));
+        }
+    }
+    if start_timestamp > end_timestamp {
+        return Err(LighterError::Generic(format!(
+            "Invalid PnL range, the start {start_timestamp} must not be after the end {end_timestamp}"
+        )));
+    }
+    Ok(())
+}
+
+/// A point of a [`PnlSeries`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PnlPoint {
+    pub timestamp: i64,
+    /// PnL of the trades and of the public pool shares
+    pub value: f64,
+    /// Deposits and transfers in, minus the withdrawals and transfers out, of the account and
+    /// of its public pool shares
+    pub net_inflow: f64,
+}
+
+impl From<&PnLEntry> for PnlPoint {
+    fn from(entry: &PnLEntry) -> Self {
+        Self {
+            timestamp: entry.timestamp,
+            value: entry.trade_pnl + entry.pool_pnl,
+            net_inflow: entry.inflow - entry.outflow + entry.pool_inflow - entry.pool_outflow,
+        }
+    }
+}
+
+/// PnL chart of an account, as points ordered by timestamp, see [`AccountApi::pnl_series`]
+///
+/// When requested with `ignore_transfers`, the deposits, withdrawals and transfers are left out
+/// of the chart, so the values only move with the trading and the public pools and
+/// [`PnlSeries::total_return`] is the return of the strategy. Otherwise a deposit shows up in
+/// the values as much as a gain does.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PnlSeries {
+    pub resolution: PnlResolution,
+    pub transfers_ignored: bool,
+    points: Vec<PnlPoint>,
+}
+
+impl PnlSeries {
+    /// Orders the entries of `pnl` by timestamp, `transfers_ignored` being the `ignore_transfers`
+    /// the chart was requested with
+    pub fn new(pnl: &AccountPnL, resolution: PnlResolution, transfers_ignored: bool) -> Self {
+        let mut points = pnl.pnl.iter().map(PnlPoint::from).collect::<Vec<_>>();
+        points.sort_by_key(|point| point.timestamp);
+        Self {
+            resolution,
+            transfers_ignored,
+            points,
+        }
+    }
+
+    pub fn points(&self) -> &[PnlPoint] {
+        &self.points
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, PnlPoint> {
+        self.points.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    pub fn first(&self) -> Option<&PnlPoint> {
+        self.points.first()
+    }
+
+    pub fn last(&self) -> Option<&PnlPoint> {
+        self.points.last()
+    }
+
+    /// Change of the value between the first and the last point, `None` when the series is empty
+    pub fn total_return(&self) -> Option<f64> {
+        Some(self.last()?.value - self.first()?.value)
+    }
+
+    /// Largest fall of the value from a previous peak, as a non-negative amount, `None` when the
+    /// series is empty
+    pub fn max_drawdown(&self) -> Option<f64> {
+        let first = self.first()?.value;
+        let (_, drawdown) = self
+            .iter()
+            .fold((first, 0.0_f64), |(peak, drawdown), point| {
+                let peak = peak.max(point.value);
+                (peak, drawdown.max(peak - point.value))
+            });
+        Some(drawdown)
+    }
+}
+
+impl IntoIterator for PnlSeries {
+    type Item = PnlPoint;
+    type IntoIter = std::vec::IntoIter<PnlPoint>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.points.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a PnlSeries {
+    type Item = &'a PnlPoint;
+    type IntoIter = std::slice::Iter<'a, PnlPoint>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.points.iter()
+    }
+}
+
 #[derive(Debug, strum::Display)]
 #[strum(serialize_all = "snake_case")]
 pub enum PositionFundingSide {
@@ -81,14 +539,344 @@ pub enum PublicPoolsMetadataFilter {
 
 impl AccountApi {
     pub fn new(config: &LighterConfig) -> Result<Self> {
-        let signer = FFISigner::try_from(config)?;
+        Self::new_with_configuration(config, Configuration::try_from(config)?)
+    }
+
+    /// Same as [`AccountApi::new`], sending the requests with `configuration` (e.g. shared with the
+    /// other APIs) instead of building one
+    #[cfg_attr(not(feature = "signer"), allow(unused_variables))] // `config` creates the signer
+    pub fn new_with_configuration(
+        config: &LighterConfig,
+        configuration: Configuration,
+    ) -> Result<Self> {
         Ok(Self {
-            config: Configuration::try_from(config)?,
-            signer,
+            #[cfg(feature = "signer")]
+            metadata: Arc::new(MetadataCache::with_configuration(configuration.clone())),
+            config: configuration,
+            #[cfg(feature = "signer")]
+            signer: match config.api_key_private {
+                Some(_) => Some(FFISigner::try_from(config)?),
+                None => None,
+            },
+            #[cfg(feature = "signer")]
+            tx_client: None,
+        })
+    }
+
+    /// Sends the requests with `config` instead of the one built from the `LighterConfig`, the
+    /// markets are loaded with it too
+    pub fn with_configuration(mut self, config: Configuration) -> Self {
+        #[cfg(feature = "signer")]
+        {
+            self.metadata = Arc::new(MetadataCache::with_configuration(config.clone()));
+        }
+        self.config = config;
+        self
+    }
+
+    #[cfg(all(test, feature = "signer"))]
+    pub(crate) fn signer(&self) -> Option<&FFISigner> {
+        self.signer.as_ref()
+    }
+
+    /// Creates the auth tokens with `signer`, sharing its cached token with the other APIs
+    #[cfg(feature = "signer")]
+    pub(crate) fn with_signer(mut self, signer: Option<FFISigner>) -> Self {
+        self.signer = signer;
+        self
+    }
+
+    #[cfg(feature = "signer")]
+    pub(crate) fn with_tx_client(mut self, tx_client: Option<Arc<TxClient>>) -> Self {
+        self.tx_client = tx_client;
+        self
+    }
+
+    /// Shares the metadata cache, e.g. with the other APIs of the client
+    #[cfg(feature = "signer")]
+    pub(crate) fn with_metadata(mut self, metadata: Arc<MetadataCache>) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    #[cfg(feature = "signer")]
+    fn tx_client(&self) -> Result<&TxClient> {
+        self.tx_client
+            .as_deref()
+            .ok_or_else(|| LighterError::Config("`api_key_private` is not set".into()))
+    }
+
+    /// Sign and submit the leverage of the market (e.g. `10.0` for 10x), converted to the
+    /// `initial_margin_fraction` of the tx. It's rejected before signing if below 1x or above the
+    /// max leverage of the market, cached with the markets (see
+    /// [`MetadataCache::market_info`] and [`info::MarketInfo::initial_margin_fraction`]).
+    #[cfg(feature = "signer")]
+    #[tracing::instrument(level = "debug", skip_all, fields(market_index = tracing::field::Empty))]
+    pub async fn set_leverage(
+        &self,
+        market_index: impl Into<MarketIndex>,
+        leverage: f64,
+        margin_mode: MarginMode,
+    ) -> Result<RespSendTx> {
+        let market_index = market_index.into().0;
+        tracing::Span::current().record("market_index", market_index);
+        // checked before fetching the market
+        info::leverage_to_initial_margin_fraction(leverage)?;
+        let tx_client = self.tx_client()?;
+
+        let market = self.metadata.market_info(market_index).await?;
+        let initial_margin_fraction = market.initial_margin_fraction(leverage)?;
+
+        tx_client
+            .submit(|signer, nonce| {
+                signer.sign_update_leverage(
+                    SignUpdateLeverageData::new(market_index, initial_margin_fraction, margin_mode),
+                    nonce,
+                )
+            })
+            .await
+            .inspect_err(|e| tracing::error!("unable to call `set_leverage`: {e}"))
+    }
+
+    /// Sign and submit a transfer of `usdc_amount` (with 6 decimals) between the collateral and
+    /// the isolated position of the market
+    #[cfg(feature = "signer")]
+    #[tracing::instrument(level = "debug", skip_all, fields(market_index = tracing::field::Empty))]
+    pub async fn update_margin(
+        &self,
+        market_index: impl Into<MarketIndex>,
+        usdc_amount: i64,
+        direction: MarginDirection,
+    ) -> Result<RespSendTx> {
+        let market_index = market_index.into().0;
+        tracing::Span::current().record("market_index", market_index);
+        if usdc_amount <= 0 {
+            return Err(LighterError::Generic(format!(
+                "Invalid USDC amount {usdc_amount}, expected greater than 0"
+            )));
+        }
+
+        self.tx_client()?
+            .submit(|signer, nonce| {
+                signer.sign_update_margin(
+                    SignUpdateMarginData::new(market_index, usdc_amount, direction),
+                    nonce,
+                )
+            })
+            .await
+            .inspect_err(|e| tracing::error!("unable to call `update_margin`: {e}"))
+    }
+
+    /// Sign and submit the creation of a sub account of the account of the config, then look
+    /// for it in the accounts of its L1 address. As the new account is only listed once the tx is
+    /// executed, the listing is polled a few times with a backoff.
+    #[cfg(feature = "signer")]
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub async fn create_subaccount(&self) -> Result<SubAccountCreated> {
+        const ATTEMPTS: u32 = 4;
+        const MIN_INTERVAL: Duration = Duration::from_millis(250);
+
+        let tx_client = self.tx_client()?;
+        let account_index = tx_client.account_index();
+        let l1_address = self
+            .account_by_index(account_index)
+            .await?
+            .ok_or_else(|| LighterError::AccountNotFound(account_index.to_string()))?
+            .l1_address;
+        let known = self
+            .accounts_by_l1_address(&l1_address)
+            .await?
+            .sub_accounts
+            .into_iter()
+            .map(|v| v.index)
+            .collect::<HashSet<_>>();
+
+        let resp = tx_client
+            .submit(|signer, nonce| signer.sign_create_subaccount(nonce))
+            .await
+            .inspect_err(|e| tracing::error!("unable to call `create_subaccount`: {e}"))?;
+
+        let mut interval = MIN_INTERVAL;
+        for _ in 0..ATTEMPTS {
+            tokio::time::sleep(interval).await;
+            interval *= 2;
+
+            let accounts = self.accounts_by_l1_address(&l1_address).await?;
+            let created = accounts
+                .sub_accounts
+                .iter()
+                .map(|v| v.index)
+                .filter(|v| !known.contains(v))
+                .max();
+            if created.is_some() {
+                return Ok(SubAccountCreated {
+                    tx_hash: resp.tx_hash,
+                    account_index: created,
+                });
+            }
+        }
+
+        tracing::warn!("sub account created by {} not listed yet", resp.tx_hash);
+        Ok(SubAccountCreated {
+            tx_hash: resp.tx_hash,
+            account_index: None,
         })
     }
 
+    /// Sign and submit the creation of a public pool operated by the account of the config, with
+    /// `initial_total_shares` minted to the operator
+    #[cfg(feature = "signer")]
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub async fn create_public_pool(
+        &self,
+        operator_fee: i64,
+        initial_total_shares: i64,
+        min_operator_share_rate: i64,
+    ) -> Result<RespSendTx> {
+        check_share_amount(initial_total_shares)?;
+        if operator_fee < 0 || min_operator_share_rate < 0 {
+            return Err(LighterError::Generic(format!(
+                "Invalid operator fee {operator_fee} or min operator share rate {min_operator_share_rate}, expected not negative"
+            )));
+        }
+
+        self.tx_client()?
+            .submit(|signer, nonce| {
+                signer.sign_create_public_pool(
+                    SignCreatePublicPoolData {
+                        operator_fee,
+                        initial_total_shares,
+                        min_operator_share_rate,
+                    },
+                    nonce,
+                )
+            })
+            .await
+            .inspect_err(|e| tracing::error!("unable to call `create_public_pool`: {e}"))
+    }
+
+    /// Sign and submit the new status and fees of the public pool, failing with
+    /// `LighterError::AccountNotFound` before signing if there is no such pool
+    #[cfg(feature = "signer")]
+    #[tracing::instrument(level = "debug", skip_all, fields(account_index = tracing::field::Empty))]
+    pub async fn update_public_pool(
+        &self,
+        pool_index: impl Into<AccountIndex>,
+        status: PoolStatus,
+        operator_fee: i64,
+        min_operator_share_rate: i64,
+    ) -> Result<RespSendTx> {
+        let public_pool_index = pool_index.into().0;
+        tracing::Span::current().record("account_index", public_pool_index);
+        if self.public_pool(public_pool_index).await?.is_none() {
+            return Err(LighterError::AccountNotFound(format!(
+                "public pool {public_pool_index}"
+            )));
+        }
+
+        self.tx_client()?
+            .submit(|signer, nonce| {
+                signer.sign_update_public_pool(
+                    SignUpdatePublicPoolData::new(
+                        public_pool_index,
+                        status,
+                        operator_fee,
+                        min_operator_share_rate,
+                    ),
+                    nonce,
+                )
+            })
+            .await
+            .inspect_err(|e| tracing::error!("unable to call `update_public_pool`: {e}"))
+    }
+
+    /// Sign and submit the purchase of `share_amount` shares of the public pool
+    #[cfg(feature = "signer")]
+    #[tracing::instrument(level = "debug", skip_all, fields(account_index = tracing::field::Empty))]
+    pub async fn mint_shares(
+        &self,
+        pool_index: impl Into<AccountIndex>,
+        share_amount: i64,
+    ) -> Result<RespSendTx> {
+        let public_pool_index = pool_index.into().0;
+        tracing::Span::current().record("account_index", public_pool_index);
+        check_share_amount(share_amount)?;
+
+        self.tx_client()?
+            .submit(|signer, nonce| {
+                signer.sign_mint_shares(
+                    SignMintSharesData {
+                        public_pool_index,
+                        share_amount,
+                    },
+                    nonce,
+                )
+            })
+            .await
+            .inspect_err(|e| tracing::error!("unable to call `mint_shares`: {e}"))
+    }
+
+    /// Sign and submit the redemption of `share_amount` shares of the public pool
+    #[cfg(feature = "signer")]
+    #[tracing::instrument(level = "debug", skip_all, fields(account_index = tracing::field::Empty))]
+    pub async fn burn_shares(
+        &self,
+        pool_index: impl Into<AccountIndex>,
+        share_amount: i64,
+    ) -> Result<RespSendTx> {
+        let public_pool_index = pool_index.into().0;
+        tracing::Span::current().record("account_index", public_pool_index);
+        check_share_amount(share_amount)?;
+
+        self.tx_client()?
+            .submit(|signer, nonce| {
+                signer.sign_burn_shares(
+                    SignBurnSharesData {
+                        public_pool_index,
+                        share_amount,
+                    },
+                    nonce,
+                )
+            })
+            .await
+            .inspect_err(|e| tracing::error!("unable to call `burn_shares`: {e}"))
+    }
+
+    /// Get the current state of the public pool, `None` if there is no such pool
+    #[tracing::instrument(level = "debug", skip_all, fields(account_index = tracing::field::Empty))]
+    pub async fn public_pool(
+        &self,
+        pool_index: impl Into<AccountIndex>,
+    ) -> Result<Option<PublicPoolState>> {
+        let pool_index = pool_index.into().0;
+        tracing::Span::current().record("account_index", pool_index);
+        // the pools are listed from `index`
+        let resp = self
+            .public_pools_metadata(pool_index, 1, Some(PublicPoolsMetadataFilter::All), None)
+            .await?;
+
+        resp.public_pools
+            .iter()
+            .find(|v| v.account_index == pool_index)
+            .map(PublicPoolState::try_from)
+            .transpose()
+    }
+
+    #[cfg(feature = "signer")]
+    fn auth_token(&self) -> Result<String> {
+        self.signer
+            .as_ref()
+            .ok_or_else(|| LighterError::Config("`api_key_private` is not set".into()))?
+            .get_auth_token(None)
+    }
+
+    #[cfg(not(feature = "signer"))]
+    fn auth_token(&self) -> Result<String> {
+        Err(LighterError::signer_disabled("Creating the auth token"))
+    }
+
     /// Get account by account's index. <br>More details about account index: [Account Index](https://apidocs.lighter.xyz/docs/account-index)<hr>**Response Description:**<br><br>1) **Status:** 1 is active 0 is inactive.<br>2) **Collateral:** The amount of collateral in the account.<hr>**Position Details Description:**<br>1) **OOC:** Open order count in that market.<br>2) **Sign:** 1 for Long, -1 for Short.<br>3) **Position:** The amount of position in that market.<br>4) **Avg Entry Price:** The average entry price of the position.<br>5) **Position Value:** The value of the position.<br>6) **Unrealized PnL:** The unrealized profit and loss of the position.<br>7) **Realized PnL:** The realized profit and loss of the position.
+    #[tracing::instrument(level = "debug", skip_all)]
     pub async fn account(&self, by: AccountBy, value: &str) -> Result<DetailedAccounts> {
         let resp = apis::account_api::account(&self.config, &by.to_string(), value)
             .await
@@ -99,9 +887,56 @@ impl AccountApi {
         Ok(resp)
     }
 
+    /// Get the account with the given index, `None` if it doesn't exist (see
+    /// [`AccountApi::find_accounts`])
+    #[tracing::instrument(level = "debug", skip_all, fields(account_index = tracing::field::Empty))]
+    pub async fn account_by_index(
+        &self,
+        account_index: impl Into<AccountIndex>,
+    ) -> Result<Option<DetailedAccount>> {
+        let account_index = account_index.into();
+        tracing::Span::current().record("account_index", account_index.0);
+        let accounts = self
+            .find_accounts(AccountBy::Index, &account_index.to_string())
+            .await?;
+        Ok(accounts.and_then(|v| v.into_iter().next()))
+    }
+
+    /// Get the accounts (main account and sub accounts) owned by the given L1 address, `None` if
+    /// there are none (see [`AccountApi::find_accounts`])
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub async fn account_by_l1_address(
+        &self,
+        l1_address: &str,
+    ) -> Result<Option<Vec<DetailedAccount>>> {
+        self.find_accounts(AccountBy::L1Address, l1_address).await
+    }
+
+    /// Looks up the accounts, a missing account being a normal outcome: it's `None` when the API
+    /// answers with the code [`LighterErrorCode::AccountNotFound`] (21100) or with no accounts.
+    /// Any other failure is an error.
+    async fn find_accounts(
+        &self,
+        by: AccountBy,
+        value: &str,
+    ) -> Result<Option<Vec<DetailedAccount>>> {
+        match self.account(by, value).await {
+            Ok(resp) if resp.accounts.is_empty() => Ok(None),
+            Ok(resp) => Ok(Some(resp.accounts)),
+            Err(e) if e.api_code() == Some(LighterErrorCode::AccountNotFound) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
     /// Get account limits
-    pub async fn account_limits(&self, account_index: i64) -> Result<AccountLimits> {
-        let auth_token = self.signer.get_auth_token(None)?;
+    #[tracing::instrument(level = "debug", skip_all, fields(account_index = tracing::field::Empty))]
+    pub async fn account_limits(
+        &self,
+        account_index: impl Into<AccountIndex>,
+    ) -> Result<AccountLimits> {
+        let account_index = account_index.into().0;
+        tracing::Span::current().record("account_index", account_index);
+        let auth_token = self.auth_token()?;
         let resp =
             apis::account_api::account_limits(&self.config, account_index, Some(&auth_token), None)
                 .await
@@ -111,12 +946,13 @@ impl AccountApi {
     }
 
     /// Get account metadatas
+    #[tracing::instrument(level = "debug", skip_all)]
     pub async fn account_metadata(
         &self,
         by: AccountMetadataBy,
         value: &str,
     ) -> Result<AccountMetadatas> {
-        let auth_token = self.signer.get_auth_token(None)?;
+        let auth_token = self.auth_token()?;
         let resp = apis::account_api::account_metadata(
             &self.config,
             &by.to_string(),
@@ -131,6 +967,7 @@ impl AccountApi {
     }
 
     /// Get accounts by l1_address returns all accounts associated with the given L1 address
+    #[tracing::instrument(level = "debug", skip_all)]
     pub async fn accounts_by_l1_address(&self, l1_address: &str) -> Result<SubAccounts> {
         let resp = apis::account_api::accounts_by_l1_address(&self.config, l1_address)
             .await
@@ -139,7 +976,8 @@ impl AccountApi {
         Ok(resp)
     }
 
-    /// Get account api key. Set `api_key_index` to 255 to retrieve all api keys associated with the account.
+    /// Get account api key. Set `api_key_index` to [`ALL_API_KEYS`] (255) to retrieve all api keys associated with the account, see [`AccountApi::all_api_keys`].
+    #[tracing::instrument(level = "debug", skip_all, fields(account_index = account_index))]
     pub async fn apikeys(
         &self,
         account_index: i64,
@@ -152,13 +990,58 @@ impl AccountApi {
         Ok(resp)
     }
 
-    /// Change account tier
+    /// Returns all the API keys registered for the account, sorted by index
+    #[tracing::instrument(level = "debug", skip_all, fields(account_index = tracing::field::Empty))]
+    pub async fn all_api_keys(
+        &self,
+        account_index: impl Into<AccountIndex>,
+    ) -> Result<Vec<ApiKeyInfo>> {
+        let account_index = account_index.into().0;
+        tracing::Span::current().record("account_index", account_index);
+        let mut api_keys = self
+            .apikeys(account_index, Some(ALL_API_KEYS))
+            .await?
+            .api_keys
+            .into_iter()
+            .map(ApiKeyInfo::from)
+            .collect::<Vec<_>>();
+        api_keys.sort_by_key(|v| v.api_key_index);
+        Ok(api_keys)
+    }
+
+    /// Returns the API key of the account with the given index, `None` if not registered.
+    /// `api_key_index` must be below [`ALL_API_KEYS`], see [`AccountApi::all_api_keys`].
+    #[tracing::instrument(level = "debug", skip_all, fields(account_index = tracing::field::Empty))]
+    pub async fn api_key(
+        &self,
+        account_index: impl Into<AccountIndex>,
+        api_key_index: i32,
+    ) -> Result<Option<ApiKeyInfo>> {
+        let account_index = account_index.into().0;
+        tracing::Span::current().record("account_index", account_index);
+        if !(0..ALL_API_KEYS).contains(&api_key_index) {
+            return Err(LighterError::Generic(format!(
+                "Invalid API key index {api_key_index}, must be between 0 and {}",
+                ALL_API_KEYS - 1
+            )));
+        }
+
+        let resp = self.apikeys(account_index, Some(api_key_index)).await?;
+        Ok(resp
+            .api_keys
+            .into_iter()
+            .find(|v| v.api_key_index == api_key_index)
+            .map(ApiKeyInfo::from))
+    }
+
+    /// Change account tier, see [`AccountTierChange`] for the outcome
+    #[tracing::instrument(level = "debug", skip_all, fields(account_index = account_index))]
     pub async fn change_account_tier(
         &self,
         account_index: i64,
         new_tier: AccountTier,
-    ) -> Result<RespChangeAccountTier> {
-        let auth_token = self.signer.get_auth_token(None)?;
+    ) -> Result<AccountTierChange> {
+        let auth_token = self.auth_token()?;
         let resp = apis::account_api::change_account_tier(
             &self.config,
             account_index,
@@ -169,12 +1052,13 @@ impl AccountApi {
         .await
         .inspect_err(|e| tracing::error!("unable to call `change_account_tier`: {e}"))?;
 
-        Ok(resp)
+        Ok(resp.into())
     }
 
     /// Get L1 metadata
+    #[tracing::instrument(level = "debug", skip_all)]
     pub async fn l1_metadata(&self, l1_address: &str) -> Result<L1Metadata> {
-        let auth_token = self.signer.get_auth_token(None)?;
+        let auth_token = self.auth_token()?;
         let resp =
             apis::account_api::l1_metadata(&self.config, l1_address, Some(&auth_token), None)
                 .await
@@ -184,30 +1068,46 @@ impl AccountApi {
     }
 
     /// Get liquidation infos
+    ///
+    /// `options` can override the timeout, since long histories can take a while.
+    #[tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(account_index = tracing::field::Empty, market_index = market_id)
+    )]
     pub async fn liquidations(
         &self,
-        account_index: i64,
+        account_index: impl Into<AccountIndex>,
         limit: i64,
         market_id: Option<i32>,
         cursor: Option<&str>,
+        options: RequestOptions,
     ) -> Result<LiquidationInfos> {
-        let auth_token = self.signer.get_auth_token(None)?;
-        let resp = apis::account_api::liquidations(
-            &self.config,
-            account_index,
-            limit,
-            Some(&auth_token),
-            None,
-            market_id,
-            cursor,
-        )
-        .await
-        .inspect_err(|e| tracing::error!("unable to call `liquidations`: {e}"))?;
+        let account_index = account_index.into().0;
+        tracing::Span::current().record("account_index", account_index);
+        let auth_token = self.auth_token()?;
+        let resp = options
+            .scope(apis::account_api::liquidations(
+                &self.config,
+                account_index,
+                limit,
+                Some(&auth_token),
+                None,
+                market_id,
+                cursor,
+            ))
+            .await
+            .inspect_err(|e| tracing::error!("unable to call `liquidations`: {e}"))?;
 
         Ok(resp)
     }
 
     /// Get account PnL chart
+    ///
+    /// The range is rejected when `start_timestamp` is after `end_timestamp`, when one of them is
+    /// above [`MAX_PNL_TIMESTAMP`] or when `resolution` isn't served, see [`PnlResolution`].
+    /// `options` can override the timeout, see [`AccountApi::liquidations`].
+    #[tracing::instrument(level = "debug", skip_all)]
     pub async fn pnl(
         &self,
         by: PnlBy,
@@ -217,53 +1117,97 @@ impl AccountApi {
         end_timestamp: i64,
         count_back: i64,
         ignore_transfers: Option<bool>,
+        options: RequestOptions,
     ) -> Result<AccountPnL> {
-        let auth_token = self.signer.get_auth_token(None)?;
-        let resp = apis::account_api::pnl(
-            &self.config,
-            &by.to_string(),
-            value,
-            &resolution.to_string(),
-            start_timestamp,
-            end_timestamp,
-            count_back,
-            Some(&auth_token),
-            None,
-            ignore_transfers,
-        )
-        .await
-        .inspect_err(|e| tracing::error!("unable to call `pnl`: {e}"))?;
+        check_pnl_range(resolution, start_timestamp, end_timestamp)?;
+        let auth_token = self.auth_token()?;
+        let resp = options
+            .scope(apis::account_api::pnl(
+                &self.config,
+                &by.to_string(),
+                value,
+                &resolution.to_string(),
+                start_timestamp,
+                end_timestamp,
+                count_back,
+                Some(&auth_token),
+                None,
+                ignore_transfers,
+            ))
+            .await
+            .inspect_err(|e| tracing::error!("unable to call `pnl`: {e}"))?;
 
         Ok(resp)
     }
 
+    /// Get account PnL chart as a [`PnlSeries`], see [`AccountApi::pnl`]
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub async fn pnl_series(
+        &self,
+        by: PnlBy,
+        value: &str,
+        resolution: PnlResolution,
+        start_timestamp: i64,
+        end_timestamp: i64,
+        count_back: i64,
+        ignore_transfers: bool,
+        options: RequestOptions,
+    ) -> Result<PnlSeries> {
+        let resp = self
+            .pnl(
+                by,
+                value,
+                resolution,
+                start_timestamp,
+                end_timestamp,
+                count_back,
+                Some(ignore_transfers),
+                options,
+            )
+            .await?;
+
+        Ok(PnlSeries::new(&resp, resolution, ignore_transfers))
+    }
+
     /// Get accounts position fundings
+    ///
+    /// `options` can override the timeout, see [`AccountApi::liquidations`].
+    #[tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(account_index = tracing::field::Empty, market_index = market_id)
+    )]
     pub async fn position_funding(
         &self,
-        account_index: i64,
+        account_index: impl Into<AccountIndex>,
         limit: i64,
         market_id: Option<i32>,
         cursor: Option<&str>,
         side: Option<PositionFundingSide>,
+        options: RequestOptions,
     ) -> Result<PositionFundings> {
-        let auth_token = self.signer.get_auth_token(None)?;
-        let resp = apis::account_api::position_funding(
-            &self.config,
-            account_index,
-            limit,
-            Some(&auth_token),
-            None,
-            market_id,
-            cursor,
-            side.map(|v| v.to_string()).as_deref(),
-        )
-        .await
-        .inspect_err(|e| tracing::error!("unable to call `position_fundings`: {e}"))?;
+        let account_index = account_index.into().0;
+        tracing::Span::current().record("account_index", account_index);
+        let auth_token = self.auth_token()?;
+        let resp = options
+            .scope(apis::account_api::position_funding(
+                &self.config,
+                account_index,
+                limit,
+                Some(&auth_token),
+                None,
+                market_id,
+                cursor,
+                side.map(|v| v.to_string()).as_deref(),
+            ))
+            .await
+            .inspect_err(|e| tracing::error!("unable to call `position_fundings`: {e}"))?;
 
         Ok(resp)
     }
 
     /// Get public pools metadata
+    #[tracing::instrument(level = "debug", skip_all, fields(account_index = account_index))]
     pub async fn public_pools_metadata(
         &self,
         index: i64,
@@ -271,7 +1215,7 @@ impl AccountApi {
         filter: Option<PublicPoolsMetadataFilter>,
         account_index: Option<i64>,
     ) -> Result<RespPublicPoolsMetadata> {
-        let auth_token = self.signer.get_auth_token(None)?;
+        let auth_token = self.auth_token()?;
         let resp = apis::account_api::public_pools_metadata(
             &self.config,
             index,
@@ -302,6 +1246,837 @@ mod tests {
     //     "0x4fd51c004ad02a003e321d5154d9b22c6bb89e1e5017bdc832c69ef28f65c04e";
     static TEST_ACCOUNT_ADDRESS: &str = "0x2b8a17334f9474ceE44CdeD230dc6fE537eda02E";
 
+    fn mock_config(base_url: String) -> LighterConfig {
+        LighterConfig {
+            base_url,
+            ..LighterConfig::new()
+                .with_api_key_private(TEST_API_KEY_PRIVATE)
+                .with_account_index(TEST_ACCOUNT_INDEX.parse().unwrap())
+                .with_api_key_index(TEST_API_KEY_INDEX)
+        }
+    }
+
+    fn detailed_accounts(indexes: &[i64]) -> String {
+        let accounts = indexes
+            .iter()
+            .map(|index| DetailedAccount {
+                index: *index,
+                account_index: *index,
+                l1_address: TEST_ACCOUNT_ADDRESS.into(),
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        serde_json::to_string(&DetailedAccounts::new(200, indexes.len() as i64, accounts)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_account_by_index_single() {
+        let mut server = mockito::Server::new_async().await;
+        let account = server
+            .mock("GET", "/api/v1/account")
+            .match_query(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::UrlEncoded("by".into(), "index".into()),
+                mockito::Matcher::UrlEncoded("value".into(), "28".into()),
+            ]))
+            .with_header("content-type", "application/json")
+            .with_body(detailed_accounts(&[28]))
+            .create_async()
+            .await;
+
+        let api = AccountApi::new(&mock_config(server.url())).unwrap();
+        assert_eq!(api.account_by_index(28).await.unwrap().unwrap().index, 28);
+        account.assert_async().await;
+    }
+
+    #[derive(Debug)]
+    struct RecordedSpan {
+        id: u64,
+        name: String,
+        fields: Vec<String>,
+    }
+
+    /// Collects the names of the spans and the fields recorded on them
+    #[derive(Debug, Clone, Default)]
+    struct SpansLayer(std::sync::Arc<std::sync::Mutex<Vec<RecordedSpan>>>);
+
+    struct FieldsVisitor<'a>(&'a mut Vec<String>);
+
+    impl tracing::field::Visit for FieldsVisitor<'_> {
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+            self.0.push(format!("{}={value:?}", field.name()));
+        }
+    }
+
+    impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for SpansLayer {
+        fn on_new_span(
+            &self,
+            attrs: &tracing::span::Attributes<'_>,
+            id: &tracing::span::Id,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            let mut fields = Vec::new();
+            attrs.record(&mut FieldsVisitor(&mut fields));
+            self.0.lock().unwrap().push(RecordedSpan {
+                id: id.into_u64(),
+                name: attrs.metadata().name().to_string(),
+                fields,
+            });
+        }
+
+        fn on_record(
+            &self,
+            id: &tracing::span::Id,
+            values: &tracing::span::Record<'_>,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            let mut spans = self.0.lock().unwrap();
+            if let Some(span) = spans.iter_mut().find(|v| v.id == id.into_u64()) {
+                values.record(&mut FieldsVisitor(&mut span.fields));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_api_call_span() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/api/v1/account")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(detailed_accounts(&[28]))
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/api/v1/apikeys")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"api_keys":[]}"#)
+            .create_async()
+            .await;
+        let api = AccountApi::new(&mock_config(server.url())).unwrap();
+
+        let layer = SpansLayer::default();
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(layer.clone()));
+        api.account_by_index(28).await.unwrap();
+        api.apikeys(28, Some(2)).await.unwrap();
+
+        let spans = layer.0.lock().unwrap();
+        let fields = |name: &str| {
+            spans
+                .iter()
+                .find(|v| v.name == name)
+                .map(|v| v.fields.clone())
+                .unwrap_or_else(|| panic!("no `{name}` span in {spans:?}"))
+        };
+        assert_eq!(fields("account_by_index"), vec!["account_index=28"]);
+        assert_eq!(fields("apikeys"), vec!["account_index=28"]);
+        assert!(fields("http_request").contains(&"status=200".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_api_keys() {
+        let mut server = mockito::Server::new_async().await;
+        let all = server
+            .mock("GET", "/api/v1/apikeys")
+            .match_query(mockito::Matcher::UrlEncoded(
+                "api_key_index".into(),
+                "255".into(),
+            ))
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"code":200,"api_keys":[
+                    {"account_index":28,"api_key_index":3,"nonce":1,"public_key":"0x3"},
+                    {"account_index":28,"api_key_index":2,"nonce":7,"public_key":"0x2"}
+                ]}"#,
+            )
+            .expect(1)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/api/v1/apikeys")
+            .match_query(mockito::Matcher::UrlEncoded(
+                "api_key_index".into(),
+                "2".into(),
+            ))
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"code":200,"api_keys":[{"account_index":28,"api_key_index":2,"nonce":7,"public_key":"0x2"}]}"#,
+            )
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/api/v1/apikeys")
+            .match_query(mockito::Matcher::UrlEncoded(
+                "api_key_index".into(),
+                "4".into(),
+            ))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"api_keys":[]}"#)
+            .create_async()
+            .await;
+        let api = AccountApi::new(&mock_config(server.url())).unwrap();
+
+        let api_keys = api.all_api_keys(28).await.unwrap();
+        assert_eq!(
+            api_keys.iter().map(|v| v.api_key_index).collect::<Vec<_>>(),
+            [2, 3]
+        );
+        assert_eq!(
+            api_keys[0],
+            ApiKeyInfo {
+                account_index: 28,
+                api_key_index: 2,
+                public_key: "0x2".into(),
+                nonce: 7,
+            }
+        );
+        all.assert_async().await;
+
+        assert_eq!(api.api_key(28, 2).await.unwrap(), Some(api_keys[0].clone()));
+        assert_eq!(api.api_key(28, 4).await.unwrap(), None);
+        for index in [-1, ALL_API_KEYS] {
+            let res = api.api_key(28, index).await;
+            assert!(matches!(res, Err(LighterError::Generic(e)) if e.contains("API key index")));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_with_configuration() {
+        let mut server = mockito::Server::new_async().await;
+        let account = server
+            .mock("GET", "/api/v1/account?by=index&value=28")
+            .with_header("content-type", "application/json")
+            .with_body(detailed_accounts(&[28]))
+            .expect(1)
+            .create_async()
+            .await;
+
+        // nothing listens there, the requests go to the mock server
+        let config = mock_config("http://lighter.invalid".into());
+        let configuration = Configuration {
+            base_path: server.url(),
+            ..Configuration::try_from(&config).unwrap()
+        };
+        let api = AccountApi::new(&config)
+            .unwrap()
+            .with_configuration(configuration);
+
+        let resp = api.account(AccountBy::Index, "28").await.unwrap();
+        assert_eq!(resp.total, 1);
+        assert_eq!(resp.accounts[0].l1_address, TEST_ACCOUNT_ADDRESS);
+        account.assert_async().await;
+    }
+
+    #[cfg(feature = "signer")]
+    #[tokio::test]
+    async fn test_set_leverage_and_update_margin() {
+        let mut server = mockito::Server::new_async().await;
+        let mut send_tx = Vec::new();
+        for tx_type in [20, 29] {
+            let mock = server
+                .mock("POST", "/api/v1/sendTx")
+                .match_body(mockito::Matcher::Regex(format!(
+                    r#"name="tx_type"\r\n\r\n{tx_type}\r\n"#
+                )))
+                .with_header("content-type", "application/json")
+                .with_body(format!(
+                    r#"{{"code":200,"tx_hash":"0x{tx_type}","predicted_execution_time_ms":1}}"#
+                ))
+                .expect(1)
+                .create_async()
+                .await;
+            send_tx.push(mock);
+        }
+        server
+            .mock("GET", "/api/v1/nextNonce")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"nonce":5}"#)
+            .create_async()
+            .await;
+        // 20x at most
+        let detail = crate::models::OrderBookDetail {
+            market_id: 1,
+            symbol: "BTC".into(),
+            min_base_amount: "0.01".into(),
+            min_quote_amount: "10".into(),
+            min_initial_margin_fraction: 500,
+            ..Default::default()
+        };
+        // fetched once for each market, then cached
+        let details = server
+            .mock("GET", "/api/v1/orderBookDetails")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(serde_json::json!({"code": 200, "order_book_details": [detail]}).to_string())
+            .expect(2)
+            .create_async()
+            .await;
+
+        let config = LighterConfig {
+            local_nonce: false,
+            ..mock_config(server.url())
+        };
+        let api = AccountApi::new(&config)
+            .unwrap()
+            .with_tx_client(Some(Arc::new(TxClient::new(&config, None).unwrap())));
+
+        let resp = api
+            .set_leverage(1, 10.0, MarginMode::Isolated)
+            .await
+            .unwrap();
+        assert_eq!(resp.tx_hash, "0x20");
+        let resp = api
+            .update_margin(1, 5_000_000, MarginDirection::Add)
+            .await
+            .unwrap();
+        assert_eq!(resp.tx_hash, "0x29");
+        for mock in send_tx {
+            mock.assert_async().await;
+        }
+
+        // rejected before signing
+        for leverage in [0.5, f64::NAN] {
+            let res = api.set_leverage(1, leverage, MarginMode::Cross).await;
+            assert!(matches!(res, Err(LighterError::Generic(e)) if e.contains("Invalid leverage")));
+        }
+        let res = api.set_leverage(1, 25.0, MarginMode::Cross).await;
+        assert!(matches!(res, Err(LighterError::Generic(e)) if e.contains("max leverage 20x")));
+        let res = api.set_leverage(2, 10.0, MarginMode::Cross).await;
+        assert!(matches!(res, Err(LighterError::MarketNotFound(_))));
+        let res = api.update_margin(1, 0, MarginDirection::Remove).await;
+        assert!(matches!(res, Err(LighterError::Generic(e)) if e.contains("USDC amount")));
+        details.assert_async().await;
+
+        // without a tx client
+        let api = AccountApi::new(&config).unwrap();
+        let res = api.set_leverage(1, 10.0, MarginMode::Cross).await;
+        assert!(matches!(res, Err(LighterError::Config(_))));
+    }
+
+    #[cfg(feature = "signer")]
+    #[tokio::test]
+    async fn test_create_subaccount() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/api/v1/account")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(detailed_accounts(&[28]))
+            .create_async()
+            .await;
+        let sub_accounts = |indexes: &[i64]| {
+            let accounts = indexes
+                .iter()
+                .map(|index| crate::models::Account {
+                    index: *index,
+                    ..Default::default()
+                })
+                .collect();
+            serde_json::to_string(&SubAccounts::new(
+                200,
+                TEST_ACCOUNT_ADDRESS.into(),
+                accounts,
+            ))
+            .unwrap()
+        };
+        // listed before the tx and on the first poll, then with the new account
+        let listed = server
+            .mock("GET", "/api/v1/accountsByL1Address")
+            .match_query(mockito::Matcher::UrlEncoded(
+                "l1_address".into(),
+                TEST_ACCOUNT_ADDRESS.into(),
+            ))
+            .with_header("content-type", "application/json")
+            .with_body(sub_accounts(&[28, 100]))
+            .expect(2)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/api/v1/accountsByL1Address")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(sub_accounts(&[28, 100, 101]))
+            .create_async()
+            .await;
+        let send_tx = server
+            .mock("POST", "/api/v1/sendTx")
+            .match_body(mockito::Matcher::Regex(
+                r#"name="tx_type"\r\n\r\n9\r\n"#.into(),
+            ))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"tx_hash":"0x9","predicted_execution_time_ms":1}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/api/v1/nextNonce")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"nonce":5}"#)
+            .create_async()
+            .await;
+
+        let config = LighterConfig {
+            local_nonce: false,
+            ..mock_config(server.url())
+        };
+        let api = AccountApi::new(&config)
+            .unwrap()
+            .with_tx_client(Some(Arc::new(TxClient::new(&config, None).unwrap())));
+
+        let created = api.create_subaccount().await.unwrap();
+        assert_eq!(
+            created,
+            SubAccountCreated {
+                tx_hash: "0x9".into(),
+                account_index: Some(101),
+            }
+        );
+        listed.assert_async().await;
+        send_tx.assert_async().await;
+
+        // without a tx client
+        let api = AccountApi::new(&config).unwrap();
+        assert!(matches!(
+            api.create_subaccount().await,
+            Err(LighterError::Config(_))
+        ));
+    }
+
+    #[cfg(feature = "signer")]
+    #[tokio::test]
+    async fn test_public_pool_lifecycle() {
+        let mut server = mockito::Server::new_async().await;
+        let pools = |indexes: &[i64]| {
+            let pools = indexes
+                .iter()
+                .map(|index| PublicPoolMetadata {
+                    account_index: *index,
+                    name: "pool".into(),
+                    operator_fee: "10.5".into(),
+                    total_asset_value: "1000".into(),
+                    total_shares: 500,
+                    ..Default::default()
+                })
+                .collect();
+            serde_json::to_string(&RespPublicPoolsMetadata::new(200, pools)).unwrap()
+        };
+        server
+            .mock("GET", "/api/v1/publicPoolsMetadata")
+            .match_query(mockito::Matcher::UrlEncoded("index".into(), "300".into()))
+            .with_header("content-type", "application/json")
+            .with_body(pools(&[300]))
+            .create_async()
+            .await;
+        // no such pool, the next one is listed
+        server
+            .mock("GET", "/api/v1/publicPoolsMetadata")
+            .match_query(mockito::Matcher::UrlEncoded("index".into(), "299".into()))
+            .with_header("content-type", "application/json")
+            .with_body(pools(&[300]))
+            .create_async()
+            .await;
+        let mut send_tx = Vec::new();
+        for tx_type in [10, 11, 18, 19] {
+            let mock = server
+                .mock("POST", "/api/v1/sendTx")
+                .match_body(mockito::Matcher::Regex(format!(
+                    r#"name="tx_type"\r\n\r\n{tx_type}\r\n"#
+                )))
+                .with_header("content-type", "application/json")
+                .with_body(format!(
+                    r#"{{"code":200,"tx_hash":"0x{tx_type}","predicted_execution_time_ms":1}}"#
+                ))
+                .expect(1)
+                .create_async()
+                .await;
+            send_tx.push(mock);
+        }
+        server
+            .mock("GET", "/api/v1/nextNonce")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"nonce":5}"#)
+            .create_async()
+            .await;
+
+        let config = LighterConfig {
+            local_nonce: false,
+            ..mock_config(server.url())
+        };
+        let api = AccountApi::new(&config)
+            .unwrap()
+            .with_tx_client(Some(Arc::new(TxClient::new(&config, None).unwrap())));
+
+        let pool = api.public_pool(300).await.unwrap().unwrap();
+        assert_eq!(pool.status, PoolStatus::Active);
+        assert_eq!(pool.operator_fee, 10.5);
+        assert_eq!(pool.total_shares, 500);
+        assert_eq!(api.public_pool(299).await.unwrap(), None);
+
+        let resp = api.create_public_pool(100, 1000, 10).await.unwrap();
+        assert_eq!(resp.tx_hash, "0x10");
+        let resp = api
+            .update_public_pool(300, PoolStatus::Frozen, 200, 10)
+            .await
+            .unwrap();
+        assert_eq!(resp.tx_hash, "0x11");
+        let resp = api.mint_shares(300, 10).await.unwrap();
+        assert_eq!(resp.tx_hash, "0x18");
+        let resp = api.burn_shares(300, 10).await.unwrap();
+        assert_eq!(resp.tx_hash, "0x19");
+        for mock in send_tx {
+            mock.assert_async().await;
+        }
+
+        // rejected before signing
+        let res = api
+            .update_public_pool(299, PoolStatus::Active, 200, 10)
+            .await;
+        assert!(matches!(res, Err(LighterError::AccountNotFound(e)) if e.contains("299")));
+        let res = api.mint_shares(300, 0).await;
+        assert!(matches!(res, Err(LighterError::Generic(e)) if e.contains("share amount")));
+        let res = api.burn_shares(300, -1).await;
+        assert!(matches!(res, Err(LighterError::Generic(e)) if e.contains("share amount")));
+        let res = api.create_public_pool(100, 0, 10).await;
+        assert!(matches!(res, Err(LighterError::Generic(e)) if e.contains("share amount")));
+    }
+
+    #[test]
+    fn test_account_positions() {
+        let position = |market_id: i32, sign: i32, position: &str| {
+            serde_json::json!({
+                "market_id": market_id,
+                "symbol": "ETH",
+                "initial_margin_fraction": "10.00",
+                "open_order_count": 2,
+                "pending_order_count": 0,
+                "position_tied_order_count": 0,
+                "sign": sign,
+                "position": position,
+                "avg_entry_price": "2500.50",
+                "position_value": "3750.75",
+                "unrealized_pnl": "-12.5",
+                "realized_pnl": "3.25",
+                "liquidation_price": "1200",
+                "margin_mode": 0,
+                "allocated_margin": "0.000000",
+            })
+        };
+        let body = serde_json::json!({
+            "code": 200,
+            "total": 1,
+            "accounts": [{
+                "code": 0,
+                "account_type": 0,
+                "index": 28,
+                "l1_address": TEST_ACCOUNT_ADDRESS,
+                "cancel_all_time": 0,
+                "total_order_count": 2,
+                "total_isolated_order_count": 0,
+                "pending_order_count": 0,
+                "available_balance": "100",
+                "status": 1,
+                "collateral": "1000",
+                "account_index": 28,
+                "name": "",
+                "description": "",
+                "can_invite": true,
+                "referral_points_percentage": "",
+                "positions": [position(0, -1, "1.5"), position(1, 1, "0.0")],
+            }],
+        });
+        let accounts: DetailedAccounts = serde_json::from_value(body).unwrap();
+        let account = &accounts.accounts[0];
+
+        let eth = account.position(0).unwrap().unwrap();
+        assert_eq!(eth.side, Some(PositionSide::Short));
+        assert_eq!(eth.size, 1.5);
+        assert_eq!(eth.net_size(), -1.5);
+        assert_eq!(eth.avg_entry_price, 2500.5);
+        assert_eq!(eth.unrealized_pnl, -12.5);
+        assert_eq!(eth.margin_mode, MarginMode::Cross);
+        assert_eq!(eth.total_funding_paid_out, None);
+
+        let flat = account.position(MarketIndex(1)).unwrap().unwrap();
+        assert_eq!(flat.side, None);
+        assert_eq!(flat.net_size(), 0.0);
+
+        assert_eq!(account.position(2).unwrap(), None);
+        assert_eq!(account.typed_positions().unwrap().len(), 2);
+        assert_eq!(account.open_order_count(0), 2);
+        assert_eq!(account.open_order_count(2), 0);
+
+        let mut account = account.clone();
+        account.positions[0].position = "abc".into();
+        assert!(account.position(0).is_err());
+    }
+
+    #[test]
+    fn test_margin_mode_and_direction_mapping() {
+        assert_eq!(i32::from(MarginMode::Cross), 0);
+        assert_eq!(i32::from(MarginMode::Isolated), 1);
+        assert_eq!(i32::from(MarginDirection::Remove), 0);
+        assert_eq!(i32::from(MarginDirection::Add), 1);
+
+        for mode in [MarginMode::Cross, MarginMode::Isolated] {
+            assert_eq!(MarginMode::try_from(i32::from(mode)).unwrap(), mode);
+        }
+        for direction in [MarginDirection::Remove, MarginDirection::Add] {
+            assert_eq!(
+                MarginDirection::try_from(i32::from(direction)).unwrap(),
+                direction
+            );
+        }
+    }
+
+    #[test]
+    fn test_margin_mode_and_direction_from_i32() {
+        assert_eq!(MarginMode::try_from(1).unwrap(), MarginMode::Isolated);
+        assert_eq!(
+            MarginDirection::try_from(0).unwrap(),
+            MarginDirection::Remove
+        );
+        assert!(
+            matches!(MarginMode::try_from(2), Err(LighterError::Generic(e)) if e.contains("isolated"))
+        );
+        assert!(MarginDirection::try_from(-1).is_err());
+    }
+
+    #[test]
+    fn test_pool_status_mapping() {
+        assert_eq!(i32::from(PoolStatus::Active), 0);
+        assert_eq!(i32::from(PoolStatus::Frozen), 1);
+        assert_eq!(PoolStatus::try_from(1).unwrap(), PoolStatus::Frozen);
+        assert!(
+            matches!(PoolStatus::try_from(2), Err(LighterError::Generic(e)) if e.contains("frozen"))
+        );
+
+        let pool = PublicPoolMetadata {
+            status: 3,
+            operator_fee: "0".into(),
+            total_asset_value: "0".into(),
+            ..Default::default()
+        };
+        assert!(PublicPoolState::try_from(&pool).is_err());
+    }
+
+    #[test]
+    fn test_account_tier_change_mapping() {
+        let change = AccountTierChange::from(RespChangeAccountTier::new(200));
+        assert_eq!(change, AccountTierChange::Changed);
+        assert!(change.is_changed());
+
+        let resp = RespChangeAccountTier {
+            code: 21600,
+            message: Some("positions are open".into()),
+        };
+        assert_eq!(
+            AccountTierChange::from(resp),
+            AccountTierChange::Rejected {
+                code: 21600,
+                message: "positions are open".into()
+            }
+        );
+        assert!(!AccountTierChange::from(RespChangeAccountTier::new(400)).is_changed());
+    }
+
+    #[tokio::test]
+    async fn test_account_not_found() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/api/v1/account")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(detailed_accounts(&[]))
+            .create_async()
+            .await;
+
+        let api = AccountApi::new(&mock_config(server.url())).unwrap();
+        assert!(api.account_by_index(28).await.unwrap().is_none());
+        assert!(api
+            .account_by_l1_address(TEST_ACCOUNT_ADDRESS)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_account_not_found_code() {
+        let mut server = mockito::Server::new_async().await;
+        let not_found = server
+            .mock("GET", "/api/v1/account")
+            .match_query(mockito::Matcher::UrlEncoded("value".into(), "28".into()))
+            .with_status(400)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":21100,"message":"account not found"}"#)
+            .create_async()
+            .await;
+        let invalid = server
+            .mock("GET", "/api/v1/account")
+            .match_query(mockito::Matcher::UrlEncoded("value".into(), "-1".into()))
+            .with_status(400)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":20001,"message":"invalid param"}"#)
+            .create_async()
+            .await;
+
+        let api = AccountApi::new(&mock_config(server.url())).unwrap();
+        assert!(api.account_by_index(28).await.unwrap().is_none());
+        // real failures are still errors
+        assert_eq!(
+            api.account_by_index(-1).await.unwrap_err().api_code(),
+            Some(LighterErrorCode::InvalidParam)
+        );
+
+        not_found.assert_async().await;
+        invalid.assert_async().await;
+    }
+
+    #[cfg(not(feature = "signer"))]
+    #[tokio::test]
+    async fn test_account_limits_signer_disabled() {
+        let api = AccountApi::new(&LighterConfig::new()).unwrap();
+        match api.account_limits(28).await {
+            Err(LighterError::Config(msg)) => assert!(msg.contains("`signer` feature")),
+            res => panic!("unexpected result: {res:?}"),
+        }
+    }
+
+    fn account_pnl(values: &[(i64, f64)]) -> AccountPnL {
+        let entries = values
+            .iter()
+            .map(|(timestamp, trade_pnl)| PnLEntry {
+                timestamp: *timestamp,
+                trade_pnl: *trade_pnl,
+                inflow: 10.0,
+                pool_pnl: 1.0,
+                ..Default::default()
+            })
+            .collect();
+        AccountPnL::new(200, "1h".into(), entries)
+    }
+
+    #[test]
+    fn test_pnl_series() {
+        let pnl = account_pnl(&[(3, 5.0), (1, 0.0), (4, 2.0), (2, 10.0), (5, 8.0)]);
+        let series = PnlSeries::new(&pnl, PnlResolution::OneHour, true);
+        assert_eq!(
+            series.iter().map(|p| p.timestamp).collect::<Vec<_>>(),
+            [1, 2, 3, 4, 5]
+        );
+        assert_eq!(series.first().unwrap().value, 1.0);
+        assert_eq!(series.first().unwrap().net_inflow, 10.0);
+        assert_eq!(series.total_return(), Some(8.0));
+        assert_eq!(series.max_drawdown(), Some(8.0));
+        assert_eq!((&series).into_iter().count(), 5);
+        assert_eq!(series.into_iter().last().unwrap().value, 9.0);
+
+        let series = PnlSeries::new(&account_pnl(&[]), PnlResolution::OneHour, false);
+        assert!(series.is_empty());
+        assert_eq!(series.total_return(), None);
+        assert_eq!(series.max_drawdown(), None);
+
+        let pnl = account_pnl(&[(1, 1.0), (2, 2.0)]);
+        let series = PnlSeries::new(&pnl, PnlResolution::OneHour, false);
+        assert_eq!(series.max_drawdown(), Some(0.0));
+    }
+
+    #[tokio::test]
+    async fn test_account_pnl_rejects_range() {
+        let api = AccountApi::new(&mock_config("http://127.0.0.1:1".into())).unwrap();
+        for (resolution, start, end, msg) in [
+            (PnlResolution::OneHour, 10, 1, "range"),
+            (PnlResolution::OneHour, -1, 1, "timestamp"),
+            (
+                PnlResolution::OneHour,
+                1,
+                MAX_PNL_TIMESTAMP + 1,
+                "timestamp",
+            ),
+            (PnlResolution::ThirtyMinutes, 1, 10, "resolution"),
+        ] {
+            let res = api
+                .pnl_series(
+                    PnlBy::Index,
+                    TEST_ACCOUNT_INDEX,
+                    resolution,
+                    start,
+                    end,
+                    1,
+                    false,
+                    RequestOptions::default(),
+                )
+                .await;
+            assert!(
+                matches!(&res, Err(LighterError::Generic(e)) if e.contains(msg)),
+                "{res:?}"
+            );
+        }
+    }
+
+    #[cfg(feature = "signer")]
+    #[tokio::test]
+    async fn test_account_pnl_series() {
+        let mut server = mockito::Server::new_async().await;
+        let pnl = server
+            .mock("GET", "/api/v1/pnl")
+            .match_query(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::UrlEncoded("resolution".into(), "1h".into()),
+                mockito::Matcher::UrlEncoded("ignore_transfers".into(), "true".into()),
+            ]))
+            .with_header("content-type", "application/json")
+            .with_body(serde_json::to_string(&account_pnl(&[(2, 4.0), (1, 2.0)])).unwrap())
+            .create_async()
+            .await;
+
+        let api = AccountApi::new(&mock_config(server.url())).unwrap();
+        let series = api
+            .pnl_series(
+                PnlBy::Index,
+                TEST_ACCOUNT_INDEX,
+                PnlResolution::OneHour,
+                1,
+                10,
+                2,
+                true,
+                RequestOptions::default(),
+            )
+            .await
+            .unwrap();
+        assert!(series.transfers_ignored);
+        assert_eq!(series.resolution, PnlResolution::OneHour);
+        assert_eq!(series.total_return(), Some(2.0));
+        pnl.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_account_by_l1_address_all() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/api/v1/account")
+            .match_query(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::UrlEncoded("by".into(), "l1_address".into()),
+                mockito::Matcher::UrlEncoded("value".into(), TEST_ACCOUNT_ADDRESS.into()),
+            ]))
+            .with_header("content-type", "application/json")
+            .with_body(detailed_accounts(&[28, 29]))
+            .create_async()
+            .await;
+
+        let api = AccountApi::new(&mock_config(server.url())).unwrap();
+        let accounts = api
+            .account_by_l1_address(TEST_ACCOUNT_ADDRESS)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(accounts.len(), 2);
+    }
+
     #[tokio::test]
     async fn test_account_by_index() {
         let config = LighterConfig::new()
@@ -350,6 +2125,41 @@ mod tests {
         println!("res: {res:?}")
     }
 
+    #[tokio::test]
+    async fn test_account_all_api_keys() {
+        let config = LighterConfig::new()
+            .with_base_url("https://testnet.zklighter.elliot.ai")
+            .unwrap()
+            .with_api_key_private(TEST_API_KEY_PRIVATE)
+            .with_account_index(TEST_ACCOUNT_INDEX.parse().unwrap())
+            .with_api_key_index(TEST_API_KEY_INDEX);
+
+        let client = HttpClient::builder()
+            .with_config(config)
+            .with_account()
+            .build()
+            .unwrap();
+        let api = client.api().account().unwrap();
+
+        let account_index = TEST_ACCOUNT_INDEX.parse::<i64>().unwrap();
+        let api_keys = api.all_api_keys(account_index).await.unwrap();
+        let api_key = api
+            .api_key(account_index, TEST_API_KEY_INDEX)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(api_keys.contains(&api_key));
+        assert_eq!(api_key.api_key_index, TEST_API_KEY_INDEX);
+        assert!(api_keys
+            .iter()
+            .all(|v| v.account_index == account_index && v.api_key_index < ALL_API_KEYS));
+        // sorted by index, each key once
+        assert!(api_keys
+            .windows(2)
+            .all(|v| v[0].api_key_index < v[1].api_key_index));
+    }
+
+    #[cfg(feature = "signer")]
     #[tokio::test]
     async fn test_account_limits() {
         let config = LighterConfig::new()
@@ -369,12 +2179,13 @@ mod tests {
             .api()
             .account()
             .unwrap()
-            .account_limits(TEST_ACCOUNT_INDEX.parse().unwrap())
+            .account_limits(TEST_ACCOUNT_INDEX.parse::<i64>().unwrap())
             .await
             .unwrap();
         println!("res: {res:?}");
     }
 
+    #[cfg(feature = "signer")]
     #[tokio::test]
     async fn test_account_metadata_by_index() {
         let config = LighterConfig::new()
@@ -450,6 +2261,7 @@ mod tests {
         println!("res: {res:?}");
     }
 
+    #[cfg(feature = "signer")]
     #[tokio::test]
     async fn test_account_l1_metadata() {
         let config = LighterConfig::new()
@@ -475,6 +2287,7 @@ mod tests {
         println!("res: {res:?}");
     }
 
+    #[cfg(feature = "signer")]
     #[tokio::test]
     async fn test_account_liquidations() {
         let config = LighterConfig::new()
@@ -494,12 +2307,19 @@ mod tests {
             .api()
             .account()
             .unwrap()
-            .liquidations(TEST_ACCOUNT_INDEX.parse().unwrap(), 10, None, None)
+            .liquidations(
+                TEST_ACCOUNT_INDEX.parse::<i64>().unwrap(),
+                10,
+                None,
+                None,
+                RequestOptions::new().with_timeout(std::time::Duration::from_secs(30)),
+            )
             .await
             .unwrap();
         println!("res: {res:?}");
     }
 
+    #[cfg(feature = "signer")]
     #[tokio::test]
     async fn test_account_pnl() {
         let config = LighterConfig::new()
@@ -527,12 +2347,14 @@ mod tests {
                 10,
                 1,
                 None,
+                RequestOptions::default(),
             )
             .await
             .unwrap();
         println!("res: {res:?}");
     }
 
+    #[cfg(feature = "signer")]
     #[tokio::test]
     async fn test_account_position_funding() {
         let config = LighterConfig::new()
@@ -552,7 +2374,14 @@ mod tests {
             .api()
             .account()
             .unwrap()
-            .position_funding(TEST_ACCOUNT_INDEX.parse().unwrap(), 10, None, None, None)
+            .position_funding(
+                TEST_ACCOUNT_INDEX.parse::<i64>().unwrap(),
+                10,
+                None,
+                None,
+                None,
+                RequestOptions::default(),
+            )
             .await
             .unwrap();
         println!("res: {res:?}");
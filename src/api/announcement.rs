@@ -1,10 +1,66 @@
 use crate::{
     apis::{self, configuration::Configuration},
     config::LighterConfig,
-    models::Announcements,
+    error::LighterError,
+    models::{Announcement, Announcements},
     Result,
 };
 
+/// Severity of an announcement. The endpoint doesn't return it, so it's inferred from the
+/// title.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AnnouncementSeverity {
+    Info,
+    Maintenance,
+    Critical,
+}
+
+impl AnnouncementSeverity {
+    fn from_title(title: &str) -> Self {
+        let title = title.to_lowercase();
+        if ["critical", "urgent", "incident", "outage"]
+            .iter()
+            .any(|v| title.contains(v))
+        {
+            Self::Critical
+        } else if ["maintenance", "upgrade", "downtime"]
+            .iter()
+            .any(|v| title.contains(v))
+        {
+            Self::Maintenance
+        } else {
+            Self::Info
+        }
+    }
+}
+
+/// Announcement of the venue, see [`AnnouncementApi::list`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnnouncementEntry {
+    pub title: String,
+    pub body: String,
+    pub published_at: i64,
+    pub severity: AnnouncementSeverity,
+}
+
+impl From<Announcement> for AnnouncementEntry {
+    fn from(announcement: Announcement) -> Self {
+        Self {
+            severity: AnnouncementSeverity::from_title(&announcement.title),
+            title: announcement.title,
+            body: announcement.content,
+            published_at: announcement.created_at,
+        }
+    }
+}
+
+/// Page of announcements, the next one is requested with `next_cursor`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnnouncementPage {
+    pub announcements: Vec<AnnouncementEntry>,
+    pub next_cursor: Option<String>,
+}
+
 #[derive(Debug)]
 pub struct AnnouncementApi {
     config: apis::configuration::Configuration,
@@ -12,12 +68,22 @@ pub struct AnnouncementApi {
 
 impl AnnouncementApi {
     pub fn new(config: &LighterConfig) -> Result<Self> {
-        Ok(Self {
-            config: Configuration::try_from(config)?,
-        })
+        Ok(Self::from_configuration(Configuration::try_from(config)?))
+    }
+
+    /// Sends the requests with `config`, e.g. shared with the other APIs, without building one
+    pub fn from_configuration(config: Configuration) -> Self {
+        Self { config }
+    }
+
+    /// Sends the requests with `config` instead of the one built from the `LighterConfig`
+    pub fn with_configuration(mut self, config: Configuration) -> Self {
+        self.config = config;
+        self
     }
 
     /// Get announcement
+    #[tracing::instrument(level = "debug", skip_all)]
     pub async fn announcement(&self) -> Result<Announcements> {
         let resp = apis::announcement_api::announcement(&self.config)
             .await
@@ -25,4 +91,132 @@ impl AnnouncementApi {
 
         Ok(resp)
     }
+
+    /// Returns `limit` announcements, the most recent first, starting from `cursor` (the
+    /// `next_cursor` of the previous page). The endpoint isn't paginated: all the announcements
+    /// are requested and paged here.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub async fn list(&self, limit: usize, cursor: Option<&str>) -> Result<AnnouncementPage> {
+        if limit == 0 {
+            return Err(LighterError::Generic(
+                "Invalid limit 0, must be greater than 0".into(),
+            ));
+        }
+        let offset = match cursor {
+            Some(cursor) => cursor
+                .parse::<usize>()
+                .map_err(|_| LighterError::Generic(format!("Invalid cursor {cursor}")))?,
+            None => 0,
+        };
+
+        let mut announcements = self.announcement().await?.announcements;
+        announcements.sort_by_key(|v| std::cmp::Reverse(v.created_at));
+
+        let total = announcements.len();
+        let announcements = announcements
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .map(AnnouncementEntry::from)
+            .collect::<Vec<_>>();
+        let next_cursor = (offset + limit < total).then(|| (offset + limit).to_string());
+
+        Ok(AnnouncementPage {
+            announcements,
+            next_cursor,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_severity_from_title() {
+        assert_eq!(
+            AnnouncementSeverity::from_title("Scheduled Maintenance"),
+            AnnouncementSeverity::Maintenance
+        );
+        assert_eq!(
+            AnnouncementSeverity::from_title("URGENT: withdrawals paused"),
+            AnnouncementSeverity::Critical
+        );
+        assert_eq!(
+            AnnouncementSeverity::from_title("New market listed"),
+            AnnouncementSeverity::Info
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list() {
+        let mut server = mockito::Server::new_async().await;
+        let announcements = server
+            .mock("GET", "/api/v1/announcement")
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"code":200,"announcements":[
+                    {"title":"New market","content":"a","created_at":1},
+                    {"title":"Maintenance","content":"b","created_at":3},
+                    {"title":"Incident","content":"c","created_at":2}
+                ]}"#,
+            )
+            .expect(2)
+            .create_async()
+            .await;
+
+        let config = LighterConfig {
+            base_url: server.url(),
+            retry_config: None,
+            ..LighterConfig::new()
+        };
+        let api = AnnouncementApi::new(&config).unwrap();
+
+        let page = api.list(2, None).await.unwrap();
+        assert_eq!(
+            page.announcements
+                .iter()
+                .map(|v| v.severity)
+                .collect::<Vec<_>>(),
+            vec![
+                AnnouncementSeverity::Maintenance,
+                AnnouncementSeverity::Critical
+            ]
+        );
+        assert_eq!(page.announcements[0].body, "b");
+
+        let page = api.list(2, page.next_cursor.as_deref()).await.unwrap();
+        assert_eq!(page.announcements.len(), 1);
+        assert_eq!(page.announcements[0].published_at, 1);
+        assert_eq!(page.next_cursor, None);
+        announcements.assert_async().await;
+
+        // rejected before sending
+        assert!(api.list(0, None).await.is_err());
+        assert!(api.list(1, Some("abc")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_list_empty() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/api/v1/announcement")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"announcements":[]}"#)
+            .create_async()
+            .await;
+
+        let config = LighterConfig {
+            base_url: server.url(),
+            retry_config: None,
+            ..LighterConfig::new()
+        };
+        let page = AnnouncementApi::new(&config)
+            .unwrap()
+            .list(10, None)
+            .await
+            .unwrap();
+        assert!(page.announcements.is_empty());
+        assert_eq!(page.next_cursor, None);
+    }
 }
@@ -1,7 +1,9 @@
 use crate::{
+    api::transaction::BlockTxsBy,
     apis::{self, configuration::Configuration},
     config::LighterConfig,
-    models::{Blocks, CurrentHeight},
+    error::LighterError,
+    models::{Block, Blocks, CurrentHeight, Tx},
     Result,
 };
 
@@ -19,6 +21,33 @@ pub enum BlocksSort {
     Desc,
 }
 
+/// Summary of a block, see [`BlockApi::by_height`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockInfo {
+    pub height: i64,
+    pub hash: String, // commitment of the block
+    pub timestamp: i64,
+    pub tx_count: i64,
+}
+
+impl From<&Block> for BlockInfo {
+    fn from(block: &Block) -> Self {
+        Self {
+            height: block.height,
+            hash: block.commitment.clone(),
+            timestamp: block.committed_at,
+            tx_count: block.size.into(),
+        }
+    }
+}
+
+/// Page of the txs of a block, the next one is requested with `next_cursor`
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockTxsPage {
+    pub txs: Vec<Tx>,
+    pub next_cursor: Option<String>,
+}
+
 #[derive(Debug)]
 pub struct BlockApi {
     config: apis::configuration::Configuration,
@@ -26,12 +55,22 @@ pub struct BlockApi {
 
 impl BlockApi {
     pub fn new(config: &LighterConfig) -> Result<Self> {
-        Ok(Self {
-            config: Configuration::try_from(config)?,
-        })
+        Ok(Self::from_configuration(Configuration::try_from(config)?))
+    }
+
+    /// Sends the requests with `config`, e.g. shared with the other APIs, without building one
+    pub fn from_configuration(config: Configuration) -> Self {
+        Self { config }
+    }
+
+    /// Sends the requests with `config` instead of the one built from the `LighterConfig`
+    pub fn with_configuration(mut self, config: Configuration) -> Self {
+        self.config = config;
+        self
     }
 
     /// Get block by its height or commitment
+    #[tracing::instrument(level = "debug", skip_all)]
     pub async fn block(&self, by: BlockBy, value: &str) -> Result<Blocks> {
         let resp = apis::block_api::block(&self.config, &by.to_string(), value)
             .await
@@ -40,7 +79,71 @@ impl BlockApi {
         Ok(resp)
     }
 
+    /// Returns the block at `height`, `None` if it doesn't exist yet
+    #[tracing::instrument(level = "debug", skip_all, fields(height = height))]
+    pub async fn by_height(&self, height: i64) -> Result<Option<BlockInfo>> {
+        check_height(height)?;
+        let resp = self.block(BlockBy::Height, &height.to_string()).await?;
+
+        Ok(resp.blocks.first().map(BlockInfo::from))
+    }
+
+    /// Returns the most recent block
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub async fn latest(&self) -> Result<BlockInfo> {
+        let resp = self.blocks(1, None, Some(BlocksSort::Desc)).await?;
+
+        resp.blocks
+            .first()
+            .map(BlockInfo::from)
+            .ok_or_else(|| LighterError::Generic("No block returned".into()))
+    }
+
+    /// Returns `limit` txs of the block at `height`, starting from `cursor` (the `next_cursor`
+    /// of the previous page). The endpoint isn't paginated: all the txs of the block are
+    /// requested and paged here.
+    #[tracing::instrument(level = "debug", skip_all, fields(height = height))]
+    pub async fn transactions(
+        &self,
+        height: i64,
+        limit: usize,
+        cursor: Option<&str>,
+    ) -> Result<BlockTxsPage> {
+        check_height(height)?;
+        if limit == 0 {
+            return Err(LighterError::Generic(
+                "Invalid limit 0, must be greater than 0".into(),
+            ));
+        }
+        let offset = match cursor {
+            Some(cursor) => cursor
+                .parse::<usize>()
+                .map_err(|_| LighterError::Generic(format!("Invalid cursor {cursor}")))?,
+            None => 0,
+        };
+
+        let resp = apis::transaction_api::block_txs(
+            &self.config,
+            &BlockTxsBy::BlockHeight.to_string(),
+            &height.to_string(),
+        )
+        .await
+        .inspect_err(|e| tracing::error!("unable to call `block_txs`: {e}"))?;
+
+        let total = resp.txs.len();
+        let txs = resp
+            .txs
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .collect::<Vec<_>>();
+        let next_cursor = (offset + limit < total).then(|| (offset + limit).to_string());
+
+        Ok(BlockTxsPage { txs, next_cursor })
+    }
+
     /// Get blocks
+    #[tracing::instrument(level = "debug", skip_all)]
     pub async fn blocks(
         &self,
         limit: i64,
@@ -60,6 +163,7 @@ impl BlockApi {
     }
 
     /// Get current height
+    #[tracing::instrument(level = "debug", skip_all)]
     pub async fn current_height(&self) -> Result<CurrentHeight> {
         let resp = apis::block_api::current_height(&self.config)
             .await
@@ -68,3 +172,135 @@ impl BlockApi {
         Ok(resp)
     }
 }
+
+fn check_height(height: i64) -> Result<()> {
+    if height < 0 {
+        return Err(LighterError::Generic(format!(
+            "Invalid block height {height}, must not be negative"
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(height: i64, size: i32) -> serde_json::Value {
+        serde_json::to_value(Block {
+            height,
+            commitment: format!("0x{height}"),
+            committed_at: 1000 + height,
+            size,
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    fn test_config(url: String) -> LighterConfig {
+        LighterConfig {
+            base_url: url,
+            retry_config: None,
+            ..LighterConfig::new()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_by_height() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/api/v1/block")
+            .match_query(mockito::Matcher::UrlEncoded("value".into(), "7".into()))
+            .with_header("content-type", "application/json")
+            .with_body(
+                serde_json::json!({"code": 200, "total": 1, "blocks": [block(7, 3)]}).to_string(),
+            )
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/api/v1/block")
+            .match_query(mockito::Matcher::UrlEncoded("value".into(), "8".into()))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"total":0,"blocks":[]}"#)
+            .create_async()
+            .await;
+        let api = BlockApi::new(&test_config(server.url())).unwrap();
+
+        assert_eq!(
+            api.by_height(7).await.unwrap(),
+            Some(BlockInfo {
+                height: 7,
+                hash: "0x7".into(),
+                timestamp: 1007,
+                tx_count: 3,
+            })
+        );
+        assert_eq!(api.by_height(8).await.unwrap(), None);
+
+        // rejected before sending
+        let res = api.by_height(-1).await;
+        assert!(matches!(res, Err(LighterError::Generic(e)) if e.contains("height")));
+    }
+
+    #[tokio::test]
+    async fn test_latest() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/api/v1/blocks")
+            .match_query(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::UrlEncoded("limit".into(), "1".into()),
+                mockito::Matcher::UrlEncoded("sort".into(), "desc".into()),
+            ]))
+            .with_header("content-type", "application/json")
+            .with_body(
+                serde_json::json!({"code": 200, "total": 1, "blocks": [block(9, 0)]}).to_string(),
+            )
+            .create_async()
+            .await;
+        let api = BlockApi::new(&test_config(server.url())).unwrap();
+
+        assert_eq!(api.latest().await.unwrap().height, 9);
+    }
+
+    #[tokio::test]
+    async fn test_transactions() {
+        let mut server = mockito::Server::new_async().await;
+        let txs = (0..3)
+            .map(|i| {
+                serde_json::to_value(Tx {
+                    hash: format!("0x{i}"),
+                    block_height: 7,
+                    ..Default::default()
+                })
+                .unwrap()
+            })
+            .collect::<Vec<_>>();
+        server
+            .mock("GET", "/api/v1/blockTxs")
+            .match_query(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::UrlEncoded("by".into(), "block_height".into()),
+                mockito::Matcher::UrlEncoded("value".into(), "7".into()),
+            ]))
+            .with_header("content-type", "application/json")
+            .with_body(serde_json::json!({"code": 200, "txs": txs}).to_string())
+            .expect(2)
+            .create_async()
+            .await;
+        let api = BlockApi::new(&test_config(server.url())).unwrap();
+
+        let page = api.transactions(7, 2, None).await.unwrap();
+        assert_eq!(page.txs.len(), 2);
+        assert_eq!(page.next_cursor.as_deref(), Some("2"));
+
+        let page = api
+            .transactions(7, 2, page.next_cursor.as_deref())
+            .await
+            .unwrap();
+        assert_eq!(page.txs[0].hash, "0x2");
+        assert_eq!(page.next_cursor, None);
+
+        // rejected before sending
+        assert!(api.transactions(-1, 2, None).await.is_err());
+        assert!(api.transactions(7, 0, None).await.is_err());
+    }
+}
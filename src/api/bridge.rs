@@ -1,23 +1,249 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+use tokio::time::Instant;
+
 use crate::{
+    api::transaction::{is_tx_not_found, TxStatus},
+    api::transaction::{DepositHistoryFilter, WithdrawHistoryFilter},
     apis::{self, configuration::Configuration},
     config::LighterConfig,
-    models::RespGetFastBridgeInfo,
-    Result,
+    error::LighterError,
+    models::{
+        deposit_history_item, withdraw_history_item, DepositHistoryItem, EnrichedTx,
+        RespGetFastBridgeInfo, WithdrawHistoryItem,
+    },
+    AccountIndex, Result,
 };
 
+#[cfg(feature = "signer")]
+use std::sync::Arc;
+
+#[cfg(feature = "signer")]
+use crate::{
+    client::TxClient,
+    models::RespSendTx,
+    signer::{data::SignWithdrawData, FFISigner},
+};
+
+/// Field identifying a deposit or a withdraw, see [`BridgeApi::withdraw_status`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BridgeTxBy {
+    Id,
+    L1TxHash,
+    /// Hash of the L2 tx returned by [`BridgeApi::withdraw`], only for the withdraws
+    TxHash,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BridgeDirection {
+    Deposit,
+    Withdraw,
+}
+
+/// Status of a deposit or a withdraw
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BridgeStatus {
+    Pending,
+    /// Processed by Lighter, waiting to be claimed on L1
+    Claimable,
+    Confirmed,
+    /// Failed or refunded
+    Failed,
+}
+
+impl BridgeStatus {
+    pub fn is_pending(&self) -> bool {
+        matches!(self, Self::Pending)
+    }
+}
+
+impl From<&deposit_history_item::Status> for BridgeStatus {
+    fn from(status: &deposit_history_item::Status) -> Self {
+        use deposit_history_item::Status;
+        match status {
+            Status::Pending => Self::Pending,
+            Status::Claimable => Self::Claimable,
+            Status::Completed => Self::Confirmed,
+            Status::Failed => Self::Failed,
+        }
+    }
+}
+
+impl From<&withdraw_history_item::Status> for BridgeStatus {
+    fn from(status: &withdraw_history_item::Status) -> Self {
+        use withdraw_history_item::Status;
+        match status {
+            Status::Pending => Self::Pending,
+            Status::Claimable => Self::Claimable,
+            Status::Completed => Self::Confirmed,
+            Status::Failed | Status::Refunded => Self::Failed,
+        }
+    }
+}
+
+/// Deposit or withdraw between L1 and Lighter
+#[derive(Debug, Clone, PartialEq)]
+pub struct BridgeTransfer {
+    pub id: String,
+    pub l1_tx_hash: String,
+    pub direction: BridgeDirection,
+    pub status: BridgeStatus,
+    pub amount: f64,
+    pub timestamp: i64,
+}
+
+impl BridgeTransfer {
+    fn matches(&self, by: BridgeTxBy, value: &str) -> bool {
+        match by {
+            BridgeTxBy::Id => self.id == value,
+            BridgeTxBy::L1TxHash => self.l1_tx_hash.eq_ignore_ascii_case(value),
+            BridgeTxBy::TxHash => false, // not in the history, see `BridgeApi::withdraw_status`
+        }
+    }
+}
+
+/// Fields of the withdraw tx info needed to find the withdraw in the history
+#[derive(Debug, Deserialize)]
+struct WithdrawTxInfo {
+    #[serde(rename = "USDCAmount")]
+    usdc_amount: i64,
+}
+
+impl WithdrawTxInfo {
+    /// Returns `true` if `transfer` can be the withdraw requested by this tx, queued at
+    /// `queued_at`: the history has no L2 tx hash, so the withdraws are matched by amount
+    fn matches(&self, transfer: &BridgeTransfer, queued_at: i64) -> bool {
+        (transfer.amount * 1e6).round() as i64 == self.usdc_amount
+            && transfer.timestamp >= queued_at
+    }
+}
+
+impl TryFrom<&DepositHistoryItem> for BridgeTransfer {
+    type Error = LighterError;
+
+    fn try_from(item: &DepositHistoryItem) -> Result<Self> {
+        Ok(Self {
+            id: item.id.clone(),
+            l1_tx_hash: item.l1_tx_hash.clone(),
+            direction: BridgeDirection::Deposit,
+            status: BridgeStatus::from(&item.status),
+            amount: item
+                .amount
+                .parse()
+                .map_err(|_| LighterError::Generic(format!("Invalid deposit {item:?}")))?,
+            timestamp: item.timestamp,
+        })
+    }
+}
+
+impl TryFrom<&WithdrawHistoryItem> for BridgeTransfer {
+    type Error = LighterError;
+
+    fn try_from(item: &WithdrawHistoryItem) -> Result<Self> {
+        Ok(Self {
+            id: item.id.clone(),
+            l1_tx_hash: item.l1_tx_hash.clone(),
+            direction: BridgeDirection::Withdraw,
+            status: BridgeStatus::from(&item.status),
+            amount: item
+                .amount
+                .parse()
+                .map_err(|_| LighterError::Generic(format!("Invalid withdraw {item:?}")))?,
+            timestamp: item.timestamp,
+        })
+    }
+}
+
 #[derive(Debug)]
 pub struct BridgeApi {
     config: apis::configuration::Configuration,
+    #[cfg(feature = "signer")]
+    signer: Option<FFISigner>, // creates the auth tokens
+    #[cfg(feature = "signer")]
+    tx_client: Option<Arc<TxClient>>, // needed only for the withdraws
 }
 
 impl BridgeApi {
     pub fn new(config: &LighterConfig) -> Result<Self> {
+        Self::new_with_configuration(config, Configuration::try_from(config)?)
+    }
+
+    /// Same as [`BridgeApi::new`], sending the requests with `configuration` (e.g. shared with the
+    /// other APIs) instead of building one
+    #[cfg_attr(not(feature = "signer"), allow(unused_variables))] // `config` creates the signer
+    pub fn new_with_configuration(
+        config: &LighterConfig,
+        configuration: Configuration,
+    ) -> Result<Self> {
         Ok(Self {
-            config: Configuration::try_from(config)?,
+            config: configuration,
+            #[cfg(feature = "signer")]
+            signer: match config.api_key_private {
+                Some(_) => Some(FFISigner::try_from(config)?),
+                None => None,
+            },
+            #[cfg(feature = "signer")]
+            tx_client: None,
         })
     }
 
+    /// Sends the requests with `config` instead of the one built from the `LighterConfig`
+    pub fn with_configuration(mut self, config: Configuration) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Creates the auth tokens with `signer`, sharing its cached token with the other APIs
+    #[cfg(feature = "signer")]
+    pub(crate) fn with_signer(mut self, signer: Option<FFISigner>) -> Self {
+        self.signer = signer;
+        self
+    }
+
+    #[cfg(feature = "signer")]
+    pub(crate) fn with_tx_client(mut self, tx_client: Option<Arc<TxClient>>) -> Self {
+        self.tx_client = tx_client;
+        self
+    }
+
+    /// Sign and submit a withdraw of `usdc_amount` (with 6 decimals) from the account of the
+    /// config to its L1 address. The native signer only supports the USDC withdraws.
+    ///
+    /// The withdraw can be followed with [`BridgeApi::withdraw_status`] and
+    /// [`BridgeApi::wait_for_withdraw`], by [`BridgeTxBy::TxHash`] with the returned `tx_hash`.
+    #[cfg(feature = "signer")]
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub async fn withdraw(&self, usdc_amount: i64) -> Result<RespSendTx> {
+        if usdc_amount <= 0 {
+            return Err(LighterError::Generic(format!(
+                "Invalid USDC amount {usdc_amount}, expected greater than 0"
+            )));
+        }
+
+        self.tx_client
+            .as_deref()
+            .ok_or_else(|| LighterError::Config("`api_key_private` is not set".into()))?
+            .submit(|signer, nonce| signer.sign_withdraw(SignWithdrawData { usdc_amount }, nonce))
+            .await
+            .inspect_err(|e| tracing::error!("unable to call `withdraw`: {e}"))
+    }
+
+    #[cfg(feature = "signer")]
+    fn auth_token(&self) -> Result<String> {
+        self.signer
+            .as_ref()
+            .ok_or_else(|| LighterError::Config("`api_key_private` is not set".into()))?
+            .get_auth_token(None)
+    }
+
+    #[cfg(not(feature = "signer"))]
+    fn auth_token(&self) -> Result<String> {
+        Err(LighterError::signer_disabled("Creating the auth token"))
+    }
+
     /// Get fast bridge info
+    #[tracing::instrument(level = "debug", skip_all)]
     pub async fn fastbridge_info(&self) -> Result<RespGetFastBridgeInfo> {
         let resp = apis::bridge_api::fastbridge_info(&self.config)
             .await
@@ -25,4 +251,571 @@ impl BridgeApi {
 
         Ok(resp)
     }
+
+    /// Looks up the deposit of the account in its history, `None` if it's not indexed yet (e.g.
+    /// right after the L1 tx). The auth token is created with the API key of the config, so it
+    /// must belong to `account_index`.
+    #[tracing::instrument(level = "debug", skip_all, fields(account_index = tracing::field::Empty))]
+    pub async fn deposit_status(
+        &self,
+        account_index: impl Into<AccountIndex>,
+        l1_address: &str,
+        by: BridgeTxBy,
+        value: &str,
+    ) -> Result<Option<BridgeTransfer>> {
+        let account_index = account_index.into().0;
+        tracing::Span::current().record("account_index", account_index);
+        if by == BridgeTxBy::TxHash {
+            return Err(LighterError::Generic(
+                "The deposits can't be looked up by L2 tx hash".into(),
+            ));
+        }
+        let auth_token = self.auth_token()?;
+
+        let mut cursor: Option<String> = None;
+        loop {
+            let resp = apis::transaction_api::deposit_history(
+                &self.config,
+                account_index,
+                l1_address,
+                Some(&auth_token),
+                None,
+                cursor.as_deref(),
+                Some(&DepositHistoryFilter::All.to_string()),
+            )
+            .await
+            .inspect_err(|e| tracing::error!("unable to call `deposit_history`: {e}"))?;
+
+            for item in &resp.deposits {
+                let transfer = BridgeTransfer::try_from(item)?;
+                if transfer.matches(by, value) {
+                    return Ok(Some(transfer));
+                }
+            }
+            if resp.deposits.is_empty() || resp.cursor.is_empty() {
+                return Ok(None);
+            }
+            cursor = Some(resp.cursor);
+        }
+    }
+
+    /// Looks up the withdraw of the account in its history, `None` if it's not indexed yet (e.g.
+    /// right after [`BridgeApi::withdraw`]). The auth token is created with the API key of the
+    /// config, so it must belong to `account_index`.
+    ///
+    /// With [`BridgeTxBy::TxHash`], the L2 tx is looked up first: a failed tx is returned as a
+    /// failed withdraw, and once executed, the withdraw is the first one of the history with
+    /// the same amount, requested after the tx was queued.
+    #[tracing::instrument(level = "debug", skip_all, fields(account_index = tracing::field::Empty))]
+    pub async fn withdraw_status(
+        &self,
+        account_index: impl Into<AccountIndex>,
+        by: BridgeTxBy,
+        value: &str,
+    ) -> Result<Option<BridgeTransfer>> {
+        let account_index = account_index.into().0;
+        tracing::Span::current().record("account_index", account_index);
+        let auth_token = self.auth_token()?;
+
+        let tx = match by {
+            BridgeTxBy::TxHash => {
+                let Some(tx) = self.withdraw_tx(value).await? else {
+                    return Ok(None);
+                };
+                let info = serde_json::from_str::<WithdrawTxInfo>(&tx.info)
+                    .map_err(|_| LighterError::Generic(format!("Invalid withdraw tx {tx:?}")))?;
+                match TxStatus::from(&tx) {
+                    TxStatus::Pending => return Ok(None),
+                    TxStatus::Failed { .. } => {
+                        return Ok(Some(BridgeTransfer {
+                            id: tx.hash,
+                            l1_tx_hash: String::new(),
+                            direction: BridgeDirection::Withdraw,
+                            status: BridgeStatus::Failed,
+                            amount: info.usdc_amount as f64 / 1e6,
+                            timestamp: tx.queued_at,
+                        }));
+                    }
+                    TxStatus::Included { .. } => Some((info, tx.queued_at)),
+                }
+            }
+            _ => None,
+        };
+
+        // the history is sorted from the latest withdraw, so with a tx, the earliest match
+        // requested after it wins
+        let mut found = None;
+        let mut cursor: Option<String> = None;
+        loop {
+            let resp = apis::transaction_api::withdraw_history(
+                &self.config,
+                account_index,
+                Some(&auth_token),
+                None,
+                cursor.as_deref(),
+                Some(&WithdrawHistoryFilter::All.to_string()),
+            )
+            .await
+            .inspect_err(|e| tracing::error!("unable to call `withdraw_history`: {e}"))?;
+
+            for item in &resp.withdraws {
+                let transfer = BridgeTransfer::try_from(item)?;
+                match &tx {
+                    Some((info, queued_at)) => {
+                        if transfer.timestamp < *queued_at {
+                            return Ok(found);
+                        }
+                        if info.matches(&transfer, *queued_at) {
+                            found = Some(transfer);
+                        }
+                    }
+                    None if transfer.matches(by, value) => return Ok(Some(transfer)),
+                    None => {}
+                }
+            }
+            if resp.withdraws.is_empty() || resp.cursor.is_empty() {
+                return Ok(found);
+            }
+            cursor = Some(resp.cursor);
+        }
+    }
+
+    /// Gets the withdraw tx, `None` if it's not indexed yet right after being sent
+    async fn withdraw_tx(&self, tx_hash: &str) -> Result<Option<EnrichedTx>> {
+        match apis::transaction_api::tx(&self.config, "hash", tx_hash).await {
+            Ok(tx) => Ok(Some(tx)),
+            Err(e) => match LighterError::from(e) {
+                e if is_tx_not_found(&e) => Ok(None),
+                e => {
+                    tracing::error!("unable to call `tx`: {e}");
+                    Err(e)
+                }
+            },
+        }
+    }
+
+    /// Polls the withdraw until it's claimable, confirmed or failed, backing off between the
+    /// attempts. Returns the last status seen once `timeout` elapses, `None` if it was never
+    /// indexed.
+    #[tracing::instrument(level = "debug", skip_all, fields(account_index = tracing::field::Empty))]
+    pub async fn wait_for_withdraw(
+        &self,
+        account_index: impl Into<AccountIndex>,
+        by: BridgeTxBy,
+        value: &str,
+        timeout: Duration,
+    ) -> Result<Option<BridgeTransfer>> {
+        let account_index = account_index.into();
+        tracing::Span::current().record("account_index", account_index.0);
+        const MIN_INTERVAL: Duration = Duration::from_millis(500);
+        const MAX_INTERVAL: Duration = Duration::from_secs(10);
+
+        let deadline = Instant::now() + timeout;
+        let mut interval = MIN_INTERVAL;
+        loop {
+            let transfer = self.withdraw_status(account_index, by, value).await?;
+
+            let now = Instant::now();
+            let done = transfer.as_ref().is_some_and(|v| !v.status.is_pending());
+            if done || now >= deadline {
+                return Ok(transfer);
+            }
+
+            tokio::time::sleep(interval.min(deadline - now)).await;
+            interval = (interval * 2).min(MAX_INTERVAL);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bridge_transfer_from_history() {
+        let item = WithdrawHistoryItem {
+            id: "w1".into(),
+            amount: "12.5".into(),
+            timestamp: 1000,
+            status: withdraw_history_item::Status::Refunded,
+            r#type: withdraw_history_item::Type::Secure,
+            l1_tx_hash: "0xABC".into(),
+        };
+        let transfer = BridgeTransfer::try_from(&item).unwrap();
+        assert_eq!(transfer.status, BridgeStatus::Failed);
+        assert_eq!(transfer.amount, 12.5);
+        assert!(transfer.matches(BridgeTxBy::L1TxHash, "0xabc"));
+        assert!(!transfer.matches(BridgeTxBy::Id, "w2"));
+
+        let item = DepositHistoryItem {
+            id: "d1".into(),
+            amount: "abc".into(),
+            timestamp: 1000,
+            status: deposit_history_item::Status::Claimable,
+            l1_tx_hash: "0x1".into(),
+        };
+        assert!(BridgeTransfer::try_from(&item).is_err());
+        assert_eq!(
+            BridgeStatus::from(&deposit_history_item::Status::Claimable),
+            BridgeStatus::Claimable
+        );
+        assert!(!BridgeStatus::Claimable.is_pending());
+    }
+
+    #[cfg(not(feature = "signer"))]
+    #[tokio::test]
+    async fn test_withdraw_status_signer_disabled() {
+        let api = BridgeApi::new(&LighterConfig::new()).unwrap();
+        let res = api.withdraw_status(28, BridgeTxBy::Id, "w1").await;
+        assert!(matches!(res, Err(LighterError::Config(e)) if e.contains("`signer` feature")));
+    }
+
+    #[cfg(feature = "signer")]
+    #[tokio::test]
+    async fn test_withdraw() {
+        let mut server = mockito::Server::new_async().await;
+        let send_tx = server
+            .mock("POST", "/api/v1/sendTx")
+            .match_body(mockito::Matcher::Regex(
+                r#"name="tx_type"\r\n\r\n13\r\n"#.into(),
+            ))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"tx_hash":"0x13","predicted_execution_time_ms":1}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/api/v1/nextNonce")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"nonce":5}"#)
+            .create_async()
+            .await;
+
+        let config = LighterConfig {
+            base_url: server.url(),
+            local_nonce: false,
+            ..LighterConfig::new()
+        }
+        .with_api_key_private(
+            "01db9eed031d59d6bd0ee00ee5a7dc1f62087bf217b51caea57eb6e17a02c49e0a748d2f155a2f60",
+        )
+        .with_api_key_index(2)
+        .with_account_index(28);
+        let api = BridgeApi::new(&config)
+            .unwrap()
+            .with_tx_client(Some(Arc::new(TxClient::new(&config, None).unwrap())));
+
+        let resp = api.withdraw(1_000_000).await.unwrap();
+        assert_eq!(resp.tx_hash, "0x13");
+        send_tx.assert_async().await;
+
+        // rejected before signing
+        for usdc_amount in [0, -1] {
+            let res = api.withdraw(usdc_amount).await;
+            assert!(matches!(res, Err(LighterError::Generic(e)) if e.contains("USDC amount")));
+        }
+
+        // without a tx client
+        let api = BridgeApi::new(&config).unwrap();
+        let res = api.withdraw(1_000_000).await;
+        assert!(matches!(res, Err(LighterError::Config(_))));
+    }
+
+    #[cfg(feature = "signer")]
+    #[tokio::test]
+    async fn test_withdraw_until_claimable() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/api/v1/sendTx")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"tx_hash":"0x13","predicted_execution_time_ms":1}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/api/v1/nextNonce")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"nonce":5}"#)
+            .create_async()
+            .await;
+        // not indexed right after being sent, then executed
+        server
+            .mock("GET", "/api/v1/tx")
+            .match_query(mockito::Matcher::UrlEncoded("value".into(), "0x13".into()))
+            .with_status(400)
+            .with_body(r#"{"code":21500,"message":"transaction not found"}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/api/v1/tx")
+            .match_query(mockito::Matcher::UrlEncoded("value".into(), "0x13".into()))
+            .with_header("content-type", "application/json")
+            .with_body(
+                serde_json::to_string(&EnrichedTx {
+                    code: 200,
+                    hash: "0x13".into(),
+                    r#type: 13,
+                    info: r#"{"FromAccountIndex":28,"USDCAmount":1000000,"Nonce":5}"#.into(),
+                    status: 2,
+                    queued_at: 1000,
+                    ..Default::default()
+                })
+                .unwrap(),
+            )
+            .create_async()
+            .await;
+        let page = |status: &str| {
+            serde_json::json!({
+                "code": 200,
+                "withdraws": [{
+                    "id": "w1",
+                    "amount": "1",
+                    "timestamp": 1001,
+                    "status": status,
+                    "type": "secure",
+                    "l1_tx_hash": "",
+                }],
+                "cursor": "",
+            })
+            .to_string()
+        };
+        // not in the history yet, then pending, then claimable
+        let empty = server
+            .mock("GET", "/api/v1/withdraw/history")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"withdraws":[],"cursor":""}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let pending = server
+            .mock("GET", "/api/v1/withdraw/history")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(page("pending"))
+            .expect(1)
+            .create_async()
+            .await;
+        let claimable = server
+            .mock("GET", "/api/v1/withdraw/history")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(page("claimable"))
+            .expect(1)
+            .create_async()
+            .await;
+
+        let config = LighterConfig {
+            base_url: server.url(),
+            local_nonce: false,
+            retry_config: None,
+            ..LighterConfig::new()
+        }
+        .with_api_key_private(
+            "01db9eed031d59d6bd0ee00ee5a7dc1f62087bf217b51caea57eb6e17a02c49e0a748d2f155a2f60",
+        )
+        .with_api_key_index(2)
+        .with_account_index(28);
+        let api = BridgeApi::new(&config)
+            .unwrap()
+            .with_tx_client(Some(Arc::new(TxClient::new(&config, None).unwrap())));
+
+        let resp = api.withdraw(1_000_000).await.unwrap();
+        let transfer = api
+            .wait_for_withdraw(
+                28,
+                BridgeTxBy::TxHash,
+                &resp.tx_hash,
+                Duration::from_secs(10),
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(transfer.id, "w1");
+        assert_eq!(transfer.status, BridgeStatus::Claimable);
+        assert_eq!(transfer.amount, 1.0);
+        // returned once claimable, without waiting for the claim
+        empty.assert_async().await;
+        pending.assert_async().await;
+        claimable.assert_async().await;
+    }
+
+    #[cfg(feature = "signer")]
+    #[tokio::test]
+    async fn test_withdraw_status_by_tx_hash() {
+        let mut server = mockito::Server::new_async().await;
+        let tx = |hash: &str, status: i64| {
+            serde_json::to_string(&EnrichedTx {
+                code: 200,
+                hash: hash.into(),
+                r#type: 13,
+                info: r#"{"FromAccountIndex":28,"USDCAmount":10000000,"Nonce":5}"#.into(),
+                status,
+                queued_at: 1000,
+                ..Default::default()
+            })
+            .unwrap()
+        };
+        for (hash, status) in [
+            ("0x1", 2),
+            ("0x2", TxStatus::FAILED),
+            ("0x3", TxStatus::PENDING),
+        ] {
+            server
+                .mock("GET", "/api/v1/tx")
+                .match_query(mockito::Matcher::UrlEncoded("value".into(), hash.into()))
+                .with_header("content-type", "application/json")
+                .with_body(tx(hash, status))
+                .create_async()
+                .await;
+        }
+        server
+            .mock("GET", "/api/v1/tx")
+            .match_query(mockito::Matcher::UrlEncoded("value".into(), "0x4".into()))
+            .with_status(400)
+            .with_body(r#"{"code":21500,"message":"transaction not found"}"#)
+            .create_async()
+            .await;
+        let item = |id: &str, amount: &str, timestamp: i64| {
+            serde_json::json!({
+                "id": id,
+                "amount": amount,
+                "timestamp": timestamp,
+                "status": "claimable",
+                "type": "secure",
+                "l1_tx_hash": "",
+            })
+        };
+        // latest first, the withdraws before the tx are not looked at
+        server
+            .mock("GET", "/api/v1/withdraw/history")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(
+                serde_json::json!({
+                    "code": 200,
+                    "withdraws": [
+                        item("w4", "10", 1200),
+                        item("w3", "5", 1100),
+                        item("w2", "10", 1000),
+                        item("w1", "10", 900),
+                    ],
+                    "cursor": "",
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let config = LighterConfig {
+            base_url: server.url(),
+            retry_config: None,
+            ..LighterConfig::new()
+        }
+        .with_api_key_private(
+            "01db9eed031d59d6bd0ee00ee5a7dc1f62087bf217b51caea57eb6e17a02c49e0a748d2f155a2f60",
+        )
+        .with_api_key_index(2)
+        .with_account_index(28);
+        let api = BridgeApi::new(&config).unwrap();
+
+        let transfer = api
+            .withdraw_status(28, BridgeTxBy::TxHash, "0x1")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(transfer.id, "w2");
+        assert_eq!(transfer.status, BridgeStatus::Claimable);
+
+        let transfer = api
+            .withdraw_status(28, BridgeTxBy::TxHash, "0x2")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(transfer.status, BridgeStatus::Failed);
+        assert_eq!(transfer.amount, 10.0);
+
+        // pending or not indexed yet
+        for hash in ["0x3", "0x4"] {
+            let res = api.withdraw_status(28, BridgeTxBy::TxHash, hash).await;
+            assert_eq!(res.unwrap(), None);
+        }
+
+        let res = api
+            .deposit_status(28, "0x0", BridgeTxBy::TxHash, "0x1")
+            .await;
+        assert!(matches!(res, Err(LighterError::Generic(_))));
+    }
+
+    #[cfg(feature = "signer")]
+    #[tokio::test]
+    async fn test_wait_for_withdraw() {
+        let mut server = mockito::Server::new_async().await;
+        let page = |status: &str, cursor: &str| {
+            serde_json::json!({
+                "code": 200,
+                "withdraws": [{
+                    "id": "w1",
+                    "amount": "10",
+                    "timestamp": 1000,
+                    "status": status,
+                    "type": "secure",
+                    "l1_tx_hash": "0x1",
+                }],
+                "cursor": cursor,
+            })
+            .to_string()
+        };
+        // first indexed as pending, then completed
+        server
+            .mock("GET", "/api/v1/withdraw/history")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"withdraws":[],"cursor":""}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/api/v1/withdraw/history")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(page("pending", ""))
+            .expect(1)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/api/v1/withdraw/history")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(page("completed", ""))
+            .create_async()
+            .await;
+
+        let config = LighterConfig {
+            base_url: server.url(),
+            retry_config: None,
+            ..LighterConfig::new()
+        }
+        .with_api_key_private(
+            "01db9eed031d59d6bd0ee00ee5a7dc1f62087bf217b51caea57eb6e17a02c49e0a748d2f155a2f60",
+        )
+        .with_api_key_index(2)
+        .with_account_index(28);
+        let api = BridgeApi::new(&config).unwrap();
+
+        assert_eq!(
+            api.withdraw_status(28, BridgeTxBy::Id, "w1").await.unwrap(),
+            None
+        );
+        let transfer = api
+            .wait_for_withdraw(28, BridgeTxBy::Id, "w1", Duration::from_secs(5))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(transfer.status, BridgeStatus::Confirmed);
+        assert_eq!(transfer.direction, BridgeDirection::Withdraw);
+    }
 }
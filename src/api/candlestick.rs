@@ -1,8 +1,8 @@
 use crate::{
     apis::{self, configuration::Configuration},
     config::LighterConfig,
-    models::{Candlesticks, Fundings},
-    Result,
+    models::{Candlestick, Candlesticks, Fundings},
+    LighterError, MarketIndex, Result,
 };
 
 #[derive(Debug, Clone, Copy, strum::Display, PartialEq, Eq)]
@@ -25,6 +25,119 @@ pub enum CandlestickInterval {
     OneWeek,
 }
 
+impl CandlestickInterval {
+    /// Duration of a candle
+    pub fn as_millis(self) -> i64 {
+        const MINUTE: i64 = 60_000;
+        match self {
+            Self::OneMinute => MINUTE,
+            Self::FiveMinutes => 5 * MINUTE,
+            Self::FifteenMinutes => 15 * MINUTE,
+            Self::ThirtyMinutes => 30 * MINUTE,
+            Self::OneHour => 60 * MINUTE,
+            Self::FourHours => 4 * 60 * MINUTE,
+            Self::OneDay => 24 * 60 * MINUTE,
+            Self::OneWeek => 7 * 24 * 60 * MINUTE,
+        }
+    }
+}
+
+/// Highest start and end timestamp of [`CandlestickApi::candles`], in milliseconds
+pub const MAX_CANDLE_TIMESTAMP: i64 = 5_000_000_000_000;
+
+/// OHLCV candle of [`CandlestickApi::candles`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Candle {
+    /// Start of the candle, in milliseconds
+    pub open_time: i64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    /// Traded size, in base asset
+    pub volume: f64,
+}
+
+impl Candle {
+    /// Candle without trades, flat at `price`
+    fn flat(open_time: i64, price: f64) -> Self {
+        Self {
+            open_time,
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            volume: 0.0,
+        }
+    }
+}
+
+impl From<&Candlestick> for Candle {
+    fn from(val: &Candlestick) -> Self {
+        Self {
+            open_time: val.timestamp,
+            open: val.open,
+            high: val.high,
+            low: val.low,
+            close: val.close,
+            volume: val.volume0,
+        }
+    }
+}
+
+fn check_candles_range(resolution: CandlestickInterval, start: i64, end: i64) -> Result<()> {
+    if matches!(
+        resolution,
+        CandlestickInterval::ThirtyMinutes | CandlestickInterval::OneWeek
+    ) {
+        return Err(LighterError::Generic(format!(
+            "Invalid candle resolution {resolution}, must be one of 1m, 5m, 15m, 1h, 4h or 1d"
+        )));
+    }
+    for timestamp in [start, end] {
+        if !(0..=MAX_CANDLE_TIMESTAMP).contains(&timestamp) {
+            return Err(LighterError::Generic(format!(
+                "Invalid candle timestamp {timestamp}, must be between 0 and {MAX_CANDLE_TIMESTAMP}"
+            )));
+        }
+    }
+    if start >= end {
+        return Err(LighterError::Generic(format!(
+            "Invalid candle range, the start {start} must be before the end {end}"
+        )));
+    }
+    Ok(())
+}
+
+/// Sorts the candles by open time and drops the duplicates, inserting the flat candles of the
+/// intervals without trades if `fill_gaps`
+fn to_candles(candlesticks: &[Candlestick], interval: i64, fill_gaps: bool) -> Vec<Candle> {
+    let mut sorted = candlesticks.iter().map(Candle::from).collect::<Vec<_>>();
+    sorted.sort_by_key(|v| v.open_time);
+    sorted.dedup_by_key(|v| v.open_time);
+    if !fill_gaps {
+        return sorted;
+    }
+
+    let mut candles = Vec::with_capacity(sorted.len());
+    for candle in sorted {
+        if let Some(previous) = candles.last().copied() {
+            let Candle {
+                mut open_time,
+                close,
+                ..
+            } = previous;
+            open_time += interval;
+            while open_time < candle.open_time {
+                candles.push(Candle::flat(open_time, close));
+                open_time += interval;
+            }
+        }
+        candles.push(candle);
+    }
+    candles
+}
+
 #[derive(Debug, Clone, Copy, strum::Display, PartialEq, Eq)]
 pub enum FundingInterval {
     #[strum(to_string = "1h")]
@@ -33,6 +146,17 @@ pub enum FundingInterval {
     OneDay,
 }
 
+impl FundingInterval {
+    /// Duration of a funding period
+    pub fn as_millis(self) -> i64 {
+        const HOUR: i64 = 3_600_000;
+        match self {
+            Self::OneHour => HOUR,
+            Self::OneDay => 24 * HOUR,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct CandlestickApi {
     config: apis::configuration::Configuration,
@@ -40,12 +164,22 @@ pub struct CandlestickApi {
 
 impl CandlestickApi {
     pub fn new(config: &LighterConfig) -> Result<Self> {
-        Ok(Self {
-            config: Configuration::try_from(config)?,
-        })
+        Ok(Self::from_configuration(Configuration::try_from(config)?))
+    }
+
+    /// Sends the requests with `config`, e.g. shared with the other APIs, without building one
+    pub fn from_configuration(config: Configuration) -> Self {
+        Self { config }
+    }
+
+    /// Sends the requests with `config` instead of the one built from the `LighterConfig`
+    pub fn with_configuration(mut self, config: Configuration) -> Self {
+        self.config = config;
+        self
     }
 
     /// Get candlesticks
+    #[tracing::instrument(level = "debug", skip_all, fields(market_index = market_id))]
     pub async fn candlesticks(
         &self,
         market_id: i32,
@@ -72,7 +206,44 @@ impl CandlestickApi {
         Ok(resp)
     }
 
+    /// Get the candles opened between `start_timestamp` and `end_timestamp` (in milliseconds),
+    /// sorted by open time. With `fill_gaps` the intervals without trades get a flat candle at
+    /// the previous close, so that the open times are evenly spaced (the candles before the first
+    /// trade of the range are still missing).
+    ///
+    /// The range is rejected when `start_timestamp` isn't before `end_timestamp`, when one of
+    /// them is above [`MAX_CANDLE_TIMESTAMP`] or when `resolution` isn't served (30m and 1w).
+    #[tracing::instrument(level = "debug", skip_all, fields(market_index = tracing::field::Empty))]
+    pub async fn candles(
+        &self,
+        market_index: impl Into<MarketIndex>,
+        resolution: CandlestickInterval,
+        start_timestamp: i64,
+        end_timestamp: i64,
+        fill_gaps: bool,
+    ) -> Result<Vec<Candle>> {
+        let market_index = market_index.into().0;
+        tracing::Span::current().record("market_index", market_index);
+        check_candles_range(resolution, start_timestamp, end_timestamp)?;
+
+        let interval = resolution.as_millis();
+        let count_back = (end_timestamp - start_timestamp) / interval + 1;
+        let resp = self
+            .candlesticks(
+                market_index,
+                resolution,
+                start_timestamp,
+                end_timestamp,
+                count_back,
+                None,
+            )
+            .await?;
+
+        Ok(to_candles(&resp.candlesticks, interval, fill_gaps))
+    }
+
     /// Get fundings
+    #[tracing::instrument(level = "debug", skip_all, fields(market_index = market_id))]
     pub async fn fundings(
         &self,
         market_id: i32,
@@ -97,3 +268,129 @@ impl CandlestickApi {
         Ok(resp)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: i64 = 60_000;
+
+    fn candlestick(timestamp: i64, open: f64, close: f64) -> Candlestick {
+        Candlestick::new(
+            timestamp,
+            open,
+            open.max(close) + 1.0,
+            open.min(close) - 1.0,
+            close,
+            2.5,
+            2.5 * close,
+            1,
+        )
+    }
+
+    fn config(base_url: String) -> LighterConfig {
+        LighterConfig {
+            base_url,
+            ..LighterConfig::new()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_candles_decode() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/api/v1/candlesticks")
+            .match_query(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::UrlEncoded("market_id".into(), "1".into()),
+                mockito::Matcher::UrlEncoded("resolution".into(), "1m".into()),
+                mockito::Matcher::UrlEncoded("count_back".into(), "3".into()),
+            ]))
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"code":200,"resolution":"1m","candlesticks":[
+                    {"timestamp":120000,"open":11.5,"high":13,"low":11,"close":12,"volume0":4,"volume1":48,"last_trade_id":9},
+                    {"timestamp":60000,"open":10,"high":12,"low":9.5,"close":11.5,"volume0":1.5,"volume1":17,"last_trade_id":5}
+                ]}"#,
+            )
+            .create_async()
+            .await;
+
+        let api = CandlestickApi::new(&config(server.url())).unwrap();
+        let candles = api
+            .candles(1, CandlestickInterval::OneMinute, MINUTE, 3 * MINUTE, false)
+            .await
+            .unwrap();
+        mock.assert_async().await;
+
+        // sorted ascending
+        assert_eq!(
+            candles,
+            [
+                Candle {
+                    open_time: MINUTE,
+                    open: 10.0,
+                    high: 12.0,
+                    low: 9.5,
+                    close: 11.5,
+                    volume: 1.5,
+                },
+                Candle {
+                    open_time: 2 * MINUTE,
+                    open: 11.5,
+                    high: 13.0,
+                    low: 11.0,
+                    close: 12.0,
+                    volume: 4.0,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_candles_rejects_range() {
+        let api = CandlestickApi::new(&config("http://127.0.0.1:1".into())).unwrap();
+        for (resolution, start, end, msg) in [
+            (CandlestickInterval::OneMinute, 10, 10, "range"),
+            (CandlestickInterval::OneMinute, 10, 1, "range"),
+            (CandlestickInterval::OneMinute, -1, 1, "timestamp"),
+            (
+                CandlestickInterval::OneHour,
+                0,
+                MAX_CANDLE_TIMESTAMP + 1,
+                "timestamp",
+            ),
+            (CandlestickInterval::ThirtyMinutes, 0, 1, "resolution"),
+            (CandlestickInterval::OneWeek, 0, 1, "resolution"),
+        ] {
+            let res = api.candles(1, resolution, start, end, false).await;
+            assert!(
+                matches!(&res, Err(LighterError::Generic(e)) if e.contains(msg)),
+                "{res:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_candles_fill_gaps() {
+        let candlesticks = [
+            candlestick(4 * MINUTE, 13.0, 14.0),
+            candlestick(MINUTE, 10.0, 11.0),
+            candlestick(2 * MINUTE, 11.0, 12.0),
+            candlestick(2 * MINUTE, 11.0, 12.0),
+        ];
+
+        let candles = to_candles(&candlesticks, MINUTE, false);
+        let open_times = candles.iter().map(|v| v.open_time).collect::<Vec<_>>();
+        assert_eq!(open_times, [MINUTE, 2 * MINUTE, 4 * MINUTE]);
+
+        let candles = to_candles(&candlesticks, MINUTE, true);
+        let open_times = candles.iter().map(|v| v.open_time).collect::<Vec<_>>();
+        assert_eq!(open_times, [MINUTE, 2 * MINUTE, 3 * MINUTE, 4 * MINUTE]);
+        // flat at the previous close
+        assert_eq!(candles[2], Candle::flat(3 * MINUTE, 12.0));
+        assert_eq!(candles[2].volume, 0.0);
+        assert_eq!(candles[3], Candle::from(&candlesticks[0]));
+
+        assert!(to_candles(&[], MINUTE, true).is_empty());
+    }
+}
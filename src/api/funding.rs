@@ -1,10 +1,49 @@
 use crate::{
+    api::candlestick::FundingInterval,
     apis::{self, configuration::Configuration},
     config::LighterConfig,
-    models::FundingRates,
-    Result,
+    error::LighterError,
+    models::{Funding, FundingRates},
+    MarketIndex, Result,
 };
 
+/// Side paying the funding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum FundingDirection {
+    Long,
+    Short,
+}
+
+/// Funding of a market over a funding period
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FundingRatePoint {
+    pub timestamp: i64,
+    pub rate: f64,
+    pub value: f64,
+    pub direction: FundingDirection,
+}
+
+impl TryFrom<&Funding> for FundingRatePoint {
+    type Error = LighterError;
+
+    fn try_from(funding: &Funding) -> Result<Self> {
+        let invalid = || LighterError::Generic(format!("Invalid funding {funding:?}"));
+        let direction = match funding.direction.as_str() {
+            "long" => FundingDirection::Long,
+            "short" => FundingDirection::Short,
+            _ => return Err(invalid()),
+        };
+
+        Ok(Self {
+            timestamp: funding.timestamp,
+            rate: funding.rate.parse().map_err(|_| invalid())?,
+            value: funding.value.parse().map_err(|_| invalid())?,
+            direction,
+        })
+    }
+}
+
 #[derive(Debug)]
 pub struct FundingApi {
     config: apis::configuration::Configuration,
@@ -12,12 +51,22 @@ pub struct FundingApi {
 
 impl FundingApi {
     pub fn new(config: &LighterConfig) -> Result<Self> {
-        Ok(Self {
-            config: Configuration::try_from(config)?,
-        })
+        Ok(Self::from_configuration(Configuration::try_from(config)?))
+    }
+
+    /// Sends the requests with `config`, e.g. shared with the other APIs, without building one
+    pub fn from_configuration(config: Configuration) -> Self {
+        Self { config }
+    }
+
+    /// Sends the requests with `config` instead of the one built from the `LighterConfig`
+    pub fn with_configuration(mut self, config: Configuration) -> Self {
+        self.config = config;
+        self
     }
 
     /// Get funding rates
+    #[tracing::instrument(level = "debug", skip_all)]
     pub async fn funding_rates(&self) -> Result<FundingRates> {
         let resp = apis::funding_api::funding_rates(&self.config)
             .await
@@ -25,4 +74,107 @@ impl FundingApi {
 
         Ok(resp)
     }
+
+    /// Get the fundings of the market between `start_timestamp` and `end_timestamp` (ms), sorted
+    /// by timestamp. The endpoint isn't paginated: all the periods of the range are requested at
+    /// once.
+    #[tracing::instrument(level = "debug", skip_all, fields(market_index = tracing::field::Empty))]
+    pub async fn funding_rate_history(
+        &self,
+        market_index: impl Into<MarketIndex>,
+        start_timestamp: i64,
+        end_timestamp: i64,
+        resolution: FundingInterval,
+    ) -> Result<Vec<FundingRatePoint>> {
+        let market_index = market_index.into().0;
+        tracing::Span::current().record("market_index", market_index);
+        if start_timestamp > end_timestamp {
+            return Err(LighterError::Generic(format!(
+                "Invalid time range, start {start_timestamp} is after end {end_timestamp}"
+            )));
+        }
+
+        let count_back = (end_timestamp - start_timestamp) / resolution.as_millis() + 1;
+        let resp = apis::candlestick_api::fundings(
+            &self.config,
+            market_index,
+            &resolution.to_string(),
+            start_timestamp,
+            end_timestamp,
+            count_back,
+        )
+        .await
+        .inspect_err(|e| tracing::error!("unable to call `funding_rate_history`: {e}"))?;
+
+        let mut points = resp
+            .fundings
+            .iter()
+            .map(FundingRatePoint::try_from)
+            .collect::<Result<Vec<_>>>()?;
+        points.sort_by_key(|v| v.timestamp);
+
+        Ok(points)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_funding_rate_history() {
+        let mut server = mockito::Server::new_async().await;
+        let fundings = server
+            .mock("GET", "/api/v1/fundings")
+            .match_query(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::UrlEncoded("market_id".into(), "1".into()),
+                mockito::Matcher::UrlEncoded("resolution".into(), "1h".into()),
+                mockito::Matcher::UrlEncoded("count_back".into(), "3".into()),
+            ]))
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"code":200,"resolution":"1h","fundings":[
+                    {"timestamp":7200,"value":"0.02","rate":"0.0002","direction":"short"},
+                    {"timestamp":3600,"value":"0.01","rate":"0.0001","direction":"long"}
+                ]}"#,
+            )
+            .create_async()
+            .await;
+
+        let config = LighterConfig {
+            base_url: server.url(),
+            retry_config: None,
+            ..LighterConfig::new()
+        };
+        let api = FundingApi::new(&config).unwrap();
+
+        let points = api
+            .funding_rate_history(1, 0, 7_200_000, FundingInterval::OneHour)
+            .await
+            .unwrap();
+        assert_eq!(
+            points,
+            vec![
+                FundingRatePoint {
+                    timestamp: 3600,
+                    rate: 0.0001,
+                    value: 0.01,
+                    direction: FundingDirection::Long,
+                },
+                FundingRatePoint {
+                    timestamp: 7200,
+                    rate: 0.0002,
+                    value: 0.02,
+                    direction: FundingDirection::Short,
+                },
+            ]
+        );
+        fundings.assert_async().await;
+
+        // rejected before sending
+        let res = api
+            .funding_rate_history(1, 2, 1, FundingInterval::OneDay)
+            .await;
+        assert!(matches!(res, Err(LighterError::Generic(e)) if e.contains("time range")));
+    }
 }
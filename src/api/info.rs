@@ -1,10 +1,203 @@
 use crate::{
     apis::{self, configuration::Configuration},
     config::LighterConfig,
-    models::{RespWithdrawalDelay, TransferFeeInfo},
-    Result,
+    models::{
+        order_book_detail, ExchangeStats, OrderBookDetail, OrderBookStats, RespWithdrawalDelay,
+        TransferFeeInfo,
+    },
+    LighterError, Result,
 };
 
+/// Max number of decimals of the prices and sizes of a market
+const MAX_DECIMALS: i32 = 18;
+
+/// Highest `initial_margin_fraction` (100%, i.e. 1x leverage), in hundredths of a percent
+pub const MAX_INITIAL_MARGIN_FRACTION: i32 = 10_000;
+
+/// Converts a leverage (e.g. `10.0` for 10x) to the `initial_margin_fraction` of the txs, in
+/// hundredths of a percent (e.g. `1000`), rounded. The leverage must be at least 1x.
+pub fn leverage_to_initial_margin_fraction(leverage: f64) -> Result<i32> {
+    if !leverage.is_finite() || leverage < 1.0 {
+        return Err(LighterError::Generic(format!(
+            "Invalid leverage {leverage}, expected at least 1x"
+        )));
+    }
+    Ok(((MAX_INITIAL_MARGIN_FRACTION as f64 / leverage).round() as i32).max(1))
+}
+
+/// Converts an `initial_margin_fraction` of the txs back to a leverage, `None` unless positive
+pub fn initial_margin_fraction_to_leverage(initial_margin_fraction: i32) -> Option<f64> {
+    (initial_margin_fraction > 0)
+        .then(|| MAX_INITIAL_MARGIN_FRACTION as f64 / initial_margin_fraction as f64)
+}
+
+/// Market metadata and daily stats, see [`InfoApi::markets`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarketInfo {
+    pub market_index: i32,
+    pub symbol: String,
+    pub active: bool,
+    pub size_decimals: u32,  // of the integer base amounts of the txs
+    pub price_decimals: u32, // of the integer prices of the txs
+    pub supported_size_decimals: u32,
+    pub supported_price_decimals: u32,
+    pub min_base_amount: f64,
+    pub min_quote_amount: f64,
+    pub min_initial_margin_fraction: i32, // of the max leverage, see `MarketInfo::max_leverage`
+    pub last_trade_price: f64,
+    pub open_interest: f64,
+    pub daily_base_volume: f64,
+    pub daily_quote_volume: f64,
+    pub daily_price_change: f64,
+}
+
+impl MarketInfo {
+    /// Converts `size` to the integer base amount of the txs, rounding to the size decimals
+    pub fn to_base_amount(&self, size: f64) -> i64 {
+        (size * 10f64.powi(self.size_decimals as i32)).round() as i64
+    }
+
+    /// Converts `price` to the integer price of the txs, rounding to the price decimals
+    pub fn to_int_price(&self, price: f64) -> i64 {
+        (price * 10f64.powi(self.price_decimals as i32)).round() as i64
+    }
+
+    /// Step of the integer base amounts, they must be a multiple of it
+    pub fn lot_size(&self) -> i64 {
+        10i64.pow(
+            self.size_decimals
+                .saturating_sub(self.supported_size_decimals),
+        )
+    }
+
+    /// Step of the integer prices (tick size), they must be a multiple of it
+    pub fn price_tick(&self) -> i64 {
+        10i64.pow(
+            self.price_decimals
+                .saturating_sub(self.supported_price_decimals),
+        )
+    }
+
+    /// Converts an integer base amount of the txs back to a size
+    pub fn base_amount_to_size(&self, base_amount: i64) -> f64 {
+        base_amount as f64 / 10f64.powi(self.size_decimals as i32)
+    }
+
+    /// Converts an integer price of the txs back to a price
+    pub fn int_price_to_price(&self, price: i64) -> f64 {
+        price as f64 / 10f64.powi(self.price_decimals as i32)
+    }
+
+    /// Highest leverage allowed on the market (e.g. `20.0` for 20x), `None` if the market has no
+    /// min `initial_margin_fraction`
+    pub fn max_leverage(&self) -> Option<f64> {
+        initial_margin_fraction_to_leverage(self.min_initial_margin_fraction)
+    }
+
+    /// Converts `leverage` to the `initial_margin_fraction` of the txs (see
+    /// [`leverage_to_initial_margin_fraction`]), failing if it's above the max leverage
+    pub fn initial_margin_fraction(&self, leverage: f64) -> Result<i32> {
+        let initial_margin_fraction = leverage_to_initial_margin_fraction(leverage)?;
+        match self.max_leverage() {
+            Some(max_leverage) if initial_margin_fraction < self.min_initial_margin_fraction => {
+                Err(LighterError::Generic(format!(
+                    "Leverage {leverage}x exceeds the max leverage {max_leverage}x of market {} ({})",
+                    self.market_index, self.symbol
+                )))
+            }
+            _ => Ok(initial_margin_fraction),
+        }
+    }
+}
+
+impl TryFrom<&OrderBookDetail> for MarketInfo {
+    type Error = LighterError;
+
+    fn try_from(detail: &OrderBookDetail) -> Result<Self> {
+        let invalid = |what: &str| {
+            LighterError::Generic(format!(
+                "Invalid {what} of market {} ({})",
+                detail.market_id, detail.symbol
+            ))
+        };
+        let decimals = |v: i32, what: &str| match v {
+            0..=MAX_DECIMALS => Ok(v as u32),
+            _ => Err(invalid(what)),
+        };
+        let amount = |v: &str, what: &str| v.parse::<f64>().map_err(|_| invalid(what));
+
+        Ok(Self {
+            market_index: detail.market_id,
+            symbol: detail.symbol.clone(),
+            active: detail.status == order_book_detail::Status::Active,
+            size_decimals: decimals(detail.size_decimals, "size decimals")?,
+            price_decimals: decimals(detail.price_decimals, "price decimals")?,
+            supported_size_decimals: decimals(
+                detail.supported_size_decimals,
+                "supported size decimals",
+            )?,
+            supported_price_decimals: decimals(
+                detail.supported_price_decimals,
+                "supported price decimals",
+            )?,
+            min_base_amount: amount(&detail.min_base_amount, "min base amount")?,
+            min_quote_amount: amount(&detail.min_quote_amount, "min quote amount")?,
+            min_initial_margin_fraction: detail.min_initial_margin_fraction,
+            last_trade_price: detail.last_trade_price,
+            open_interest: detail.open_interest,
+            daily_base_volume: detail.daily_base_token_volume,
+            daily_quote_volume: detail.daily_quote_token_volume,
+            daily_price_change: detail.daily_price_change,
+        })
+    }
+}
+
+/// Daily stats of a market, see [`InfoApi::exchange_stats`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarketStats {
+    pub symbol: String,
+    pub last_trade_price: f64,
+    pub daily_trades_count: i64,
+    pub daily_base_volume: f64,
+    pub daily_quote_volume: f64,
+    pub daily_price_change: f64,
+}
+
+impl From<&OrderBookStats> for MarketStats {
+    fn from(stats: &OrderBookStats) -> Self {
+        Self {
+            symbol: stats.symbol.clone(),
+            last_trade_price: stats.last_trade_price,
+            daily_trades_count: stats.daily_trades_count,
+            daily_base_volume: stats.daily_base_token_volume,
+            daily_quote_volume: stats.daily_quote_token_volume,
+            daily_price_change: stats.daily_price_change,
+        }
+    }
+}
+
+/// Daily stats of the exchange
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExchangeSummary {
+    pub daily_usd_volume: f64,
+    pub daily_trades_count: i64,
+    pub markets: Vec<MarketStats>,
+}
+
+impl From<&ExchangeStats> for ExchangeSummary {
+    fn from(stats: &ExchangeStats) -> Self {
+        Self {
+            daily_usd_volume: stats.daily_usd_volume,
+            daily_trades_count: stats.daily_trades_count,
+            markets: stats
+                .order_book_stats
+                .iter()
+                .map(MarketStats::from)
+                .collect(),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct InfoApi {
     config: apis::configuration::Configuration,
@@ -12,12 +205,22 @@ pub struct InfoApi {
 
 impl InfoApi {
     pub fn new(config: &LighterConfig) -> Result<Self> {
-        Ok(Self {
-            config: Configuration::try_from(config)?,
-        })
+        Ok(Self::from_configuration(Configuration::try_from(config)?))
+    }
+
+    /// Sends the requests with `config`, e.g. shared with the other APIs, without building one
+    pub fn from_configuration(config: Configuration) -> Self {
+        Self { config }
+    }
+
+    /// Sends the requests with `config` instead of the one built from the `LighterConfig`
+    pub fn with_configuration(mut self, config: Configuration) -> Self {
+        self.config = config;
+        self
     }
 
     /// Withdrawal delay in seconds
+    #[tracing::instrument(level = "debug", skip_all, fields(account_index = account_index))]
     pub async fn transfer_fee_info(
         &self,
         account_index: i64,
@@ -40,6 +243,35 @@ impl InfoApi {
         Ok(resp)
     }
 
+    /// Returns the daily stats of the exchange and of its markets
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub async fn exchange_stats(&self) -> Result<ExchangeSummary> {
+        let resp = apis::order_api::exchange_stats(&self.config)
+            .await
+            .inspect_err(|e| tracing::error!("unable to call `exchange_stats`: {e}"))?;
+
+        Ok(ExchangeSummary::from(&resp))
+    }
+
+    /// Returns all the markets with their decimals and daily stats, sorted by index. The symbols
+    /// are resolved by [`crate::MetadataCache`].
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub async fn markets(&self) -> Result<Vec<MarketInfo>> {
+        let resp = apis::order_api::order_book_details(&self.config, None)
+            .await
+            .inspect_err(|e| tracing::error!("unable to call `order_book_details`: {e}"))?;
+
+        let mut markets = resp
+            .order_book_details
+            .iter()
+            .map(MarketInfo::try_from)
+            .collect::<Result<Vec<_>>>()?;
+        markets.sort_by_key(|v| v.market_index);
+
+        Ok(markets)
+    }
+
+    #[tracing::instrument(level = "debug", skip_all)]
     pub async fn withdrawal_delay(&self) -> Result<RespWithdrawalDelay> {
         let resp = apis::info_api::withdrawal_delay(&self.config)
             .await
@@ -48,3 +280,165 @@ impl InfoApi {
         Ok(resp)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detail(symbol: &str, market_id: i32, size_decimals: i32) -> OrderBookDetail {
+        OrderBookDetail {
+            symbol: symbol.into(),
+            market_id,
+            status: order_book_detail::Status::Active,
+            min_base_amount: "0.01".into(),
+            min_quote_amount: "10".into(),
+            size_decimals,
+            price_decimals: 2,
+            supported_size_decimals: size_decimals,
+            supported_price_decimals: 2,
+            last_trade_price: 3000.5,
+            open_interest: 12.0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_leverage_conversions() {
+        assert_eq!(leverage_to_initial_margin_fraction(1.0).unwrap(), 10_000);
+        assert_eq!(leverage_to_initial_margin_fraction(10.0).unwrap(), 1000);
+        assert_eq!(leverage_to_initial_margin_fraction(12.5).unwrap(), 800);
+        assert_eq!(leverage_to_initial_margin_fraction(3.0).unwrap(), 3333);
+        // the smallest fraction
+        assert_eq!(leverage_to_initial_margin_fraction(1e9).unwrap(), 1);
+        for leverage in [0.0, 0.99, -2.0, f64::INFINITY, f64::NAN] {
+            assert!(leverage_to_initial_margin_fraction(leverage).is_err());
+        }
+
+        assert_eq!(initial_margin_fraction_to_leverage(1000), Some(10.0));
+        assert_eq!(
+            initial_margin_fraction_to_leverage(MAX_INITIAL_MARGIN_FRACTION),
+            Some(1.0)
+        );
+        assert_eq!(initial_margin_fraction_to_leverage(0), None);
+        assert_eq!(initial_margin_fraction_to_leverage(-500), None);
+    }
+
+    #[test]
+    fn test_market_info_conversions() {
+        let market = MarketInfo::try_from(&detail("ETH", 0, 4)).unwrap();
+        assert!(market.active);
+        assert_eq!(market.min_base_amount, 0.01);
+        assert_eq!(market.to_base_amount(1.23456), 12346);
+        assert_eq!(market.to_int_price(3000.5), 300050);
+        assert_eq!(market.base_amount_to_size(12346), 1.2346);
+        assert_eq!(market.int_price_to_price(300050), 3000.5);
+
+        assert_eq!((market.lot_size(), market.price_tick()), (1, 1));
+        let coarse = MarketInfo::try_from(&OrderBookDetail {
+            supported_size_decimals: 2,
+            supported_price_decimals: 1,
+            ..detail("ETH", 0, 4)
+        })
+        .unwrap();
+        assert_eq!((coarse.lot_size(), coarse.price_tick()), (100, 10));
+
+        assert!(MarketInfo::try_from(&detail("ETH", 0, -1)).is_err());
+
+        let market = MarketInfo::try_from(&OrderBookDetail {
+            min_initial_margin_fraction: 500,
+            ..detail("ETH", 0, 4)
+        })
+        .unwrap();
+        assert_eq!(market.max_leverage(), Some(20.0));
+        assert_eq!(market.initial_margin_fraction(20.0).unwrap(), 500);
+        assert_eq!(market.initial_margin_fraction(3.0).unwrap(), 3333);
+        assert!(
+            matches!(market.initial_margin_fraction(25.0), Err(LighterError::Generic(e)) if e.contains("max leverage 20x"))
+        );
+        assert!(market.initial_margin_fraction(0.5).is_err());
+        // no max leverage
+        let uncapped = MarketInfo::try_from(&detail("ETH", 0, 4)).unwrap();
+        assert_eq!(uncapped.max_leverage(), None);
+        assert_eq!(uncapped.initial_margin_fraction(50.0).unwrap(), 200);
+        let invalid = OrderBookDetail {
+            min_base_amount: "abc".into(),
+            ..detail("ETH", 0, 4)
+        };
+        assert!(MarketInfo::try_from(&invalid).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_markets() {
+        let mut server = mockito::Server::new_async().await;
+        let details = server
+            .mock("GET", "/api/v1/orderBookDetails")
+            .with_header("content-type", "application/json")
+            .with_body(
+                serde_json::json!({
+                    "code": 200,
+                    "order_book_details": [detail("BTC", 1, 5), detail("ETH", 0, 4)],
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let config = LighterConfig {
+            base_url: server.url(),
+            retry_config: None,
+            ..LighterConfig::new()
+        };
+        let markets = InfoApi::new(&config).unwrap().markets().await.unwrap();
+        assert_eq!(
+            markets
+                .iter()
+                .map(|v| v.symbol.as_str())
+                .collect::<Vec<_>>(),
+            vec!["ETH", "BTC"]
+        );
+        assert_eq!(markets[1].size_decimals, 5);
+        assert_eq!(markets[0].open_interest, 12.0);
+
+        details.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_exchange_stats() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/api/v1/exchangeStats")
+            .with_header("content-type", "application/json")
+            .with_body(
+                serde_json::to_string(&ExchangeStats {
+                    code: 200,
+                    total: 1,
+                    order_book_stats: vec![OrderBookStats {
+                        symbol: "ETH".into(),
+                        last_trade_price: 3000.0,
+                        daily_quote_token_volume: 1e6,
+                        ..Default::default()
+                    }],
+                    daily_usd_volume: 2e6,
+                    daily_trades_count: 10,
+                    ..Default::default()
+                })
+                .unwrap(),
+            )
+            .create_async()
+            .await;
+
+        let config = LighterConfig {
+            base_url: server.url(),
+            retry_config: None,
+            ..LighterConfig::new()
+        };
+        let stats = InfoApi::new(&config)
+            .unwrap()
+            .exchange_stats()
+            .await
+            .unwrap();
+        assert_eq!(stats.daily_usd_volume, 2e6);
+        assert_eq!(stats.markets[0].symbol, "ETH");
+        assert_eq!(stats.markets[0].daily_quote_volume, 1e6);
+    }
+}
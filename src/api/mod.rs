@@ -1,3 +1,20 @@
+//! Typed interfaces of the REST APIs.
+//!
+//! Every API is built from a [`crate::LighterConfig`]; its requests can be redirected (e.g. to a
+//! mock server in the tests) with `with_configuration`, keeping the rest of the API (signer,
+//! shared state) as built:
+//!
+//! ```ignore
+//! let configuration = Configuration {
+//!     base_path: server.url(),
+//!     ..Configuration::try_from(&config)?
+//! };
+//! let api = AccountApi::new(&config)?.with_configuration(configuration);
+//! ```
+//!
+//! Every call runs in a `debug` span named after the method, with the account and market indexes
+//! it's about as fields (never the auth tokens nor the keys). The HTTP requests it sends are
+//! nested `http_request` spans recording their `status` and `elapsed_ms`.
 #![allow(dead_code)]
 pub mod account;
 pub mod announcement;
@@ -8,6 +25,7 @@ pub mod funding;
 pub mod info;
 pub mod notification;
 pub mod order;
+pub mod raw;
 pub mod referral;
 pub mod root;
 pub mod transaction;
@@ -1,23 +1,113 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+
 use crate::{
     apis::{self, configuration::Configuration},
     config::LighterConfig,
+    error::LighterError,
     models::ResultCode,
     Result,
 };
 
+#[cfg(feature = "signer")]
+use crate::signer::FFISigner;
+
+/// Kind of a notification, the content depends on it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationKind {
+    Liquidation,
+    Deleverage,
+    Announcement,
+    #[serde(other)]
+    Other,
+}
+
+/// Notification of the account, pushed on the `notification` channel (see
+/// [`crate::WsClient::subscribe_notifications`])
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Notification {
+    pub id: String,
+    pub kind: NotificationKind,
+    pub account_index: i64,
+    #[serde(deserialize_with = "timestamp")]
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub content: Value,
+    #[serde(default)]
+    pub ack: bool,
+}
+
+/// Accepts both RFC 3339 dates and unix timestamps (secs or ms)
+fn timestamp<'de, D>(deserializer: D) -> std::result::Result<DateTime<Utc>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let parsed = match Value::deserialize(deserializer)? {
+        Value::String(v) => DateTime::parse_from_rfc3339(&v)
+            .ok()
+            .map(|v| v.with_timezone(&Utc)),
+        Value::Number(v) => v.as_i64().and_then(|v| {
+            if v < 10_000_000_000 {
+                DateTime::from_timestamp(v, 0)
+            } else {
+                DateTime::from_timestamp_millis(v)
+            }
+        }),
+        _ => None,
+    };
+    parsed.ok_or_else(|| serde::de::Error::custom("invalid timestamp"))
+}
+
 #[derive(Debug)]
 pub struct NotificationApi {
     config: apis::configuration::Configuration,
+    account_index: Option<i64>,
+    #[cfg(feature = "signer")]
+    signer: Option<FFISigner>, // creates the auth tokens
 }
 
 impl NotificationApi {
     pub fn new(config: &LighterConfig) -> Result<Self> {
+        Self::new_with_configuration(config, Configuration::try_from(config)?)
+    }
+
+    /// Same as [`NotificationApi::new`], sending the requests with `configuration` (e.g. shared with the
+    /// other APIs) instead of building one
+    pub fn new_with_configuration(
+        config: &LighterConfig,
+        configuration: Configuration,
+    ) -> Result<Self> {
+        #[cfg(feature = "signer")]
+        let signer = match config.api_key_private {
+            Some(_) => Some(FFISigner::try_from(config)?),
+            None => None,
+        };
+
         Ok(Self {
-            config: Configuration::try_from(config)?,
+            config: configuration,
+            account_index: config.account_index.map(i64::from),
+            #[cfg(feature = "signer")]
+            signer,
         })
     }
 
+    /// Sends the requests with `config` instead of the one built from the `LighterConfig`
+    pub fn with_configuration(mut self, config: Configuration) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Creates the auth tokens with `signer`, sharing its cached token with the other APIs
+    #[cfg(feature = "signer")]
+    pub(crate) fn with_signer(mut self, signer: Option<FFISigner>) -> Self {
+        self.signer = signer;
+        self
+    }
+
     /// Ack notification
+    #[tracing::instrument(level = "debug", skip_all, fields(account_index = account_index))]
     pub async fn notification_ack(
         &self,
         notif_id: &str,
@@ -39,4 +129,103 @@ impl NotificationApi {
 
         Ok(resp)
     }
+
+    /// Marks the notification of the configured account as read, authenticating with the
+    /// signer's auth token
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub async fn ack(&self, notif_id: &str) -> Result<ResultCode> {
+        let account_index = self
+            .account_index
+            .ok_or_else(|| LighterError::Config("`account_index` is not set".into()))?;
+        let token = self.auth_token()?;
+
+        self.notification_ack(notif_id, account_index, Some(&token), None)
+            .await
+    }
+
+    #[cfg(feature = "signer")]
+    fn auth_token(&self) -> Result<String> {
+        self.signer
+            .as_ref()
+            .ok_or_else(|| {
+                LighterError::Config(
+                    "`api_key_private` is required to acknowledge notifications".into(),
+                )
+            })?
+            .get_auth_token(None)
+    }
+
+    #[cfg(not(feature = "signer"))]
+    fn auth_token(&self) -> Result<String> {
+        Err(LighterError::signer_disabled("Creating the auth token"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notification_deserialize() {
+        let notification: Notification = serde_json::from_value(serde_json::json!({
+            "id": "n1",
+            "kind": "liquidation",
+            "account_index": 28,
+            "created_at": "2024-01-02T03:04:05Z",
+            "updated_at": "2024-01-02T03:04:05Z",
+            "content": {"market_index": 1},
+            "ack": false,
+        }))
+        .unwrap();
+        assert_eq!(notification.kind, NotificationKind::Liquidation);
+        assert_eq!(notification.created_at.timestamp(), 1704164645);
+        assert_eq!(notification.content["market_index"], 1);
+
+        let notification: Notification = serde_json::from_value(serde_json::json!({
+            "id": "n2",
+            "kind": "new_kind",
+            "account_index": 28,
+            "created_at": 1704164645000i64,
+        }))
+        .unwrap();
+        assert_eq!(notification.kind, NotificationKind::Other);
+        assert_eq!(notification.created_at.timestamp(), 1704164645);
+        assert!(!notification.ack);
+    }
+
+    #[cfg(feature = "signer")]
+    #[tokio::test]
+    async fn test_ack() {
+        let mut server = mockito::Server::new_async().await;
+        let ack = server
+            .mock("POST", "/api/v1/notification/ack")
+            .match_body(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::Regex(r#"name="notif_id"\r\n\r\nn1\r\n"#.into()),
+                mockito::Matcher::Regex(r#"name="account_index"\r\n\r\n28\r\n"#.into()),
+            ]))
+            .match_header("authorization", mockito::Matcher::Regex(".+".into()))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let config = LighterConfig {
+            base_url: server.url(),
+            retry_config: None,
+            ..LighterConfig::new().with_account_index(28)
+        };
+        // read only
+        let res = NotificationApi::new(&config).unwrap().ack("n1").await;
+        assert!(matches!(res, Err(LighterError::Config(e)) if e.contains("api_key_private")));
+
+        let config = config
+            .with_api_key_private(
+                "01db9eed031d59d6bd0ee00ee5a7dc1f62087bf217b51caea57eb6e17a02c49e0a748d2f155a2f60",
+            )
+            .with_api_key_index(2);
+        let api = NotificationApi::new(&config).unwrap();
+        assert_eq!(api.ack("n1").await.unwrap().code, 200);
+        ack.assert_async().await;
+    }
 }
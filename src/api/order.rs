@@ -1,12 +1,36 @@
 #![allow(clippy::too_many_arguments)]
+use std::sync::Arc;
+
+#[cfg(feature = "signer")]
+use chrono::{DateTime, Utc};
+
+use futures::{Stream, TryStreamExt};
+
 use crate::{
     apis::{self, configuration::Configuration},
+    client::MetadataCache,
     config::LighterConfig,
     models::{
-        order::{TimeInForce, Type as OrderType},
-        ExchangeStats, ExportData, OrderBookDetails, OrderBookOrders, OrderBooks, Orders, Trades,
+        order::{Status as OrderStatus, TimeInForce, Type as OrderType},
+        ExchangeStats, ExportData, Order, OrderBookDetails, OrderBookOrders, OrderBooks, Orders,
+        PriceLevel, SimpleOrder, Trades,
+    },
+    AccountIndex, LighterError, MarketIndex, Result,
+};
+
+#[cfg(feature = "signer")]
+use crate::{
+    api::{account::Position, transaction::TxResultCode},
+    client::{ClientOrderIdGenerator, TxClient},
+    clock::ServerClock,
+    models::{RespSendTx, RespSendTxBatch},
+    signer::{
+        data::{
+            CreateOrderData, SignCancelAllOrdersData, SignCancelOrderData,
+            SignCreateGroupedOrdersData,
+        },
+        FFISigner,
     },
-    Result,
 };
 
 #[derive(Debug, Clone, strum::Display)]
@@ -55,6 +79,29 @@ impl From<crate::models::order::Type> for u8 {
     }
 }
 
+impl TryFrom<u8> for crate::models::order::Type {
+    type Error = LighterError;
+
+    fn try_from(val: u8) -> Result<Self> {
+        Ok(match val {
+            0 => OrderType::Limit,
+            1 => OrderType::Market,
+            2 => OrderType::StopLoss,
+            3 => OrderType::StopLossLimit,
+            4 => OrderType::TakeProfit,
+            5 => OrderType::TakeProfitLimit,
+            6 => OrderType::Twap,
+            7 => OrderType::TwapSub,
+            8 => OrderType::Liquidation,
+            _ => {
+                return Err(LighterError::OrderValidation(format!(
+                    "Unknown order type {val}"
+                )))
+            }
+        })
+    }
+}
+
 impl From<crate::models::order::TimeInForce> for u8 {
     fn from(val: crate::models::order::TimeInForce) -> Self {
         match val {
@@ -66,19 +113,390 @@ impl From<crate::models::order::TimeInForce> for u8 {
     }
 }
 
+impl TryFrom<u8> for crate::models::order::TimeInForce {
+    type Error = LighterError;
+
+    fn try_from(val: u8) -> Result<Self> {
+        Ok(match val {
+            0 => TimeInForce::ImmediateOrCancel,
+            1 => TimeInForce::GoodTillTime,
+            2 => TimeInForce::PostOnly,
+            3 => TimeInForce::Unknown,
+            _ => {
+                return Err(LighterError::OrderValidation(format!(
+                    "Unknown time in force {val}"
+                )))
+            }
+        })
+    }
+}
+
+/// Time in force of a cancel all orders tx
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum CancelAllTimeInForce {
+    /// Cancel all the orders now
+    Immediate = 0,
+    /// Cancel all the orders at the given time, unless the tx is sent again with a later time
+    /// (dead man's switch)
+    Scheduled = 1,
+    /// Abort a previously scheduled cancel
+    Abort = 2,
+}
+
+impl From<CancelAllTimeInForce> for u8 {
+    fn from(val: CancelAllTimeInForce) -> Self {
+        val as u8
+    }
+}
+
+/// Order(s) taking the place of a cancelled one, see [`OrderApi::replace_order`]
+#[cfg(feature = "signer")]
+#[derive(Debug, Clone)]
+pub enum ReplacementOrder {
+    Single(CreateOrderData),
+    Grouped(SignCreateGroupedOrdersData),
+}
+
+#[cfg(feature = "signer")]
+impl From<CreateOrderData> for ReplacementOrder {
+    fn from(val: CreateOrderData) -> Self {
+        Self::Single(val)
+    }
+}
+
+#[cfg(feature = "signer")]
+impl From<SignCreateGroupedOrdersData> for ReplacementOrder {
+    fn from(val: SignCreateGroupedOrdersData) -> Self {
+        Self::Grouped(val)
+    }
+}
+
+/// Response of [`OrderApi::place_order`]
+#[cfg(feature = "signer")]
+#[derive(Debug, Clone)]
+pub struct PlacedOrder {
+    /// `client_order_index` of the order, useful to match it with its fills
+    pub client_order_index: i64,
+    /// Response of the submission, `None` if a retry was rejected as a duplicate of an attempt
+    /// whose response was lost (see [`PlacedOrder::already_placed`])
+    pub resp: Option<RespSendTx>,
+}
+
+#[cfg(feature = "signer")]
+impl PlacedOrder {
+    /// `true` if the order was placed by a previous attempt, whose tx hash is unknown
+    pub fn already_placed(&self) -> bool {
+        self.resp.is_none()
+    }
+
+    /// Hash of the tx placing the order, if its response was received
+    pub fn tx_hash(&self) -> Option<&str> {
+        self.resp.as_ref().map(|resp| resp.tx_hash.as_str())
+    }
+}
+
+/// Outcome of the cancel of one order, see [`OrderApi::cancel_orders`]
+#[cfg(feature = "signer")]
+#[derive(Debug)]
+pub struct CancelledOrder {
+    pub market_index: MarketIndex,
+    pub order_index: i64,
+    pub result: Result<RespSendTx>,
+}
+
+/// Price levels of a market, see [`OrderApi::order_book`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OrderBookDepth {
+    pub market_index: i32,
+    /// From the best (lowest) one
+    pub asks: Vec<PriceLevel>,
+    /// From the best (highest) one
+    pub bids: Vec<PriceLevel>,
+}
+
+impl OrderBookDepth {
+    /// Max number of orders per side of a snapshot
+    pub const MAX_DEPTH: u32 = 100;
+
+    /// Aggregates the resting orders by price
+    fn new(market_index: i32, orders: OrderBookOrders) -> Result<Self> {
+        Ok(Self {
+            market_index,
+            asks: price_levels(orders.asks, false)?,
+            bids: price_levels(orders.bids, true)?,
+        })
+    }
+}
+
+/// Sums the remaining amounts of the orders with the same price, from the best price
+fn price_levels(orders: Vec<SimpleOrder>, descending: bool) -> Result<Vec<PriceLevel>> {
+    let mut orders = orders
+        .into_iter()
+        .map(|order| Ok((parse_price(&order.price)?, order)))
+        .collect::<Result<Vec<_>>>()?;
+    orders.sort_by(|(a, _), (b, _)| match descending {
+        true => b.total_cmp(a),
+        false => a.total_cmp(b),
+    });
+
+    let mut levels: Vec<(f64, PriceLevel)> = Vec::new();
+    for (price, order) in orders {
+        match levels.last_mut() {
+            Some((last, level)) if *last == price => {
+                level.size = add_amounts(&level.size, &order.remaining_base_amount)?;
+            }
+            _ => levels.push((
+                price,
+                PriceLevel::new(order.price, order.remaining_base_amount),
+            )),
+        }
+    }
+    Ok(levels.into_iter().map(|(_, level)| level).collect())
+}
+
+fn parse_price(value: &str) -> Result<f64> {
+    value
+        .parse()
+        .map_err(|_| LighterError::Generic(format!("Invalid order book price: {value}")))
+}
+
+/// Adds two decimal amounts without losing precision
+fn add_amounts(a: &str, b: &str) -> Result<String> {
+    let invalid = |v: &str| LighterError::Generic(format!("Invalid order book amount: {v}"));
+    let split = |v: &str| {
+        let (int, frac) = v.split_once('.').unwrap_or((v, ""));
+        match int.chars().chain(frac.chars()).all(|c| c.is_ascii_digit()) {
+            true => Ok((int.to_string(), frac.to_string())),
+            false => Err(invalid(v)),
+        }
+    };
+    let ((a_int, a_frac), (b_int, b_frac)) = (split(a)?, split(b)?);
+
+    let decimals = a_frac.len().max(b_frac.len());
+    let units = |int: &str, frac: &str, v: &str| {
+        format!("{int}{frac:0<decimals$}")
+            .parse::<u128>()
+            .map_err(|_| invalid(v))
+    };
+    let sum = (units(&a_int, &a_frac, a)? + units(&b_int, &b_frac, b)?).to_string();
+
+    if decimals == 0 {
+        return Ok(sum);
+    }
+    let sum = format!("{sum:0>width$}", width = decimals + 1);
+    let (int, frac) = sum.split_at(sum.len() - decimals);
+    Ok(format!("{int}.{frac}"))
+}
+
+/// Fill status of an order of the history, see [`HistoricalOrder`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FillStatus {
+    /// Fully filled
+    Filled,
+    /// Cancelled (or expired) after being partially filled
+    PartiallyFilled,
+    /// Cancelled (or expired) without any fill
+    Cancelled,
+    /// Not executed yet, e.g. still open or pending
+    Open,
+}
+
+/// Order of the history of an account, see [`OrderApi::order_history`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoricalOrder {
+    pub order_index: i64,
+    pub client_order_index: i64,
+    pub market_index: i32,
+    pub is_ask: bool,
+    pub order_type: OrderType,
+    pub status: OrderStatus,
+    pub fill_status: FillStatus,
+    pub size: f64,
+    pub price: f64,
+    pub filled_size: f64,
+    pub filled_quote: f64,
+    /// `None` if nothing was filled
+    pub average_fill_price: Option<f64>,
+    /// Last update of the order (ms)
+    pub timestamp: i64,
+    /// When the trigger order got triggered (ms), if it did
+    pub trigger_time: Option<i64>,
+}
+
+impl TryFrom<&Order> for HistoricalOrder {
+    type Error = LighterError;
+
+    fn try_from(order: &Order) -> Result<Self> {
+        let parse = |v: &str| {
+            v.parse::<f64>()
+                .map_err(|_| LighterError::Generic(format!("Invalid order amount: {v}")))
+        };
+        let filled_size = parse(&order.filled_base_amount)?;
+        let filled_quote = parse(&order.filled_quote_amount)?;
+        let fill_status = match order.status {
+            OrderStatus::Filled => FillStatus::Filled,
+            OrderStatus::InProgress | OrderStatus::Pending | OrderStatus::Open => FillStatus::Open,
+            _ if filled_size > 0.0 => FillStatus::PartiallyFilled,
+            _ => FillStatus::Cancelled,
+        };
+
+        Ok(Self {
+            order_index: order.order_index,
+            client_order_index: order.client_order_index,
+            market_index: order.market_index,
+            is_ask: order.is_ask,
+            order_type: order.r#type,
+            status: order.status,
+            fill_status,
+            size: parse(&order.initial_base_amount)?,
+            price: parse(&order.price)?,
+            filled_size,
+            filled_quote,
+            average_fill_price: (filled_size > 0.0).then(|| filled_quote / filled_size),
+            timestamp: order.timestamp,
+            trigger_time: (order.trigger_time > 0).then_some(order.trigger_time),
+        })
+    }
+}
+
+/// Page of the order history, the next one is requested with `next_cursor`
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderHistoryPage {
+    pub orders: Vec<HistoricalOrder>,
+    pub next_cursor: Option<String>,
+}
+
 #[derive(Debug)]
 pub struct OrderApi {
     config: apis::configuration::Configuration,
+    metadata: Arc<MetadataCache>,
+    #[cfg(feature = "signer")]
+    signer: Option<FFISigner>, // creates the auth tokens
+    #[cfg(feature = "signer")]
+    tx_client: Option<Arc<TxClient>>, // needed only for the operations that require signing
+    #[cfg(feature = "signer")]
+    clock: Arc<ServerClock>,
+    #[cfg(feature = "signer")]
+    client_order_ids: Arc<ClientOrderIdGenerator>,
+    #[cfg(feature = "signer")]
+    check_reduce_only: bool, // see `LighterConfig::with_reduce_only_check`
 }
 
 impl OrderApi {
     pub fn new(config: &LighterConfig) -> Result<Self> {
+        Self::new_with_configuration(config, Configuration::try_from(config)?)
+    }
+
+    /// Same as [`OrderApi::new`], sending the requests with `configuration` (e.g. shared with the
+    /// other APIs) instead of building one
+    #[cfg_attr(not(feature = "signer"), allow(unused_variables))] // `config` creates the signer
+    pub fn new_with_configuration(
+        config: &LighterConfig,
+        configuration: Configuration,
+    ) -> Result<Self> {
         Ok(Self {
-            config: Configuration::try_from(config)?,
+            metadata: Arc::new(MetadataCache::with_configuration(configuration.clone())),
+            config: configuration,
+            #[cfg(feature = "signer")]
+            signer: match config.api_key_private {
+                Some(_) => Some(FFISigner::try_from(config)?),
+                None => None,
+            },
+            #[cfg(feature = "signer")]
+            tx_client: None,
+            #[cfg(feature = "signer")]
+            clock: config.server_clock.clone(),
+            #[cfg(feature = "signer")]
+            client_order_ids: Arc::new(ClientOrderIdGenerator::from_timestamp()),
+            #[cfg(feature = "signer")]
+            check_reduce_only: config.check_reduce_only,
         })
     }
 
+    /// Max number of orders of a page of [`OrderApi::order_history`]
+    pub const MAX_ORDER_HISTORY_LIMIT: u32 = 100;
+
+    /// Sends the requests with `config` instead of the one built from the `LighterConfig`, the
+    /// markets are loaded with it too (the txs are still sent by the tx client)
+    pub fn with_configuration(mut self, config: Configuration) -> Self {
+        self.metadata = Arc::new(MetadataCache::with_configuration(config.clone()));
+        self.config = config;
+        self
+    }
+
+    #[cfg(all(test, feature = "signer"))]
+    pub(crate) fn signer(&self) -> Option<&FFISigner> {
+        self.signer.as_ref()
+    }
+
+    /// Creates the auth tokens with `signer`, sharing its cached token with the other APIs
+    #[cfg(feature = "signer")]
+    pub(crate) fn with_signer(mut self, signer: Option<FFISigner>) -> Self {
+        self.signer = signer;
+        self
+    }
+
+    /// Shares the metadata cache, e.g. with the other APIs of the client
+    pub(crate) fn with_metadata(mut self, metadata: Arc<MetadataCache>) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Returns the metadata cache used to resolve the symbols
+    pub fn markets(&self) -> &MetadataCache {
+        &self.metadata
+    }
+
+    #[cfg(feature = "signer")]
+    fn auth_token(&self) -> Result<String> {
+        self.signer
+            .as_ref()
+            .ok_or_else(|| LighterError::Config("`api_key_private` is not set".into()))?
+            .get_auth_token(None)
+    }
+
+    #[cfg(not(feature = "signer"))]
+    fn auth_token(&self) -> Result<String> {
+        Err(LighterError::signer_disabled("Creating the auth token"))
+    }
+
+    /// Get the resting orders of the account, on `market_index` or on all the markets. The auth
+    /// token is created with the API key of the config, so it must belong to `account_index`.
+    #[tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(account_index = tracing::field::Empty, market_index = market_index)
+    )]
+    pub async fn active_orders(
+        &self,
+        account_index: impl Into<AccountIndex>,
+        market_index: Option<i32>,
+    ) -> Result<Vec<Order>> {
+        let account_index = account_index.into().0;
+        tracing::Span::current().record("account_index", account_index);
+        let auth_token = self.auth_token()?;
+        let market_indexes = match market_index {
+            Some(market_index) => vec![market_index],
+            None => self.metadata.reload().await?.indexes(),
+        };
+
+        let mut orders = Vec::new();
+        for market_index in market_indexes {
+            let resp = self
+                .account_active_orders(account_index, market_index, Some(&auth_token), None)
+                .await?;
+            orders.extend(resp.orders);
+        }
+        Ok(orders)
+    }
+
     /// Get account active orders. `auth` can be generated using the SDK.
+    #[tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(account_index = account_index, market_index = market_id)
+    )]
     pub async fn account_active_orders(
         &self,
         account_index: i64,
@@ -100,6 +518,11 @@ impl OrderApi {
     }
 
     /// Get account inactive orders
+    #[tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(account_index = account_index, market_index = market_id)
+    )]
     pub async fn account_inactive_orders(
         &self,
         account_index: i64,
@@ -128,7 +551,86 @@ impl OrderApi {
         Ok(resp)
     }
 
+    /// Returns `limit` (1 to [`OrderApi::MAX_ORDER_HISTORY_LIMIT`]) past orders of the account,
+    /// on `market_index` or on all the markets, starting from `cursor` (the `next_cursor` of the
+    /// previous page). The auth token is created with the API key of the config, so it must
+    /// belong to `account_index`.
+    #[tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(account_index = tracing::field::Empty, market_index = market_index)
+    )]
+    pub async fn order_history(
+        &self,
+        account_index: impl Into<AccountIndex>,
+        market_index: Option<i32>,
+        limit: u32,
+        cursor: Option<&str>,
+    ) -> Result<OrderHistoryPage> {
+        let account_index = account_index.into().0;
+        tracing::Span::current().record("account_index", account_index);
+        if !(1..=Self::MAX_ORDER_HISTORY_LIMIT).contains(&limit) {
+            return Err(LighterError::Generic(format!(
+                "Invalid limit {limit}, must be between 1 and {}",
+                Self::MAX_ORDER_HISTORY_LIMIT
+            )));
+        }
+
+        let auth_token = self.auth_token()?;
+        let resp = self
+            .account_inactive_orders(
+                account_index,
+                limit.into(),
+                Some(&auth_token),
+                None,
+                market_index,
+                None,
+                None,
+                cursor,
+            )
+            .await?;
+
+        Ok(OrderHistoryPage {
+            orders: resp
+                .orders
+                .iter()
+                .map(HistoricalOrder::try_from)
+                .collect::<Result<_>>()?,
+            next_cursor: resp.next_cursor.filter(|v| !v.is_empty()),
+        })
+    }
+
+    /// Same as [`OrderApi::order_history`], but going through all the pages (of `limit` orders)
+    /// until the end of the history
+    pub fn order_history_stream(
+        &self,
+        account_index: impl Into<AccountIndex>,
+        market_index: Option<i32>,
+        limit: u32,
+    ) -> impl Stream<Item = Result<HistoricalOrder>> + '_ {
+        let account_index = account_index.into();
+        // `None` once the last page is fetched, `Some(None)` before the first one
+        futures::stream::try_unfold(
+            Some(None),
+            move |cursor: Option<Option<String>>| async move {
+                let Some(cursor) = cursor else {
+                    return Ok::<_, LighterError>(None);
+                };
+                let page = self
+                    .order_history(account_index, market_index, limit, cursor.as_deref())
+                    .await?;
+                let next = page.next_cursor.map(Some);
+                Ok(Some((
+                    futures::stream::iter(page.orders.into_iter().map(Ok)),
+                    next,
+                )))
+            },
+        )
+        .try_flatten()
+    }
+
     /// Get exchange stats
+    #[tracing::instrument(level = "debug", skip_all)]
     pub async fn exchange_stats(&self) -> Result<ExchangeStats> {
         let resp = apis::order_api::exchange_stats(&self.config)
             .await
@@ -138,6 +640,11 @@ impl OrderApi {
     }
 
     /// Export data
+    #[tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(account_index = account_index, market_index = market_id)
+    )]
     pub async fn export(
         &self,
         export_type: ExportType,
@@ -161,6 +668,7 @@ impl OrderApi {
     }
 
     /// Get order books metadata
+    #[tracing::instrument(level = "debug", skip_all, fields(market_index = market_id))]
     pub async fn order_book_details(&self, market_id: Option<i32>) -> Result<OrderBookDetails> {
         let resp = apis::order_api::order_book_details(&self.config, market_id)
             .await
@@ -170,6 +678,7 @@ impl OrderApi {
     }
 
     /// Get order book orders
+    #[tracing::instrument(level = "debug", skip_all, fields(market_index = market_id))]
     pub async fn order_book_orders(&self, market_id: i32, limit: i64) -> Result<OrderBookOrders> {
         let resp = apis::order_api::order_book_orders(&self.config, market_id, limit)
             .await
@@ -178,7 +687,34 @@ impl OrderApi {
         Ok(resp)
     }
 
+    /// Get the price levels of the market, from the `depth` best orders per side (1 to
+    /// [`OrderBookDepth::MAX_DEPTH`], the max by default). Orders with the same price are merged,
+    /// so there can be less than `depth` levels.
+    ///
+    /// The snapshot has no offset, so it can't be synced with the WS updates of
+    /// [`crate::client::OrderBook`].
+    #[tracing::instrument(level = "debug", skip_all, fields(market_index = tracing::field::Empty))]
+    pub async fn order_book(
+        &self,
+        market_index: impl Into<MarketIndex>,
+        depth: Option<u32>,
+    ) -> Result<OrderBookDepth> {
+        let market_index = market_index.into().0;
+        tracing::Span::current().record("market_index", market_index);
+        let depth = depth.unwrap_or(OrderBookDepth::MAX_DEPTH);
+        if !(1..=OrderBookDepth::MAX_DEPTH).contains(&depth) {
+            return Err(LighterError::Generic(format!(
+                "Invalid depth {depth}, must be between 1 and {}",
+                OrderBookDepth::MAX_DEPTH
+            )));
+        }
+
+        let orders = self.order_book_orders(market_index, depth.into()).await?;
+        OrderBookDepth::new(market_index, orders)
+    }
+
     /// Get order books metadata.<hr>**Response Description:**<br><br>1) **Taker and maker fees** are in percentage.<br>2) **Min base amount:** The amount of base token that can be traded in a single order.<br>3) **Min quote amount:** The amount of quote token that can be traded in a single order.<br>4) **Supported size decimals:** The number of decimal places that can be used for the size of the order.<br>5) **Supported price decimals:** The number of decimal places that can be used for the price of the order.<br>6) **Supported quote decimals:** Size Decimals + Quote Decimals.
+    #[tracing::instrument(level = "debug", skip_all, fields(market_index = market_id))]
     pub async fn order_books(&self, market_id: Option<i32>) -> Result<OrderBooks> {
         let resp = apis::order_api::order_books(&self.config, market_id)
             .await
@@ -188,6 +724,7 @@ impl OrderApi {
     }
 
     /// Get recent trades
+    #[tracing::instrument(level = "debug", skip_all, fields(market_index = market_id))]
     pub async fn recent_trades(&self, market_id: i32, limit: i64) -> Result<Trades> {
         let resp = apis::order_api::recent_trades(&self.config, market_id, limit)
             .await
@@ -197,6 +734,11 @@ impl OrderApi {
     }
 
     /// Get trades
+    #[tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(account_index = account_index, market_index = market_id)
+    )]
     pub async fn trades(
         &self,
         sort_by: TradesSortBy,
@@ -231,3 +773,1125 @@ impl OrderApi {
         Ok(resp)
     }
 }
+
+#[cfg(feature = "signer")]
+impl OrderApi {
+    pub(crate) fn with_tx_client(mut self, tx_client: Option<Arc<TxClient>>) -> Self {
+        self.tx_client = tx_client;
+        self
+    }
+
+    pub(crate) fn with_client_order_ids(
+        mut self,
+        client_order_ids: Arc<ClientOrderIdGenerator>,
+    ) -> Self {
+        self.client_order_ids = client_order_ids;
+        self
+    }
+
+    fn tx_client(&self) -> Result<&TxClient> {
+        self.tx_client
+            .as_deref()
+            .ok_or_else(|| LighterError::Config("`api_key_private` is not set".into()))
+    }
+
+    /// Cancel all the orders of the account.
+    ///
+    /// `scheduled_at` is required (and only used) with [`CancelAllTimeInForce::Scheduled`]: all the
+    /// orders will be cancelled at that time, unless this is called again with a later time.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub async fn cancel_all_orders(
+        &self,
+        time_in_force: CancelAllTimeInForce,
+        scheduled_at: Option<DateTime<Utc>>,
+    ) -> Result<RespSendTx> {
+        let time = match (time_in_force, scheduled_at) {
+            (CancelAllTimeInForce::Scheduled, Some(scheduled_at)) => {
+                if scheduled_at <= self.clock.server_now() {
+                    return Err(LighterError::OrderValidation(
+                        "`scheduled_at` must be in the future".into(),
+                    ));
+                }
+                scheduled_at.timestamp_millis()
+            }
+            (CancelAllTimeInForce::Scheduled, None) => {
+                return Err(LighterError::OrderValidation(
+                    "`scheduled_at` is required for a scheduled cancel".into(),
+                ))
+            }
+            _ => 0,
+        };
+
+        self.tx_client()?
+            .submit(|signer, nonce| {
+                signer.sign_cancel_all_orders(
+                    SignCancelAllOrdersData {
+                        time_in_force: time_in_force.into(),
+                        time,
+                    },
+                    nonce,
+                )
+            })
+            .await
+            .inspect_err(|e| tracing::error!("unable to call `cancel_all_orders`: {e}"))
+    }
+
+    /// Sign and submit a new order
+    ///
+    /// If `order.client_order_index` is [`CreateOrderData::AUTO_CLIENT_ORDER_INDEX`], a unique one
+    /// is assigned and returned with the response.
+    ///
+    /// The order is first checked against the market constraints (see
+    /// [`CreateOrderData::validate`]), so that an invalid one doesn't use a nonce. With
+    /// [`LighterConfig::with_reduce_only_check`], a reduce only order is checked against the
+    /// position of the account too (see [`CreateOrderData::validate_reduce_only`]).
+    ///
+    /// When the submission fails without an answer (e.g. a timeout, see
+    /// [`LighterError::is_transient`]), the order may have landed anyway: it's submitted again with
+    /// the same `client_order_index`, which can't be placed twice (see
+    /// [`TxClient::submit_idempotent`]). A rejection as [`TxResultCode::DuplicateClientOrderIndex`]
+    /// then means that the first attempt went through, and `resp` is `None`.
+    #[tracing::instrument(level = "debug", skip_all, fields(market_index = order.market_index))]
+    pub async fn place_order(&self, mut order: CreateOrderData) -> Result<PlacedOrder> {
+        let tx_client = self.tx_client()?;
+        let market = self.metadata.market_info(order.market_index).await?;
+        order.validate(&market)?;
+        if order.reduce_only && self.check_reduce_only {
+            let position = self
+                .position(tx_client.account_index(), order.market_index)
+                .await?;
+            order.validate_reduce_only(position.as_ref(), &market)?;
+        }
+        if order.client_order_index == CreateOrderData::AUTO_CLIENT_ORDER_INDEX {
+            order.client_order_index = self.client_order_ids.next_id();
+        }
+
+        let resp = tx_client
+            .submit_idempotent(
+                |signer, nonce| signer.sign_create_order(order.clone(), nonce),
+                TxResultCode::DuplicateClientOrderIndex,
+            )
+            .await
+            .inspect_err(|e| tracing::error!("unable to call `place_order`: {e}"))?;
+        if resp.is_none() {
+            tracing::info!(
+                "order {} already placed by a previous attempt",
+                order.client_order_index
+            );
+        }
+
+        Ok(PlacedOrder {
+            client_order_index: order.client_order_index,
+            resp,
+        })
+    }
+
+    /// Returns the position of the account on the market, see
+    /// [`DetailedAccount::position`](crate::models::DetailedAccount::position)
+    async fn position(&self, account_index: i64, market_index: i32) -> Result<Option<Position>> {
+        let accounts =
+            apis::account_api::account(&self.config, "index", &account_index.to_string())
+                .await
+                .inspect_err(|e| tracing::error!("unable to call `account`: {e}"))?;
+        accounts
+            .accounts
+            .first()
+            .ok_or_else(|| LighterError::AccountNotFound(account_index.to_string()))?
+            .position(market_index)
+    }
+
+    /// Same as [`OrderApi::place_order`], but on the market with the given symbol (e.g. `ETH` or
+    /// `ETH-USD`) instead of `order.market_index`
+    #[tracing::instrument(level = "debug", skip_all, fields(symbol = symbol))]
+    pub async fn place_order_by_symbol(
+        &self,
+        symbol: &str,
+        mut order: CreateOrderData,
+    ) -> Result<PlacedOrder> {
+        order.market_index = self.metadata.index_of(symbol).await?;
+        self.place_order(order).await
+    }
+
+    /// Cancel the order `order_index` and place `replacement` (a single order or grouped orders)
+    /// in a single request.
+    ///
+    /// Grouped orders (see [`GroupingType`]) can only link orders created together, they can't
+    /// cancel an order already in the book, so the protocol has no atomic cancel-and-replace.
+    /// Instead, the cancel and the replacement are signed with consecutive nonces and sent in the
+    /// same `send_tx_batch`, so they are executed back to back and in order. The batch is not
+    /// all-or-nothing though: if the cancel fails (e.g. the order got filled in the meantime) the
+    /// replacement can still be executed.
+    #[tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(market_index = tracing::field::Empty, order_index = order_index)
+    )]
+    pub async fn replace_order(
+        &self,
+        market_index: impl Into<MarketIndex>,
+        order_index: i64,
+        replacement: impl Into<ReplacementOrder>,
+    ) -> Result<RespSendTxBatch> {
+        let market_index = market_index.into().0;
+        tracing::Span::current().record("market_index", market_index);
+        let replacement = replacement.into();
+
+        self.tx_client()?
+            .submit_batch(2, |signer, nonces| {
+                let cancel = signer.sign_cancel_order(
+                    SignCancelOrderData {
+                        market_index,
+                        order_index,
+                    },
+                    nonces[0],
+                )?;
+                let create = match replacement.clone() {
+                    ReplacementOrder::Single(data) => signer.sign_create_order(data, nonces[1])?,
+                    ReplacementOrder::Grouped(data) => {
+                        signer.sign_create_grouped_orders(data, nonces[1])?
+                    }
+                };
+                Ok(vec![cancel, create])
+            })
+            .await
+            .inspect_err(|e| tracing::error!("unable to call `replace_order`: {e}"))
+    }
+
+    /// Cancel the given orders, as `(market_index, order_index)` pairs, returning the outcome of
+    /// each cancel in the same order.
+    ///
+    /// The protocol has no batch cancel, and a `send_tx_batch` only reports a single code for
+    /// all its txs. Instead, the cancels are signed with a block of consecutive nonces and sent
+    /// concurrently one by one (see [`TxClient::submit_each`]), so that a failed cancel (e.g. of
+    /// an order already filled) doesn't hide the others. The failed ones are not retried.
+    #[tracing::instrument(level = "debug", skip_all, fields(orders = orders.len()))]
+    pub async fn cancel_orders(
+        &self,
+        orders: Vec<(MarketIndex, i64)>,
+    ) -> Result<Vec<CancelledOrder>> {
+        if orders.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let results = self
+            .tx_client()?
+            .submit_each(orders.len(), |signer, nonces| {
+                orders
+                    .iter()
+                    .zip(nonces)
+                    .map(|((market_index, order_index), nonce)| {
+                        signer.sign_cancel_order(
+                            SignCancelOrderData {
+                                market_index: market_index.0,
+                                order_index: *order_index,
+                            },
+                            *nonce,
+                        )
+                    })
+                    .collect()
+            })
+            .await
+            .inspect_err(|e| tracing::error!("unable to call `cancel_orders`: {e}"))?;
+
+        Ok(orders
+            .into_iter()
+            .zip(results)
+            .map(|((market_index, order_index), result)| {
+                if let Err(e) = &result {
+                    tracing::warn!("unable to cancel order {order_index}: {e}");
+                }
+                CancelledOrder {
+                    market_index,
+                    order_index,
+                    result,
+                }
+            })
+            .collect())
+    }
+
+    /// Schedule the cancellation of all the orders after `after` (dead man's switch). Calling it
+    /// again before the deadline pushes it further.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub async fn cancel_all_orders_after(&self, after: std::time::Duration) -> Result<RespSendTx> {
+        let after = chrono::Duration::from_std(after)
+            .map_err(|e| LighterError::OrderValidation(format!("Invalid duration: {e}")))?;
+        let scheduled_at = self.clock.server_now() + after;
+        self.cancel_all_orders(CancelAllTimeInForce::Scheduled, Some(scheduled_at))
+            .await
+    }
+}
+
+#[cfg(all(test, feature = "signer"))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_cancel_all_orders_scheduled_requires_time() {
+        let api = OrderApi::new(&LighterConfig::new()).unwrap();
+
+        let res = api
+            .cancel_all_orders(CancelAllTimeInForce::Scheduled, None)
+            .await;
+        assert!(matches!(res, Err(LighterError::OrderValidation(_))));
+
+        let res = api
+            .cancel_all_orders(
+                CancelAllTimeInForce::Scheduled,
+                Some(Utc::now() - chrono::Duration::seconds(1)),
+            )
+            .await;
+        assert!(matches!(res, Err(LighterError::OrderValidation(_))));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_all_orders_scheduled_on_server_time() {
+        let config = LighterConfig::new();
+        config
+            .server_clock
+            .set_offset(chrono::Duration::minutes(10));
+        let api = OrderApi::new(&config).unwrap();
+
+        // in the future locally, but already in the past for the server
+        let res = api
+            .cancel_all_orders(
+                CancelAllTimeInForce::Scheduled,
+                Some(Utc::now() + chrono::Duration::minutes(1)),
+            )
+            .await;
+        assert!(matches!(res, Err(LighterError::OrderValidation(_))));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_all_orders_requires_signer() {
+        let api = OrderApi::new(&LighterConfig::new()).unwrap();
+
+        let res = api
+            .cancel_all_orders(CancelAllTimeInForce::Immediate, None)
+            .await;
+        assert!(matches!(res, Err(LighterError::Config(_))));
+    }
+
+    fn mock_config(base_url: String) -> LighterConfig {
+        LighterConfig {
+            base_url,
+            local_nonce: false,
+            ..LighterConfig::new()
+                .with_api_key_private(
+                    "01db9eed031d59d6bd0ee00ee5a7dc1f62087bf217b51caea57eb6e17a02c49e0a748d2f155a2f60",
+                )
+                .with_account_index(28)
+                .with_api_key_index(2)
+        }
+    }
+
+    /// Mocks the exchange info, loaded along with the markets
+    fn exchange_info(server: &mut mockito::ServerGuard) -> mockito::Mock {
+        server
+            .mock("GET", "/info")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"contract_address":"0x1"}"#)
+    }
+
+    /// Mocks the details of market 1, validating `create_order_data`
+    fn market_details(server: &mut mockito::ServerGuard) -> mockito::Mock {
+        let detail = crate::models::OrderBookDetail {
+            symbol: "BTC".into(),
+            market_id: 1,
+            status: crate::models::order_book_detail::Status::Active,
+            min_base_amount: "0".into(),
+            min_quote_amount: "0".into(),
+            ..Default::default()
+        };
+        server
+            .mock("GET", "/api/v1/orderBookDetails")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(serde_json::json!({"code": 200, "order_book_details": [detail]}).to_string())
+    }
+
+    fn create_order_data() -> CreateOrderData {
+        CreateOrderData {
+            market_index: 1,
+            client_order_index: 1,
+            base_amount: 1,
+            price: 1,
+            is_ask: true,
+            order_type: OrderType::Limit.into(),
+            time_in_force: TimeInForce::GoodTillTime.into(),
+            reduce_only: false,
+            trigger_price: 0,
+            order_expiry: (Utc::now() + chrono::Duration::days(1)).timestamp_millis(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_place_order() {
+        let mut server = mockito::Server::new_async().await;
+        market_details(&mut server).create_async().await;
+        let send_tx = server
+            .mock("POST", "/api/v1/sendTx")
+            .match_body(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::Regex(r#"name="tx_type"\r\n\r\n14\r\n"#.into()),
+                mockito::Matcher::Regex(r#""Nonce":5,"#.into()),
+            ]))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"tx_hash":"0x1","predicted_execution_time_ms":1}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/api/v1/nextNonce")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"nonce":5}"#)
+            .create_async()
+            .await;
+
+        let config = mock_config(server.url());
+        let api = OrderApi::new(&config)
+            .unwrap()
+            .with_tx_client(Some(Arc::new(TxClient::new(&config, None).unwrap())));
+
+        let placed = api.place_order(create_order_data()).await.unwrap();
+        assert_eq!(placed.tx_hash(), Some("0x1"));
+        assert_eq!(placed.client_order_index, 1);
+        assert!(!placed.already_placed());
+        send_tx.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_place_order_retries_after_timeout() {
+        let mut server = mockito::Server::new_async().await;
+        market_details(&mut server).create_async().await;
+        // the first attempt lands, but its response comes too late
+        let timeout = server
+            .mock("POST", "/api/v1/sendTx")
+            .match_body(mockito::Matcher::Regex(r#""ClientOrderIndex":1,"#.into()))
+            .with_header("content-type", "application/json")
+            .with_chunked_body(|w| {
+                std::thread::sleep(std::time::Duration::from_millis(1500));
+                w.write_all(br#"{"code":200,"tx_hash":"0x1","predicted_execution_time_ms":1}"#)
+            })
+            .expect(1)
+            .create_async()
+            .await;
+        let duplicate = server
+            .mock("POST", "/api/v1/sendTx")
+            .match_body(mockito::Matcher::Regex(r#""ClientOrderIndex":1,"#.into()))
+            .with_status(400)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":21711,"message":"duplicate client order index"}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/api/v1/nextNonce")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"nonce":5}"#)
+            .create_async()
+            .await;
+
+        let config = mock_config(server.url()).with_timeout(1);
+        let api = OrderApi::new(&config)
+            .unwrap()
+            .with_tx_client(Some(Arc::new(TxClient::new(&config, None).unwrap())));
+
+        let placed = api.place_order(create_order_data()).await.unwrap();
+        assert!(placed.already_placed());
+        assert_eq!(placed.tx_hash(), None);
+        assert_eq!(placed.client_order_index, 1);
+        timeout.assert_async().await;
+        duplicate.assert_async().await;
+
+        // but a first attempt rejected as a duplicate is an error
+        let res = api.place_order(create_order_data()).await;
+        assert!(matches!(
+            res,
+            Err(LighterError::ApiError {
+                code: Some(21711),
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_place_order_gives_up_after_max_attempts() {
+        let mut server = mockito::Server::new_async().await;
+        market_details(&mut server).create_async().await;
+        // no answer, retried by the tx client only
+        let unavailable = server
+            .mock("POST", "/api/v1/sendTx")
+            .with_status(503)
+            .expect(3)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/api/v1/nextNonce")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"nonce":5}"#)
+            .create_async()
+            .await;
+
+        let config = mock_config(server.url());
+        let api = OrderApi::new(&config)
+            .unwrap()
+            .with_tx_client(Some(Arc::new(TxClient::new(&config, None).unwrap())));
+
+        let res = api.place_order(create_order_data()).await;
+        assert_eq!(res.unwrap_err().http_status(), Some(503));
+        unavailable.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_place_order_assigns_client_order_index() {
+        let mut server = mockito::Server::new_async().await;
+        market_details(&mut server).create_async().await;
+        let send_tx = server
+            .mock("POST", "/api/v1/sendTx")
+            .match_body(mockito::Matcher::Regex(r#""ClientOrderIndex":42,"#.into()))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"tx_hash":"0x1","predicted_execution_time_ms":1}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/api/v1/nextNonce")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"nonce":5}"#)
+            .create_async()
+            .await;
+
+        let config = mock_config(server.url());
+        let api = OrderApi::new(&config)
+            .unwrap()
+            .with_tx_client(Some(Arc::new(TxClient::new(&config, None).unwrap())))
+            .with_client_order_ids(Arc::new(ClientOrderIdGenerator::new(42)));
+
+        let order = CreateOrderData {
+            client_order_index: CreateOrderData::AUTO_CLIENT_ORDER_INDEX,
+            ..create_order_data()
+        };
+        let placed = api.place_order(order).await.unwrap();
+        assert_eq!(placed.client_order_index, 42);
+        send_tx.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_place_order_by_symbol() {
+        let mut server = mockito::Server::new_async().await;
+        exchange_info(&mut server).create_async().await;
+        market_details(&mut server).create_async().await;
+        server
+            .mock("GET", "/api/v1/orderBooks")
+            .with_header("content-type", "application/json")
+            .with_body(
+                serde_json::to_string(&OrderBooks::new(
+                    200,
+                    vec![crate::models::OrderBook {
+                        symbol: "BTC".into(),
+                        market_id: 1,
+                        ..Default::default()
+                    }],
+                ))
+                .unwrap(),
+            )
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/api/v1/nextNonce")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"nonce":5}"#)
+            .create_async()
+            .await;
+        let send_tx = server
+            .mock("POST", "/api/v1/sendTx")
+            .match_body(mockito::Matcher::Regex(r#""MarketIndex":1,"#.into()))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"tx_hash":"0x1","predicted_execution_time_ms":1}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let config = mock_config(server.url());
+        let api = OrderApi::new(&config)
+            .unwrap()
+            .with_tx_client(Some(Arc::new(TxClient::new(&config, None).unwrap())));
+
+        let order = CreateOrderData {
+            market_index: 0,
+            ..create_order_data()
+        };
+        api.place_order_by_symbol("BTC-USD", order).await.unwrap();
+        send_tx.assert_async().await;
+
+        assert!(matches!(
+            api.place_order_by_symbol("SOL", create_order_data()).await,
+            Err(LighterError::MarketNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_place_order_rejects_invalid_order() {
+        let mut server = mockito::Server::new_async().await;
+        let details = market_details(&mut server).expect(1).create_async().await;
+        let next_nonce = server
+            .mock("GET", "/api/v1/nextNonce")
+            .match_query(mockito::Matcher::Any)
+            .expect(0)
+            .create_async()
+            .await;
+
+        let config = mock_config(server.url());
+        let api = OrderApi::new(&config)
+            .unwrap()
+            .with_tx_client(Some(Arc::new(TxClient::new(&config, None).unwrap())));
+
+        for order in [
+            CreateOrderData {
+                base_amount: 0,
+                ..create_order_data()
+            },
+            CreateOrderData {
+                price: 0,
+                ..create_order_data()
+            },
+        ] {
+            let res = api.place_order(order).await;
+            assert!(matches!(res, Err(LighterError::OrderValidation(_))));
+        }
+        // the market details are cached
+        details.assert_async().await;
+        next_nonce.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_place_order_checks_reduce_only() {
+        let mut server = mockito::Server::new_async().await;
+        market_details(&mut server).create_async().await;
+        let account = crate::models::DetailedAccounts::new(
+            200,
+            1,
+            vec![crate::models::DetailedAccount {
+                index: 28,
+                positions: vec![crate::models::AccountPosition {
+                    market_id: 1,
+                    symbol: "BTC".into(),
+                    initial_margin_fraction: "10".into(),
+                    sign: 1,
+                    position: "0.5".into(),
+                    avg_entry_price: "60000".into(),
+                    position_value: "30000".into(),
+                    unrealized_pnl: "0".into(),
+                    realized_pnl: "0".into(),
+                    liquidation_price: "0".into(),
+                    allocated_margin: "0".into(),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+        );
+        let account = server
+            .mock("GET", "/api/v1/account")
+            .match_query(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::UrlEncoded("by".into(), "index".into()),
+                mockito::Matcher::UrlEncoded("value".into(), "28".into()),
+            ]))
+            .with_header("content-type", "application/json")
+            .with_body(serde_json::to_string(&account).unwrap())
+            .expect(2)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/api/v1/nextNonce")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"nonce":5}"#)
+            .create_async()
+            .await;
+        let send_tx = server
+            .mock("POST", "/api/v1/sendTx")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"tx_hash":"0x1","predicted_execution_time_ms":1}"#)
+            .expect(2)
+            .create_async()
+            .await;
+
+        let config = mock_config(server.url()).with_reduce_only_check(true);
+        let api = OrderApi::new(&config)
+            .unwrap()
+            .with_tx_client(Some(Arc::new(TxClient::new(&config, None).unwrap())));
+
+        // a buy would increase the long position
+        let order = CreateOrderData {
+            is_ask: false,
+            reduce_only: true,
+            ..create_order_data()
+        };
+        let res = api.place_order(order).await;
+        assert!(
+            matches!(&res, Err(LighterError::OrderValidation(e)) if e.contains("increase")),
+            "{res:?}"
+        );
+
+        let order = CreateOrderData {
+            is_ask: true,
+            reduce_only: true,
+            ..create_order_data()
+        };
+        api.place_order(order).await.unwrap();
+        // not fetched for the other orders
+        api.place_order(create_order_data()).await.unwrap();
+
+        account.assert_async().await;
+        send_tx.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_replace_order_requires_signer() {
+        let api = OrderApi::new(&LighterConfig::new()).unwrap();
+
+        let res = api.replace_order(1, 1, create_order_data()).await;
+        assert!(matches!(res, Err(LighterError::Config(_))));
+    }
+
+    #[tokio::test]
+    async fn test_replace_order_sends_cancel_then_create() {
+        let mut server = mockito::Server::new_async().await;
+        let next_nonce = server
+            .mock("GET", "/api/v1/nextNonce")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"nonce":5}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let send_tx_batch = server
+            .mock("POST", "/api/v1/sendTxBatch")
+            .match_body(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::Regex(r"\[15,14\]".into()),
+                mockito::Matcher::Regex(r#"Nonce\\":5,"#.into()),
+                mockito::Matcher::Regex(r#"Nonce\\":6,"#.into()),
+            ]))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"tx_hash":["0x1","0x2"],"predicted_execution_time_ms":1}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let config = mock_config(server.url());
+        let tx_client = Arc::new(TxClient::new(&config, None).unwrap());
+        let api = OrderApi::new(&config)
+            .unwrap()
+            .with_tx_client(Some(tx_client));
+
+        let resp = api.replace_order(1, 1, create_order_data()).await.unwrap();
+        assert_eq!(resp.tx_hash.len(), 2);
+
+        next_nonce.assert_async().await;
+        send_tx_batch.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_cancel_orders_reports_each_outcome() {
+        let mut server = mockito::Server::new_async().await;
+        let next_nonce = server
+            .mock("GET", "/api/v1/nextNonce")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"nonce":5}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let cancelled = server
+            .mock("POST", "/api/v1/sendTx")
+            .match_body(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::Regex(r#""Index":10,"#.into()),
+                mockito::Matcher::Regex(r#""Nonce":5,"#.into()),
+            ]))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"tx_hash":"0x1","predicted_execution_time_ms":1}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let filled = server
+            .mock("POST", "/api/v1/sendTx")
+            .match_body(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::Regex(r#""Index":11,"#.into()),
+                mockito::Matcher::Regex(r#""Nonce":6,"#.into()),
+            ]))
+            .with_status(400)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":21501,"message":"order not found"}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let config = mock_config(server.url());
+        let api = OrderApi::new(&config)
+            .unwrap()
+            .with_tx_client(Some(Arc::new(TxClient::new(&config, None).unwrap())));
+
+        let res = api
+            .cancel_orders(vec![(MarketIndex(1), 10), (MarketIndex(2), 11)])
+            .await
+            .unwrap();
+        assert_eq!(res.len(), 2);
+        assert_eq!(res[0].order_index, 10);
+        assert_eq!(res[0].result.as_ref().unwrap().tx_hash, "0x1");
+        assert_eq!(res[1].market_index, MarketIndex(2));
+        assert!(matches!(
+            res[1].result,
+            Err(LighterError::ApiError {
+                code: Some(21501),
+                ..
+            })
+        ));
+
+        next_nonce.assert_async().await;
+        cancelled.assert_async().await;
+        filled.assert_async().await;
+
+        assert!(api.cancel_orders(Vec::new()).await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_add_amounts() {
+        assert_eq!(add_amounts("0.1", "0.2").unwrap(), "0.3");
+        assert_eq!(add_amounts("1.25", "0.0075").unwrap(), "1.2575");
+        assert_eq!(add_amounts("0.05", "0.05").unwrap(), "0.10");
+        assert_eq!(add_amounts("12", "30").unwrap(), "42");
+        assert!(add_amounts("1.0", "-1").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_order_book() {
+        let mut server = mockito::Server::new_async().await;
+        let order = |price: &str, size: &str| SimpleOrder {
+            price: price.into(),
+            remaining_base_amount: size.into(),
+            ..Default::default()
+        };
+        let orders = OrderBookOrders::new(
+            200,
+            3,
+            vec![
+                order("3001.50", "1.0"),
+                order("3001.00", "0.1"),
+                order("3001.00", "0.2"),
+            ],
+            2,
+            vec![order("2999.00", "1.5"), order("3000.00", "2.0")],
+        );
+        let mock = server
+            .mock("GET", "/api/v1/orderBookOrders")
+            .match_query(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::UrlEncoded("market_id".into(), "1".into()),
+                mockito::Matcher::UrlEncoded("limit".into(), "100".into()),
+            ]))
+            .with_header("content-type", "application/json")
+            .with_body(serde_json::to_string(&orders).unwrap())
+            .expect(1)
+            .create_async()
+            .await;
+
+        let api = OrderApi::new(&mock_config(server.url())).unwrap();
+        let depth = api.order_book(1, None).await.unwrap();
+        assert_eq!(depth.market_index, 1);
+        assert_eq!(
+            depth.asks,
+            vec![
+                PriceLevel::new("3001.00".into(), "0.3".into()),
+                PriceLevel::new("3001.50".into(), "1.0".into()),
+            ]
+        );
+        assert_eq!(
+            depth.bids,
+            vec![
+                PriceLevel::new("3000.00".into(), "2.0".into()),
+                PriceLevel::new("2999.00".into(), "1.5".into()),
+            ]
+        );
+        mock.assert_async().await;
+
+        for depth in [0, OrderBookDepth::MAX_DEPTH + 1] {
+            assert!(matches!(
+                api.order_book(1, Some(depth)).await,
+                Err(LighterError::Generic(_))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_active_orders_all_markets() {
+        let mut server = mockito::Server::new_async().await;
+        exchange_info(&mut server).create_async().await;
+        let order_books = crate::models::OrderBooks::new(
+            200,
+            [("ETH", 0), ("BTC", 1)]
+                .into_iter()
+                .map(|(symbol, market_id)| crate::models::OrderBook {
+                    symbol: symbol.into(),
+                    market_id,
+                    ..Default::default()
+                })
+                .collect(),
+        );
+        server
+            .mock("GET", "/api/v1/orderBooks")
+            .with_header("content-type", "application/json")
+            .with_body(serde_json::to_string(&order_books).unwrap())
+            .create_async()
+            .await;
+        let orders = |orders: Vec<Order>| serde_json::to_string(&Orders::new(200, orders)).unwrap();
+        let eth = server
+            .mock("GET", "/api/v1/accountActiveOrders")
+            .match_query(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::UrlEncoded("account_index".into(), "28".into()),
+                mockito::Matcher::UrlEncoded("market_id".into(), "0".into()),
+            ]))
+            .match_header("authorization", mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(orders(vec![Order {
+                order_index: 7,
+                client_order_index: 3,
+                market_index: 0,
+                ..Default::default()
+            }]))
+            .expect(1)
+            .create_async()
+            .await;
+        let btc = server
+            .mock("GET", "/api/v1/accountActiveOrders")
+            .match_query(mockito::Matcher::UrlEncoded("market_id".into(), "1".into()))
+            .with_header("content-type", "application/json")
+            .with_body(orders(vec![]))
+            .expect(2)
+            .create_async()
+            .await;
+
+        let api = OrderApi::new(&mock_config(server.url())).unwrap();
+        let orders = api.active_orders(28, None).await.unwrap();
+        assert_eq!(orders.len(), 1);
+        assert_eq!(
+            (orders[0].order_index, orders[0].client_order_index),
+            (7, 3)
+        );
+
+        assert!(api
+            .active_orders(AccountIndex(28), Some(1))
+            .await
+            .unwrap()
+            .is_empty());
+
+        eth.assert_async().await;
+        btc.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_order_history() {
+        let mut server = mockito::Server::new_async().await;
+        let order =
+            |order_index, status, filled_base_amount: &str, filled_quote_amount: &str| Order {
+                order_index,
+                market_index: 1,
+                initial_base_amount: "2.0".into(),
+                price: "100.0".into(),
+                filled_base_amount: filled_base_amount.into(),
+                filled_quote_amount: filled_quote_amount.into(),
+                status,
+                timestamp: 1_700_000_000_000,
+                ..Default::default()
+            };
+        let page = |orders: Vec<Order>, next_cursor: &str| {
+            let mut orders = Orders::new(200, orders);
+            orders.next_cursor = Some(next_cursor.into());
+            serde_json::to_string(&orders).unwrap()
+        };
+        // the last page first, since the first one matches its query too
+        let last = server
+            .mock("GET", "/api/v1/accountInactiveOrders")
+            .match_query(mockito::Matcher::UrlEncoded("cursor".into(), "next".into()))
+            .with_header("content-type", "application/json")
+            .with_body(page(vec![order(3, OrderStatus::Canceled, "0", "0")], ""))
+            .expect(2)
+            .create_async()
+            .await;
+        let first = server
+            .mock("GET", "/api/v1/accountInactiveOrders")
+            .match_query(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::UrlEncoded("account_index".into(), "28".into()),
+                mockito::Matcher::UrlEncoded("market_id".into(), "1".into()),
+                mockito::Matcher::UrlEncoded("limit".into(), "2".into()),
+            ]))
+            .match_header("authorization", mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(page(
+                vec![
+                    order(1, OrderStatus::Filled, "2.0", "201.0"),
+                    order(2, OrderStatus::CanceledExpired, "0.5", "49.5"),
+                ],
+                "next",
+            ))
+            .expect(2)
+            .create_async()
+            .await;
+
+        let api = OrderApi::new(&mock_config(server.url())).unwrap();
+        let page = api.order_history(28, Some(1), 2, None).await.unwrap();
+        assert_eq!(page.next_cursor.as_deref(), Some("next"));
+        let filled = &page.orders[0];
+        assert_eq!(filled.fill_status, FillStatus::Filled);
+        assert_eq!(
+            (filled.size, filled.filled_size, filled.average_fill_price),
+            (2.0, 2.0, Some(100.5))
+        );
+        assert_eq!(page.orders[1].fill_status, FillStatus::PartiallyFilled);
+        assert_eq!(page.orders[1].average_fill_price, Some(99.0));
+
+        let page = api
+            .order_history(28, Some(1), 2, page.next_cursor.as_deref())
+            .await
+            .unwrap();
+        assert_eq!(page.orders[0].fill_status, FillStatus::Cancelled);
+        assert_eq!(page.orders[0].average_fill_price, None);
+        assert_eq!(page.next_cursor, None);
+
+        // all the pages, until the end of the history
+        let orders = api
+            .order_history_stream(28, Some(1), 2)
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(
+            orders.iter().map(|v| v.order_index).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+
+        first.assert_async().await;
+        last.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_order_history_keeps_auth_token_out_of_url() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/api/v1/accountInactiveOrders")
+            .match_query(mockito::Matcher::Any)
+            .match_request(|req| !req.path_and_query().contains("auth="))
+            .match_header("authorization", mockito::Matcher::Regex(".+".into()))
+            .with_header("content-type", "application/json")
+            .with_body(serde_json::to_string(&Orders::new(200, vec![])).unwrap())
+            .expect(1)
+            .create_async()
+            .await;
+
+        let api = OrderApi::new(&mock_config(server.url())).unwrap();
+        let page = api.order_history(28, None, 10, None).await.unwrap();
+        assert!(page.orders.is_empty());
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_order_history_rejects_limit() {
+        let api = OrderApi::new(&mock_config("http://127.0.0.1:1".into())).unwrap();
+        for limit in [0, OrderApi::MAX_ORDER_HISTORY_LIMIT + 1] {
+            let res = api.order_history(28, None, limit, None).await;
+            assert!(
+                matches!(&res, Err(LighterError::Generic(e)) if e.contains("Invalid limit")),
+                "{res:?}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_with_configuration() {
+        let mut server = mockito::Server::new_async().await;
+        exchange_info(&mut server).create_async().await;
+        let order_books = server
+            .mock("GET", "/api/v1/orderBooks")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"order_books":[]}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let config = LighterConfig::new();
+        let configuration = Configuration {
+            base_path: server.url(),
+            ..Configuration::try_from(&config).unwrap()
+        };
+        let api = OrderApi::new(&config)
+            .unwrap()
+            .with_configuration(configuration);
+
+        // the markets are loaded from the mock server too
+        assert!(matches!(
+            api.markets().index_of("ETH").await,
+            Err(LighterError::MarketNotFound(_))
+        ));
+        order_books.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_active_orders_requires_api_key() {
+        let api = OrderApi::new(&LighterConfig::new()).unwrap();
+        assert!(matches!(
+            api.active_orders(28, None).await,
+            Err(LighterError::Config(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_active_orders_testnet() {
+        let config = LighterConfig::new()
+            .with_base_url("https://testnet.zklighter.elliot.ai")
+            .unwrap()
+            .with_api_key_private(
+                "01db9eed031d59d6bd0ee00ee5a7dc1f62087bf217b51caea57eb6e17a02c49e0a748d2f155a2f60",
+            )
+            .with_account_index(28)
+            .with_api_key_index(2);
+        let api = OrderApi::new(&config).unwrap();
+
+        // the account may have no resting orders
+        let orders = api.active_orders(28, Some(0)).await.unwrap();
+        for order in &orders {
+            assert_eq!(order.market_index, 0, "{order:?}");
+            assert_eq!(order.owner_account_index, 28, "{order:?}");
+            assert!(
+                matches!(
+                    order.status,
+                    OrderStatus::InProgress | OrderStatus::Pending | OrderStatus::Open
+                ),
+                "{order:?}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_order_history_testnet() {
+        let config = LighterConfig::new()
+            .with_base_url("https://testnet.zklighter.elliot.ai")
+            .unwrap()
+            .with_api_key_private(
+                "01db9eed031d59d6bd0ee00ee5a7dc1f62087bf217b51caea57eb6e17a02c49e0a748d2f155a2f60",
+            )
+            .with_account_index(28)
+            .with_api_key_index(2);
+        let api = OrderApi::new(&config).unwrap();
+
+        // the account may have no past orders
+        let page = api.order_history(28, Some(0), 10, None).await.unwrap();
+        assert!(page.orders.len() <= 10);
+        assert!(page.orders.iter().all(|order| order.market_index == 0));
+        // newest first
+        assert!(page
+            .orders
+            .windows(2)
+            .all(|v| v[0].timestamp >= v[1].timestamp));
+    }
+}
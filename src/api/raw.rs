@@ -0,0 +1,169 @@
+use reqwest::Method;
+use reqwest_middleware::RequestBuilder;
+use serde_json::Value;
+
+use crate::{
+    apis::configuration::Configuration, config::LighterConfig, error::LighterError, Result,
+};
+
+#[cfg(feature = "signer")]
+use crate::signer::FFISigner;
+
+/// Untyped access to any endpoint, e.g. to the ones not mapped yet by the crate.
+///
+/// The requests go through the same middleware (retries, rate limiting, ..) as the typed APIs,
+/// and carry the auth token if the API key is set, but the responses are returned as JSON
+/// without any check of their content: nothing protects against a renamed or missing field.
+#[derive(Debug)]
+pub struct RawApi {
+    config: Configuration,
+    #[cfg(feature = "signer")]
+    signer: Option<FFISigner>,
+}
+
+impl RawApi {
+    pub fn new(config: &LighterConfig) -> Result<Self> {
+        Self::new_with_configuration(config, Configuration::try_from(config)?)
+    }
+
+    /// Same as [`RawApi::new`], sending the requests with `configuration` (e.g. shared with the
+    /// other APIs) instead of building one
+    #[cfg_attr(not(feature = "signer"), allow(unused_variables))] // `config` creates the signer
+    pub fn new_with_configuration(
+        config: &LighterConfig,
+        configuration: Configuration,
+    ) -> Result<Self> {
+        #[cfg(feature = "signer")]
+        let signer = match config.api_key_private {
+            Some(_) => Some(FFISigner::try_from(config)?),
+            None => None,
+        };
+
+        Ok(Self {
+            config: configuration,
+            #[cfg(feature = "signer")]
+            signer,
+        })
+    }
+
+    /// Sends the requests with `config` instead of the one built from the `LighterConfig`
+    pub fn with_configuration(mut self, config: Configuration) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Creates the auth tokens with `signer`, sharing its cached token with the other APIs
+    #[cfg(feature = "signer")]
+    pub(crate) fn with_signer(mut self, signer: Option<FFISigner>) -> Self {
+        self.signer = signer;
+        self
+    }
+
+    /// Sends a GET request to `path` (e.g. `/api/v1/orderBooks`) with the `query` parameters
+    #[tracing::instrument(level = "debug", skip_all, fields(path = path))]
+    pub async fn get(&self, path: &str, query: &[(&str, &str)]) -> Result<Value> {
+        let builder = self.request(Method::GET, path)?.query(query);
+        self.send(path, builder).await
+    }
+
+    /// Sends a POST request to `path` with the `form` fields, url encoded. Like any other request
+    /// it's retried on the transient errors, so the endpoint should be idempotent.
+    #[tracing::instrument(level = "debug", skip_all, fields(path = path))]
+    pub async fn post(&self, path: &str, form: &[(&str, &str)]) -> Result<Value> {
+        let builder = self.request(Method::POST, path)?.form(form);
+        self.send(path, builder).await
+    }
+
+    fn request(&self, method: Method, path: &str) -> Result<RequestBuilder> {
+        let uri_str = format!(
+            "{}/{}",
+            self.config.base_path.trim_end_matches('/'),
+            path.trim_start_matches('/')
+        );
+        let mut builder = self.config.client.request(method, &uri_str);
+        if let Some(user_agent) = &self.config.user_agent {
+            builder = builder.header(reqwest::header::USER_AGENT, user_agent);
+        }
+
+        #[cfg(feature = "signer")]
+        if let Some(signer) = &self.signer {
+            builder = builder.header(reqwest::header::AUTHORIZATION, signer.get_auth_token(None)?);
+        }
+
+        Ok(builder)
+    }
+
+    async fn send(&self, path: &str, builder: RequestBuilder) -> Result<Value> {
+        let resp = builder
+            .send()
+            .await
+            .inspect_err(|e| tracing::error!("unable to call `{path}`: {e}"))?;
+
+        let status = resp.status();
+        let content = resp
+            .text()
+            .await
+            .map_err(|e| LighterError::Http(Box::new(e)))?;
+        if !status.is_success() {
+            tracing::error!("unable to call `{path}`: status code {status}");
+            return Err(LighterError::from_response(status.as_u16(), content));
+        }
+
+        Ok(serde_json::from_str(&content)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_get_and_post() {
+        let mut server = mockito::Server::new_async().await;
+        let get = server
+            .mock("GET", "/api/v1/newEndpoint")
+            .match_query(mockito::Matcher::UrlEncoded("market_id".into(), "0".into()))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"unmapped_field":[1,2]}"#);
+        #[cfg(feature = "signer")]
+        let get = get.match_header("authorization", mockito::Matcher::Regex(".+".into()));
+        let get = get.create_async().await;
+        let post = server
+            .mock("POST", "/api/v1/other")
+            .match_body(mockito::Matcher::UrlEncoded("value".into(), "a b".into()))
+            .with_status(400)
+            .with_body(r#"{"code":21100,"message":"account not found"}"#)
+            .create_async()
+            .await;
+
+        let config = LighterConfig {
+            base_url: server.url(),
+            retry_config: None,
+            ..LighterConfig::new()
+                .with_api_key_private(
+                    "01db9eed031d59d6bd0ee00ee5a7dc1f62087bf217b51caea57eb6e17a02c49e0a748d2f155a2f60",
+                )
+                .with_account_index(28)
+                .with_api_key_index(2)
+        };
+        let api = RawApi::new(&config).unwrap();
+
+        let value = api
+            .get("api/v1/newEndpoint", &[("market_id", "0")])
+            .await
+            .unwrap();
+        assert_eq!(value["unmapped_field"][1], 2);
+        get.assert_async().await;
+
+        let res = api.post("/api/v1/other", &[("value", "a b")]).await;
+        assert!(matches!(
+            res,
+            Err(LighterError::ApiError {
+                http_status: 400,
+                code: Some(21100),
+                ..
+            })
+        ));
+        post.assert_async().await;
+    }
+}
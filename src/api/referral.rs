@@ -1,23 +1,88 @@
 use crate::{
     apis::{self, configuration::Configuration},
     config::LighterConfig,
+    error::LighterError,
     models::ReferralPoints,
-    Result,
+    AccountIndex, Result,
 };
 
+#[cfg(feature = "signer")]
+use crate::signer::FFISigner;
+
 #[derive(Debug)]
 pub struct ReferralApi {
     config: apis::configuration::Configuration,
+    #[cfg(feature = "signer")]
+    signer: Option<FFISigner>, // creates the auth tokens
 }
 
 impl ReferralApi {
     pub fn new(config: &LighterConfig) -> Result<Self> {
+        Self::new_with_configuration(config, Configuration::try_from(config)?)
+    }
+
+    /// Same as [`ReferralApi::new`], sending the requests with `configuration` (e.g. shared with the
+    /// other APIs) instead of building one
+    #[cfg_attr(not(feature = "signer"), allow(unused_variables))] // `config` creates the signer
+    pub fn new_with_configuration(
+        config: &LighterConfig,
+        configuration: Configuration,
+    ) -> Result<Self> {
         Ok(Self {
-            config: Configuration::try_from(config)?,
+            config: configuration,
+            #[cfg(feature = "signer")]
+            signer: match config.api_key_private {
+                Some(_) => Some(FFISigner::try_from(config)?),
+                None => None,
+            },
         })
     }
 
+    /// Sends the requests with `config` instead of the one built from the `LighterConfig`
+    pub fn with_configuration(mut self, config: Configuration) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Creates the auth tokens with `signer`, sharing its cached token with the other APIs
+    #[cfg(feature = "signer")]
+    pub(crate) fn with_signer(mut self, signer: Option<FFISigner>) -> Self {
+        self.signer = signer;
+        self
+    }
+
+    /// Returns the referral points of the account and of the accounts it referred. The auth
+    /// token is created with the API key of the config, so it must belong to `account_index`.
+    #[tracing::instrument(level = "debug", skip_all, fields(account_index = tracing::field::Empty))]
+    pub async fn points(&self, account_index: impl Into<AccountIndex>) -> Result<ReferralPoints> {
+        let account_index = account_index.into().0;
+        tracing::Span::current().record("account_index", account_index);
+        if account_index < 0 {
+            return Err(LighterError::Generic(format!(
+                "Invalid account index {account_index}, must not be negative"
+            )));
+        }
+        let auth_token = self.auth_token()?;
+
+        self.referral_points(account_index, Some(&auth_token), None)
+            .await
+    }
+
+    #[cfg(feature = "signer")]
+    fn auth_token(&self) -> Result<String> {
+        self.signer
+            .as_ref()
+            .ok_or_else(|| LighterError::Config("`api_key_private` is not set".into()))?
+            .get_auth_token(None)
+    }
+
+    #[cfg(not(feature = "signer"))]
+    fn auth_token(&self) -> Result<String> {
+        Err(LighterError::signer_disabled("Creating the auth token"))
+    }
+
     /// Get referral points
+    #[tracing::instrument(level = "debug", skip_all, fields(account_index = account_index))]
     pub async fn referral_points(
         &self,
         account_index: i64,
@@ -34,3 +99,70 @@ impl ReferralApi {
         Ok(resp)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_points_invalid_account_index() {
+        let api = ReferralApi::new(&LighterConfig::new()).unwrap();
+        let res = api.points(-1).await;
+        assert!(matches!(res, Err(LighterError::Generic(e)) if e.contains("-1")));
+    }
+
+    #[cfg(feature = "signer")]
+    #[tokio::test]
+    async fn test_points() {
+        let mut server = mockito::Server::new_async().await;
+        let points = server
+            .mock("GET", "/api/v1/referral/points")
+            .match_query(mockito::Matcher::UrlEncoded(
+                "account_index".into(),
+                "28".into(),
+            ))
+            .match_header("authorization", mockito::Matcher::Regex(".+".into()))
+            .with_header("content-type", "application/json")
+            .with_body(
+                serde_json::json!({
+                    "referrals": [{
+                        "l1_address": "0xabc",
+                        "total_points": 10,
+                        "week_points": 2,
+                        "total_reward_points": 1,
+                        "week_reward_points": 0,
+                        "reward_point_multiplier": "0.1",
+                    }],
+                    "user_total_points": 100,
+                    "user_last_week_points": 20,
+                    "user_total_referral_reward_points": 1,
+                    "user_last_week_referral_reward_points": 0,
+                    "reward_point_multiplier": "1",
+                })
+                .to_string(),
+            )
+            .expect(1)
+            .create_async()
+            .await;
+
+        let config = LighterConfig {
+            base_url: server.url(),
+            retry_config: None,
+            ..LighterConfig::new()
+        };
+        // read only
+        let res = ReferralApi::new(&config).unwrap().points(28).await;
+        assert!(matches!(res, Err(LighterError::Config(_))));
+
+        let config = config
+            .with_api_key_private(
+                "01db9eed031d59d6bd0ee00ee5a7dc1f62087bf217b51caea57eb6e17a02c49e0a748d2f155a2f60",
+            )
+            .with_api_key_index(2)
+            .with_account_index(28);
+        let res = ReferralApi::new(&config).unwrap().points(28).await.unwrap();
+        assert_eq!(res.user_total_points, 100);
+        assert_eq!(res.referrals[0].l1_address, "0xabc");
+        points.assert_async().await;
+    }
+}
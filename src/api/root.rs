@@ -1,10 +1,21 @@
+use chrono::{DateTime, Utc};
+
 use crate::{
     apis::{self, configuration::Configuration},
+    clock,
     config::LighterConfig,
     models::{Status, ZkLighterInfo},
-    Result,
+    LighterError, Result,
 };
 
+/// Status of the API, see [`RootApi::health`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiHealth {
+    pub network_id: i32,
+    pub block_height: i64,
+    pub server_time: DateTime<Utc>,
+}
+
 #[derive(Debug)]
 pub struct RootApi {
     config: apis::configuration::Configuration,
@@ -12,12 +23,22 @@ pub struct RootApi {
 
 impl RootApi {
     pub fn new(config: &LighterConfig) -> Result<Self> {
-        Ok(Self {
-            config: Configuration::try_from(config)?,
-        })
+        Ok(Self::from_configuration(Configuration::try_from(config)?))
+    }
+
+    /// Sends the requests with `config`, e.g. shared with the other APIs, without building one
+    pub fn from_configuration(config: Configuration) -> Self {
+        Self { config }
+    }
+
+    /// Sends the requests with `config` instead of the one built from the `LighterConfig`
+    pub fn with_configuration(mut self, config: Configuration) -> Self {
+        self.config = config;
+        self
     }
 
     /// Get info of zklighter
+    #[tracing::instrument(level = "debug", skip_all)]
     pub async fn info(&self) -> Result<ZkLighterInfo> {
         let resp = apis::root_api::info(&self.config)
             .await
@@ -27,6 +48,7 @@ impl RootApi {
     }
 
     /// Get status of zklighter
+    #[tracing::instrument(level = "debug", skip_all)]
     pub async fn status(&self) -> Result<Status> {
         let resp = apis::root_api::status(&self.config)
             .await
@@ -34,4 +56,101 @@ impl RootApi {
 
         Ok(resp)
     }
+
+    /// Checks that the base URL is reachable and serves the Lighter API, returning the network,
+    /// the current block height and the server time. No credentials are needed.
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub async fn health(&self) -> Result<ApiHealth> {
+        let (status, height) = tokio::try_join!(self.status(), async {
+            apis::block_api::current_height(&self.config)
+                .await
+                .inspect_err(|e| tracing::error!("unable to call `current_height`: {e}"))
+                .map_err(LighterError::from)
+        })?;
+        if status.status != 200 {
+            return Err(LighterError::ApiError {
+                http_status: status.status as u16,
+                code: None,
+                message: "The API is not healthy".into(),
+            });
+        }
+
+        let server_time = DateTime::from_timestamp_millis(clock::status_timestamp_ms(&status))
+            .ok_or_else(|| {
+                LighterError::Generic(format!("Invalid server time: {}", status.timestamp))
+            })?;
+
+        Ok(ApiHealth {
+            network_id: status.network_id,
+            block_height: height.height,
+            server_time,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mock_config(base_url: String) -> LighterConfig {
+        LighterConfig {
+            base_url,
+            retry_config: None,
+            ..Default::default()
+        }
+    }
+
+    async fn mock_server(status: i32) -> mockito::ServerGuard {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/")
+            .with_header("content-type", "application/json")
+            .with_body(format!(
+                r#"{{"status":{status},"network_id":300,"timestamp":1700000000}}"#
+            ))
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/api/v1/currentHeight")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"height":12345}"#)
+            .create_async()
+            .await;
+        server
+    }
+
+    #[tokio::test]
+    async fn test_health() {
+        let server = mock_server(200).await;
+        let api = RootApi::new(&mock_config(server.url())).unwrap();
+
+        let health = api.health().await.unwrap();
+        assert_eq!(health.network_id, 300);
+        assert_eq!(health.block_height, 12345);
+        assert_eq!(health.server_time.timestamp(), 1_700_000_000);
+
+        let server = mock_server(503).await;
+        let api = RootApi::new(&mock_config(server.url())).unwrap();
+        assert!(matches!(
+            api.health().await,
+            Err(LighterError::ApiError {
+                http_status: 503,
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_health_not_the_api() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", mockito::Matcher::Any)
+            .with_header("content-type", "text/html")
+            .with_body("<html>hello</html>")
+            .create_async()
+            .await;
+
+        let api = RootApi::new(&mock_config(server.url())).unwrap();
+        assert!(api.health().await.is_err());
+    }
 }
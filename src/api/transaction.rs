@@ -1,4 +1,8 @@
 #![allow(clippy::too_many_arguments)]
+use std::time::Duration;
+
+use tokio::time::Instant;
+
 use crate::{
     apis::{self, configuration::Configuration},
     config::LighterConfig,
@@ -6,9 +10,23 @@ use crate::{
         DepositHistory, EnrichedTx, NextNonce, RespSendTx, RespSendTxBatch, TransferHistory, Txs,
         WithdrawHistory,
     },
-    Result,
+    LighterError, LighterErrorCode, Result,
+};
+
+#[cfg(feature = "signer")]
+use std::sync::Arc;
+
+#[cfg(feature = "signer")]
+use crate::{
+    client::TxClient,
+    signer::data::{SignTransferData, TxType},
 };
 
+/// Max number of transfers sent in a single `send_tx_batch`, see
+/// [`TransactionApi::transfer_batch`]
+#[cfg(feature = "signer")]
+pub const TRANSFER_BATCH_LEN: usize = 50;
+
 #[derive(Debug, strum::Display)]
 #[strum(serialize_all = "snake_case")]
 pub enum AccountTxsBy {
@@ -45,19 +63,242 @@ pub enum WithdrawHistoryFilter {
     Claimable,
 }
 
+/// Result codes returned by `send_tx`/`send_tx_batch`.
+///
+/// Codes not known by the SDK are kept in `Unknown` so that they can still be inspected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TxResultCode {
+    Success,
+    NonceTooLow,
+    InsufficientMargin,
+    InvalidSignature,
+    RateLimited,
+    /// An order with the same `client_order_index` is already placed
+    DuplicateClientOrderIndex,
+    Unknown(i32),
+}
+
+impl TxResultCode {
+    pub const SUCCESS: i32 = 200;
+    pub const NONCE_TOO_LOW: i32 = 21104;
+    pub const INSUFFICIENT_MARGIN: i32 = 21706;
+    pub const INVALID_SIGNATURE: i32 = 21120;
+    pub const RATE_LIMITED: i32 = 23000;
+    pub const DUPLICATE_CLIENT_ORDER_INDEX: i32 = 21711;
+
+    pub fn code(&self) -> i32 {
+        match self {
+            Self::Success => Self::SUCCESS,
+            Self::NonceTooLow => Self::NONCE_TOO_LOW,
+            Self::InsufficientMargin => Self::INSUFFICIENT_MARGIN,
+            Self::InvalidSignature => Self::INVALID_SIGNATURE,
+            Self::RateLimited => Self::RATE_LIMITED,
+            Self::DuplicateClientOrderIndex => Self::DUPLICATE_CLIENT_ORDER_INDEX,
+            Self::Unknown(code) => *code,
+        }
+    }
+
+    pub fn is_success(&self) -> bool {
+        matches!(self, Self::Success)
+    }
+
+    /// Returns `true` if submitting the transaction again (with a fresh nonce when needed) can succeed.
+    /// Margin and signature failures won't change on retry, so they fail fast.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, Self::NonceTooLow | Self::RateLimited)
+    }
+}
+
+impl From<i32> for TxResultCode {
+    fn from(code: i32) -> Self {
+        match code {
+            Self::SUCCESS => Self::Success,
+            Self::NONCE_TOO_LOW => Self::NonceTooLow,
+            Self::INSUFFICIENT_MARGIN => Self::InsufficientMargin,
+            Self::INVALID_SIGNATURE => Self::InvalidSignature,
+            Self::RATE_LIMITED => Self::RateLimited,
+            Self::DUPLICATE_CLIENT_ORDER_INDEX => Self::DuplicateClientOrderIndex,
+            code => Self::Unknown(code),
+        }
+    }
+}
+
+impl From<&RespSendTx> for TxResultCode {
+    fn from(resp: &RespSendTx) -> Self {
+        resp.code.into()
+    }
+}
+
+impl From<&RespSendTxBatch> for TxResultCode {
+    fn from(resp: &RespSendTxBatch) -> Self {
+        resp.code.into()
+    }
+}
+
+/// Status of a submitted transaction, see [`TransactionApi::wait_for_tx`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TxStatus {
+    /// Not executed yet, or not known by the API yet
+    Pending,
+    /// Executed and included in a block
+    Included {
+        block_height: i64,
+    },
+    Failed {
+        reason: String,
+    },
+}
+
+impl TxStatus {
+    pub const FAILED: i64 = 0;
+    pub const PENDING: i64 = 1;
+    // any later status (executed, packed, committed, verified) means the tx is included
+
+    pub fn is_pending(&self) -> bool {
+        matches!(self, Self::Pending)
+    }
+}
+
+impl From<&EnrichedTx> for TxStatus {
+    fn from(tx: &EnrichedTx) -> Self {
+        match tx.status {
+            Self::FAILED => Self::Failed {
+                reason: tx
+                    .message
+                    .clone()
+                    .filter(|v| !v.is_empty())
+                    .unwrap_or_else(|| tx.event_info.clone()),
+            },
+            Self::PENDING => Self::Pending,
+            _ => Self::Included {
+                block_height: tx.block_height,
+            },
+        }
+    }
+}
+
+/// Outcome of [`TransactionApi::transfer_batch`]
+#[cfg(feature = "signer")]
+#[derive(Debug)]
+pub struct TransferBatchReport {
+    /// Index (in the given transfers) and hash of the submitted transfers
+    pub submitted: Vec<(usize, String)>,
+    /// Error that stopped the submission: the transfers not in `submitted` were not executed
+    pub error: Option<LighterError>,
+}
+
+#[cfg(feature = "signer")]
+impl TransferBatchReport {
+    pub fn is_complete(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
 #[derive(Debug)]
 pub struct TransactionApi {
     config: apis::configuration::Configuration,
+    tx_config: apis::configuration::Configuration, // without retries, to send the txs
+    #[cfg(feature = "signer")]
+    tx_client: Option<Arc<TxClient>>, // needed only for the operations that require signing
 }
 
 impl TransactionApi {
     pub fn new(config: &LighterConfig) -> Result<Self> {
-        Ok(Self {
-            config: Configuration::try_from(config)?,
-        })
+        // txs are sent as multipart forms, which can't be cloned by the retry middleware, see
+        // `TxClient::new`
+        let tx_config = LighterConfig {
+            retry_config: None,
+            ..config.clone()
+        };
+
+        Ok(Self::from_configuration(
+            Configuration::try_from(config)?,
+            Configuration::try_from(&tx_config)?,
+        ))
+    }
+
+    /// Sends the requests with `config` and the txs with `tx_config` (e.g. shared with the tx
+    /// client), which must not retry them, without building any
+    pub fn from_configuration(config: Configuration, tx_config: Configuration) -> Self {
+        Self {
+            config,
+            tx_config,
+            #[cfg(feature = "signer")]
+            tx_client: None,
+        }
+    }
+
+    /// Sends the requests with `config` instead of the one built from the `LighterConfig`, but
+    /// the txs, which can't be retried, see [`TransactionApi::with_tx_configuration`]
+    pub fn with_configuration(mut self, config: Configuration) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Sends the txs with `config`, which must not retry them (they are multipart forms, which
+    /// the retry middleware can't clone), the other requests keep theirs
+    pub fn with_tx_configuration(mut self, config: Configuration) -> Self {
+        self.tx_config = config;
+        self
+    }
+
+    #[cfg(feature = "signer")]
+    pub(crate) fn with_tx_client(mut self, tx_client: Option<Arc<TxClient>>) -> Self {
+        self.tx_client = tx_client;
+        self
+    }
+
+    /// Signs the transfers with sequential nonces and submits them, in batches of
+    /// [`TRANSFER_BATCH_LEN`].
+    ///
+    /// The batches are sent one after the other and the submission stops at the first failed
+    /// one, since the following nonces would not be valid anymore. The report tells which of the
+    /// transfers were submitted.
+    #[cfg(feature = "signer")]
+    #[tracing::instrument(level = "debug", skip_all)]
+    pub async fn transfer_batch(
+        &self,
+        transfers: Vec<SignTransferData>,
+    ) -> Result<TransferBatchReport> {
+        let tx_client = self
+            .tx_client
+            .as_deref()
+            .ok_or_else(|| LighterError::Config("`api_key_private` is not set".into()))?;
+
+        let mut report = TransferBatchReport {
+            submitted: Vec::with_capacity(transfers.len()),
+            error: None,
+        };
+        for (i, chunk) in transfers.chunks(TRANSFER_BATCH_LEN).enumerate() {
+            let res = tx_client
+                .submit_batch(chunk.len(), |signer, nonces| {
+                    signer.sign_batch_transfer(chunk.to_vec(), nonces)
+                })
+                .await;
+
+            match res {
+                Ok(resp) => {
+                    let offset = i * TRANSFER_BATCH_LEN;
+                    report.submitted.extend(
+                        resp.tx_hash
+                            .into_iter()
+                            .enumerate()
+                            .map(|(j, hash)| (offset + j, hash)),
+                    );
+                }
+                Err(e) => {
+                    tracing::error!("unable to submit the transfers batch {i}: {e}");
+                    report.error = Some(e);
+                    break;
+                }
+            }
+        }
+
+        Ok(report)
     }
 
     /// Get transactions of a specific account
+    #[tracing::instrument(level = "debug", skip_all)]
     pub async fn account_txs(
         &self,
         limit: i64,
@@ -85,6 +326,7 @@ impl TransactionApi {
     }
 
     /// Get transactions in a block
+    #[tracing::instrument(level = "debug", skip_all)]
     pub async fn block_txs(&self, by: BlockTxsBy, value: &str) -> Result<Txs> {
         let resp = apis::transaction_api::block_txs(&self.config, &by.to_string(), value)
             .await
@@ -94,6 +336,7 @@ impl TransactionApi {
     }
 
     /// Get deposit history
+    #[tracing::instrument(level = "debug", skip_all, fields(account_index = account_index))]
     pub async fn deposit_history(
         &self,
         account_index: i64,
@@ -119,6 +362,11 @@ impl TransactionApi {
     }
 
     /// Get next nonce for a specific account and api key
+    #[tracing::instrument(
+        level = "debug",
+        skip_all,
+        fields(account_index = account_index, api_key_index = api_key_index)
+    )]
     pub async fn next_nonce(&self, account_index: i64, api_key_index: i32) -> Result<NextNonce> {
         let resp = apis::transaction_api::next_nonce(&self.config, account_index, api_key_index)
             .await
@@ -128,22 +376,51 @@ impl TransactionApi {
     }
 
     /// You need to sign the transaction body before sending it to the server. More details can be found in the Get Started docs: [Get Started For Programmers](https://apidocs.lighter.xyz/docs/get-started-for-programmers)
+    #[tracing::instrument(level = "debug", skip_all, fields(tx_type = tx_type))]
     pub async fn send_tx(
         &self,
         tx_type: i32,
         tx_info: &str,
         price_protection: Option<bool>,
     ) -> Result<RespSendTx> {
-        let resp = apis::transaction_api::send_tx(&self.config, tx_type, tx_info, price_protection)
-            .await
-            .inspect_err(|e| tracing::error!("unable to call `send_tx`: {e}"))?;
+        let resp =
+            apis::transaction_api::send_tx(&self.tx_config, tx_type, tx_info, price_protection)
+                .await
+                .inspect_err(|e| tracing::error!("unable to call `send_tx`: {e}"))?;
+
+        Ok(resp)
+    }
+
+    /// Submits a tx signed beforehand, e.g. offline or by a custom flow built on
+    /// [`Signer`](crate::signer::Signer), `payload` being its
+    /// [`TxInfo::payload`](crate::signer::data::TxInfo::payload). The nonce is the one it was
+    /// signed with, so it's sent once, without retries.
+    ///
+    /// Unlike [`TransactionApi::send_tx`], it fails with `LighterError::ApiError` unless the tx
+    /// is accepted (see [`TxResultCode`]).
+    #[cfg(feature = "signer")]
+    #[tracing::instrument(level = "debug", skip_all, fields(tx_type = ?tx_type))]
+    pub async fn send_signed_tx(&self, tx_type: TxType, payload: &str) -> Result<RespSendTx> {
+        let resp = self.send_tx(tx_type.into(), payload, None).await?;
+        let code = TxResultCode::from(&resp);
+        if !code.is_success() {
+            return Err(LighterError::ApiError {
+                http_status: 200,
+                code: Some(code.code() as i64),
+                message: format!(
+                    "tx {tx_type:?} failed: {}",
+                    resp.message.unwrap_or_default()
+                ),
+            });
+        }
 
         Ok(resp)
     }
 
     /// You need to sign the transaction body before sending it to the server. More details can be found in the Get Started docs: [Get Started For Programmers](https://apidocs.lighter.xyz/docs/get-started-for-programmers)
+    #[tracing::instrument(level = "debug", skip_all)]
     pub async fn send_tx_batch(&self, tx_types: &str, tx_infos: &str) -> Result<RespSendTxBatch> {
-        let resp = apis::transaction_api::send_tx_batch(&self.config, tx_types, tx_infos)
+        let resp = apis::transaction_api::send_tx_batch(&self.tx_config, tx_types, tx_infos)
             .await
             .inspect_err(|e| tracing::error!("uanble to call `send_tx_batch`: {e}"))?;
 
@@ -151,6 +428,7 @@ impl TransactionApi {
     }
 
     /// Get transfer history
+    #[tracing::instrument(level = "debug", skip_all, fields(account_index = account_index))]
     pub async fn transfer_history(
         &self,
         account_index: i64,
@@ -172,6 +450,7 @@ impl TransactionApi {
     }
 
     /// Get transaction by hash or sequence index
+    #[tracing::instrument(level = "debug", skip_all)]
     pub async fn tx(&self, by: TxBy, value: &str) -> Result<EnrichedTx> {
         let resp = apis::transaction_api::tx(&self.config, &by.to_string(), value)
             .await
@@ -180,7 +459,36 @@ impl TransactionApi {
         Ok(resp)
     }
 
+    /// Polls the tx until it's included or failed, backing off between the attempts. Returns
+    /// `TxStatus::Pending` if it's still pending (or not found yet) once `timeout` elapses, any
+    /// other error (e.g. an invalid hash) is returned at once.
+    #[tracing::instrument(level = "debug", skip_all, fields(tx_hash = tx_hash))]
+    pub async fn wait_for_tx(&self, tx_hash: &str, timeout: Duration) -> Result<TxStatus> {
+        const MIN_INTERVAL: Duration = Duration::from_millis(100);
+        const MAX_INTERVAL: Duration = Duration::from_secs(2);
+
+        let deadline = Instant::now() + timeout;
+        let mut interval = MIN_INTERVAL;
+        loop {
+            let status = match self.tx(TxBy::Hash, tx_hash).await {
+                Ok(tx) => TxStatus::from(&tx),
+                // the tx may not be indexed yet right after being sent
+                Err(e) if is_tx_not_found(&e) => TxStatus::Pending,
+                Err(e) => return Err(e),
+            };
+
+            let now = Instant::now();
+            if !status.is_pending() || now >= deadline {
+                return Ok(status);
+            }
+
+            tokio::time::sleep(interval.min(deadline - now)).await;
+            interval = (interval * 2).min(MAX_INTERVAL);
+        }
+    }
+
     /// Get L1 transaction by L1 transaction hash
+    #[tracing::instrument(level = "debug", skip_all)]
     pub async fn tx_from_l1_tx_hash(&self, hash: &str) -> Result<EnrichedTx> {
         let resp = apis::transaction_api::tx_from_l1_tx_hash(&self.config, hash)
             .await
@@ -190,6 +498,7 @@ impl TransactionApi {
     }
 
     /// Get transactions which are already packed into blocks
+    #[tracing::instrument(level = "debug", skip_all)]
     pub async fn txs(&self, limit: i64, index: Option<i64>) -> Result<Txs> {
         let resp = apis::transaction_api::txs(&self.config, limit, index)
             .await
@@ -199,6 +508,7 @@ impl TransactionApi {
     }
 
     /// Get withdraw history
+    #[tracing::instrument(level = "debug", skip_all, fields(account_index = account_index))]
     pub async fn withdraw_history(
         &self,
         account_index: i64,
@@ -221,3 +531,335 @@ impl TransactionApi {
         Ok(resp)
     }
 }
+
+/// Returns `true` if the tx isn't known by the API (yet), either with a `404` or with the
+/// [`LighterErrorCode::TxNotFound`] code
+pub(crate) fn is_tx_not_found(e: &LighterError) -> bool {
+    e.http_status() == Some(404) || e.api_code() == Some(LighterErrorCode::TxNotFound)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tx_result_code_mapping() {
+        assert_eq!(TxResultCode::from(200), TxResultCode::Success);
+        assert_eq!(TxResultCode::from(21104), TxResultCode::NonceTooLow);
+        assert_eq!(TxResultCode::from(21706), TxResultCode::InsufficientMargin);
+        assert_eq!(TxResultCode::from(21120), TxResultCode::InvalidSignature);
+        assert_eq!(TxResultCode::from(23000), TxResultCode::RateLimited);
+        assert_eq!(
+            TxResultCode::from(21711),
+            TxResultCode::DuplicateClientOrderIndex
+        );
+        assert_eq!(TxResultCode::from(12345), TxResultCode::Unknown(12345));
+
+        for code in [200, 21104, 21706, 21120, 23000, 21711, 12345] {
+            assert_eq!(TxResultCode::from(code).code(), code);
+        }
+    }
+
+    #[test]
+    fn test_tx_result_code_is_retryable() {
+        assert!(TxResultCode::NonceTooLow.is_retryable());
+        assert!(TxResultCode::RateLimited.is_retryable());
+
+        assert!(!TxResultCode::Success.is_retryable());
+        assert!(!TxResultCode::InsufficientMargin.is_retryable());
+        assert!(!TxResultCode::InvalidSignature.is_retryable());
+        assert!(!TxResultCode::Unknown(12345).is_retryable());
+    }
+
+    fn enriched_tx(status: i64) -> String {
+        serde_json::to_string(&EnrichedTx {
+            code: 200,
+            hash: "0x1".into(),
+            status,
+            block_height: 10,
+            event_info: "not enough margin".into(),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_tx() {
+        let mut server = mockito::Server::new_async().await;
+        let not_found = server
+            .mock("GET", "/api/v1/tx")
+            .match_query(mockito::Matcher::Any)
+            .with_status(400)
+            .with_body(r#"{"code":21500,"message":"transaction not found"}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let config = LighterConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+        let api = TransactionApi::new(&config).unwrap();
+
+        // not found until the timeout
+        let status = api
+            .wait_for_tx("0x1", Duration::from_millis(50))
+            .await
+            .unwrap();
+        assert_eq!(status, TxStatus::Pending);
+        not_found.remove_async().await;
+
+        // an invalid hash fails at once, instead of polling until the timeout
+        let invalid = server
+            .mock("GET", "/api/v1/tx")
+            .match_query(mockito::Matcher::Any)
+            .with_status(400)
+            .with_body(r#"{"code":20001,"message":"invalid param: value"}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let res = api.wait_for_tx("garbage", Duration::from_secs(10)).await;
+        assert!(
+            matches!(&res, Err(e) if e.api_code() == Some(LighterErrorCode::InvalidParam)),
+            "{res:?}"
+        );
+        invalid.assert_async().await;
+        invalid.remove_async().await;
+
+        let included = server
+            .mock("GET", "/api/v1/tx")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(enriched_tx(3))
+            .expect(1)
+            .create_async()
+            .await;
+        let status = api
+            .wait_for_tx("0x1", Duration::from_secs(1))
+            .await
+            .unwrap();
+        assert_eq!(status, TxStatus::Included { block_height: 10 });
+        included.assert_async().await;
+        included.remove_async().await;
+
+        server
+            .mock("GET", "/api/v1/tx")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(enriched_tx(TxStatus::FAILED))
+            .create_async()
+            .await;
+        let status = api
+            .wait_for_tx("0x1", Duration::from_secs(1))
+            .await
+            .unwrap();
+        assert_eq!(
+            status,
+            TxStatus::Failed {
+                reason: "not enough margin".into()
+            }
+        );
+    }
+
+    #[cfg(feature = "signer")]
+    #[tokio::test]
+    async fn test_transfer_batch_partial_failure() {
+        use crate::{client::nonce::NonceManager, signer::data::Memo};
+        use std::str::FromStr;
+
+        let mut server = mockito::Server::new_async().await;
+        let first = server
+            .mock("POST", "/api/v1/sendTxBatch")
+            .match_body(mockito::Matcher::Regex(r#"Nonce\\":5,"#.into()))
+            .with_header("content-type", "application/json")
+            .with_body(
+                serde_json::to_string(&RespSendTxBatch::new(
+                    200,
+                    (0..TRANSFER_BATCH_LEN).map(|i| format!("0x{i}")).collect(),
+                    1,
+                ))
+                .unwrap(),
+            )
+            .expect(1)
+            .create_async()
+            .await;
+        let second = server
+            .mock("POST", "/api/v1/sendTxBatch")
+            .match_body(mockito::Matcher::Regex(r#"Nonce\\":55,"#.into()))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":21706,"message":"insufficient margin","tx_hash":[],"predicted_execution_time_ms":1}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let config = LighterConfig {
+            base_url: server.url(),
+            ..LighterConfig::new()
+                .with_api_key_private(
+                    "01db9eed031d59d6bd0ee00ee5a7dc1f62087bf217b51caea57eb6e17a02c49e0a748d2f155a2f60",
+                )
+                .with_account_index(28)
+                .with_api_key_index(2)
+                .with_eth_private_key(
+                    "0x4fd51c004ad02a003e321d5154d9b22c6bb89e1e5017bdc832c69ef28f65c04e",
+                )
+        };
+        let nonce_manager = NonceManager::new();
+        nonce_manager.reset(5).unwrap();
+        let tx_client = TxClient::new(&config, Some(Arc::new(nonce_manager))).unwrap();
+        let api = TransactionApi::new(&config)
+            .unwrap()
+            .with_tx_client(Some(Arc::new(tx_client)));
+
+        let memo = Memo::from_str("Hal Finney was `Running Bitcoin`").unwrap();
+        let transfers = (0..TRANSFER_BATCH_LEN as i64 + 1)
+            .map(|i| SignTransferData::new(i + 1, 100, 2).with_memo(memo))
+            .collect();
+        let report = api.transfer_batch(transfers).await.unwrap();
+
+        assert!(!report.is_complete());
+        assert_eq!(report.submitted.len(), TRANSFER_BATCH_LEN);
+        assert_eq!(report.submitted[1], (1, "0x1".into()));
+        first.assert_async().await;
+        second.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_send_tx_without_retries() {
+        let mut server = mockito::Server::new_async().await;
+        let send_tx = server
+            .mock("POST", "/api/v1/sendTx")
+            .with_status(503)
+            .expect(1)
+            .create_async()
+            .await;
+        let send_tx_batch = server
+            .mock("POST", "/api/v1/sendTxBatch")
+            .with_status(503)
+            .expect(1)
+            .create_async()
+            .await;
+
+        // the default retries would fail on the multipart forms, and resend the nonces
+        let config = LighterConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+        assert!(config.retry_config.is_some());
+        let api = TransactionApi::new(&config).unwrap();
+
+        let res = api.send_tx(14, "{}", None).await;
+        assert_eq!(res.unwrap_err().http_status(), Some(503));
+        let res = api.send_tx_batch("[14]", "[{}]").await;
+        assert_eq!(res.unwrap_err().http_status(), Some(503));
+        send_tx.assert_async().await;
+        send_tx_batch.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_send_tx_with_configuration() {
+        let mut server = mockito::Server::new_async().await;
+        let send_tx = server
+            .mock("POST", "/api/v1/sendTx")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"tx_hash":"0x7","predicted_execution_time_ms":1}"#)
+            .expect(2)
+            .create_async()
+            .await;
+
+        // with the default retries, which the txs keep away from
+        let config = LighterConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+        assert!(config.retry_config.is_some());
+        let api = TransactionApi::new(&config)
+            .unwrap()
+            .with_configuration(Configuration::try_from(&config).unwrap());
+        let resp = api.send_tx(14, "{}", None).await.unwrap();
+        assert_eq!(resp.tx_hash, "0x7");
+
+        // the txs redirected, without retries
+        let injected = LighterConfig {
+            retry_config: None,
+            ..config
+        };
+        let api = TransactionApi::new(&LighterConfig::default())
+            .unwrap()
+            .with_tx_configuration(Configuration::try_from(&injected).unwrap());
+        let resp = api.send_tx(14, "{}", None).await.unwrap();
+        assert_eq!(resp.tx_hash, "0x7");
+        send_tx.assert_async().await;
+    }
+
+    #[cfg(feature = "signer")]
+    #[tokio::test]
+    async fn test_send_signed_tx() {
+        use crate::{models::order, signer::data::CreateOrderData, signer::Signer};
+
+        let mut server = mockito::Server::new_async().await;
+        let accepted = server
+            .mock("POST", "/api/v1/sendTx")
+            .match_body(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::Regex(r#"name="tx_type"\r\n\r\n14\r\n"#.into()),
+                mockito::Matcher::Regex(r#""Nonce":7,"#.into()),
+            ]))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"tx_hash":"0x7","predicted_execution_time_ms":1}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        server
+            .mock("POST", "/api/v1/sendTx")
+            .match_body(mockito::Matcher::Regex(r#""Nonce":8,"#.into()))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":21706,"message":"insufficient margin","tx_hash":"","predicted_execution_time_ms":1}"#)
+            .create_async()
+            .await;
+
+        // with the default retries, which don't apply to the txs
+        let config = LighterConfig {
+            base_url: server.url(),
+            ..LighterConfig::new()
+                .with_api_key_private(
+                    "01db9eed031d59d6bd0ee00ee5a7dc1f62087bf217b51caea57eb6e17a02c49e0a748d2f155a2f60",
+                )
+                .with_account_index(28)
+                .with_api_key_index(2)
+        };
+        let signer = Signer::try_from(&config).unwrap();
+        let order = CreateOrderData {
+            market_index: 1,
+            client_order_index: 1,
+            base_amount: 100,
+            price: 300_000,
+            is_ask: false,
+            order_type: order::Type::Limit.into(),
+            time_in_force: order::TimeInForce::GoodTillTime.into(),
+            reduce_only: false,
+            trigger_price: 0,
+            order_expiry: (chrono::Utc::now() + chrono::Duration::days(1)).timestamp_millis(),
+        };
+        let api = TransactionApi::new(&config).unwrap();
+
+        let tx = signer.sign_create_order(order.clone(), 7).unwrap();
+        let resp = api.send_signed_tx(tx.tx_type, &tx.payload).await.unwrap();
+        assert_eq!(resp.tx_hash, "0x7");
+        accepted.assert_async().await;
+
+        let tx = signer.sign_create_order(order, 8).unwrap();
+        let res = api.send_signed_tx(tx.tx_type, &tx.payload).await;
+        assert!(
+            matches!(res, Err(LighterError::ApiError { code: Some(21706), message, .. }) if message.contains("insufficient margin"))
+        );
+    }
+
+    #[test]
+    fn test_tx_result_code_from_resp_send_tx() {
+        let resp = RespSendTx::new(200, "0xabc".into(), 10);
+        assert!(TxResultCode::from(&resp).is_success());
+
+        let resp = RespSendTxBatch::new(21104, vec![], 10);
+        assert_eq!(TxResultCode::from(&resp), TxResultCode::NonceTooLow);
+    }
+}
@@ -0,0 +1,127 @@
+//! Blocking `HttpClient`, for the consumers not running inside an async runtime.
+//!
+//! It wraps the async [`crate::HttpClient`] and drives it with its own current-thread runtime,
+//! similarly to `reqwest::blocking`. As such, it must not be used from within an async runtime.
+//!
+//! ```ignore
+//! use lighter_rust::{blocking, HttpClient, LighterConfig};
+//!
+//! let client = blocking::HttpClient::new(
+//!     HttpClient::builder().with_config(config).with_account().with_order(),
+//! )?;
+//! let account = client.account_by_index(YOUR_ACCOUNT_INDEX)?; // `None` if it doesn't exist
+//! // any other API can be called through `block_on`
+//! let stats = client.block_on(client.inner().api().order()?.exchange_stats())?;
+//! ```
+use std::future::Future;
+
+use tokio::runtime::{Builder, Runtime};
+
+#[cfg(feature = "signer")]
+use crate::{api::order::PlacedOrder, signer::data::CreateOrderData};
+use crate::{
+    client::HttpClientBuilder, models::DetailedAccount, AccountIndex, LighterError, Result,
+};
+
+#[derive(Debug)]
+pub struct HttpClient {
+    inner: crate::HttpClient,
+    runtime: Runtime,
+}
+
+impl HttpClient {
+    /// Builds the client out of the async client builder
+    pub fn new(builder: HttpClientBuilder) -> Result<Self> {
+        let runtime = Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .inspect_err(|e| tracing::error!("unable to create the runtime: {e}"))?;
+        let inner = {
+            let _guard = runtime.enter();
+            builder.build()?
+        };
+
+        Ok(Self { inner, runtime })
+    }
+
+    /// Returns the wrapped async client
+    pub fn inner(&self) -> &crate::HttpClient {
+        &self.inner
+    }
+
+    /// Runs any future (e.g. a call to one of the APIs of [`HttpClient::inner`]) to completion
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+
+    /// See [`crate::api::account::AccountApi::account_by_index`]
+    pub fn account_by_index(
+        &self,
+        account_index: impl Into<AccountIndex>,
+    ) -> Result<Option<DetailedAccount>> {
+        self.block_on(self.inner.api().account()?.account_by_index(account_index))
+    }
+
+    /// See [`crate::HttpClient::get_nonce`]
+    pub fn get_nonce(&self) -> Result<i64> {
+        self.block_on(self.inner.get_nonce())
+    }
+
+    /// See [`crate::HttpClient::reserve_nonces`]
+    pub fn reserve_nonces(&self, n: usize) -> Result<Vec<i64>> {
+        self.block_on(self.inner.reserve_nonces(n))
+    }
+
+    /// See [`crate::api::order::OrderApi::place_order`]
+    #[cfg(feature = "signer")]
+    pub fn place_order(&self, order: CreateOrderData) -> Result<PlacedOrder> {
+        self.block_on(self.inner.api().order()?.place_order(order))
+    }
+}
+
+impl TryFrom<HttpClientBuilder> for HttpClient {
+    type Error = LighterError;
+
+    fn try_from(builder: HttpClientBuilder) -> Result<Self> {
+        Self::new(builder)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LighterConfig;
+
+    #[test]
+    fn test_blocking_client() {
+        let mut server = mockito::Server::new();
+        let next_nonce = server
+            .mock("GET", "/api/v1/nextNonce")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"nonce":5}"#)
+            .expect(1)
+            .create();
+
+        let config = LighterConfig {
+            base_url: server.url(),
+            local_nonce: false,
+            ..LighterConfig::new()
+                .with_account_index(28)
+                .with_api_key_index(2)
+        };
+        let client = HttpClient::new(
+            crate::HttpClient::builder()
+                .with_config(config)
+                .with_transaction(),
+        )
+        .unwrap();
+
+        assert_eq!(client.get_nonce().unwrap(), 5);
+        // the account API is not enabled
+        assert!(client
+            .account_by_index(28)
+            .is_err_and(|e| matches!(e, LighterError::Generic(_))));
+        next_nonce.assert();
+    }
+}
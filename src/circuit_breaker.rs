@@ -0,0 +1,243 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use http::Extensions;
+use reqwest::{Request, Response};
+use reqwest_middleware::{Middleware, Next};
+
+use crate::{LighterError, Result};
+
+/// Stops sending requests to a host after `failure_threshold` consecutive failures (connection
+/// errors and `5xx` responses), failing fast with `LighterError::CircuitOpen` instead.
+///
+/// After `cooldown` a single request is let through to probe the host: the circuit closes if it
+/// succeeds, otherwise it stays open for another `cooldown`.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    cooldown: Duration,
+    hosts: Mutex<HashMap<String, CircuitState>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CircuitState {
+    Closed { failures: u32 },
+    Open { until: Instant },
+    HalfOpen { until: Instant }, // the probe is in flight, another one is sent after `until`
+}
+
+/// Error returned by the middleware when the circuit is open, converted into
+/// `LighterError::CircuitOpen`
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("{host}")]
+pub(crate) struct CircuitOpen {
+    host: String,
+}
+
+impl CircuitOpen {
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, cooldown: Duration) -> Result<Self> {
+        if failure_threshold == 0 {
+            return Err(LighterError::Config(
+                "`failure_threshold` must be greater than 0".into(),
+            ));
+        }
+
+        Ok(Self {
+            failure_threshold,
+            cooldown,
+            hosts: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Returns `true` if a request can be sent to `host`
+    fn try_acquire(&self, host: &str) -> bool {
+        let mut hosts = self.hosts.lock().unwrap_or_else(|e| e.into_inner());
+        let state = hosts
+            .entry(host.to_string())
+            .or_insert(CircuitState::Closed { failures: 0 });
+
+        match *state {
+            CircuitState::Closed { .. } => true,
+            // the probe may never complete, e.g. if its future is dropped
+            CircuitState::Open { until } | CircuitState::HalfOpen { until }
+                if Instant::now() >= until =>
+            {
+                tracing::info!("circuit of `{host}` half open, probing it");
+                *state = CircuitState::HalfOpen {
+                    until: Instant::now() + self.cooldown,
+                };
+                true
+            }
+            CircuitState::Open { .. } | CircuitState::HalfOpen { .. } => false,
+        }
+    }
+
+    /// Records the outcome of a request sent to `host`
+    fn record(&self, host: &str, success: bool) {
+        let mut hosts = self.hosts.lock().unwrap_or_else(|e| e.into_inner());
+        let state = hosts
+            .entry(host.to_string())
+            .or_insert(CircuitState::Closed { failures: 0 });
+
+        *state = match (*state, success) {
+            (CircuitState::HalfOpen { .. }, true) => {
+                tracing::info!("circuit of `{host}` closed");
+                CircuitState::Closed { failures: 0 }
+            }
+            (_, true) => CircuitState::Closed { failures: 0 },
+            (CircuitState::Closed { failures }, false) if failures + 1 < self.failure_threshold => {
+                CircuitState::Closed {
+                    failures: failures + 1,
+                }
+            }
+            (_, false) => {
+                tracing::warn!("circuit of `{host}` open for {:?}", self.cooldown);
+                CircuitState::Open {
+                    until: Instant::now() + self.cooldown,
+                }
+            }
+        };
+    }
+
+    #[cfg(test)]
+    fn state(&self, host: &str) -> Option<CircuitState> {
+        self.hosts
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(host)
+            .copied()
+    }
+}
+
+/// Middleware failing fast when the circuit of the host is open. It must come after the retry
+/// middleware, so that every attempt is counted and the retries stop once it opens.
+pub(crate) struct CircuitBreakerMiddleware {
+    breaker: Arc<CircuitBreaker>,
+}
+
+impl CircuitBreakerMiddleware {
+    pub fn new(breaker: Arc<CircuitBreaker>) -> Self {
+        Self { breaker }
+    }
+}
+
+#[async_trait::async_trait]
+impl Middleware for CircuitBreakerMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        let url = req.url();
+        let host = format!(
+            "{}:{}",
+            url.host_str().unwrap_or_default(),
+            url.port_or_known_default().unwrap_or_default()
+        );
+        if !self.breaker.try_acquire(&host) {
+            return Err(reqwest_middleware::Error::middleware(CircuitOpen { host }));
+        }
+
+        let result = next.run(req, extensions).await;
+        let success = matches!(&result, Ok(resp) if !resp.status().is_server_error());
+        self.breaker.record(&host, success);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        apis::{self, configuration::Configuration},
+        config::{LighterConfig, RetryConfig},
+    };
+
+    #[test]
+    fn test_circuit_breaker_states() {
+        let breaker = CircuitBreaker::new(2, Duration::from_millis(50)).unwrap();
+        assert!(CircuitBreaker::new(0, Duration::ZERO).is_err());
+
+        assert!(breaker.try_acquire("a"));
+        breaker.record("a", false);
+        breaker.record("a", true); // not consecutive
+        breaker.record("a", false);
+        assert_eq!(
+            breaker.state("a"),
+            Some(CircuitState::Closed { failures: 1 })
+        );
+        breaker.record("a", false);
+        assert!(!breaker.try_acquire("a"));
+        assert!(breaker.try_acquire("b")); // per host
+
+        // a single probe after the cooldown
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(breaker.try_acquire("a"));
+        assert!(!breaker.try_acquire("a"));
+        breaker.record("a", false);
+        assert!(matches!(
+            breaker.state("a"),
+            Some(CircuitState::Open { .. })
+        ));
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(breaker.try_acquire("a"));
+        breaker.record("a", true);
+        assert_eq!(
+            breaker.state("a"),
+            Some(CircuitState::Closed { failures: 0 })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker_shared_across_configurations() {
+        let mut server = mockito::Server::new_async().await;
+        let unavailable = server
+            .mock("GET", "/")
+            .with_status(503)
+            .expect(3)
+            .create_async()
+            .await;
+
+        let config = LighterConfig {
+            base_url: server.url(),
+            retry_config: Some(RetryConfig {
+                max_retries: 10,
+                min_retry_interval: 1,
+                max_retry_interval: 10,
+                ..RetryConfig::default()
+            }),
+            ..LighterConfig::new()
+                .with_circuit_breaker(3, Duration::from_secs(60))
+                .unwrap()
+        };
+        // e.g. two different APIs
+        let first = Configuration::try_from(&config).unwrap();
+        let second = Configuration::try_from(&config).unwrap();
+
+        // the retries stop once the circuit opens
+        let res = apis::root_api::status(&first).await;
+        assert!(matches!(
+            LighterError::from(res.unwrap_err()),
+            LighterError::CircuitOpen(_)
+        ));
+
+        // and the other requests are not sent either
+        let res = apis::root_api::status(&second).await;
+        assert!(matches!(
+            LighterError::from(res.unwrap_err()),
+            LighterError::CircuitOpen(host) if host.starts_with("127.0.0.1:")
+        ));
+        unavailable.assert_async().await;
+    }
+}
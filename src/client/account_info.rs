@@ -0,0 +1,214 @@
+use std::sync::{Arc, RwLock};
+
+use crate::{
+    api::account::{AccountApi, AccountMetadataBy, ApiKeyInfo},
+    client::tasks::BackgroundTasks,
+    models::{AccountLimits, AccountMetadata},
+    LighterError, Result,
+};
+
+/// Snapshot of the metadata of the configured account, see [`crate::HttpClient::account_info`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccountInfo {
+    pub account_index: i64,
+    /// `None` if the account has no metadata yet
+    pub metadata: Option<AccountMetadata>,
+    pub limits: AccountLimits,
+    pub api_keys: Vec<ApiKeyInfo>,
+}
+
+impl AccountInfo {
+    /// Returns the tier of the account (e.g. `premium`), see [`AccountLimits::user_tier`]
+    pub fn tier(&self) -> &str {
+        &self.limits.user_tier
+    }
+
+    /// Returns the API key with the given index, if registered
+    pub fn api_key(&self, api_key_index: i32) -> Option<&ApiKeyInfo> {
+        self.api_keys
+            .iter()
+            .find(|v| v.api_key_index == api_key_index)
+    }
+}
+
+/// Cache holding the last `AccountInfo` fetched. It needs the auth token, so the API key of the
+/// account.
+#[derive(Debug)]
+pub struct AccountInfoCache {
+    api: AccountApi,
+    account_index: i64,
+    info: RwLock<Option<Arc<AccountInfo>>>,
+}
+
+impl AccountInfoCache {
+    pub fn new(api: AccountApi, account_index: i64) -> Self {
+        Self {
+            api,
+            account_index,
+            info: RwLock::new(None),
+        }
+    }
+
+    pub fn get(&self) -> Option<Arc<AccountInfo>> {
+        self.info.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Fetches the metadata, the limits and the API keys concurrently, replacing the cached
+    /// snapshot. The previous one is kept if any of the endpoints fails.
+    pub async fn refresh(&self) -> Result<Arc<AccountInfo>> {
+        let account_index = self.account_index.to_string();
+        let (metadatas, limits, api_keys) = tokio::try_join!(
+            self.api
+                .account_metadata(AccountMetadataBy::Index, &account_index),
+            self.api.account_limits(self.account_index),
+            self.api.all_api_keys(self.account_index),
+        )?;
+
+        let info = Arc::new(AccountInfo {
+            account_index: self.account_index,
+            metadata: metadatas
+                .account_metadatas
+                .into_iter()
+                .find(|v| v.account_index == self.account_index),
+            limits,
+            api_keys,
+        });
+        *self.info.write().unwrap_or_else(|e| e.into_inner()) = Some(info.clone());
+        Ok(info)
+    }
+}
+
+/// Spawns the first fetch of the cache in the background, it fails if `tasks` are shut down first
+pub(crate) fn spawn_refresh(
+    cache: Arc<AccountInfoCache>,
+    tasks: &BackgroundTasks,
+) -> tokio::task::JoinHandle<Result<()>> {
+    let shutdown = tasks.token().clone();
+    tasks.spawn(async move {
+        shutdown
+            .run_until_cancelled(cache.refresh())
+            .await
+            .ok_or_else(|| LighterError::Generic("The client was shut down".into()))?
+            .inspect_err(|e| tracing::error!("unable to fetch the account info: {e}"))?;
+        Ok(())
+    })
+}
+
+#[cfg(all(test, feature = "signer"))]
+mod tests {
+    use super::*;
+    use crate::config::LighterConfig;
+
+    #[tokio::test]
+    async fn test_account_info_refresh() {
+        let mut server = mockito::Server::new_async().await;
+        let metadata = server
+            .mock("GET", "/api/v1/accountMetadata")
+            .match_query(mockito::Matcher::UrlEncoded("value".into(), "28".into()))
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"code":200,"account_metadatas":[{"account_index":28,"name":"bot","description":"","can_invite":false,"referral_points_percentage":"0"}]}"#,
+            )
+            .expect(2)
+            .create_async()
+            .await;
+        let limits = server
+            .mock("GET", "/api/v1/accountLimits")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"max_llp_percentage":25,"user_tier":"premium"}"#)
+            .expect(2)
+            .create_async()
+            .await;
+        let api_keys = server
+            .mock("GET", "/api/v1/apikeys")
+            .match_query(mockito::Matcher::UrlEncoded(
+                "api_key_index".into(),
+                "255".into(),
+            ))
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{"code":200,"api_keys":[{"account_index":28,"api_key_index":2,"nonce":5,"public_key":"0x2"}]}"#,
+            )
+            .expect(2)
+            .create_async()
+            .await;
+
+        let config = LighterConfig {
+            base_url: server.url(),
+            ..LighterConfig::new()
+                .with_api_key_private(
+                    "01db9eed031d59d6bd0ee00ee5a7dc1f62087bf217b51caea57eb6e17a02c49e0a748d2f155a2f60",
+                )
+                .with_account_index(28)
+                .with_api_key_index(2)
+        };
+        let cache = Arc::new(AccountInfoCache::new(AccountApi::new(&config).unwrap(), 28));
+        assert!(cache.get().is_none());
+
+        spawn_refresh(cache.clone(), &BackgroundTasks::default())
+            .await
+            .unwrap()
+            .unwrap();
+        let info = cache.get().unwrap();
+        assert_eq!(info.metadata.as_ref().unwrap().name, "bot");
+        assert_eq!(info.tier(), "premium");
+        assert_eq!(info.api_key(2).unwrap().nonce, 5);
+        assert!(info.api_key(3).is_none());
+
+        // fetched again on refresh
+        assert_eq!(*cache.refresh().await.unwrap(), *info);
+        metadata.assert_async().await;
+        limits.assert_async().await;
+        api_keys.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_account_info_refresh_keeps_snapshot() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/api/v1/accountMetadata")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"account_metadatas":[]}"#)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/api/v1/accountLimits")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"max_llp_percentage":25,"user_tier":"standard"}"#)
+            .create_async()
+            .await;
+        let api_keys = server
+            .mock("GET", "/api/v1/apikeys")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"api_keys":[]}"#)
+            .create_async()
+            .await;
+
+        let config = LighterConfig {
+            base_url: server.url(),
+            ..LighterConfig::new()
+                .with_api_key_private(
+                    "01db9eed031d59d6bd0ee00ee5a7dc1f62087bf217b51caea57eb6e17a02c49e0a748d2f155a2f60",
+                )
+                .with_account_index(28)
+                .with_api_key_index(2)
+        };
+        let cache = AccountInfoCache::new(AccountApi::new(&config).unwrap(), 28);
+        let info = cache.refresh().await.unwrap();
+        assert!(info.metadata.is_none());
+
+        api_keys.remove_async().await;
+        server
+            .mock("GET", "/api/v1/apikeys")
+            .match_query(mockito::Matcher::Any)
+            .with_status(404)
+            .create_async()
+            .await;
+        assert!(cache.refresh().await.is_err());
+        assert_eq!(cache.get().unwrap().tier(), "standard");
+    }
+}
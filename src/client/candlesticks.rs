@@ -0,0 +1,281 @@
+use std::{
+    collections::VecDeque,
+    pin::Pin,
+    task::{Context, Poll},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use futures::{Stream, StreamExt};
+
+use crate::{
+    api::candlestick::{CandlestickApi, CandlestickInterval},
+    client::{TradeUpdate, WsClient, WsSubscription},
+    models::{Candlestick, Trade},
+    MarketIndex, Result,
+};
+
+/// Candles of a market: first the historical ones, then the live ones built from the trades.
+///
+/// A candle is yielded once it's closed, i.e. when a trade of a later candle is received, so the
+/// open times are strictly increasing. The candles without trades are yielded too, flat at the
+/// previous close. The last historical candle is usually still open, so it's completed with the
+/// trades following its `last_trade_id`.
+#[derive(Debug)]
+pub struct CandlestickStream {
+    trades: WsSubscription<TradeUpdate>,
+    candlesticks: Candlesticks,
+}
+
+impl CandlestickStream {
+    /// Subscribes to the trades, then fetches the last `count_back` candles, so that no trade is
+    /// missed in between (the ones already in the candles are skipped)
+    pub async fn new(
+        candlestick: &CandlestickApi,
+        ws: &WsClient,
+        market_id: impl Into<MarketIndex>,
+        resolution: CandlestickInterval,
+        count_back: i64,
+    ) -> Result<Self> {
+        let market_id = market_id.into().0;
+        let trades = ws.subscribe_trades(market_id).await?;
+
+        let interval = resolution.as_millis();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as i64;
+        let candlesticks = candlestick
+            .candlesticks(
+                market_id,
+                resolution,
+                now - (count_back + 1) * interval,
+                now,
+                count_back,
+                None,
+            )
+            .await?
+            .candlesticks;
+
+        Ok(Self {
+            trades,
+            candlesticks: Candlesticks::new(interval, candlesticks),
+        })
+    }
+
+    /// Returns the candle still open, if any
+    pub fn current(&self) -> Option<&Candlestick> {
+        self.candlesticks.current.as_ref()
+    }
+}
+
+/// Candles built from the historical ones and the following trades
+#[derive(Debug)]
+struct Candlesticks {
+    interval: i64,
+    closed: VecDeque<Candlestick>, // not yielded yet
+    current: Option<Candlestick>,
+    last_trade_id: i64,
+}
+
+impl Candlesticks {
+    fn new(interval: i64, mut candlesticks: Vec<Candlestick>) -> Self {
+        candlesticks.sort_by_key(|v| v.timestamp);
+        candlesticks.dedup_by_key(|v| v.timestamp);
+
+        let last_trade_id = candlesticks
+            .iter()
+            .map(|v| v.last_trade_id)
+            .max()
+            .unwrap_or_default();
+        let current = candlesticks.pop();
+
+        Self {
+            interval,
+            closed: candlesticks.into(),
+            current,
+            last_trade_id,
+        }
+    }
+
+    fn add_trades(&mut self, mut trades: Vec<Trade>) {
+        // the snapshots aren't sorted
+        trades.sort_by_key(|v| v.trade_id);
+        for trade in trades {
+            self.add_trade(&trade);
+        }
+    }
+
+    fn add_trade(&mut self, trade: &Trade) {
+        if trade.trade_id <= self.last_trade_id {
+            return; // already in the candles
+        }
+        let (Ok(price), Ok(size)) = (trade.price.parse::<f64>(), trade.size.parse::<f64>()) else {
+            tracing::warn!(
+                "invalid trade {}: price `{}`, size `{}`",
+                trade.trade_id,
+                trade.price,
+                trade.size
+            );
+            return;
+        };
+        self.last_trade_id = trade.trade_id;
+
+        // aligned on the historical candles, e.g. for the weekly ones
+        let origin = self.current.as_ref().map_or(0, |v| v.timestamp);
+        let timestamp = trade.timestamp - (trade.timestamp - origin).rem_euclid(self.interval);
+
+        let current = match self.current.take() {
+            Some(current) if timestamp < current.timestamp => {
+                tracing::warn!(
+                    "trade {} of a closed candle ({})",
+                    trade.trade_id,
+                    trade.timestamp
+                );
+                self.current = Some(current);
+                return;
+            }
+            Some(mut current) if timestamp == current.timestamp => {
+                current.high = current.high.max(price);
+                current.low = current.low.min(price);
+                current.close = price;
+                current
+            }
+            previous => {
+                if let Some(previous) = previous {
+                    let close = previous.close;
+                    let mut next = previous.timestamp + self.interval;
+                    self.closed.push_back(previous);
+
+                    // the candles without trades
+                    while next < timestamp {
+                        self.closed.push_back(Candlestick::new(
+                            next, close, close, close, close, 0.0, 0.0, 0,
+                        ));
+                        next += self.interval;
+                    }
+                }
+                Candlestick::new(timestamp, price, price, price, price, 0.0, 0.0, 0)
+            }
+        };
+
+        self.current = Some(Candlestick {
+            volume0: current.volume0 + size,
+            volume1: current.volume1 + price * size,
+            last_trade_id: trade.trade_id,
+            ..current
+        });
+    }
+}
+
+impl Stream for CandlestickStream {
+    type Item = Result<Candlestick>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some(candlestick) = self.candlesticks.closed.pop_front() {
+                return Poll::Ready(Some(Ok(candlestick)));
+            }
+
+            match self.trades.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(update))) => self.candlesticks.add_trades(update.trades),
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: i64 = 60_000;
+
+    fn candle(timestamp: i64, close: f64, last_trade_id: i64) -> Candlestick {
+        Candlestick::new(
+            timestamp,
+            close,
+            close,
+            close,
+            close,
+            1.0,
+            close,
+            last_trade_id,
+        )
+    }
+
+    fn trade(trade_id: i64, timestamp: i64, price: &str) -> Trade {
+        Trade {
+            trade_id,
+            timestamp,
+            price: price.into(),
+            size: "2".into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_candlesticks_backfill_then_trades() {
+        // the last one is still open, the duplicate is dropped
+        let mut candlesticks = Candlesticks::new(
+            MINUTE,
+            vec![
+                candle(2 * MINUTE, 12.0, 20),
+                candle(MINUTE, 11.0, 10),
+                candle(2 * MINUTE, 12.0, 20),
+            ],
+        );
+        assert_eq!(candlesticks.closed.len(), 1);
+
+        candlesticks.add_trades(vec![
+            trade(21, 2 * MINUTE + 10, "14"),
+            trade(19, 2 * MINUTE + 5, "99"), // already in the candle
+            trade(22, 2 * MINUTE + 20, "13"),
+        ]);
+        assert_eq!(candlesticks.closed.len(), 1);
+        let current = candlesticks.current.clone().unwrap();
+        assert_eq!(current.timestamp, 2 * MINUTE);
+        assert_eq!(
+            (current.open, current.high, current.low),
+            (12.0, 14.0, 12.0)
+        );
+        assert_eq!(
+            (current.close, current.volume0, current.volume1),
+            (13.0, 5.0, 66.0)
+        );
+        assert_eq!(current.last_trade_id, 22);
+
+        // no trade during the 4th minute
+        candlesticks.add_trades(vec![trade(23, 4 * MINUTE + 1, "15")]);
+        candlesticks.add_trades(vec![trade(24, 3 * MINUTE, "1")]); // of a closed candle
+
+        let closed = candlesticks.closed.drain(..).collect::<Vec<_>>();
+        let timestamps = closed.iter().map(|v| v.timestamp).collect::<Vec<_>>();
+        assert_eq!(timestamps, vec![MINUTE, 2 * MINUTE, 3 * MINUTE]);
+        assert_eq!(closed[1], current);
+        assert_eq!(
+            closed[2],
+            Candlestick::new(3 * MINUTE, 13.0, 13.0, 13.0, 13.0, 0.0, 0.0, 0)
+        );
+
+        let current = candlesticks.current.unwrap();
+        assert_eq!(current.timestamp, 4 * MINUTE);
+        assert_eq!(
+            (current.open, current.close, current.volume0),
+            (15.0, 15.0, 2.0)
+        );
+    }
+
+    #[test]
+    fn test_candlesticks_without_backfill() {
+        let mut candlesticks = Candlesticks::new(MINUTE, vec![]);
+        candlesticks.add_trades(vec![trade(2, MINUTE + 30, "11"), trade(1, 30, "10")]);
+
+        assert_eq!(
+            candlesticks.closed.pop_front().unwrap(),
+            Candlestick::new(0, 10.0, 10.0, 10.0, 10.0, 2.0, 20.0, 1)
+        );
+        assert_eq!(candlesticks.current.unwrap().timestamp, MINUTE);
+    }
+}
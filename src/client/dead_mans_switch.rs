@@ -0,0 +1,219 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use tokio::{task::JoinHandle, time::MissedTickBehavior};
+
+use crate::{
+    api::order::CancelAllTimeInForce,
+    client::{tasks::BackgroundTasks, TxClient},
+    clock::ServerClock,
+    models::RespSendTx,
+    signer::data::SignCancelAllOrdersData,
+    LighterError, Result,
+};
+
+/// Keeps a scheduled cancel of all the orders `grace` ahead, renewing it every `interval` as long
+/// as [`DeadMansSwitch::heartbeat`] is called within `grace`.
+///
+/// If the heartbeats stop (e.g. the trading loop is stuck) or the process dies, the cancel isn't
+/// renewed anymore and the venue cancels all the orders once it's due.
+/// Dropping the switch, or shutting down the client that started it, stops the renewals too,
+/// leaving the last cancel scheduled.
+#[derive(Debug)]
+pub struct DeadMansSwitch {
+    tx_client: Arc<TxClient>,
+    last_heartbeat: Arc<Mutex<Instant>>,
+    task: JoinHandle<()>,
+}
+
+impl DeadMansSwitch {
+    pub(crate) fn start(
+        tx_client: Arc<TxClient>,
+        clock: Arc<ServerClock>,
+        interval: Duration,
+        grace: Duration,
+        tasks: &BackgroundTasks,
+    ) -> Result<Self> {
+        if interval.is_zero() || interval >= grace {
+            return Err(LighterError::Config(
+                "the dead man's switch `interval` must be shorter than `grace`".into(),
+            ));
+        }
+        let after = chrono::Duration::from_std(grace)
+            .map_err(|e| LighterError::Config(format!("Invalid grace: {e}")))?;
+
+        let last_heartbeat = Arc::new(Mutex::new(Instant::now()));
+        let task = tasks.spawn({
+            let tx_client = tx_client.clone();
+            let last_heartbeat = last_heartbeat.clone();
+            let shutdown = tasks.token().clone();
+            async move {
+                let mut ticker = tokio::time::interval(interval);
+                ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+                let renew = async move {
+                    loop {
+                        ticker.tick().await;
+                        let elapsed = last_heartbeat
+                            .lock()
+                            .unwrap_or_else(|e| e.into_inner())
+                            .elapsed();
+                        if elapsed >= grace {
+                            tracing::warn!(
+                                "no heartbeat for {elapsed:?}, the cancel isn't renewed"
+                            );
+                            continue;
+                        }
+
+                        let time = (clock.server_now() + after).timestamp_millis();
+                        if let Err(e) =
+                            schedule(&tx_client, CancelAllTimeInForce::Scheduled, time).await
+                        {
+                            tracing::warn!("unable to renew the scheduled cancel: {e}");
+                        }
+                    }
+                };
+                shutdown.run_until_cancelled(renew).await;
+            }
+        });
+
+        Ok(Self {
+            tx_client,
+            last_heartbeat,
+            task,
+        })
+    }
+
+    /// Signals that the caller is still alive, so that the scheduled cancel keeps being renewed
+    pub fn heartbeat(&self) {
+        *self
+            .last_heartbeat
+            .lock()
+            .unwrap_or_else(|e| e.into_inner()) = Instant::now();
+    }
+
+    /// Stops the renewals and aborts the scheduled cancel, leaving the orders open
+    pub async fn stop(self) -> Result<RespSendTx> {
+        self.task.abort();
+        schedule(&self.tx_client, CancelAllTimeInForce::Abort, 0)
+            .await
+            .inspect_err(|e| tracing::error!("unable to abort the scheduled cancel: {e}"))
+    }
+}
+
+impl Drop for DeadMansSwitch {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn schedule(
+    tx_client: &TxClient,
+    time_in_force: CancelAllTimeInForce,
+    time: i64,
+) -> Result<RespSendTx> {
+    tx_client
+        .submit(|signer, nonce| {
+            signer.sign_cancel_all_orders(
+                SignCancelAllOrdersData {
+                    time_in_force: time_in_force.into(),
+                    time,
+                },
+                nonce,
+            )
+        })
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::LighterConfig;
+
+    fn send_tx_body(time_in_force: u8) -> mockito::Matcher {
+        mockito::Matcher::AllOf(vec![
+            mockito::Matcher::Regex(r#"name="tx_type"\r\n\r\n16\r\n"#.into()),
+            mockito::Matcher::Regex(format!(r#""TimeInForce":{time_in_force}\b"#)),
+        ])
+    }
+
+    #[tokio::test]
+    async fn test_dead_mans_switch() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/api/v1/nextNonce")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"nonce":5}"#)
+            .create_async()
+            .await;
+        let renew = server
+            .mock("POST", "/api/v1/sendTx")
+            .match_body(send_tx_body(CancelAllTimeInForce::Scheduled.into()))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"tx_hash":"0x1","predicted_execution_time_ms":1}"#)
+            .expect_at_least(2)
+            .create_async()
+            .await;
+        let abort = server
+            .mock("POST", "/api/v1/sendTx")
+            .match_body(send_tx_body(CancelAllTimeInForce::Abort.into()))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"tx_hash":"0x2","predicted_execution_time_ms":1}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let config = LighterConfig {
+            base_url: server.url(),
+            local_nonce: false,
+            ..LighterConfig::new()
+                .with_api_key_private(
+                    "01db9eed031d59d6bd0ee00ee5a7dc1f62087bf217b51caea57eb6e17a02c49e0a748d2f155a2f60",
+                )
+                .with_account_index(28)
+                .with_api_key_index(2)
+        };
+        let tx_client = Arc::new(TxClient::new(&config, None).unwrap());
+        let clock = config.server_clock.clone();
+
+        let res = DeadMansSwitch::start(
+            tx_client.clone(),
+            clock.clone(),
+            Duration::from_secs(2),
+            Duration::from_secs(1),
+            &BackgroundTasks::default(),
+        );
+        assert!(matches!(res, Err(LighterError::Config(_))));
+
+        let switch = DeadMansSwitch::start(
+            tx_client,
+            clock,
+            Duration::from_millis(50),
+            Duration::from_millis(300),
+            &BackgroundTasks::default(),
+        )
+        .unwrap();
+        for _ in 0..3 {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            switch.heartbeat();
+        }
+        renew.assert_async().await;
+
+        // without heartbeats the cancel isn't renewed anymore
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        renew.remove_async().await;
+        let renew = server
+            .mock("POST", "/api/v1/sendTx")
+            .match_body(send_tx_body(CancelAllTimeInForce::Scheduled.into()))
+            .expect(0)
+            .create_async()
+            .await;
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        renew.assert_async().await;
+
+        assert_eq!(switch.stop().await.unwrap().tx_hash, "0x2");
+        abort.assert_async().await;
+    }
+}
@@ -0,0 +1,360 @@
+use std::{
+    collections::VecDeque,
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard},
+    task::{Context, Poll, Waker},
+};
+
+use futures::{Stream, StreamExt};
+use serde::de::DeserializeOwned;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    client::{
+        AccountUpdate, NotificationUpdate, OrderBookUpdate, TradeUpdate, WsClient, WsSubscription,
+    },
+    AccountIndex, LighterError, MarketIndex, Result,
+};
+
+/// Update of any of the channels merged by an [`EventBus`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum LighterEvent {
+    OrderBook(OrderBookUpdate),
+    Trades(TradeUpdate),
+    Account(AccountUpdate),
+    Notifications(NotificationUpdate),
+}
+
+/// What an [`EventBus`] does when an update comes while its buffer is full
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Drops the oldest buffered event to make room for the new one, see
+    /// [`EventBus::dropped`]. It keeps the latency low, but an order book kept from the updates
+    /// then gets a gap (see [`crate::OrderBook::apply`]).
+    #[default]
+    DropOldest,
+    /// Ends the bus with an error once the buffered events are consumed, unsubscribing from all
+    /// the channels: the consumer can't keep up and has to start over.
+    Error,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    events: VecDeque<Result<LighterEvent>>,
+    active: usize,    // subscriptions still forwarding their updates
+    subscribed: bool, // `true` once there was a subscription, so that the bus can end
+    dropped: u64,
+    overflowed: bool, // the overflow error is yet to be yielded
+    closed: bool,
+    waker: Option<Waker>,
+}
+
+/// Bounded buffer filled by the subscriptions and drained by the bus
+#[derive(Debug)]
+struct Buffer {
+    capacity: usize,
+    overflow: OverflowPolicy,
+    state: Mutex<State>,
+    shutdown: CancellationToken, // stops the forwarding of all the subscriptions
+}
+
+impl Buffer {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Buffers the event, returns `false` if the bus is closed so the subscription must stop
+    fn push(&self, event: Result<LighterEvent>) -> bool {
+        let mut state = self.lock();
+        if state.closed {
+            return false;
+        }
+
+        if state.events.len() >= self.capacity {
+            match self.overflow {
+                OverflowPolicy::DropOldest => {
+                    state.events.pop_front();
+                    state.dropped += 1;
+                    tracing::warn!("event bus full, dropped the oldest event");
+                }
+                OverflowPolicy::Error => {
+                    tracing::error!("event bus full, closing it");
+                    state.overflowed = true;
+                    state.closed = true;
+                    self.shutdown.cancel();
+                    wake(&mut state);
+                    return false;
+                }
+            }
+        }
+
+        state.events.push_back(event);
+        wake(&mut state);
+        true
+    }
+
+    fn attach(&self) {
+        let mut state = self.lock();
+        state.active += 1;
+        state.subscribed = true;
+    }
+
+    fn detach(&self) {
+        let mut state = self.lock();
+        state.active -= 1;
+        wake(&mut state);
+    }
+}
+
+fn wake(state: &mut State) {
+    if let Some(waker) = state.waker.take() {
+        waker.wake();
+    }
+}
+
+/// Single stream of the updates of several channels (order books, trades, account,
+/// notifications), merged as they are received: the updates of a channel keep their order.
+///
+/// The channels are subscribed through the `subscribe_*` methods, on the connection of the
+/// [`WsClient`], so they reconnect the same way. Their updates are read in the background into a
+/// buffer of `capacity` events, so that a slow consumer doesn't hold the connection back; when
+/// it's full, the [`OverflowPolicy`] applies.
+///
+/// The bus ends once all its subscriptions have ended (e.g. after the shutdown of the client),
+/// their errors being yielded before. Dropping it unsubscribes from all the channels.
+#[derive(Debug)]
+pub struct EventBus {
+    ws: WsClient,
+    buffer: Arc<Buffer>,
+}
+
+impl EventBus {
+    pub fn new(ws: &WsClient, capacity: usize, overflow: OverflowPolicy) -> Result<Self> {
+        if capacity == 0 {
+            return Err(LighterError::Config(
+                "The capacity of the event bus must be positive".into(),
+            ));
+        }
+
+        Ok(Self {
+            ws: ws.clone(),
+            buffer: Arc::new(Buffer {
+                capacity,
+                overflow,
+                state: Mutex::new(State::default()),
+                shutdown: ws.tasks().token().child_token(),
+            }),
+        })
+    }
+
+    /// Adds the order book of the market, see [`WsClient::subscribe_order_book`]
+    pub async fn subscribe_order_book(&self, market_id: impl Into<MarketIndex>) -> Result<()> {
+        self.check_open()?;
+        let subscription = self.ws.subscribe_order_book(market_id).await?;
+        self.forward(subscription, LighterEvent::OrderBook);
+        Ok(())
+    }
+
+    /// Adds the trades of the market, see [`WsClient::subscribe_trades`]
+    pub async fn subscribe_trades(&self, market_id: impl Into<MarketIndex>) -> Result<()> {
+        self.check_open()?;
+        let subscription = self.ws.subscribe_trades(market_id).await?;
+        self.forward(subscription, LighterEvent::Trades);
+        Ok(())
+    }
+
+    /// Adds the state of the account, see [`WsClient::subscribe_account`]
+    pub async fn subscribe_account(&self, account_index: impl Into<AccountIndex>) -> Result<()> {
+        self.check_open()?;
+        let subscription = self.ws.subscribe_account(account_index).await?;
+        self.forward(subscription, LighterEvent::Account);
+        Ok(())
+    }
+
+    /// Adds the notifications of the account, see [`WsClient::subscribe_notifications`]
+    pub async fn subscribe_notifications(
+        &self,
+        account_index: impl Into<AccountIndex>,
+    ) -> Result<()> {
+        self.check_open()?;
+        let subscription = self.ws.subscribe_notifications(account_index).await?;
+        self.forward(subscription, LighterEvent::Notifications);
+        Ok(())
+    }
+
+    /// Returns how many events were dropped so far with [`OverflowPolicy::DropOldest`]
+    pub fn dropped(&self) -> u64 {
+        self.buffer.lock().dropped
+    }
+
+    fn check_open(&self) -> Result<()> {
+        if self.buffer.lock().closed {
+            return Err(LighterError::Generic("The event bus is closed".into()));
+        }
+        Ok(())
+    }
+
+    /// Reads the updates of the subscription into the buffer until it ends or the bus is closed
+    fn forward<T>(&self, mut subscription: WsSubscription<T>, event: fn(T) -> LighterEvent)
+    where
+        T: DeserializeOwned + Send + 'static,
+    {
+        let buffer = self.buffer.clone();
+        buffer.attach();
+        self.ws.tasks().spawn(async move {
+            buffer
+                .shutdown
+                .run_until_cancelled(async {
+                    while let Some(update) = subscription.next().await {
+                        if !buffer.push(update.map(event)) {
+                            break;
+                        }
+                    }
+                })
+                .await;
+            buffer.detach();
+            // dropping the subscription unsubscribes from the channel
+        });
+    }
+}
+
+impl Stream for EventBus {
+    type Item = Result<LighterEvent>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut state = self.buffer.lock();
+        if let Some(event) = state.events.pop_front() {
+            return Poll::Ready(Some(event));
+        }
+        if std::mem::take(&mut state.overflowed) {
+            return Poll::Ready(Some(Err(LighterError::Generic(format!(
+                "Event bus overflowed its buffer of {} events",
+                self.buffer.capacity
+            )))));
+        }
+        if state.closed || (state.subscribed && state.active == 0) {
+            return Poll::Ready(None);
+        }
+
+        state.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl Drop for EventBus {
+    fn drop(&mut self) {
+        self.buffer.shutdown.cancel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::client::ws::tests::{expect, mock_config, mock_server, order_book, trades};
+
+    #[tokio::test]
+    async fn test_event_bus_merges_channels() {
+        let ws_url = mock_server(vec![
+            expect("subscribe", "order_book/0"),
+            order_book("subscribed", 0, "3000", 1),
+            expect("subscribe", "trade/1"),
+            trades("subscribed", 1, &[1]),
+            order_book("update", 0, "3001", 2),
+            trades("update", 1, &[2]),
+            trades("update", 1, &[3]),
+            order_book("update", 0, "3002", 3),
+        ])
+        .await;
+        let client = WsClient::new(&mock_config(ws_url)).unwrap();
+        let bus = EventBus::new(&client, 16, OverflowPolicy::DropOldest).unwrap();
+        bus.subscribe_order_book(0).await.unwrap();
+        bus.subscribe_trades(1).await.unwrap();
+
+        // it ends with the connection
+        let events = tokio::time::timeout(Duration::from_secs(5), bus.collect::<Vec<_>>())
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 6);
+
+        let mut offsets = Vec::new();
+        let mut trade_ids = Vec::new();
+        for event in events {
+            match event.unwrap() {
+                LighterEvent::OrderBook(update) => offsets.push(update.order_book.offset),
+                LighterEvent::Trades(update) => {
+                    trade_ids.extend(update.trades.iter().map(|v| v.trade_id))
+                }
+                event => panic!("unexpected event {event:?}"),
+            }
+        }
+        assert_eq!(offsets, [1, 2, 3]);
+        assert_eq!(trade_ids, [1, 2, 3]);
+    }
+
+    fn order_book_updates() -> Vec<serde_json::Value> {
+        vec![
+            expect("subscribe", "order_book/0"),
+            order_book("subscribed", 0, "3000", 1),
+            order_book("update", 0, "3001", 2),
+            order_book("update", 0, "3002", 3),
+            order_book("update", 0, "3003", 4),
+            json!({"stall": true}),
+        ]
+    }
+
+    fn offsets(events: &[Result<LighterEvent>]) -> Vec<i64> {
+        events
+            .iter()
+            .filter_map(|v| match v {
+                Ok(LighterEvent::OrderBook(update)) => Some(update.order_book.offset),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_event_bus_drops_oldest() {
+        let ws_url = mock_server(order_book_updates()).await;
+        let client = WsClient::new(&mock_config(ws_url)).unwrap();
+        let mut bus = EventBus::new(&client, 2, OverflowPolicy::DropOldest).unwrap();
+        bus.subscribe_order_book(0).await.unwrap();
+
+        // not consumed meanwhile
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(bus.dropped(), 2);
+
+        let events = vec![bus.next().await.unwrap(), bus.next().await.unwrap()];
+        assert_eq!(offsets(&events), [3, 4]);
+        client.shutdown().await;
+        assert!(bus.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_event_bus_overflow_error() {
+        let ws_url = mock_server(order_book_updates()).await;
+        let client = WsClient::new(&mock_config(ws_url)).unwrap();
+        let bus = EventBus::new(&client, 2, OverflowPolicy::Error).unwrap();
+        bus.subscribe_order_book(0).await.unwrap();
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(bus.subscribe_order_book(1).await.is_err());
+
+        // the buffered events first, then the error
+        let events = tokio::time::timeout(Duration::from_secs(5), bus.collect::<Vec<_>>())
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 3);
+        assert_eq!(offsets(&events), [1, 2]);
+        assert!(
+            matches!(&events[2], Err(LighterError::Generic(e)) if e.contains("overflowed")),
+            "{:?}",
+            events[2]
+        );
+
+        assert!(EventBus::new(&client, 0, OverflowPolicy::Error).is_err());
+    }
+}
@@ -1,14 +1,42 @@
+use std::sync::Arc;
+
 use crate::{
     api::{
-        account::AccountApi, announcement::AnnouncementApi, block::BlockApi, bridge::BridgeApi,
-        candlestick::CandlestickApi, funding::FundingApi, info::InfoApi,
-        notification::NotificationApi, order::OrderApi, referral::ReferralApi, root::RootApi,
+        account::AccountApi,
+        announcement::AnnouncementApi,
+        block::BlockApi,
+        bridge::BridgeApi,
+        candlestick::CandlestickApi,
+        funding::FundingApi,
+        info::InfoApi,
+        notification::NotificationApi,
+        order::OrderApi,
+        raw::RawApi,
+        referral::ReferralApi,
+        root::{ApiHealth, RootApi},
         transaction::TransactionApi,
     },
-    client::nonce::NonceManager,
+    client::{
+        account_info::{self, AccountInfoCache},
+        metadata::{self, MetadataCache},
+        nonce::NonceManager,
+        tasks::BackgroundTasks,
+        AccountInfo, ClientOrderIdGenerator, ExchangeMetadata, FileNonceStore, NonceStore,
+    },
+    clock::{self, TimeSync},
     config::LighterConfig,
-    LighterError, Result,
+    Configuration, ConnectionMetrics, ConnectionStats, LighterError, Result,
+};
+
+#[cfg(feature = "signer")]
+use crate::{
+    client::{DeadMansSwitch, TxClient},
+    models::RespSendTx,
+    signer::{data::ChangePubKeyData, FFISigner, Signer},
 };
+#[cfg(feature = "signer")]
+use secrecy::SecretString;
+use tokio_util::sync::CancellationToken;
 
 #[derive(Default, Debug)]
 pub struct ApiInterface {
@@ -45,6 +73,12 @@ impl ApiInterface {
             .ok_or_else(|| LighterError::Generic("Block API not initialized".into()))
     }
 
+    pub fn bridge(&self) -> Result<&BridgeApi> {
+        self.bridge
+            .as_ref()
+            .ok_or_else(|| LighterError::Generic("Bridge API not initialized".into()))
+    }
+
     pub fn candlestick(&self) -> Result<&CandlestickApi> {
         self.candlestick
             .as_ref()
@@ -94,13 +128,31 @@ impl ApiInterface {
     }
 }
 
+/// Client of the REST APIs.
+///
+/// The tasks it spawns in the background (time sync, metadata warm up, dead man's switch) run
+/// until [`HttpClient::shutdown`]. Dropping the client without calling it cancels them too, but
+/// doesn't wait for them: they may keep running briefly, and the nonce isn't flushed.
 #[derive(Debug)]
 pub struct HttpClient {
     // instance specific
-    account_index: i64,
-    api_key_index: i32,
+    account_index: Option<i64>, // not needed by the read only clients
+    api_key_index: Option<i32>,
     apis: ApiInterface,
-    nonce_manager: Option<NonceManager>, // it can be API or local nonce management, so it's optional
+    nonce_manager: Option<Arc<NonceManager>>, // it can be API or local nonce management, so it's optional
+    #[cfg(feature = "signer")]
+    tx_client: Option<Arc<TxClient>>,
+    #[cfg(feature = "signer")]
+    signer_error: Option<LighterError>, // why the signer couldn't be initialized, if so
+    metadata: Arc<MetadataCache>, // shared with the order and account APIs
+    account_info: Option<Arc<AccountInfoCache>>, // only with `HttpClientBuilder::with_account_info`
+    time_sync: Arc<TimeSync>,
+    client_order_ids: Arc<ClientOrderIdGenerator>,
+    health: RootApi,            // always available, unlike the root API
+    tx_health: Option<RootApi>, // on the pool of the txs, only if they can be sent
+    raw: RawApi,
+    tasks: BackgroundTasks,
+    connection_metrics: Arc<ConnectionMetrics>,
 }
 
 impl HttpClient {
@@ -113,17 +165,255 @@ impl HttpClient {
         &self.apis
     }
 
+    /// Fetches and caches the exchange metadata (markets, decimals and exchange info), so that
+    /// any later lookup is local. Fails if any of the metadata endpoints is unreachable.
+    pub async fn warm_metadata(&self) -> Result<()> {
+        self.metadata.warm().await?;
+        Ok(())
+    }
+
+    /// Same as [`HttpClient::warm_metadata`], but running in the background. The returned handle
+    /// can be awaited to know the outcome.
+    pub fn warm_metadata_in_background(&self) -> tokio::task::JoinHandle<Result<()>> {
+        metadata::spawn_warm(self.metadata.clone(), &self.tasks)
+    }
+
+    /// Returns the cached exchange metadata, if already loaded
+    pub fn metadata(&self) -> Option<Arc<ExchangeMetadata>> {
+        self.metadata.get()
+    }
+
+    /// Returns the metadata of the configured account (tier, limits, API keys), as fetched at
+    /// build time or by the last [`HttpClient::refresh_account_info`]. `None` until fetched, or
+    /// if the account info isn't enabled (see [`HttpClientBuilder::with_account_info`]).
+    pub fn account_info(&self) -> Option<Arc<AccountInfo>> {
+        self.account_info.as_ref().and_then(|v| v.get())
+    }
+
+    /// Fetches the metadata of the configured account again, see [`HttpClient::account_info`].
+    /// On failure the previous snapshot is kept.
+    pub async fn refresh_account_info(&self) -> Result<Arc<AccountInfo>> {
+        self.account_info
+            .as_ref()
+            .ok_or_else(|| {
+                LighterError::Config(
+                    "The account info is not enabled, see `HttpClientBuilder::with_account_info`"
+                        .into(),
+                )
+            })?
+            .refresh()
+            .await
+    }
+
+    /// Returns the metadata cache, used to resolve the market symbols
+    pub fn markets(&self) -> &MetadataCache {
+        &self.metadata
+    }
+
+    /// Returns the generator of the `client_order_index` values assigned by `place_order`
+    pub fn client_order_ids(&self) -> &ClientOrderIdGenerator {
+        &self.client_order_ids
+    }
+
+    /// Syncs the server clock (see [`crate::ServerClock`]) of the config, so that the deadlines
+    /// are computed on the server time. Returns the offset from the local clock.
+    pub async fn sync_time(&self) -> Result<chrono::Duration> {
+        self.time_sync.sync().await
+    }
+
+    /// Returns the offset of the server clock from the local one, without syncing the server
+    /// clock of the config. A large offset explains the auth tokens and the order expiries
+    /// rejected by the server.
+    pub async fn check_clock_skew(&self) -> Result<chrono::Duration> {
+        self.time_sync.measure().await
+    }
+
+    /// Same as [`HttpClient::sync_time`], but repeated every `interval` in the background until
+    /// the returned handle is aborted or the client is shut down
+    pub fn sync_time_periodically(
+        &self,
+        interval: std::time::Duration,
+    ) -> tokio::task::JoinHandle<()> {
+        clock::spawn_sync(self.time_sync.clone(), interval, &self.tasks)
+    }
+
+    /// Returns `true` if txs can be signed and submitted, i.e. the API key is set and the signer
+    /// could be initialized (see [`HttpClientBuilder::build`])
+    pub fn can_sign(&self) -> bool {
+        #[cfg(feature = "signer")]
+        return self.tx_client.is_some();
+        #[cfg(not(feature = "signer"))]
+        false
+    }
+
+    #[cfg(feature = "signer")]
+    fn tx_client(&self) -> Result<&Arc<TxClient>> {
+        self.tx_client
+            .as_ref()
+            .ok_or_else(|| match &self.signer_error {
+                Some(e) => LighterError::Signing(format!(
+                    "The signer couldn't be initialized when building the client: {e}"
+                )),
+                None => LighterError::Config("`api_key_private` is not set".into()),
+            })
+    }
+
+    /// Starts a dead man's switch (see [`DeadMansSwitch`]): all the orders are cancelled by the
+    /// venue unless [`DeadMansSwitch::heartbeat`] is called within `grace`. The scheduled cancel
+    /// is renewed every `interval`, which must be shorter than `grace`.
+    #[cfg(feature = "signer")]
+    pub fn start_dead_mans_switch(
+        &self,
+        interval: std::time::Duration,
+        grace: std::time::Duration,
+    ) -> Result<DeadMansSwitch> {
+        DeadMansSwitch::start(
+            self.tx_client()?.clone(),
+            self.time_sync.clock().clone(),
+            interval,
+            grace,
+            &self.tasks,
+        )
+    }
+
+    /// Token cancelled at the shutdown of the client, e.g. to shut down a
+    /// [`WsClient`](crate::WsClient) along with it, see
+    /// [`WsClient::with_shutdown_token`](crate::WsClient::with_shutdown_token)
+    pub fn shutdown_token(&self) -> CancellationToken {
+        self.tasks.token().clone()
+    }
+
+    /// Stops all the background tasks and waits for them to end, then persists the local nonce
+    /// (see [`LighterConfig::local_nonce_path`]). The tasks started afterwards stop right away.
+    pub async fn shutdown(&self) -> Result<()> {
+        self.tasks.shutdown().await;
+        if let Some(nonce_manager) = &self.nonce_manager {
+            nonce_manager.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Returns the requests sent and the connections opened so far by all the clients sharing
+    /// the config, to tune the connection pool (see [`ConnectionMetrics`]). They are not counted
+    /// with [`LighterConfig::with_http_client`].
+    pub fn connection_stats(&self) -> ConnectionStats {
+        self.connection_metrics.snapshot()
+    }
+
+    /// Checks that the API is reachable at the configured base URL, see [`RootApi::health`]
+    pub async fn ping(&self) -> Result<ApiHealth> {
+        self.health.health().await
+    }
+
+    /// Opens the connections to the base URL ahead of the first requests, sending a status
+    /// request on the pool of the APIs and on the one shared by the tx client and the transaction
+    /// API to submit the txs (if any), so that the following requests reuse them. They stay in the pool as long as allowed by
+    /// [`LighterConfig::pool_max_idle_per_host`] and [`LighterConfig::pool_idle_timeout`].
+    ///
+    /// To be called at startup, so that the first orders don't pay for the DNS lookup and the TCP
+    /// and TLS handshakes.
+    pub async fn warm_up(&self) -> Result<()> {
+        match &self.tx_health {
+            Some(tx_health) => _ = tokio::try_join!(self.health.status(), tx_health.status())?,
+            None => _ = self.health.status().await?,
+        }
+        Ok(())
+    }
+
+    /// Sends a GET request to any endpoint, returning the untyped response. It bypasses the
+    /// models, e.g. to reach a field or an endpoint not mapped yet, see [`RawApi`].
+    pub async fn get_raw(&self, path: &str, query: &[(&str, &str)]) -> Result<serde_json::Value> {
+        self.raw.get(path, query).await
+    }
+
+    /// Same as [`HttpClient::get_raw`], sending the `form` fields in a POST request
+    pub async fn post_raw(&self, path: &str, form: &[(&str, &str)]) -> Result<serde_json::Value> {
+        self.raw.post(path, form).await
+    }
+
+    /// Generates a new API key, returning its private key and its public key (see
+    /// [`HttpClient::register_api_key`])
+    #[cfg(feature = "signer")]
+    pub fn generate_api_key() -> Result<(SecretString, String)> {
+        FFISigner::generate_api_key(None)
+    }
+
+    /// Sign and submit a `ChangePubKey` tx, setting `public_key` as the key of `api_key_index`.
+    /// The tx is signed with the configured `api_key_private`, which must be the private key of
+    /// `public_key`, and with the `eth_private_key` of the account.
+    #[cfg(feature = "signer")]
+    pub async fn register_api_key(&self, public_key: &str) -> Result<RespSendTx> {
+        self.tx_client()?
+            .submit(|signer, nonce| {
+                signer.sign_change_pubkey(
+                    ChangePubKeyData {
+                        new_pubk: public_key.to_string(),
+                    },
+                    nonce,
+                )
+            })
+            .await
+            .inspect_err(|e| tracing::error!("unable to call `register_api_key`: {e}"))
+    }
+
+    /// Same as [`HttpClient::get_nonce`], but returning `n` nonces reserved at once, to submit a
+    /// burst of txs without getting a nonce for each of them. In case of API nonce only the first
+    /// one is fetched, the others follow it.
+    pub async fn reserve_nonces(&self, n: usize) -> Result<Vec<i64>> {
+        #[cfg(feature = "signer")]
+        if let Some(tx_client) = &self.tx_client {
+            return tx_client.next_nonces(n).await;
+        }
+
+        if let Some(nonce_manager) = &self.nonce_manager {
+            nonce_manager.reserve(n)
+        } else {
+            let (account_index, api_key_index) = self.indexes()?;
+            let nonce = self
+                .apis
+                .transaction()?
+                .next_nonce(account_index, api_key_index)
+                .await?
+                .nonce;
+            Ok((0..n as i64).map(|i| nonce + i).collect())
+        }
+    }
+
     pub async fn get_nonce(&self) -> Result<i64> {
+        #[cfg(feature = "signer")]
+        if let Some(tx_client) = &self.tx_client {
+            return tx_client.next_nonce().await;
+        }
+
         if let Some(nonce_manager) = &self.nonce_manager {
             nonce_manager.generate()
         } else {
+            let (account_index, api_key_index) = self.indexes()?;
             self.apis
                 .transaction()?
-                .next_nonce(self.account_index, self.api_key_index)
+                .next_nonce(account_index, api_key_index)
                 .await
                 .map(|v| v.nonce)
         }
     }
+
+    /// Returns the configured account and API key indexes, which the read only clients may lack
+    fn indexes(&self) -> Result<(i64, i32)> {
+        let account_index = self
+            .account_index
+            .ok_or_else(|| LighterError::Config("`account_index` is not set".into()))?;
+        let api_key_index = self
+            .api_key_index
+            .ok_or_else(|| LighterError::Config("`api_key_index` is not set".into()))?;
+        Ok((account_index, api_key_index))
+    }
+}
+
+impl Drop for HttpClient {
+    fn drop(&mut self) {
+        // the tasks stop at their next poll, see `shutdown` to wait for them
+        self.tasks.token().cancel();
+    }
 }
 
 #[derive(Default)]
@@ -141,6 +431,7 @@ pub struct HttpClientBuilder {
     referral: bool,
     root: bool,
     transaction: bool,
+    account_info: bool,
 }
 
 impl HttpClientBuilder {
@@ -209,75 +500,614 @@ impl HttpClientBuilder {
         self
     }
 
+    /// Fetches the metadata of the configured account in the background at build time and
+    /// caches it, see [`HttpClient::account_info`]. The requests are authenticated, so the API
+    /// key must be set. Not enabled by [`HttpClientBuilder::with_all`].
+    pub fn with_account_info(mut self) -> Self {
+        self.account_info = true;
+        self
+    }
+
+    /// Enables all the APIs
+    pub fn with_all(self) -> Self {
+        self.with_account()
+            .with_announcement()
+            .with_block()
+            .with_bridge()
+            .with_candlestick()
+            .with_funding()
+            .with_info()
+            .with_notification()
+            .with_order()
+            .with_referral()
+            .with_root()
+            .with_transaction()
+    }
+
+    /// Returns `true` if any of the enabled APIs creates the auth tokens or signs txs
+    #[cfg(feature = "signer")]
+    fn needs_signer(&self) -> bool {
+        self.account
+            || self.bridge
+            || self.notification
+            || self.order
+            || self.referral
+            || self.transaction
+            || self.account_info
+    }
+
+    /// Same as [`HttpClientBuilder::build`], with all the APIs enabled
+    pub fn build_all(self) -> Result<HttpClient> {
+        self.with_all().build()
+    }
+
+    /// Builds the client. If the signer can't be initialized (e.g. an invalid API key, or a
+    /// `lighter-signer` lib that is missing, built for another arch or fails to create its client)
+    /// while only the APIs that don't sign were enabled, the client is built read only: the signing
+    /// calls then fail with the error, and [`HttpClient::can_sign`] returns `false`.
     pub fn build(self) -> Result<HttpClient> {
+        #[cfg(feature = "signer")]
+        let needs_signer = self.needs_signer();
         let config = self.config.unwrap_or_default();
+        config.validate()?;
+
+        // a single signer for all the APIs, so that they share the auth token
+        #[cfg(feature = "signer")]
+        let (config, signer, signer_error) = if config.api_key_private.is_some() {
+            match FFISigner::try_from(&config) {
+                Ok(signer) => (config, Some(signer), None),
+                Err(e) if !needs_signer => {
+                    tracing::warn!("unable to initialize the signer, the client is read only: {e}");
+                    let config = LighterConfig {
+                        api_key_private: None,
+                        ..config
+                    };
+                    (config, None, Some(e))
+                }
+                Err(e) => return Err(e),
+            }
+        } else {
+            (config, None, None)
+        };
+
+        // required only to sign (see `TxClient::new`) and by the account info cache
+        let account_index = config.account_index.map(|index| index as i64);
+        let api_key_index = config.api_key_index;
         let mut apis = ApiInterface::default();
 
+        let nonce_manager = if config.local_nonce {
+            let store = config.nonce_store.clone().or_else(|| {
+                config
+                    .local_nonce_path
+                    .as_ref()
+                    .map(|path| Arc::new(FileNonceStore::new(path)) as Arc<dyn NonceStore>)
+            });
+            let nonce_manager = match store {
+                Some(store) => NonceManager::with_store(store)?,
+                None => NonceManager::new(),
+            };
+            Some(Arc::new(nonce_manager))
+        } else {
+            None
+        };
+
+        // a single HTTP client for all the APIs (and the caches), so that they share the
+        // connections pool, and another one without retries shared by everything sending the txs
+        let configuration = Configuration::try_from(&config)?;
+        let tx_configuration = Configuration::try_from(&LighterConfig {
+            retry_config: None,
+            ..config.clone()
+        })?;
+
+        // we can sign and submit txs only if the api key is available; read only access otherwise
+        #[cfg(feature = "signer")]
+        let tx_client = match &signer {
+            Some(signer) => Some(Arc::new(TxClient::new_with_configuration(
+                &config,
+                tx_configuration.clone(),
+                Signer::from_ffi(&config, signer.clone())?,
+                nonce_manager.clone(),
+            )?)),
+            None => None,
+        };
+        // the APIs are given the shared signer, so they must not create their own
+        let api_config = LighterConfig {
+            api_key_private: None,
+            ..config.clone()
+        };
+
+        // shared by the order and account APIs and the client itself, so it's loaded only once
+        let metadata = Arc::new(MetadataCache::with_configuration(configuration.clone()));
+        let client_order_ids = Arc::new(ClientOrderIdGenerator::from_timestamp());
+
         if self.account {
-            apis.account = Some(AccountApi::new(&config)?);
+            let account = AccountApi::new_with_configuration(&api_config, configuration.clone())?;
+            #[cfg(feature = "signer")]
+            let account = account
+                .with_signer(signer.clone())
+                .with_tx_client(tx_client.clone())
+                .with_metadata(metadata.clone());
+            apis.account = Some(account);
         }
 
         if self.announcement {
-            apis.announcement = Some(AnnouncementApi::new(&config)?);
+            apis.announcement = Some(AnnouncementApi::from_configuration(configuration.clone()));
         }
 
         if self.block {
-            apis.block = Some(BlockApi::new(&config)?);
+            apis.block = Some(BlockApi::from_configuration(configuration.clone()));
         }
 
         if self.bridge {
-            apis.bridge = Some(BridgeApi::new(&config)?);
+            let bridge = BridgeApi::new_with_configuration(&api_config, configuration.clone())?;
+            #[cfg(feature = "signer")]
+            let bridge = bridge
+                .with_signer(signer.clone())
+                .with_tx_client(tx_client.clone());
+            apis.bridge = Some(bridge);
         }
 
         if self.candlestick {
-            apis.candlestick = Some(CandlestickApi::new(&config)?);
+            apis.candlestick = Some(CandlestickApi::from_configuration(configuration.clone()));
         }
 
         if self.funding {
-            apis.funding = Some(FundingApi::new(&config)?);
+            apis.funding = Some(FundingApi::from_configuration(configuration.clone()));
         }
 
         if self.info {
-            apis.info = Some(InfoApi::new(&config)?);
+            apis.info = Some(InfoApi::from_configuration(configuration.clone()));
         }
 
         if self.notification {
-            apis.notification = Some(NotificationApi::new(&config)?);
+            let notification =
+                NotificationApi::new_with_configuration(&api_config, configuration.clone())?;
+            #[cfg(feature = "signer")]
+            let notification = notification.with_signer(signer.clone());
+            apis.notification = Some(notification);
         }
 
         if self.order {
-            apis.order = Some(OrderApi::new(&config)?);
+            let order = OrderApi::new_with_configuration(&api_config, configuration.clone())?
+                .with_metadata(metadata.clone());
+            #[cfg(feature = "signer")]
+            let order = order
+                .with_signer(signer.clone())
+                .with_tx_client(tx_client.clone())
+                .with_client_order_ids(client_order_ids.clone());
+            apis.order = Some(order);
         }
 
         if self.referral {
-            apis.referral = Some(ReferralApi::new(&config)?);
+            let referral = ReferralApi::new_with_configuration(&api_config, configuration.clone())?;
+            #[cfg(feature = "signer")]
+            let referral = referral.with_signer(signer.clone());
+            apis.referral = Some(referral);
         }
 
         if self.root {
-            apis.root = Some(RootApi::new(&config)?);
+            apis.root = Some(RootApi::from_configuration(configuration.clone()));
         }
 
         if self.transaction {
-            apis.transaction = Some(TransactionApi::new(&config)?);
+            let transaction =
+                TransactionApi::from_configuration(configuration.clone(), tx_configuration.clone());
+            #[cfg(feature = "signer")]
+            let transaction = transaction.with_tx_client(tx_client.clone());
+            apis.transaction = Some(transaction);
         }
 
-        //let signer = Signer::try_from(&config)?;
-        let mut client = HttpClient {
-            account_index: config
-                .account_index
-                .ok_or_else(|| LighterError::Generic("`acount_index` is not set".into()))?
-                as i64,
-            api_key_index: config
-                .api_key_index
-                .ok_or_else(|| LighterError::Generic("`api_key_index` is not set".into()))?,
-            apis,
-            nonce_manager: None, // API nonce
+        let time_sync = Arc::new(TimeSync::new_with_configuration(
+            &config,
+            configuration.clone(),
+        ));
+        let tasks = BackgroundTasks::default();
+        // the check needs a runtime, it's skipped when building outside of one
+        if time_sync.warns_on_skew() && tokio::runtime::Handle::try_current().is_ok() {
+            clock::spawn_skew_check(time_sync.clone(), &tasks);
+        }
+
+        let account_info = if self.account_info {
+            let account_index = account_index
+                .ok_or_else(|| LighterError::Config("`account_index` is not set".into()))?;
+            let api = AccountApi::new_with_configuration(&api_config, configuration.clone())?;
+            #[cfg(feature = "signer")]
+            let api = api.with_signer(signer.clone());
+            let cache = Arc::new(AccountInfoCache::new(api, account_index));
+            // as for the skew check, it's fetched on the first refresh outside of a runtime
+            if tokio::runtime::Handle::try_current().is_ok() {
+                // detached, the outcome is logged and `account_info` stays `None` on failure
+                drop(account_info::spawn_refresh(cache.clone(), &tasks));
+            }
+            Some(cache)
+        } else {
+            None
         };
 
-        if config.local_nonce {
-            client.nonce_manager = Some(NonceManager::new()); // Local nonce
-        }
+        // the txs are sent by the transaction API or the tx client
+        #[cfg(feature = "signer")]
+        let sends_txs = self.transaction || tx_client.is_some();
+        #[cfg(not(feature = "signer"))]
+        let sends_txs = self.transaction;
+        let tx_health = sends_txs.then(|| RootApi::from_configuration(tx_configuration));
+
+        let raw = RawApi::new_with_configuration(&api_config, configuration.clone())?;
+        #[cfg(feature = "signer")]
+        let raw = raw.with_signer(signer);
+
+        let client = HttpClient {
+            account_index,
+            api_key_index,
+            apis,
+            nonce_manager,
+            #[cfg(feature = "signer")]
+            tx_client,
+            #[cfg(feature = "signer")]
+            signer_error,
+            metadata,
+            account_info,
+            time_sync,
+            client_order_ids,
+            health: RootApi::from_configuration(configuration),
+            tx_health,
+            raw,
+            tasks,
+            connection_metrics: config.connection_metrics.clone(),
+        };
 
         Ok(client)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection_stats::tests::keep_alive_server;
+
+    #[cfg(feature = "signer")]
+    use secrecy::ExposeSecret;
+
+    static TEST_API_KEY_PRIVATE: &str =
+        "01db9eed031d59d6bd0ee00ee5a7dc1f62087bf217b51caea57eb6e17a02c49e0a748d2f155a2f60";
+
+    #[test]
+    fn test_build_all() {
+        let config = LighterConfig::new()
+            .with_api_key_private(TEST_API_KEY_PRIVATE)
+            .with_account_index(28)
+            .with_api_key_index(2);
+        let client = HttpClient::builder()
+            .with_config(config)
+            .build_all()
+            .unwrap();
+
+        let apis = client.api();
+        assert!(apis.account().is_ok());
+        assert!(apis.announcement().is_ok());
+        assert!(apis.block().is_ok());
+        assert!(apis.bridge().is_ok());
+        assert!(apis.candlestick().is_ok());
+        assert!(apis.funding().is_ok());
+        assert!(apis.info().is_ok());
+        assert!(apis.notification().is_ok());
+        assert!(apis.order().is_ok());
+        assert!(apis.referral().is_ok());
+        assert!(apis.root().is_ok());
+        assert!(apis.transaction().is_ok());
+    }
+
+    #[cfg(feature = "signer")]
+    #[test]
+    fn test_build_shares_auth_token() {
+        let config = LighterConfig::new()
+            .with_api_key_private(TEST_API_KEY_PRIVATE)
+            .with_account_index(28)
+            .with_api_key_index(2);
+        let client = HttpClient::builder()
+            .with_config(config)
+            .build_all()
+            .unwrap();
+
+        let account = client.api().account().unwrap();
+        let order = client.api().order().unwrap();
+        assert_eq!(order.signer().unwrap().auth_token_renewal_in(), None);
+
+        // created once, by any of the APIs
+        let token = account.signer().unwrap().get_auth_token(None).unwrap();
+        assert!(order.signer().unwrap().auth_token_renewal_in().is_some());
+        assert_eq!(order.signer().unwrap().get_auth_token(None).unwrap(), token);
+        // the tx client signs with the same signer, not another client of the native library
+        let tx_client = client.tx_client().unwrap();
+        assert_eq!(
+            tx_client.signer().ffi().get_auth_token(None).unwrap(),
+            token
+        );
+    }
+
+    #[test]
+    fn test_build_all_requires_indexes() {
+        let config = LighterConfig::new().with_api_key_private(TEST_API_KEY_PRIVATE);
+
+        let res = HttpClient::builder()
+            .with_config(config.clone().with_api_key_index(2))
+            .build_all();
+        assert!(matches!(res, Err(LighterError::Config(e)) if e.contains("account_index")));
+
+        let res = HttpClient::builder()
+            .with_config(config.with_account_index(28))
+            .build_all();
+        assert!(matches!(res, Err(LighterError::Config(e)) if e.contains("api_key_index")));
+
+        // read only, the indexes are needed only to fetch the nonce
+        let client = HttpClient::builder()
+            .with_config(LighterConfig::new())
+            .build_all()
+            .unwrap();
+        assert!(!client.can_sign());
+
+        let res = HttpClient::builder()
+            .with_config(LighterConfig::new())
+            .with_account_info()
+            .build();
+        assert!(matches!(res, Err(LighterError::Config(e)) if e.contains("account_index")));
+    }
+
+    #[tokio::test]
+    async fn test_get_nonce_requires_indexes() {
+        let client = HttpClient::builder()
+            .with_config(LighterConfig::new().with_local_nonce(false))
+            .with_transaction()
+            .build()
+            .unwrap();
+
+        let res = client.get_nonce().await;
+        assert!(matches!(res, Err(LighterError::Config(e)) if e.contains("account_index")));
+    }
+
+    #[tokio::test]
+    async fn test_ping() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":200,"network_id":300,"timestamp":1700000000}"#)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/api/v1/currentHeight")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"height":12345}"#)
+            .create_async()
+            .await;
+
+        // no API needs to be enabled
+        let config = LighterConfig {
+            base_url: server.url(),
+            ..LighterConfig::new()
+                .with_account_index(28)
+                .with_api_key_index(2)
+        };
+        let client = HttpClient::builder().with_config(config).build().unwrap();
+
+        assert_eq!(client.ping().await.unwrap().block_height, 12345);
+        let stats = client.connection_stats();
+        assert_eq!(stats.requests, 2);
+        assert!(stats.opened >= 1);
+    }
+
+    #[tokio::test]
+    async fn test_warm_up() {
+        // read only, without the indexes
+        let config = LighterConfig {
+            base_url: keep_alive_server().await,
+            ..LighterConfig::new()
+        };
+        let client = HttpClient::builder()
+            .with_config(config)
+            .with_root()
+            .build()
+            .unwrap();
+
+        client.warm_up().await.unwrap();
+        assert_eq!(client.connection_stats().opened, 1);
+
+        // the APIs share the connection opened
+        client.api().root().unwrap().status().await.unwrap();
+        let stats = client.connection_stats();
+        assert_eq!(stats.requests, 2);
+        assert_eq!(stats.opened, 1);
+        assert_eq!(stats.reused(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_warm_up_tx_pool() {
+        let config = LighterConfig {
+            base_url: keep_alive_server().await,
+            ..LighterConfig::new()
+        };
+        let client = HttpClient::builder()
+            .with_config(config)
+            .with_transaction()
+            .build()
+            .unwrap();
+
+        // one connection on the pool of the APIs, the other one on the pool of the txs
+        client.warm_up().await.unwrap();
+        assert_eq!(client.connection_stats().opened, 2);
+
+        let transaction = client.api().transaction().unwrap();
+        transaction.send_tx(14, "{}", None).await.unwrap();
+        let stats = client.connection_stats();
+        assert_eq!(stats.requests, 3);
+        assert_eq!(stats.opened, 2);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":200,"network_id":300,"timestamp":1700000000}"#)
+            .create_async()
+            .await;
+
+        let path = std::env::temp_dir().join(format!("lighter-nonce-{}", uuid::Uuid::new_v4()));
+        let config = LighterConfig {
+            base_url: server.url(),
+            ..LighterConfig::new()
+                .with_account_index(28)
+                .with_api_key_index(2)
+                .with_local_nonce_path(&path)
+        };
+        let client = HttpClient::builder().with_config(config).build().unwrap();
+        client.nonce_manager.as_ref().unwrap().reset(7).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let sync = client.sync_time_periodically(std::time::Duration::from_millis(10));
+        tokio::time::timeout(std::time::Duration::from_secs(1), client.shutdown())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(sync.is_finished());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "7");
+
+        // started after the shutdown
+        let res = client.warm_metadata_in_background().await.unwrap();
+        assert!(matches!(res, Err(LighterError::Generic(e)) if e.contains("shut down")));
+        let sync = client.sync_time_periodically(std::time::Duration::from_millis(10));
+        tokio::time::timeout(std::time::Duration::from_secs(1), sync)
+            .await
+            .unwrap()
+            .unwrap();
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_account_info_opt_in() {
+        let config = LighterConfig::new()
+            .with_account_index(28)
+            .with_api_key_index(2);
+        let client = HttpClient::builder().with_config(config).build().unwrap();
+
+        // read only, nothing is fetched
+        assert!(client.account_info().is_none());
+        let res = client.refresh_account_info().await;
+        assert!(matches!(res, Err(LighterError::Config(e)) if e.contains("with_account_info")));
+    }
+
+    #[cfg(feature = "signer")]
+    #[tokio::test]
+    async fn test_build_read_only_without_signer() {
+        let mut server = mockito::Server::new_async().await;
+        // no auth token without the signer
+        let stats = server
+            .mock("GET", "/api/v1/exchangeStats")
+            .match_header("authorization", mockito::Matcher::Missing)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"total":3,"daily_usd_volume":1.5}"#)
+            .create_async()
+            .await;
+
+        // the signer can't be initialized with this key
+        let config = LighterConfig {
+            base_url: server.url(),
+            ..LighterConfig::new()
+                .with_api_key_private("0x1234") // too short
+                .with_account_index(28)
+                .with_api_key_index(2)
+        };
+        let client = HttpClient::builder()
+            .with_config(config.clone())
+            .with_candlestick()
+            .with_info()
+            .build()
+            .unwrap();
+        assert!(!client.can_sign());
+
+        // reads still work
+        let resp = client.get_raw("/api/v1/exchangeStats", &[]).await.unwrap();
+        assert_eq!(resp["total"], 3);
+        stats.assert_async().await;
+
+        // the signing calls fail with the cause
+        let res = client.register_api_key("0x1").await;
+        assert!(
+            matches!(&res, Err(LighterError::Signing(e)) if e.contains("couldn't be initialized")),
+            "{res:?}"
+        );
+
+        // an API that signs can't be built without it
+        let res = HttpClient::builder()
+            .with_config(config)
+            .with_order()
+            .build();
+        assert!(matches!(res, Err(LighterError::Signing(_))));
+    }
+
+    #[tokio::test]
+    async fn test_get_raw() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/api/v1/exchangeStats")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"total":3,"daily_usd_volume":1.5}"#)
+            .create_async()
+            .await;
+
+        let config = LighterConfig {
+            base_url: server.url(),
+            ..LighterConfig::new()
+                .with_account_index(28)
+                .with_api_key_index(2)
+        };
+        let client = HttpClient::builder().with_config(config).build().unwrap();
+
+        let stats = client.get_raw("/api/v1/exchangeStats", &[]).await.unwrap();
+        assert_eq!(stats["daily_usd_volume"], 1.5);
+    }
+
+    #[cfg(feature = "signer")]
+    #[tokio::test]
+    async fn test_generate_and_register_api_key() {
+        let (private_key, public_key) = HttpClient::generate_api_key().unwrap();
+
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/api/v1/nextNonce")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"nonce":0}"#)
+            .create_async()
+            .await;
+        let send_tx = server
+            .mock("POST", "/api/v1/sendTx")
+            .match_body(mockito::Matcher::Regex(
+                r#"name="tx_type"\r\n\r\n8\r\n"#.into(),
+            ))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"tx_hash":"0x8","predicted_execution_time_ms":1}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        // signed with the new key
+        let config = LighterConfig {
+            base_url: server.url(),
+            local_nonce: false,
+            ..LighterConfig::new()
+                .with_api_key_private(private_key.expose_secret())
+                .with_account_index(28)
+                .with_api_key_index(3)
+                .with_eth_private_key(
+                    "0x4fd51c004ad02a003e321d5154d9b22c6bb89e1e5017bdc832c69ef28f65c04e",
+                )
+        };
+        let client = HttpClient::builder().with_config(config).build().unwrap();
+
+        let resp = client.register_api_key(&public_key).await.unwrap();
+        assert_eq!(resp.tx_hash, "0x8");
+        send_tx.assert_async().await;
+    }
+}
@@ -0,0 +1,383 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use tokio::time::Instant;
+
+use crate::{
+    api::info::MarketInfo,
+    apis::{self, configuration::Configuration},
+    client::tasks::BackgroundTasks,
+    config::LighterConfig,
+    models::{OrderBook, OrderBooks, ZkLighterInfo},
+    LighterError, Result,
+};
+
+/// How long the market infos are cached, their last trade price drifts
+const MARKET_INFO_TTL: Duration = Duration::from_secs(60);
+
+/// Exchange metadata (markets and their decimals, exchange info) fetched once and then served
+/// locally, see [`MetadataCache`].
+#[derive(Debug, Clone)]
+pub struct ExchangeMetadata {
+    markets: HashMap<i32, OrderBook>,
+    symbols: HashMap<String, i32>,
+    info: ZkLighterInfo,
+}
+
+impl ExchangeMetadata {
+    pub fn new(order_books: OrderBooks, info: ZkLighterInfo) -> Self {
+        let symbols = order_books
+            .order_books
+            .iter()
+            .map(|v| (v.symbol.clone(), v.market_id))
+            .collect();
+        let markets = order_books
+            .order_books
+            .into_iter()
+            .map(|v| (v.market_id, v))
+            .collect();
+
+        Self {
+            markets,
+            symbols,
+            info,
+        }
+    }
+
+    /// Returns all the markets
+    pub fn markets(&self) -> impl Iterator<Item = &OrderBook> {
+        self.markets.values()
+    }
+
+    /// Returns the market with the given index
+    pub fn market(&self, market_id: i32) -> Option<&OrderBook> {
+        self.markets.get(&market_id)
+    }
+
+    /// Returns the market with the given symbol, either the exchange symbol (`ETH`) or the pair
+    /// (`ETH-USD`)
+    pub fn market_by_symbol(&self, symbol: &str) -> Option<&OrderBook> {
+        self.symbols
+            .get(symbol)
+            .or_else(|| {
+                symbol
+                    .strip_suffix("-USD")
+                    .and_then(|v| self.symbols.get(v))
+            })
+            .and_then(|market_id| self.markets.get(market_id))
+    }
+
+    /// Returns the indexes of all the markets, in ascending order
+    pub fn indexes(&self) -> Vec<i32> {
+        let mut indexes = self.markets.keys().copied().collect::<Vec<_>>();
+        indexes.sort_unstable();
+        indexes
+    }
+
+    /// Returns the exchange info
+    pub fn info(&self) -> &ZkLighterInfo {
+        &self.info
+    }
+}
+
+/// Cache holding the `ExchangeMetadata`. It's loaded on the first lookup (or warmed up front),
+/// and its markets are reloaded when one is not found, since markets can be added at any time.
+/// The infos of the markets (see [`MetadataCache::market_info`]) are cached too.
+#[derive(Debug)]
+pub struct MetadataCache {
+    config: Configuration,
+    metadata: RwLock<Option<Arc<ExchangeMetadata>>>,
+    infos: RwLock<HashMap<i32, (Instant, MarketInfo)>>, // validate the orders and the leverage
+}
+
+impl MetadataCache {
+    pub fn new(config: &LighterConfig) -> Result<Self> {
+        Ok(Self::with_configuration(Configuration::try_from(config)?))
+    }
+
+    pub(crate) fn with_configuration(config: Configuration) -> Self {
+        Self {
+            config,
+            metadata: RwLock::new(None),
+            infos: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the cached metadata, if already loaded
+    pub fn get(&self) -> Option<Arc<ExchangeMetadata>> {
+        self.metadata
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Fetches all the metadata concurrently, failing if any of the endpoints is not reachable.
+    /// It's a no-op if the cache is already warm.
+    pub async fn warm(&self) -> Result<Arc<ExchangeMetadata>> {
+        match self.get() {
+            Some(metadata) => Ok(metadata),
+            None => self.reload().await,
+        }
+    }
+
+    /// Fetches the markets again, replacing the cached ones. The exchange info doesn't change,
+    /// so it's only fetched along with them the first time.
+    pub async fn reload(&self) -> Result<Arc<ExchangeMetadata>> {
+        let cached_info = self.get().map(|v| v.info().clone());
+        let (order_books, info) = tokio::try_join!(
+            async {
+                apis::order_api::order_books(&self.config, None)
+                    .await
+                    .inspect_err(|e| tracing::error!("unable to call `order_books`: {e}"))
+                    .map_err(LighterError::from)
+            },
+            async {
+                match cached_info {
+                    Some(info) => Ok(info),
+                    None => apis::root_api::info(&self.config)
+                        .await
+                        .inspect_err(|e| tracing::error!("unable to call `info`: {e}"))
+                        .map_err(LighterError::from),
+                }
+            },
+        )?;
+
+        let metadata = Arc::new(ExchangeMetadata::new(order_books, info));
+        *self.metadata.write().unwrap_or_else(|e| e.into_inner()) = Some(metadata.clone());
+        Ok(metadata)
+    }
+
+    /// Returns the index of the market, see [`ExchangeMetadata::market_by_symbol`]
+    pub async fn index_of(&self, symbol: &str) -> Result<i32> {
+        self.lookup(|metadata| metadata.market_by_symbol(symbol).map(|v| v.market_id))
+            .await?
+            .ok_or_else(|| LighterError::MarketNotFound(format!("symbol {symbol}")))
+    }
+
+    /// Returns the symbol of the market
+    pub async fn symbol_of(&self, index: i32) -> Result<String> {
+        self.lookup(|metadata| metadata.market(index).map(|v| v.symbol.clone()))
+            .await?
+            .ok_or_else(|| LighterError::MarketNotFound(format!("index {index}")))
+    }
+
+    /// Returns the info of the market, fetched again once older than `MARKET_INFO_TTL`
+    pub async fn market_info(&self, market_index: i32) -> Result<MarketInfo> {
+        let cached = self
+            .infos
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&market_index)
+            .filter(|(fetched_at, _)| fetched_at.elapsed() < MARKET_INFO_TTL)
+            .map(|(_, info)| info.clone());
+        if let Some(info) = cached {
+            return Ok(info);
+        }
+
+        let info = apis::order_api::order_book_details(&self.config, Some(market_index))
+            .await
+            .inspect_err(|e| tracing::error!("unable to call `order_book_details`: {e}"))?
+            .order_book_details
+            .iter()
+            .find(|v| v.market_id == market_index)
+            .map(MarketInfo::try_from)
+            .ok_or_else(|| LighterError::MarketNotFound(format!("index {market_index}")))??;
+        self.infos
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(market_index, (Instant::now(), info.clone()));
+
+        Ok(info)
+    }
+
+    /// Looks up the cached metadata, reloading the markets once on a miss
+    async fn lookup<T>(&self, f: impl Fn(&ExchangeMetadata) -> Option<T>) -> Result<Option<T>> {
+        if let Some(value) = self.get().and_then(|metadata| f(&metadata)) {
+            return Ok(Some(value));
+        }
+
+        Ok(f(&*self.reload().await?))
+    }
+}
+
+/// Spawns the warm up of the cache in the background, it fails if `tasks` are shut down first
+pub(crate) fn spawn_warm(
+    cache: Arc<MetadataCache>,
+    tasks: &BackgroundTasks,
+) -> tokio::task::JoinHandle<Result<()>> {
+    let shutdown = tasks.token().clone();
+    tasks.spawn(async move {
+        shutdown
+            .run_until_cancelled(cache.warm())
+            .await
+            .ok_or_else(|| LighterError::Generic("The client was shut down".into()))?
+            .inspect_err(|e| tracing::error!("unable to warm the metadata: {e}"))?;
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order_book(symbol: &str, market_id: i32, size_decimals: i32) -> OrderBook {
+        OrderBook {
+            symbol: symbol.into(),
+            market_id,
+            supported_size_decimals: size_decimals,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_exchange_metadata_lookup() {
+        let metadata = ExchangeMetadata::new(
+            OrderBooks::new(200, vec![order_book("ETH", 0, 4), order_book("BTC", 1, 5)]),
+            ZkLighterInfo::new("0x1".into()),
+        );
+
+        assert_eq!(metadata.markets().count(), 2);
+        assert_eq!(metadata.market(1).unwrap().symbol, "BTC");
+        assert_eq!(
+            metadata
+                .market_by_symbol("ETH")
+                .unwrap()
+                .supported_size_decimals,
+            4
+        );
+        assert_eq!(metadata.market_by_symbol("BTC-USD").unwrap().market_id, 1);
+        assert!(metadata.market(2).is_none());
+        assert!(metadata.market_by_symbol("SOL").is_none());
+        assert_eq!(metadata.indexes(), vec![0, 1]);
+        assert_eq!(metadata.info().contract_address, "0x1");
+    }
+
+    #[test]
+    fn test_metadata_cache_cold() {
+        let cache = MetadataCache::new(&LighterConfig::new()).unwrap();
+        assert!(cache.get().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_metadata_cache_warm() {
+        let mut server = mockito::Server::new_async().await;
+        let order_books = server
+            .mock("GET", "/api/v1/orderBooks")
+            .with_header("content-type", "application/json")
+            .with_body(
+                serde_json::to_string(&OrderBooks::new(200, vec![order_book("ETH", 0, 4)]))
+                    .unwrap(),
+            )
+            .expect(1)
+            .create_async()
+            .await;
+        let info = server
+            .mock("GET", "/info")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"contract_address":"0x1"}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let config = LighterConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+        let cache = MetadataCache::new(&config).unwrap();
+
+        let metadata = cache.warm().await.unwrap();
+        assert_eq!(metadata.market_by_symbol("ETH").unwrap().market_id, 0);
+
+        // already warm, no further requests
+        cache.warm().await.unwrap();
+        order_books.assert_async().await;
+        info.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_metadata_cache_reload_on_miss() {
+        let order_books = |markets: Vec<OrderBook>| {
+            serde_json::to_string(&OrderBooks::new(200, markets)).unwrap()
+        };
+        let mut server = mockito::Server::new_async().await;
+        let first = server
+            .mock("GET", "/api/v1/orderBooks")
+            .with_header("content-type", "application/json")
+            .with_body(order_books(vec![order_book("ETH", 0, 4)]))
+            .expect(2)
+            .create_async()
+            .await;
+        let info = server
+            .mock("GET", "/info")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"contract_address":"0x1"}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let config = LighterConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+        let cache = MetadataCache::new(&config).unwrap();
+
+        // loaded on the first lookup, then served locally
+        assert_eq!(cache.index_of("ETH").await.unwrap(), 0);
+        assert_eq!(cache.symbol_of(0).await.unwrap(), "ETH");
+        cache.warm().await.unwrap();
+
+        // a miss reloads the markets, but not the exchange info
+        assert!(matches!(
+            cache.index_of("BTC").await,
+            Err(LighterError::MarketNotFound(_))
+        ));
+        first.assert_async().await;
+        first.remove_async().await;
+
+        let second = server
+            .mock("GET", "/api/v1/orderBooks")
+            .with_header("content-type", "application/json")
+            .with_body(order_books(vec![
+                order_book("ETH", 0, 4),
+                order_book("BTC", 1, 5),
+            ]))
+            .expect(1)
+            .create_async()
+            .await;
+        assert_eq!(cache.index_of("BTC-USD").await.unwrap(), 1);
+        assert_eq!(cache.get().unwrap().markets().count(), 2);
+        second.assert_async().await;
+        info.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_metadata_cache_warm_unreachable() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/api/v1/orderBooks")
+            .with_status(404)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/info")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"contract_address":"0x1"}"#)
+            .create_async()
+            .await;
+
+        let config = LighterConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+        let cache = Arc::new(MetadataCache::new(&config).unwrap());
+
+        assert!(spawn_warm(cache.clone(), &BackgroundTasks::default())
+            .await
+            .unwrap()
+            .is_err());
+        assert!(cache.get().is_none());
+    }
+}
@@ -1,6 +1,31 @@
-mod nonce;
+pub(crate) mod nonce;
+pub(crate) mod tasks;
 
-// temporarily disabled
-//mod ws;
+mod account_info;
+mod candlesticks;
+#[cfg(feature = "signer")]
+mod dead_mans_switch;
+mod event_bus;
 mod http;
-pub use http::HttpClient;
+mod metadata;
+mod order_book;
+mod order_id;
+#[cfg(feature = "signer")]
+mod tx;
+mod ws;
+pub use account_info::AccountInfo;
+pub use candlesticks::CandlestickStream;
+#[cfg(feature = "signer")]
+pub use dead_mans_switch::DeadMansSwitch;
+pub use event_bus::{EventBus, LighterEvent, OverflowPolicy};
+pub use http::{HttpClient, HttpClientBuilder};
+pub use metadata::{ExchangeMetadata, MetadataCache};
+pub use nonce::{FileNonceStore, NonceStore};
+pub use order_book::OrderBook;
+pub use order_id::ClientOrderIdGenerator;
+#[cfg(feature = "signer")]
+pub use tx::TxClient;
+pub use ws::{
+    AccountUpdate, NotificationUpdate, OrderBookUpdate, TradeUpdate, WsClient, WsOrderBook,
+    WsSubscription,
+};
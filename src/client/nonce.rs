@@ -1,11 +1,126 @@
 use crate::error::{LighterError, Result};
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::collections::{BTreeSet, HashSet};
+use std::fmt::Debug;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::{SystemTime, UNIX_EPOCH};
 
+static NOT_SEEDED: i64 = -1;
+
+/// Storage used to persist the local nonce across restarts
+pub trait NonceStore: Debug + Send + Sync {
+    /// Returns the next nonce to use, if persisted
+    fn load(&self) -> Result<Option<i64>>;
+
+    /// Persists the next nonce to use
+    fn save(&self, next_nonce: i64) -> Result<()>;
+}
+
+/// `NonceStore` persisting the next nonce in a plain text file
+#[derive(Debug, Clone)]
+pub struct FileNonceStore {
+    path: PathBuf,
+}
+
+impl FileNonceStore {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl NonceStore for FileNonceStore {
+    fn load(&self) -> Result<Option<i64>> {
+        let content = match std::fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        let nonce = content.trim().parse::<i64>().map_err(|e| {
+            LighterError::Nonce(format!(
+                "Invalid nonce stored in {}: {e}",
+                self.path.display()
+            ))
+        })?;
+
+        Ok(Some(nonce))
+    }
+
+    fn save(&self, next_nonce: i64) -> Result<()> {
+        // write and then rename, so that we never leave a partially written file behind
+        let tmp_path = self.path.with_extension("tmp");
+        std::fs::write(&tmp_path, next_nonce.to_string())?;
+        std::fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+/// Local nonce generation.
+///
+/// Nonces are timestamp based until the manager gets `reset` to an authoritative nonce (e.g. the
+/// one returned by `next_nonce`); from then on they are handed out sequentially from it.
+/// With a `NonceStore`, the manager starts from the persisted nonce (if any) and persists every
+/// nonce it hands out.
+///
+/// Nonces allocated through `allocate_block` that end up unused (the guard is dropped without being
+/// committed) are handed out again by the next allocations, so that a failed tx doesn't leave a
+/// gap in the sequential nonces. In timestamp mode they are just discarded.
 #[derive(Debug)]
 pub struct NonceManager {
     counter: AtomicU64,
     last_timestamp: AtomicU64,
+    next: AtomicI64, // sequential nonce, `NOT_SEEDED` while in timestamp mode
+    store: Option<Arc<dyn NonceStore>>,
+    store_lock: Mutex<()>, // keeps generation and persistence in order when there is a store
+    pending: Mutex<PendingNonces>, // always locked before `store_lock`
+}
+
+/// Nonces allocated and not committed yet, and the released ones to hand out again
+#[derive(Debug, Default)]
+struct PendingNonces {
+    outstanding: HashSet<i64>,
+    free: BTreeSet<i64>,
+}
+
+/// Nonce allocated by [`NonceManager::allocate_block`]. Unless committed once the tx is submitted, it
+/// goes back to the manager when dropped.
+#[derive(Debug)]
+#[must_use = "the nonce is released when the guard is dropped"]
+pub struct NonceGuard<'a> {
+    manager: Option<&'a NonceManager>, // `None` once committed (or for API nonces)
+    nonce: i64,
+}
+
+#[cfg_attr(not(feature = "signer"), allow(dead_code))] // used by the tx client
+impl<'a> NonceGuard<'a> {
+    /// Guard of a nonce not tracked by any manager (e.g. fetched from the API)
+    pub(crate) fn untracked(nonce: i64) -> Self {
+        Self {
+            manager: None,
+            nonce,
+        }
+    }
+
+    pub fn nonce(&self) -> i64 {
+        self.nonce
+    }
+
+    /// Marks the nonce as used, so that it's never handed out again
+    pub fn commit(mut self) -> i64 {
+        if let Some(manager) = self.manager.take() {
+            manager.commit(self.nonce);
+        }
+        self.nonce
+    }
+}
+
+impl Drop for NonceGuard<'_> {
+    fn drop(&mut self) {
+        if let Some(manager) = self.manager.take() {
+            manager.release(self.nonce);
+        }
+    }
 }
 
 impl NonceManager {
@@ -13,10 +128,186 @@ impl NonceManager {
         Self {
             counter: AtomicU64::new(0),
             last_timestamp: AtomicU64::new(0),
+            next: AtomicI64::new(NOT_SEEDED),
+            store: None,
+            store_lock: Mutex::new(()),
+            pending: Mutex::new(PendingNonces::default()),
+        }
+    }
+
+    /// Creates a manager backed by `store`, seeded from the persisted nonce if present
+    pub fn with_store(store: Arc<dyn NonceStore>) -> Result<Self> {
+        let next = store.load()?.unwrap_or(NOT_SEEDED);
+        Ok(Self {
+            next: AtomicI64::new(next),
+            store: Some(store),
+            ..Self::new()
+        })
+    }
+
+    /// Returns `true` if the manager is backed by a store but has nothing persisted yet, so it
+    /// has to be seeded (e.g. with the API nonce) through `reset`.
+    #[cfg_attr(not(feature = "signer"), allow(dead_code))] // used by the tx client
+    pub fn needs_seed(&self) -> bool {
+        self.store.is_some() && self.next.load(Ordering::Acquire) == NOT_SEEDED
+    }
+
+    /// Resyncs the manager: the next generated nonce will be `next_nonce`.
+    ///
+    /// The nonces allocated until now are forgotten, so releasing them has no effect.
+    #[cfg_attr(not(feature = "signer"), allow(dead_code))] // used by the tx client
+    pub fn reset(&self, next_nonce: i64) -> Result<()> {
+        let mut pending = self.lock_pending();
+        pending.outstanding.clear();
+        pending.free.clear();
+
+        let _guard = self.lock_store()?;
+        self.next.store(next_nonce, Ordering::Release);
+        if let Some(store) = &self.store {
+            store.save(next_nonce)?;
+        }
+        Ok(())
+    }
+
+    /// Persists the next nonce, if there is a store and the manager is seeded. Every nonce handed
+    /// out is already persisted, this makes sure the last one is before the process exits.
+    pub fn flush(&self) -> Result<()> {
+        let Some(store) = &self.store else {
+            return Ok(());
+        };
+
+        let _guard = self.lock_store()?;
+        let next = self.next.load(Ordering::Acquire);
+        if next != NOT_SEEDED {
+            store.save(next)?;
+        }
+        Ok(())
+    }
+
+    /// Allocates `n` nonces at once, e.g. for the txs of a batch, reusing the lowest released ones
+    /// if any. The others are a contiguous block: no concurrent allocation can get a nonce in
+    /// between. A nonce is never handed out to anyone else until its guard is dropped without
+    /// being committed.
+    #[cfg_attr(not(feature = "signer"), allow(dead_code))] // used by the tx client
+    pub fn allocate_block(&self, n: usize) -> Result<Vec<NonceGuard<'_>>> {
+        let mut pending = self.lock_pending();
+        let mut nonces = Vec::with_capacity(n);
+        while nonces.len() < n {
+            match pending.free.pop_first() {
+                Some(nonce) => nonces.push(nonce),
+                None => break,
+            }
+        }
+        match self.generate_block(n - nonces.len()) {
+            Ok(block) => nonces.extend(block),
+            Err(e) => {
+                // nothing was handed out
+                pending.free.extend(nonces);
+                return Err(e);
+            }
+        }
+        pending.outstanding.extend(&nonces);
+
+        Ok(nonces
+            .into_iter()
+            .map(|nonce| NonceGuard {
+                manager: Some(self),
+                nonce,
+            })
+            .collect())
+    }
+
+    /// Reserves `n` nonces at once, see [`NonceManager::allocate_block`]. They are marked as used,
+    /// so they are never handed out again.
+    pub fn reserve(&self, n: usize) -> Result<Vec<i64>> {
+        Ok(self
+            .allocate_block(n)?
+            .into_iter()
+            .map(NonceGuard::commit)
+            .collect())
+    }
+
+    /// Allocates a single nonce, see [`NonceManager::allocate_block`]
+    #[cfg(test)]
+    fn allocate(&self) -> Result<NonceGuard<'_>> {
+        Ok(self.allocate_block(1)?.remove(0))
+    }
+
+    /// Number of allocated nonces neither committed nor released yet
+    #[cfg(test)]
+    fn outstanding(&self) -> usize {
+        self.lock_pending().outstanding.len()
+    }
+
+    #[cfg_attr(not(feature = "signer"), allow(dead_code))] // used by the tx client
+    fn commit(&self, nonce: i64) {
+        self.lock_pending().outstanding.remove(&nonce);
+    }
+
+    fn release(&self, nonce: i64) {
+        let mut pending = self.lock_pending();
+        // nonces allocated before a `reset` are stale, timestamp ones are never reused
+        if pending.outstanding.remove(&nonce) && self.next.load(Ordering::Acquire) != NOT_SEEDED {
+            pending.free.insert(nonce);
         }
     }
 
+    fn lock_pending(&self) -> std::sync::MutexGuard<'_, PendingNonces> {
+        // the state is consistent after every operation, so it can be used even if poisoned
+        self.pending.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Generates a new nonce, ignoring the released ones
     pub fn generate(&self) -> Result<i64> {
+        let next = self.next.load(Ordering::Acquire);
+        if next != NOT_SEEDED {
+            return self.generate_sequential();
+        }
+
+        self.generate_timestamp()
+    }
+
+    /// Generates `n` new nonces, ignoring the released ones. Sequential nonces are taken as a
+    /// single block, so they are contiguous.
+    fn generate_block(&self, n: usize) -> Result<Vec<i64>> {
+        if n == 0 {
+            return Ok(Vec::new());
+        }
+        if self.next.load(Ordering::Acquire) == NOT_SEEDED {
+            return (0..n).map(|_| self.generate_timestamp()).collect();
+        }
+
+        let len = n as i64;
+        let first = match &self.store {
+            Some(store) => {
+                let _guard = self.lock_store()?;
+                let first = self.next.fetch_add(len, Ordering::AcqRel);
+                store.save(first + len)?;
+                first
+            }
+            None => self.next.fetch_add(len, Ordering::AcqRel),
+        };
+        Ok((first..first + len).collect())
+    }
+
+    fn generate_sequential(&self) -> Result<i64> {
+        let Some(store) = &self.store else {
+            return Ok(self.next.fetch_add(1, Ordering::AcqRel));
+        };
+
+        let _guard = self.lock_store()?;
+        let nonce = self.next.fetch_add(1, Ordering::AcqRel);
+        store.save(nonce + 1)?;
+        Ok(nonce)
+    }
+
+    fn lock_store(&self) -> Result<std::sync::MutexGuard<'_, ()>> {
+        self.store_lock
+            .lock()
+            .map_err(|e| LighterError::Nonce(format!("Unable to lock the nonce store: {e}")))
+    }
+
+    fn generate_timestamp(&self) -> Result<i64> {
         let current_timestamp = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .map_err(|e| LighterError::Nonce(format!("Time error: {}", e)))?
@@ -66,7 +357,6 @@ impl Default for NonceManager {
 mod tests {
     use super::*;
     use std::collections::HashSet;
-    use std::sync::Arc;
     use std::thread;
 
     #[test]
@@ -204,6 +494,190 @@ mod tests {
         assert_eq!(unique_nonces.len(), nonces.len());
     }
 
+    #[test]
+    fn test_nonce_reset() {
+        let manager = NonceManager::new();
+        assert!(manager.generate().unwrap() > 1_000_000_000_000);
+
+        manager.reset(42).unwrap();
+        assert_eq!(manager.generate().unwrap(), 42);
+        assert_eq!(manager.generate().unwrap(), 43);
+
+        // resyncing again moves to the new authoritative nonce
+        manager.reset(100).unwrap();
+        assert_eq!(manager.generate().unwrap(), 100);
+    }
+
+    #[test]
+    fn test_nonce_guard_release() {
+        let manager = NonceManager::new();
+        manager.reset(10).unwrap();
+
+        let first = manager.allocate().unwrap();
+        let second = manager.allocate().unwrap();
+        assert_eq!((first.nonce(), second.nonce()), (10, 11));
+        assert_eq!(manager.outstanding(), 2);
+
+        // the first tx failed, its nonce is handed out again
+        drop(first);
+        assert_eq!(second.commit(), 11);
+        assert_eq!(manager.outstanding(), 0);
+        assert_eq!(manager.allocate().unwrap().commit(), 10);
+        assert_eq!(manager.allocate().unwrap().commit(), 12);
+
+        // the nonces allocated before a reset are stale
+        let stale = manager.allocate().unwrap();
+        manager.reset(100).unwrap();
+        drop(stale);
+        assert_eq!(manager.allocate().unwrap().commit(), 100);
+    }
+
+    #[test]
+    fn test_nonce_guard_timestamp_not_reused() {
+        let manager = NonceManager::new();
+        let nonce = manager.allocate().unwrap().nonce();
+        assert!(manager.allocate().unwrap().nonce() > nonce);
+    }
+
+    #[test]
+    fn test_nonce_allocations_leave_no_gaps() {
+        let manager = Arc::new(NonceManager::new());
+        manager.reset(1).unwrap();
+        let committed = Arc::new(Mutex::new(HashSet::new()));
+
+        let handles = (0..8)
+            .map(|task| {
+                let manager = manager.clone();
+                let committed = committed.clone();
+                thread::spawn(move || {
+                    for i in 0..200 {
+                        let guard = manager.allocate().unwrap();
+                        // some txs fail, dropping their nonce
+                        if (task + i) % 3 == 0 {
+                            continue;
+                        }
+                        let nonce = guard.commit();
+                        assert!(committed.lock().unwrap().insert(nonce), "duplicate {nonce}");
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        // the released nonces left are the next ones handed out
+        let mut committed = committed.lock().unwrap();
+        assert_eq!(manager.outstanding(), 0);
+        let max = *committed.iter().max().unwrap();
+        while let Some(nonce) =
+            (committed.len() < max as usize).then(|| manager.allocate().unwrap().commit())
+        {
+            assert!(nonce < max, "gap before {nonce}");
+            assert!(committed.insert(nonce), "duplicate {nonce}");
+        }
+        assert_eq!(*committed, (1..=max).collect::<HashSet<_>>());
+    }
+
+    #[test]
+    fn test_nonce_reserve() {
+        let manager = NonceManager::new();
+        manager.reset(10).unwrap();
+        assert_eq!(manager.reserve(3).unwrap(), vec![10, 11, 12]);
+        assert!(manager.reserve(0).unwrap().is_empty());
+
+        // the released nonces come first
+        let first = manager.allocate().unwrap();
+        let second = manager.allocate().unwrap();
+        drop(second);
+        drop(first);
+        assert_eq!(manager.reserve(3).unwrap(), vec![13, 14, 15]);
+        assert_eq!(manager.outstanding(), 0);
+
+        // a block allocation goes back to the manager when dropped
+        drop(manager.allocate_block(2).unwrap());
+        assert_eq!(manager.reserve(3).unwrap(), vec![16, 17, 18]);
+
+        // timestamp nonces are still unique
+        let manager = NonceManager::new();
+        let nonces = manager.reserve(10).unwrap();
+        assert!(nonces.windows(2).all(|v| v[0] < v[1]));
+    }
+
+    #[test]
+    fn test_nonce_concurrent_reservations() {
+        let manager = Arc::new(NonceManager::new());
+        manager.reset(1).unwrap();
+
+        let handles = (0..8)
+            .map(|task| {
+                let manager = manager.clone();
+                thread::spawn(move || {
+                    (0..100)
+                        .map(|i| {
+                            // single allocations interleave with the reservations
+                            if (task + i) % 4 == 0 {
+                                vec![manager.allocate().unwrap().commit()]
+                            } else {
+                                manager.reserve(5).unwrap()
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut all = HashSet::new();
+        for handle in handles {
+            for block in handle.join().unwrap() {
+                // nothing released, so every block is contiguous
+                assert!(block.windows(2).all(|v| v[1] == v[0] + 1), "{block:?}");
+                for nonce in block {
+                    assert!(all.insert(nonce), "overlapping reservation of {nonce}");
+                }
+            }
+        }
+        assert_eq!(all, (1..=all.len() as i64).collect::<HashSet<_>>());
+    }
+
+    #[test]
+    fn test_nonce_persisted_across_restarts() {
+        let path = std::env::temp_dir().join(format!("lighter-nonce-{}", uuid::Uuid::new_v4()));
+        let store = Arc::new(FileNonceStore::new(&path));
+
+        // nothing persisted yet, it has to be seeded
+        let manager = NonceManager::with_store(store.clone()).unwrap();
+        assert!(manager.needs_seed());
+        manager.reset(10).unwrap();
+        assert!(!manager.needs_seed());
+        assert_eq!(manager.generate().unwrap(), 10);
+        assert_eq!(manager.generate().unwrap(), 11);
+
+        // restart
+        let manager = NonceManager::with_store(store.clone()).unwrap();
+        assert!(!manager.needs_seed());
+        assert_eq!(manager.generate().unwrap(), 12);
+
+        // the flush restores the next nonce, e.g. if the file was overwritten
+        std::fs::write(&path, "0").unwrap();
+        manager.flush().unwrap();
+        assert_eq!(store.load().unwrap(), Some(13));
+        assert!(NonceManager::new().flush().is_ok());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_file_nonce_store_invalid_content() {
+        let path = std::env::temp_dir().join(format!("lighter-nonce-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "not a nonce").unwrap();
+
+        let res = NonceManager::with_store(Arc::new(FileNonceStore::new(&path)));
+        assert!(matches!(res, Err(LighterError::Nonce(_))));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
     #[test]
     fn test_nonce_default_impl() {
         let manager1 = NonceManager::new();
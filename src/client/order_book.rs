@@ -0,0 +1,287 @@
+use std::{cmp::Ordering, collections::BTreeMap};
+
+use crate::{
+    client::{OrderBookUpdate, WsOrderBook},
+    models::PriceLevel,
+    LighterError, Result,
+};
+
+/// Price used to sort the levels
+#[derive(Debug, Clone, Copy)]
+struct Price(f64);
+
+impl PartialEq for Price {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Price {}
+
+impl PartialOrd for Price {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Price {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// Order book of a market kept from the WS snapshot and the following updates.
+///
+/// The updates must have consecutive offsets: on a gap the book is cleared and `apply` fails with
+/// `LighterError::OrderBookGap`, then the updates are ignored until the next snapshot (see
+/// [`crate::WsSubscription::resubscribe`]). Meanwhile the book is empty.
+#[derive(Debug, Clone)]
+pub struct OrderBook {
+    market_id: i32,
+    offset: Option<i64>, // `None` until the snapshot
+    asks: BTreeMap<Price, PriceLevel>,
+    bids: BTreeMap<Price, PriceLevel>,
+}
+
+impl OrderBook {
+    pub fn new(market_id: i32) -> Self {
+        Self {
+            market_id,
+            offset: None,
+            asks: BTreeMap::new(),
+            bids: BTreeMap::new(),
+        }
+    }
+
+    pub fn market_id(&self) -> i32 {
+        self.market_id
+    }
+
+    /// Returns the offset of the last update applied, `None` if waiting for a snapshot
+    pub fn offset(&self) -> Option<i64> {
+        self.offset
+    }
+
+    pub fn is_synced(&self) -> bool {
+        self.offset.is_some()
+    }
+
+    /// Applies the snapshot or the changed levels (a level with zero size is removed)
+    pub fn apply(&mut self, update: &OrderBookUpdate) -> Result<()> {
+        if update.market_id != self.market_id {
+            return Err(LighterError::Generic(format!(
+                "Update of market {} applied to the order book of market {}",
+                update.market_id, self.market_id
+            )));
+        }
+
+        let offset = update.order_book.offset;
+        if update.snapshot {
+            self.asks.clear();
+            self.bids.clear();
+            self.apply_levels(&update.order_book)
+                .inspect_err(|_| self.reset())?;
+            self.offset = Some(offset);
+            return Ok(());
+        }
+
+        match self.offset {
+            None => Ok(()),                         // waiting for the snapshot
+            Some(last) if offset <= last => Ok(()), // already applied
+            Some(last) if offset != last + 1 => {
+                tracing::warn!(
+                    "gap in the order book of market {}: expected offset {}, got {offset}",
+                    self.market_id,
+                    last + 1
+                );
+                self.reset();
+                Err(LighterError::OrderBookGap {
+                    expected: last + 1,
+                    got: offset,
+                })
+            }
+            Some(_) => {
+                self.apply_levels(&update.order_book)
+                    .inspect_err(|_| self.reset())?;
+                self.offset = Some(offset);
+                Ok(())
+            }
+        }
+    }
+
+    fn apply_levels(&mut self, order_book: &WsOrderBook) -> Result<()> {
+        for (levels, side) in [
+            (&order_book.asks, &mut self.asks),
+            (&order_book.bids, &mut self.bids),
+        ] {
+            for level in levels {
+                let price = parse(&level.price)?;
+                if parse(&level.size)? == 0.0 {
+                    side.remove(&Price(price));
+                } else {
+                    side.insert(Price(price), level.clone());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Drops the levels, waiting for a new snapshot
+    fn reset(&mut self) {
+        self.offset = None;
+        self.asks.clear();
+        self.bids.clear();
+    }
+
+    pub fn best_bid(&self) -> Option<&PriceLevel> {
+        self.bid_at(0)
+    }
+
+    pub fn best_ask(&self) -> Option<&PriceLevel> {
+        self.ask_at(0)
+    }
+
+    pub fn mid_price(&self) -> Option<f64> {
+        let bid = parse(&self.best_bid()?.price).ok()?;
+        let ask = parse(&self.best_ask()?.price).ok()?;
+        Some((bid + ask) / 2.0)
+    }
+
+    /// Returns the bid at the given depth, `0` being the best one
+    pub fn bid_at(&self, level: usize) -> Option<&PriceLevel> {
+        self.bids().nth(level)
+    }
+
+    /// Returns the ask at the given depth, `0` being the best one
+    pub fn ask_at(&self, level: usize) -> Option<&PriceLevel> {
+        self.asks().nth(level)
+    }
+
+    /// Returns the bids, from the best one
+    pub fn bids(&self) -> impl Iterator<Item = &PriceLevel> {
+        self.bids.values().rev()
+    }
+
+    /// Returns the asks, from the best one
+    pub fn asks(&self) -> impl Iterator<Item = &PriceLevel> {
+        self.asks.values()
+    }
+}
+
+fn parse(value: &str) -> Result<f64> {
+    value
+        .parse()
+        .map_err(|_| LighterError::Generic(format!("Invalid order book value: {value}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(price: &str, size: &str) -> PriceLevel {
+        PriceLevel::new(price.into(), size.into())
+    }
+
+    fn update(
+        snapshot: bool,
+        offset: i64,
+        asks: Vec<PriceLevel>,
+        bids: Vec<PriceLevel>,
+    ) -> OrderBookUpdate {
+        OrderBookUpdate {
+            market_id: 0,
+            snapshot,
+            order_book: WsOrderBook { asks, bids, offset },
+        }
+    }
+
+    #[test]
+    fn test_order_book_apply() {
+        let mut book = OrderBook::new(0);
+        assert!(book.best_bid().is_none());
+
+        book.apply(&update(
+            true,
+            10,
+            vec![level("3001.5", "1.0"), level("3001.0", "2.0")],
+            vec![level("2999.0", "1.0"), level("3000.0", "3.0")],
+        ))
+        .unwrap();
+        assert_eq!(book.best_ask().unwrap().price, "3001.0");
+        assert_eq!(book.best_bid().unwrap().price, "3000.0");
+        assert_eq!(book.bid_at(1).unwrap().price, "2999.0");
+        assert!(book.bid_at(2).is_none());
+        assert_eq!(book.mid_price(), Some(3000.5));
+
+        // the best ask is filled, a new bid and a changed size
+        book.apply(&update(
+            false,
+            11,
+            vec![level("3001.0", "0")],
+            vec![level("3000.5", "1.0"), level("2999.0", "5.0")],
+        ))
+        .unwrap();
+        assert_eq!(book.best_ask().unwrap().price, "3001.5");
+        assert_eq!(book.best_bid().unwrap().price, "3000.5");
+        assert_eq!(book.bid_at(2).unwrap().size, "5.0");
+        assert_eq!(book.asks().count(), 1);
+        assert_eq!(book.offset(), Some(11));
+
+        // already applied
+        book.apply(&update(false, 11, vec![level("3001.5", "0")], vec![]))
+            .unwrap();
+        assert_eq!(book.best_ask().unwrap().price, "3001.5");
+    }
+
+    #[test]
+    fn test_order_book_gap() {
+        let mut book = OrderBook::new(0);
+        // no snapshot yet
+        book.apply(&update(false, 1, vec![level("3001.0", "1.0")], vec![]))
+            .unwrap();
+        assert!(!book.is_synced());
+
+        book.apply(&update(
+            true,
+            1,
+            vec![level("3001.0", "1.0")],
+            vec![level("3000.0", "1.0")],
+        ))
+        .unwrap();
+        book.apply(&update(false, 2, vec![level("3002.0", "1.0")], vec![]))
+            .unwrap();
+
+        // offset 3 is missing
+        let res = book.apply(&update(false, 4, vec![level("3001.0", "0")], vec![]));
+        assert!(matches!(
+            res,
+            Err(LighterError::OrderBookGap {
+                expected: 3,
+                got: 4
+            })
+        ));
+        assert!(!book.is_synced());
+        assert!(book.best_ask().is_none() && book.best_bid().is_none());
+        assert!(book.mid_price().is_none());
+
+        // ignored until the new snapshot
+        book.apply(&update(false, 5, vec![level("2000.0", "1.0")], vec![]))
+            .unwrap();
+        assert!(book.best_ask().is_none());
+
+        book.apply(&update(true, 5, vec![level("3002.0", "1.0")], vec![]))
+            .unwrap();
+        assert_eq!(book.best_ask().unwrap().price, "3002.0");
+        assert!(book.best_bid().is_none());
+        book.apply(&update(false, 6, vec![], vec![level("3000.0", "2.0")]))
+            .unwrap();
+        assert_eq!(book.best_bid().unwrap().size, "2.0");
+    }
+
+    #[test]
+    fn test_order_book_other_market() {
+        let mut book = OrderBook::new(1);
+        assert!(book.apply(&update(true, 1, vec![], vec![])).is_err());
+    }
+}
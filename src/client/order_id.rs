@@ -0,0 +1,76 @@
+use std::{
+    sync::atomic::{AtomicI64, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Generates unique and increasing `client_order_index` values, safe to share between concurrent
+/// submissions
+#[derive(Debug)]
+pub struct ClientOrderIdGenerator {
+    next: AtomicI64,
+}
+
+impl ClientOrderIdGenerator {
+    /// Starts from `start`, which must be greater than 0
+    pub fn new(start: i64) -> Self {
+        Self {
+            next: AtomicI64::new(start.max(1)),
+        }
+    }
+
+    /// Starts from the current timestamp (ms), so that the ids don't collide with the ones
+    /// generated before a restart (unless more than one id per ms was generated)
+    pub fn from_timestamp() -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as i64;
+        Self::new(now)
+    }
+
+    pub fn next_id(&self) -> i64 {
+        self.next.fetch_add(1, Ordering::AcqRel)
+    }
+}
+
+impl Default for ClientOrderIdGenerator {
+    fn default() -> Self {
+        Self::from_timestamp()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, sync::Arc};
+
+    use super::*;
+
+    #[test]
+    fn test_client_order_id_generator() {
+        let generator = ClientOrderIdGenerator::new(10);
+        assert_eq!(generator.next_id(), 10);
+        assert_eq!(generator.next_id(), 11);
+
+        assert_eq!(ClientOrderIdGenerator::new(0).next_id(), 1);
+        assert!(ClientOrderIdGenerator::from_timestamp().next_id() > 1_700_000_000_000);
+    }
+
+    #[test]
+    fn test_client_order_id_generator_concurrent() {
+        let generator = Arc::new(ClientOrderIdGenerator::new(1));
+        let handles = (0..4)
+            .map(|_| {
+                let generator = generator.clone();
+                std::thread::spawn(move || {
+                    (0..1000).map(|_| generator.next_id()).collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let ids = handles
+            .into_iter()
+            .flat_map(|v| v.join().unwrap())
+            .collect::<HashSet<_>>();
+        assert_eq!(ids.len(), 4000);
+    }
+}
@@ -0,0 +1,86 @@
+use std::future::Future;
+
+use tokio::task::JoinHandle;
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+
+/// Tasks spawned in the background by a client, stopped all at once by [`BackgroundTasks::shutdown`].
+///
+/// The tasks are expected to stop once the shutdown token is cancelled, e.g. through
+/// [`CancellationToken::run_until_cancelled`].
+#[derive(Debug, Clone, Default)]
+pub(crate) struct BackgroundTasks {
+    shutdown: CancellationToken,
+    tracker: TaskTracker,
+}
+
+impl BackgroundTasks {
+    /// Stops the tasks when `shutdown` is cancelled, e.g. with the one of another client
+    pub(crate) fn with_token(mut self, shutdown: CancellationToken) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    pub(crate) fn token(&self) -> &CancellationToken {
+        &self.shutdown
+    }
+
+    pub(crate) fn is_shut_down(&self) -> bool {
+        self.shutdown.is_cancelled()
+    }
+
+    pub(crate) fn spawn<F>(&self, task: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.tracker.spawn(task)
+    }
+
+    /// Cancels the tasks and waits for them to stop. The ones spawned later stop right away.
+    pub(crate) async fn shutdown(&self) {
+        self.shutdown.cancel();
+        self.tracker.close();
+        self.tracker.wait().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_shutdown() {
+        let tasks = BackgroundTasks::default();
+        let stopped = Arc::new(AtomicBool::new(false));
+        let handle = tasks.spawn({
+            let shutdown = tasks.token().clone();
+            let stopped = stopped.clone();
+            async move {
+                shutdown
+                    .run_until_cancelled(tokio::time::sleep(Duration::from_secs(3600)))
+                    .await;
+                stopped.store(true, Ordering::Release);
+            }
+        });
+
+        tokio::time::timeout(Duration::from_secs(1), tasks.shutdown())
+            .await
+            .unwrap();
+        assert!(stopped.load(Ordering::Acquire));
+        assert!(handle.is_finished());
+        assert!(tasks.is_shut_down());
+
+        // shared with the clones
+        assert!(tasks.clone().is_shut_down());
+        let other = BackgroundTasks::default().with_token(tasks.token().clone());
+        assert!(other.is_shut_down());
+    }
+}
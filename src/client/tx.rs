@@ -0,0 +1,816 @@
+use std::{
+    future::Future,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use reqwest_retry::{policies::ExponentialBackoff, RetryDecision, RetryPolicy};
+
+use crate::{
+    api::transaction::TxResultCode,
+    apis::{self, configuration::Configuration},
+    client::nonce::{NonceGuard, NonceManager},
+    config::{LighterConfig, RetryConfig},
+    models::{RespSendTx, RespSendTxBatch},
+    signer::{data::TxInfo, Signer},
+    LighterError, Result,
+};
+
+static MAX_SUBMIT_ATTEMPTS: u32 = 3;
+
+/// Client used to sign and submit transactions on behalf of the configured account.
+///
+/// It is shared between the APIs exposing write operations, so that all of them use the same
+/// signer and nonce source.
+#[derive(Debug)]
+pub struct TxClient {
+    config: Configuration,
+    signer: Signer,
+    account_index: i64,
+    nonce_manager: Option<Arc<NonceManager>>, // `None` in case of API nonce
+    backoff: ExponentialBackoff,              // between the resubmissions of the rate limited txs
+}
+
+impl TxClient {
+    /// Same as [`TxClient::new_with_configuration`], creating its own signer and configuration
+    /// from `config`
+    #[cfg(test)]
+    pub(crate) fn new(
+        config: &LighterConfig,
+        nonce_manager: Option<Arc<NonceManager>>,
+    ) -> Result<Self> {
+        let tx_config = LighterConfig {
+            retry_config: None,
+            ..config.clone()
+        };
+        Self::new_with_configuration(
+            config,
+            Configuration::try_from(&tx_config)?,
+            Signer::try_from(config)?,
+            nonce_manager,
+        )
+    }
+
+    /// Signs with `signer` (e.g. built with [`Signer::from_ffi`] on the signer of the APIs), so
+    /// that no other client of the native library is created, and submits the txs with
+    /// `tx_config` (e.g. shared with [`crate::api::transaction::TransactionApi`]). The txs are
+    /// multipart forms, which can't be cloned by the retry middleware, so it must not retry them:
+    /// `submit` does, re-signing with a fresh nonce when it makes sense.
+    pub(crate) fn new_with_configuration(
+        config: &LighterConfig,
+        tx_config: Configuration,
+        signer: Signer,
+        nonce_manager: Option<Arc<NonceManager>>,
+    ) -> Result<Self> {
+        // the resubmissions of the rate limited txs wait for the backoff of the retries (the
+        // default one if they are disabled)
+        let backoff = RetryConfig {
+            max_retries: MAX_SUBMIT_ATTEMPTS,
+            ..config.retry_config.clone().unwrap_or_default()
+        }
+        .backoff();
+
+        Ok(Self {
+            config: tx_config,
+            signer,
+            account_index: config
+                .account_index
+                .ok_or_else(|| LighterError::Config("`account_index` is not set".into()))?
+                as i64,
+            nonce_manager,
+            backoff,
+        })
+    }
+
+    #[cfg(test)]
+    pub(crate) fn signer(&self) -> &Signer {
+        &self.signer
+    }
+
+    /// Index of the account the txs are signed for
+    pub fn account_index(&self) -> i64 {
+        self.account_index
+    }
+
+    /// Returns the next nonce, either generated locally or fetched from the API
+    pub async fn next_nonce(&self) -> Result<i64> {
+        Ok(self.next_nonces(1).await?[0])
+    }
+
+    /// Returns the next `n` nonces, to be used by txs sent in the same batch or in a burst. They
+    /// are reserved at once (see [`NonceManager::reserve`]), and in case of API nonce only the
+    /// first one is fetched, the others follow it.
+    pub async fn next_nonces(&self, n: usize) -> Result<Vec<i64>> {
+        let nonces = self.allocate_nonces(n).await?;
+        Ok(nonces.into_iter().map(NonceGuard::commit).collect())
+    }
+
+    /// Allocates the next `n` nonces, which go back to the nonce manager unless committed
+    async fn allocate_nonces(&self, n: usize) -> Result<Vec<NonceGuard<'_>>> {
+        if let Some(nonce_manager) = &self.nonce_manager {
+            // nothing persisted yet, so we start from the API nonce
+            if nonce_manager.needs_seed() {
+                self.resync_nonce().await?;
+            }
+            return nonce_manager.allocate_block(n);
+        }
+
+        let nonce = self.api_next_nonce().await?;
+        Ok((0..n as i64)
+            .map(|i| NonceGuard::untracked(nonce + i))
+            .collect())
+    }
+
+    async fn api_next_nonce(&self) -> Result<i64> {
+        let resp = apis::transaction_api::next_nonce(
+            &self.config,
+            self.account_index,
+            self.signer.api_key_index(),
+        )
+        .await
+        .inspect_err(|e| tracing::error!("unable to call `next_nonce`: {e}"))?;
+
+        Ok(resp.nonce)
+    }
+
+    /// Resyncs the local nonce manager (if any) with the nonce expected by the API.
+    /// Returns `false` in case of API nonce, since there is nothing to resync.
+    async fn resync_nonce(&self) -> Result<bool> {
+        let Some(nonce_manager) = &self.nonce_manager else {
+            return Ok(false);
+        };
+
+        let nonce = self.api_next_nonce().await?;
+        tracing::info!("resyncing local nonce to {nonce}");
+        nonce_manager.reset(nonce)?;
+
+        Ok(true)
+    }
+
+    /// Sends an already signed transaction
+    pub async fn send_tx(&self, tx_info: &TxInfo) -> Result<RespSendTx> {
+        let resp = apis::transaction_api::send_tx(
+            &self.config,
+            tx_info.tx_type.into(),
+            &tx_info.payload,
+            None,
+        )
+        .await
+        .inspect_err(|e| tracing::error!("unable to call `send_tx`: {e}"))?;
+
+        Ok(resp)
+    }
+
+    /// Sends already signed transactions in a single batch. They are executed in the given order.
+    pub async fn send_tx_batch(&self, txs: &[TxInfo]) -> Result<RespSendTxBatch> {
+        let (tx_types, tx_infos) = batch_payload(txs)?;
+        let resp = apis::transaction_api::send_tx_batch(&self.config, &tx_types, &tx_infos)
+            .await
+            .inspect_err(|e| tracing::error!("unable to call `send_tx_batch`: {e}"))?;
+
+        Ok(resp)
+    }
+
+    /// Signs a transaction with a fresh nonce and submits it.
+    ///
+    /// The transaction is signed again (with a new nonce) and resubmitted only when the returned
+    /// code is retryable (see [`TxResultCode::is_retryable`]), otherwise it fails fast.
+    /// If the local nonce is stale, it gets resynced with the API before retrying (only once),
+    /// when rate limited the resubmission waits for the backoff of the `retry_config`.
+    ///
+    /// On failure, the nonce is handed out again only if the tx was certainly not accepted
+    /// (rejected with a `4xx` or not even sent); after a timeout it may have landed, so the nonce
+    /// is not reused.
+    pub async fn submit<F>(&self, sign: F) -> Result<RespSendTx>
+    where
+        F: Fn(&Signer, i64) -> Result<TxInfo>,
+    {
+        self.submit_with(
+            1,
+            |signer, nonces| Ok(vec![sign(signer, nonces[0])?]),
+            |txs| async move { self.send_tx(&txs[0]).await },
+            None,
+        )
+        .await
+        .map(Submitted::into_sent)
+    }
+
+    /// Same as [`TxClient::submit`], for a tx that can't be executed twice (e.g. an order with
+    /// its `client_order_index`), which is also resubmitted when the submission fails without an
+    /// answer (see [`LighterError::is_transient`]), within the same attempts. Returns `None` if a
+    /// resubmission is rejected with the `duplicate` code: a previous attempt went through, but
+    /// its response was lost.
+    pub async fn submit_idempotent<F>(
+        &self,
+        sign: F,
+        duplicate: TxResultCode,
+    ) -> Result<Option<RespSendTx>>
+    where
+        F: Fn(&Signer, i64) -> Result<TxInfo>,
+    {
+        let submitted = self
+            .submit_with(
+                1,
+                |signer, nonces| Ok(vec![sign(signer, nonces[0])?]),
+                |txs| async move { self.send_tx(&txs[0]).await },
+                Some(duplicate),
+            )
+            .await?;
+
+        Ok(match submitted {
+            Submitted::Sent(resp) => Some(resp),
+            Submitted::Duplicate => None,
+        })
+    }
+
+    /// Signs `len` transactions with consecutive fresh nonces and submits them in a single batch.
+    /// Retries follow the same rules as [`TxClient::submit`].
+    pub async fn submit_batch<F>(&self, len: usize, sign: F) -> Result<RespSendTxBatch>
+    where
+        F: Fn(&Signer, &[i64]) -> Result<Vec<TxInfo>>,
+    {
+        self.submit_with(
+            len,
+            sign,
+            |txs| async move { self.send_tx_batch(&txs).await },
+            None,
+        )
+        .await
+        .map(Submitted::into_sent)
+    }
+
+    /// Signs `len` transactions with a block of consecutive fresh nonces and sends each of them
+    /// on its own, concurrently, returning the outcome of every tx in the given order.
+    ///
+    /// Unlike [`TxClient::submit_batch`], a failed tx doesn't fail the others, but it's not
+    /// retried either. The nonces of the rejected txs are handed out again (see
+    /// [`TxClient::submit`]), except the ones below the nonce of a tx that may have landed.
+    pub async fn submit_each<F>(&self, len: usize, sign: F) -> Result<Vec<Result<RespSendTx>>>
+    where
+        F: Fn(&Signer, &[i64]) -> Result<Vec<TxInfo>>,
+    {
+        let guards = self.allocate_nonces(len).await?;
+        let nonces = guards.iter().map(NonceGuard::nonce).collect::<Vec<_>>();
+        let txs = sign(&self.signer, &nonces)?;
+
+        let results = futures::future::join_all(txs.iter().map(|tx| async move {
+            match self.send_tx(tx).await {
+                Ok(resp) if resp.result_code().is_success() => Ok(resp),
+                Ok(resp) => Err(LighterError::ApiError {
+                    http_status: 200,
+                    code: Some(resp.result_code().code() as i64),
+                    message: format!("tx {:?} failed: {}", tx.tx_type, resp.into_message()),
+                }),
+                Err(e) => Err(e),
+            }
+        }))
+        .await;
+
+        // a failure code or a rejection, the tx was not accepted. Otherwise it may have been
+        // received anyway, so its nonce can't be reused
+        let not_accepted = |res: &Result<RespSendTx>| {
+            matches!(res, Err(e) if is_rejected(e)
+                || matches!(e, LighterError::ApiError { code: Some(_), .. }))
+        };
+        let last_used = guards
+            .iter()
+            .zip(&results)
+            .filter(|(_, res)| !not_accepted(res))
+            .map(|(guard, _)| guard.nonce())
+            .max();
+
+        for (guard, res) in guards.into_iter().zip(&results) {
+            // dropping the guard hands the nonce out again, unless a higher nonce was used: the
+            // txs are sent concurrently, so reusing it later would go behind the higher one
+            if not_accepted(res) && last_used.is_none_or(|v| guard.nonce() > v) {
+                drop(guard)
+            } else {
+                _ = guard.commit()
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// Signs and sends the txs, retrying as described in [`TxClient::submit`]. With a `duplicate`
+    /// code the txs are idempotent, see [`TxClient::submit_idempotent`].
+    async fn submit_with<R, F, S, Fut>(
+        &self,
+        len: usize,
+        sign: F,
+        send: S,
+        duplicate: Option<TxResultCode>,
+    ) -> Result<Submitted<R>>
+    where
+        R: SendTxResp,
+        F: Fn(&Signer, &[i64]) -> Result<Vec<TxInfo>>,
+        S: Fn(Vec<TxInfo>) -> Fut,
+        Fut: Future<Output = Result<R>>,
+    {
+        let mut attempt = 0;
+        let mut resynced = false;
+        let mut lost = false; // a previous attempt may have landed
+        loop {
+            attempt += 1;
+
+            // the nonces of the txs rejected (or not even sent) are handed out again, so that
+            // they don't leave a gap
+            let guards = self.allocate_nonces(len).await?;
+            let nonces = guards.iter().map(NonceGuard::nonce).collect::<Vec<_>>();
+            let txs = sign(&self.signer, &nonces)?;
+            let tx_types = txs.iter().map(|v| v.tx_type).collect::<Vec<_>>();
+
+            // failed txs can either come back as a successful response with a non-200 code or as an
+            // error response carrying the code
+            let (http_status, code, message) = match send(txs).await {
+                Ok(resp) if resp.result_code().is_success() => {
+                    guards.into_iter().for_each(|v| _ = v.commit());
+                    return Ok(Submitted::Sent(resp));
+                }
+                Ok(resp) => (200, resp.result_code(), resp.into_message()),
+                Err(LighterError::ApiError {
+                    http_status,
+                    code: Some(code),
+                    message,
+                }) => (http_status, TxResultCode::from(code as i32), message),
+                Err(e) if is_rejected(&e) => {
+                    // dropping the guards hands the nonces out again
+                    drop(guards);
+                    return Err(e);
+                }
+                Err(e) => {
+                    // the txs may have been received anyway, so their nonces can't be reused
+                    guards.into_iter().for_each(|v| _ = v.commit());
+                    if duplicate.is_some() && e.is_transient() && attempt < MAX_SUBMIT_ATTEMPTS {
+                        tracing::warn!(
+                            "tx {tx_types:?} failed with `{e}` (attempt {attempt}), retrying"
+                        );
+                        lost = true;
+                        continue;
+                    }
+                    return Err(e);
+                }
+            };
+            drop(guards);
+
+            if lost && Some(code) == duplicate {
+                tracing::info!("tx {tx_types:?} already sent by a previous attempt");
+                return Ok(Submitted::Duplicate);
+            }
+
+            if code == TxResultCode::NonceTooLow && !resynced && self.resync_nonce().await? {
+                resynced = true;
+                continue;
+            }
+
+            if code.is_retryable() && attempt < MAX_SUBMIT_ATTEMPTS {
+                // resubmitting at once would hit the same rate limit, taking a nonce each time
+                let delay = match code {
+                    TxResultCode::RateLimited => self.retry_delay(attempt),
+                    _ => Duration::ZERO,
+                };
+                tracing::warn!(
+                    "tx {tx_types:?} failed with code {} (attempt {attempt}), retrying in {delay:?}",
+                    code.code()
+                );
+                tokio::time::sleep(delay).await;
+                continue;
+            }
+
+            return Err(LighterError::ApiError {
+                http_status,
+                code: Some(code.code() as i64),
+                message: format!("tx {tx_types:?} failed: {message}"),
+            });
+        }
+    }
+
+    /// Backoff before the resubmission following the given attempt
+    fn retry_delay(&self, attempt: u32) -> Duration {
+        let now = SystemTime::now();
+        match self.backoff.should_retry(now, attempt - 1) {
+            RetryDecision::Retry { execute_after } => {
+                execute_after.duration_since(now).unwrap_or_default()
+            }
+            RetryDecision::DoNotRetry => Duration::ZERO,
+        }
+    }
+}
+
+/// Outcome of `TxClient::submit_with`
+enum Submitted<R> {
+    Sent(R),
+    Duplicate, // only for the idempotent txs
+}
+
+impl<R> Submitted<R> {
+    /// Returns the response of the txs that aren't idempotent, which can't be duplicates
+    fn into_sent(self) -> R {
+        match self {
+            Submitted::Sent(resp) => resp,
+            Submitted::Duplicate => unreachable!("only the idempotent txs are duplicates"),
+        }
+    }
+}
+
+/// Responses of `send_tx`/`send_tx_batch`
+trait SendTxResp {
+    fn result_code(&self) -> TxResultCode;
+    fn into_message(self) -> String;
+}
+
+impl SendTxResp for RespSendTx {
+    fn result_code(&self) -> TxResultCode {
+        self.into()
+    }
+
+    fn into_message(self) -> String {
+        self.message.unwrap_or_default()
+    }
+}
+
+impl SendTxResp for RespSendTxBatch {
+    fn result_code(&self) -> TxResultCode {
+        self.into()
+    }
+
+    fn into_message(self) -> String {
+        self.message.unwrap_or_default()
+    }
+}
+
+/// Returns `true` if the txs were certainly not accepted: they were rejected by the API (`4xx`)
+/// or never sent. Any other failure (e.g. a timeout) is ambiguous, the txs may have landed.
+fn is_rejected(e: &LighterError) -> bool {
+    match e {
+        LighterError::ApiError { http_status, .. } => (400..500).contains(http_status),
+        LighterError::Signing(_) | LighterError::Config(_) | LighterError::CircuitOpen(_) => true,
+        _ => false,
+    }
+}
+
+/// Builds the `tx_types` and `tx_infos` form fields of `send_tx_batch`, both JSON arrays
+fn batch_payload(txs: &[TxInfo]) -> Result<(String, String)> {
+    let tx_types = txs.iter().map(|v| i32::from(v.tx_type)).collect::<Vec<_>>();
+    let tx_infos = txs.iter().map(|v| v.payload.as_str()).collect::<Vec<_>>();
+
+    Ok((
+        serde_json::to_string(&tx_types)?,
+        serde_json::to_string(&tx_infos)?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        api::order::GroupingType,
+        client::FileNonceStore,
+        models::order::{TimeInForce, Type as OrderType},
+        signer::data::{CreateOrderTxReq, SignCancelOrderData, SignCreateGroupedOrdersData},
+    };
+
+    static TEST_API_KEY_PRIVATE: &str =
+        "01db9eed031d59d6bd0ee00ee5a7dc1f62087bf217b51caea57eb6e17a02c49e0a748d2f155a2f60";
+
+    fn config(base_url: String) -> LighterConfig {
+        LighterConfig {
+            base_url,
+            ..LighterConfig::new()
+                .with_api_key_private(TEST_API_KEY_PRIVATE)
+                .with_account_index(28)
+                .with_api_key_index(2)
+        }
+    }
+
+    fn sign_cancel_order(signer: &Signer, nonce: i64) -> Result<TxInfo> {
+        signer.sign_cancel_order(
+            SignCancelOrderData {
+                market_index: 1,
+                order_index: 1,
+            },
+            nonce,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_submit_resyncs_stale_local_nonce() {
+        let mut server = mockito::Server::new_async().await;
+        // the local (timestamp based) nonce is way ahead of the one expected by the API
+        let stale = server
+            .mock("POST", "/api/v1/sendTx")
+            .match_body(mockito::Matcher::Regex(r#""Nonce":\d{10,}"#.into()))
+            .with_status(400)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":21104,"message":"invalid nonce"}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let next_nonce = server
+            .mock("GET", "/api/v1/nextNonce")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"nonce":42}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let resynced = server
+            .mock("POST", "/api/v1/sendTx")
+            .match_body(mockito::Matcher::Regex(r#""Nonce":42,"#.into()))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"tx_hash":"0x1","predicted_execution_time_ms":1}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let nonce_manager = Arc::new(NonceManager::new());
+        let client = TxClient::new(&config(server.url()), Some(nonce_manager.clone())).unwrap();
+
+        let resp = client.submit(sign_cancel_order).await.unwrap();
+        assert_eq!(resp.tx_hash, "0x1");
+        assert_eq!(nonce_manager.generate().unwrap(), 43);
+
+        stale.assert_async().await;
+        next_nonce.assert_async().await;
+        resynced.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_submit_fails_fast_on_non_retryable_code() {
+        let mut server = mockito::Server::new_async().await;
+        let send_tx = server
+            .mock("POST", "/api/v1/sendTx")
+            .with_status(400)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":21120,"message":"invalid signature"}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let nonce_manager = Arc::new(NonceManager::new());
+        nonce_manager.reset(7).unwrap();
+        let client = TxClient::new(&config(server.url()), Some(nonce_manager.clone())).unwrap();
+
+        let err = client.submit(sign_cancel_order).await.unwrap_err();
+        assert_eq!(
+            err.api_code(),
+            Some(crate::LighterErrorCode::InvalidSignature)
+        );
+        send_tx.assert_async().await;
+
+        // the tx was rejected, so its nonce is not burned
+        assert_eq!(client.next_nonce().await.unwrap(), 7);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_submit_backs_off_when_rate_limited() {
+        let mut server = mockito::Server::new_async().await;
+        let rate_limited = server
+            .mock("POST", "/api/v1/sendTx")
+            .match_body(mockito::Matcher::Regex(r#""Nonce":7,"#.into()))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":23000,"message":"too many requests","tx_hash":"","predicted_execution_time_ms":1}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let accepted = server
+            .mock("POST", "/api/v1/sendTx")
+            .match_body(mockito::Matcher::Regex(r#""Nonce":7,"#.into()))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"tx_hash":"0x1","predicted_execution_time_ms":1}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        // the paused clock would fire the request timeouts while waiting for the server
+        let config = LighterConfig {
+            timeout_secs: None,
+            retry_config: Some(RetryConfig {
+                min_retry_interval: 2_000,
+                jitter: crate::config::RetryJitter::None,
+                ..Default::default()
+            }),
+            ..config(server.url())
+        };
+        let nonce_manager = Arc::new(NonceManager::new());
+        nonce_manager.reset(7).unwrap();
+        let client = TxClient::new(&config, Some(nonce_manager)).unwrap();
+
+        let start = tokio::time::Instant::now();
+        let resp = client.submit(sign_cancel_order).await.unwrap();
+        assert_eq!(resp.tx_hash, "0x1");
+        assert!(
+            start.elapsed() >= Duration::from_secs(2),
+            "{:?}",
+            start.elapsed()
+        );
+
+        rate_limited.assert_async().await;
+        accepted.assert_async().await;
+        // the nonce of the rate limited tx was resubmitted
+        assert_eq!(client.next_nonce().await.unwrap(), 8);
+    }
+
+    #[tokio::test]
+    async fn test_submit_rolls_back_nonce_when_rejected() {
+        let mut server = mockito::Server::new_async().await;
+        // no code in the body
+        let send_tx = server
+            .mock("POST", "/api/v1/sendTx")
+            .with_status(400)
+            .with_body("bad request")
+            .expect(1)
+            .create_async()
+            .await;
+
+        let nonce_manager = Arc::new(NonceManager::new());
+        nonce_manager.reset(7).unwrap();
+        let client = TxClient::new(&config(server.url()), Some(nonce_manager.clone())).unwrap();
+
+        let err = client.submit(sign_cancel_order).await.unwrap_err();
+        assert!(matches!(
+            err,
+            LighterError::ApiError {
+                http_status: 400,
+                code: None,
+                ..
+            }
+        ));
+        send_tx.assert_async().await;
+
+        // not even sent
+        let err = client
+            .submit(|_, _| Err(LighterError::Signing("invalid order".into())))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, LighterError::Signing(_)));
+
+        assert_eq!(client.next_nonce().await.unwrap(), 7);
+    }
+
+    #[tokio::test]
+    async fn test_submit_keeps_nonce_when_ambiguous() {
+        let mut server = mockito::Server::new_async().await;
+        let send_tx = server
+            .mock("POST", "/api/v1/sendTx")
+            .with_header("content-type", "application/json")
+            .with_chunked_body(|w| {
+                std::thread::sleep(std::time::Duration::from_millis(1500));
+                w.write_all(br#"{"code":200,"tx_hash":"0x1","predicted_execution_time_ms":1}"#)
+            })
+            .expect(1)
+            .create_async()
+            .await;
+
+        let nonce_manager = Arc::new(NonceManager::new());
+        nonce_manager.reset(7).unwrap();
+        let config = config(server.url()).with_timeout(1);
+        let client = TxClient::new(&config, Some(nonce_manager.clone())).unwrap();
+
+        // the tx may have landed
+        let err = client.submit(sign_cancel_order).await.unwrap_err();
+        assert!(err.is_transient());
+        send_tx.assert_async().await;
+
+        assert_eq!(client.next_nonce().await.unwrap(), 8);
+    }
+
+    #[tokio::test]
+    async fn test_submit_each_keeps_nonces_below_accepted_ones() {
+        let mut server = mockito::Server::new_async().await;
+        let rejected = server
+            .mock("POST", "/api/v1/sendTx")
+            .match_body(mockito::Matcher::Regex(r#""Nonce":(8|10),"#.into()))
+            .with_status(400)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":21120,"message":"invalid signature"}"#)
+            .expect(2)
+            .create_async()
+            .await;
+        let accepted = server
+            .mock("POST", "/api/v1/sendTx")
+            .match_body(mockito::Matcher::Regex(r#""Nonce":(7|9),"#.into()))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"tx_hash":"0x1","predicted_execution_time_ms":1}"#)
+            .expect(2)
+            .create_async()
+            .await;
+
+        let nonce_manager = Arc::new(NonceManager::new());
+        nonce_manager.reset(7).unwrap();
+        let client = TxClient::new(&config(server.url()), Some(nonce_manager)).unwrap();
+
+        let results = client
+            .submit_each(4, |signer, nonces| {
+                nonces
+                    .iter()
+                    .map(|&nonce| sign_cancel_order(signer, nonce))
+                    .collect()
+            })
+            .await
+            .unwrap();
+        let accepted_txs = results.iter().map(Result::is_ok).collect::<Vec<_>>();
+        assert_eq!(accepted_txs, [true, false, true, false]);
+
+        rejected.assert_async().await;
+        accepted.assert_async().await;
+        // 8 is behind 9, which was accepted, so only 10 is handed out again
+        assert_eq!(client.next_nonce().await.unwrap(), 10);
+        assert_eq!(client.next_nonce().await.unwrap(), 11);
+    }
+
+    #[tokio::test]
+    async fn test_next_nonces_fetches_api_nonce_once() {
+        let mut server = mockito::Server::new_async().await;
+        let next_nonce = server
+            .mock("GET", "/api/v1/nextNonce")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"nonce":7}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = TxClient::new(&config(server.url()), None).unwrap();
+        assert_eq!(client.next_nonces(3).await.unwrap(), vec![7, 8, 9]);
+
+        next_nonce.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_next_nonce_seeds_empty_store_from_api() {
+        let mut server = mockito::Server::new_async().await;
+        let next_nonce = server
+            .mock("GET", "/api/v1/nextNonce")
+            .match_query(mockito::Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"nonce":7}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let path = std::env::temp_dir().join(format!("lighter-nonce-{}", uuid::Uuid::new_v4()));
+        let store = Arc::new(FileNonceStore::new(&path));
+        let nonce_manager = Arc::new(NonceManager::with_store(store).unwrap());
+        let client = TxClient::new(&config(server.url()), Some(nonce_manager)).unwrap();
+
+        assert_eq!(client.next_nonce().await.unwrap(), 7);
+        // already seeded, no further requests
+        assert_eq!(client.next_nonce().await.unwrap(), 8);
+        assert_eq!(std::fs::read_to_string(&path).unwrap().trim(), "9");
+        next_nonce.assert_async().await;
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_batch_payload_cancel_and_grouped_orders() {
+        let signer = Signer::try_from(&config(String::new())).unwrap();
+        let expiry = (chrono::Utc::now() + chrono::Duration::days(1)).timestamp_millis();
+        let order = CreateOrderTxReq {
+            MarketIndex: 1,
+            ClientOrderIndex: 0,
+            BaseAmount: 1,
+            Price: 100,
+            IsAsk: 0,
+            Type: OrderType::Limit.into(),
+            TimeInForce: TimeInForce::GoodTillTime.into(),
+            ReduceOnly: 0,
+            TriggerPrice: 0,
+            OrderExpiry: expiry,
+        };
+        // the triggered order takes its size from the first one
+        let take_profit = CreateOrderTxReq {
+            BaseAmount: 0,
+            IsAsk: 1,
+            Type: OrderType::TakeProfit.into(),
+            TimeInForce: TimeInForce::ImmediateOrCancel.into(),
+            ReduceOnly: 1,
+            TriggerPrice: 110,
+            ..order
+        };
+
+        let txs = vec![
+            sign_cancel_order(&signer, 7).unwrap(),
+            signer
+                .sign_create_grouped_orders(
+                    SignCreateGroupedOrdersData {
+                        grouping_type: GroupingType::OneTriggersOther,
+                        orders: vec![order, take_profit],
+                    },
+                    8,
+                )
+                .unwrap(),
+        ];
+
+        let (tx_types, tx_infos) = batch_payload(&txs).unwrap();
+        assert_eq!(tx_types, "[15,28]");
+
+        let tx_infos = serde_json::from_str::<Vec<String>>(&tx_infos).unwrap();
+        assert_eq!(tx_infos.len(), 2);
+        for (tx_info, nonce) in tx_infos.iter().zip([7, 8]) {
+            let tx_info = serde_json::from_str::<serde_json::Value>(tx_info).unwrap();
+            assert_eq!(tx_info["Nonce"], nonce);
+        }
+    }
+}
@@ -1,28 +1,40 @@
-use crate::{
-    config::LighterConfig,
-    error::{LighterError, Result},
+use std::{
+    collections::{HashMap, VecDeque},
+    marker::PhantomData,
+    pin::Pin,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::{Duration, SystemTime},
+};
+
+use futures::{
+    channel::{mpsc, oneshot},
+    SinkExt, Stream, StreamExt,
 };
-use futures::{SinkExt, StreamExt};
-use serde::{Deserialize, Serialize};
+use reqwest_retry::{RetryDecision, RetryPolicy};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
 use serde_json::{json, Value};
-use std::collections::HashMap;
-use tokio::net::TcpStream;
+use tokio::{net::TcpStream, sync::Mutex, time::Instant};
 use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+use tokio_util::sync::CancellationToken;
 
-pub type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+use crate::{
+    api::notification::Notification,
+    client::tasks::BackgroundTasks,
+    config::{KeepaliveConfig, LighterConfig, RetryConfig},
+    error::{LighterError, LighterErrorCode, Result},
+    models::{AccountPosition, PriceLevel, PublicPoolShare, Trade},
+    AccountIndex, MarketIndex,
+};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct WsRequest {
-    pub method: String,
-    pub params: Option<Value>,
-}
+#[cfg(feature = "signer")]
+use crate::signer::FFISigner;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct WsResponse {
-    pub id: Option<String>,
-    pub result: Option<Value>,
-    pub error: Option<WsError>,
-}
+pub type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WsError {
@@ -31,292 +43,1623 @@ pub struct WsError {
     pub data: Option<Value>,
 }
 
-#[derive(Debug, strum::Display, Serialize, Deserialize)]
-#[serde(rename_all = "lowercase")]
-enum WsMessage {
-    Connected,
-    #[serde(rename = "subscribed/order_book")]
-    SubscribedOrderBook,
-    #[serde(rename = "update/order_book")]
-    UpdateOrderBook,
-    #[serde(rename = "subscribed/account_all")]
-    SubscribedAccountAll,
-    #[serde(rename = "update/account_all")]
-    UpdateAccountAll,
-    Ping,
+impl From<WsError> for LighterError {
+    fn from(error: WsError) -> Self {
+        match LighterErrorCode::from(error.code as i64) {
+            LighterErrorCode::InvalidAuth => {
+                LighterError::Auth(format!("[{}] {}", error.code, error.message))
+            }
+            _ => LighterError::Generic(format!(
+                "WebSocket error: [{}] {}",
+                error.code, error.message
+            )),
+        }
+    }
+}
+
+/// Header of the frames sent by the server, the payload depends on the channel
+#[derive(Debug, Deserialize)]
+struct FrameHeader {
+    #[serde(rename = "type", default)]
+    kind: String,
+    channel: Option<String>, // e.g. `order_book:0`
+    error: Option<WsError>,
 }
 
-#[derive(Debug, PartialEq, Eq, Hash)]
-enum WsSubscriptionType {
-    OrderBooks,
-    Accounts,
+/// Account state pushed on the `account_all` channel. The first update is the whole state at
+/// subscription time, the following ones only carry what changed.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct AccountUpdate {
+    #[serde(rename = "account")]
+    pub account_index: i64,
+    #[serde(rename = "type", deserialize_with = "is_snapshot")]
+    pub snapshot: bool, // whole state, sent again after a reconnect
+    #[serde(default, deserialize_with = "positions_by_market")]
+    pub positions: Vec<AccountPosition>,
+    #[serde(default)]
+    pub shares: Vec<PublicPoolShare>,
+    pub collateral: Option<String>,
+    pub available_balance: Option<String>,
+    pub total_order_count: Option<i64>,
+    pub pending_order_count: Option<i64>,
 }
 
-// #[derive(Debug)]
-// struct WsSubscription {
-//     id: uuid::Uuid,
-//     pub state: Option<HashMap<String, Value>>,
-// }
+/// Order book pushed on the `order_book` channel: first the snapshot, then the changed levels
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct OrderBookUpdate {
+    #[serde(rename = "channel", deserialize_with = "channel_id")]
+    pub market_id: i32,
+    #[serde(rename = "type", deserialize_with = "is_snapshot")]
+    pub snapshot: bool,
+    pub order_book: WsOrderBook,
+}
 
-#[derive(Debug)]
-pub struct WsClient<F1, F2>
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct WsOrderBook {
+    #[serde(default)]
+    pub asks: Vec<PriceLevel>,
+    #[serde(default)]
+    pub bids: Vec<PriceLevel>,
+    #[serde(default)]
+    pub offset: i64,
+}
+
+/// Trades pushed on the `trade` channel
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct TradeUpdate {
+    #[serde(rename = "channel", deserialize_with = "channel_id")]
+    pub market_id: i32,
+    #[serde(rename = "type", deserialize_with = "is_snapshot")]
+    pub snapshot: bool,
+    #[serde(default)]
+    pub trades: Vec<Trade>,
+}
+
+/// Notifications pushed on the `notification` channel: first the ones not acknowledged yet,
+/// then the new ones
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct NotificationUpdate {
+    #[serde(rename = "channel", deserialize_with = "channel_id")]
+    pub account_index: i64,
+    #[serde(rename = "type", deserialize_with = "is_snapshot")]
+    pub snapshot: bool,
+    #[serde(rename = "notifs", default)]
+    pub notifications: Vec<Notification>,
+}
+
+/// Tracks the last trade id of a market, the ids are expected to increase
+#[derive(Debug, Default)]
+struct TradeIds {
+    last: Option<i64>,
+}
+
+impl TradeIds {
+    /// Returns the ids not greater than the previous ones, logging a warning for each of them.
+    /// Snapshots are not checked, since after a reconnect they send the recent trades again.
+    fn check(&mut self, update: &TradeUpdate) -> Vec<i64> {
+        let mut out_of_order = Vec::new();
+        for trade in &update.trades {
+            match self.last {
+                Some(last) if !update.snapshot && trade.trade_id <= last => {
+                    tracing::warn!(
+                        "out of order trade {} on market {} (last {last})",
+                        trade.trade_id,
+                        update.market_id
+                    );
+                    out_of_order.push(trade.trade_id);
+                }
+                _ => {}
+            }
+            self.last = self.last.max(Some(trade.trade_id));
+        }
+        out_of_order
+    }
+}
+
+/// The positions are pushed by market index (`{"0": {..}}`), each one already carries `market_id`
+fn positions_by_market<'de, D>(
+    deserializer: D,
+) -> std::result::Result<Vec<AccountPosition>, D::Error>
 where
-    F1: Fn(String, Value) + Send + Sync + 'static,
-    F2: Fn(String, Value) + Send + Sync + 'static,
+    D: Deserializer<'de>,
 {
-    stream: WsStream,
-    subscriptions: HashMap<WsSubscriptionType, HashMap<String, Option<Value>>>,
-    on_order_book_update: Option<F1>,
-    on_account_update: Option<F2>,
-    // {OrderBook: {'1': {id, state}}}}
+    let positions = HashMap::<String, AccountPosition>::deserialize(deserializer)?;
+    let mut positions = positions.into_values().collect::<Vec<_>>();
+    positions.sort_by_key(|v| v.market_id);
+    Ok(positions)
 }
 
-pub struct WsClientBuilder<F1, F2>
+/// `subscribed/..` frames carry the whole state, `update/..` ones only the changes
+fn is_snapshot<'de, D>(deserializer: D) -> std::result::Result<bool, D::Error>
 where
-    F1: Fn(String, Value) + Send + Sync + 'static,
-    F2: Fn(String, Value) + Send + Sync + 'static,
+    D: Deserializer<'de>,
 {
-    config: Option<LighterConfig>,
-    order_books_subs: Option<(Vec<String>, F1)>,
-    accounts_subs: Option<(Vec<String>, F2)>,
+    Ok(String::deserialize(deserializer)?.starts_with("subscribed/"))
 }
 
-impl<F1, F2> WsClientBuilder<F1, F2>
+/// Parses the id at the end of the channel (e.g. the market of `order_book:0`)
+fn channel_id<'de, D, T>(deserializer: D) -> std::result::Result<T, D::Error>
 where
-    F1: Fn(String, Value) + Send + Sync + 'static,
-    F2: Fn(String, Value) + Send + Sync + 'static,
+    D: Deserializer<'de>,
+    T: FromStr,
 {
-    pub fn with_config(mut self, config: LighterConfig) -> Self {
-        self.config = Some(config);
-        self
+    let channel = String::deserialize(deserializer)?;
+    channel
+        .rsplit([':', '/'])
+        .next()
+        .and_then(|v| v.parse().ok())
+        .ok_or_else(|| serde::de::Error::custom(format!("invalid channel `{channel}`")))
+}
+
+/// Value routed to a subscription
+type Frame = Result<Value>;
+
+#[derive(Debug)]
+enum Command {
+    Subscribe {
+        id: u64,
+        channel: String,
+        auth: bool,
+        sender: mpsc::UnboundedSender<Frame>,
+        ack: oneshot::Sender<Result<()>>,
+    },
+    Unsubscribe {
+        id: u64,
+    },
+    Resubscribe {
+        id: u64,
+    },
+}
+
+/// Creates the auth tokens of the authenticated channels
+#[derive(Debug, Clone)]
+struct Auth {
+    #[cfg(feature = "signer")]
+    signer: Option<Arc<FFISigner>>,
+}
+
+impl Auth {
+    #[cfg(feature = "signer")]
+    fn new(config: &LighterConfig) -> Result<Self> {
+        let signer = match config.api_key_private {
+            Some(_) => Some(Arc::new(FFISigner::try_from(config)?)),
+            None => None,
+        };
+        Ok(Self { signer })
     }
 
-    pub fn with_order_books_subs(
-        mut self,
-        order_book_subs: Vec<String>,
-        order_book_update_fn: F1,
-    ) -> Self {
-        self.order_books_subs = Some((order_book_subs, order_book_update_fn));
-        self
+    #[cfg(not(feature = "signer"))]
+    fn new(_config: &LighterConfig) -> Result<Self> {
+        Ok(Self {})
+    }
+
+    /// Returns a valid token, it may have expired since the previous subscription
+    #[cfg(feature = "signer")]
+    fn token(&self) -> Result<String> {
+        self.signer
+            .as_ref()
+            .ok_or_else(|| {
+                LighterError::Config("`api_key_private` is required by the channel".into())
+            })?
+            .get_auth_token(None)
+    }
+
+    #[cfg(not(feature = "signer"))]
+    fn token(&self) -> Result<String> {
+        Err(LighterError::signer_disabled("Authenticating the channel"))
+    }
+
+    /// Returns when the last token is going to be replaced by a new one
+    #[cfg(feature = "signer")]
+    fn renewal_in(&self) -> Option<Duration> {
+        self.signer.as_ref()?.auth_token_renewal_in()
     }
 
-    pub fn with_accounts_subs(mut self, account_subs: Vec<String>, account_update_fn: F2) -> Self {
-        self.accounts_subs = Some((account_subs, account_update_fn));
+    #[cfg(not(feature = "signer"))]
+    fn renewal_in(&self) -> Option<Duration> {
+        None
+    }
+}
+
+/// Streaming client. All the subscriptions share a single connection, opened by the first one.
+///
+/// When the connection drops, it reconnects according to `ws_reconnect` and subscribes again to
+/// all the channels, which send their whole state again; once the attempts are exhausted the
+/// subscriptions end with an error. Without `ws_reconnect` they just end. Each connection must be
+/// established within `timeout_secs`, otherwise the attempt fails.
+///
+/// The authenticated channels are subscribed again with a new auth token before the previous
+/// one expires, so they send their whole state again too. If that fails, it reconnects.
+///
+/// With `ws_keepalive`, the connection is pinged periodically, so that it's not dropped while
+/// idle; if a pong doesn't come back in time, the connection is considered dead and it
+/// reconnects too.
+///
+/// The connection is closed by [`WsClient::shutdown`], or once the client (and its clones) and
+/// all the subscriptions are dropped.
+#[derive(Debug, Clone)]
+pub struct WsClient {
+    ws_url: String,
+    connect_timeout: Option<Duration>, // `timeout_secs` of the config
+    auth: Auth,
+    reconnect: Option<RetryConfig>,
+    keepalive: Option<KeepaliveConfig>,
+    connection: Arc<Mutex<Option<mpsc::UnboundedSender<Command>>>>,
+    next_id: Arc<AtomicU64>,
+    last_pong: Arc<AtomicU64>, // ms since the epoch, 0 until the first pong
+    tasks: BackgroundTasks,
+}
+
+impl WsClient {
+    pub fn new(config: &LighterConfig) -> Result<Self> {
+        Ok(Self {
+            ws_url: config.ws_url.clone(),
+            connect_timeout: config.timeout_secs.map(Duration::from_secs),
+            auth: Auth::new(config)?,
+            reconnect: config.ws_reconnect.clone(),
+            keepalive: config.ws_keepalive.clone(),
+            connection: Arc::new(Mutex::new(None)),
+            next_id: Arc::new(AtomicU64::new(0)),
+            last_pong: Arc::new(AtomicU64::new(0)),
+            tasks: BackgroundTasks::default(),
+        })
+    }
+
+    /// Shuts down the client when `token` is cancelled, e.g. the one of
+    /// [`HttpClient::shutdown_token`](crate::HttpClient::shutdown_token). Shutting down the
+    /// client cancels `token` too.
+    pub fn with_shutdown_token(mut self, token: CancellationToken) -> Self {
+        self.tasks = self.tasks.with_token(token);
         self
     }
 
-    pub async fn build(self) -> Result<WsClient<F1, F2>> {
-        let config = self.config.unwrap_or_default();
+    /// Closes the connection and waits for its task to end, for all the clones of the client.
+    /// The subscriptions end, and any later one fails.
+    pub async fn shutdown(&self) {
+        self.tasks.shutdown().await;
+        self.connection.lock().await.take();
+    }
+
+    /// Tasks of the client, stopped by its shutdown
+    pub(crate) fn tasks(&self) -> &BackgroundTasks {
+        &self.tasks
+    }
 
-        if self.accounts_subs.is_none() && self.order_books_subs.is_none() {
-            return Err(LighterError::Generic("No subscriptions provided".into()));
+    /// Returns when the last pong answering the keepalive pings was received, on any connection
+    /// of the client, to check its health. `None` until the first one (or without
+    /// `ws_keepalive`).
+    pub fn last_pong(&self) -> Option<SystemTime> {
+        match self.last_pong.load(Ordering::Acquire) {
+            0 => None,
+            ms => Some(SystemTime::UNIX_EPOCH + Duration::from_millis(ms)),
         }
+    }
 
-        // create client
-        let (ws_stream, _) = connect_async(&config.ws_url)
+    /// Subscribes to the order book of the market
+    pub async fn subscribe_order_book(
+        &self,
+        market_id: impl Into<MarketIndex>,
+    ) -> Result<WsSubscription<OrderBookUpdate>> {
+        self.subscribe(format!("order_book/{}", market_id.into()), false)
             .await
-            .map_err(|e| LighterError::WebSocket(Box::new(e)))?;
+    }
 
-        let mut subs = HashMap::new();
+    /// Subscribes to the trades of the market. Trades whose id is not greater than the previous
+    /// ones are still yielded, with a warning.
+    pub async fn subscribe_trades(
+        &self,
+        market_id: impl Into<MarketIndex>,
+    ) -> Result<WsSubscription<TradeUpdate>> {
+        let channel = format!("trade/{}", market_id.into());
+        let mut subscription = self.subscribe(channel, false).await?;
+        let mut trade_ids = TradeIds::default();
+        subscription.inspect = Some(Box::new(move |update| {
+            trade_ids.check(update);
+        }));
+        Ok(subscription)
+    }
 
-        let mut on_order_book_update = None;
-        if let Some((order_books_subs, handler)) = self.order_books_subs {
-            let order_books_subs = order_books_subs
-                .iter()
-                .map(|v| (v.to_string(), None))
-                .collect::<HashMap<_, _>>();
-            subs.insert(WsSubscriptionType::OrderBooks, order_books_subs);
-            on_order_book_update = Some(handler);
-        }
+    /// Subscribes to the state of the account, authenticating with the signer's auth token.
+    /// It fails with `LighterError::Auth` if the token is rejected.
+    pub async fn subscribe_account(
+        &self,
+        account_index: impl Into<AccountIndex>,
+    ) -> Result<WsSubscription<AccountUpdate>> {
+        // fail before connecting
+        self.auth.token()?;
 
-        let mut on_account_update = None;
-        if let Some((account_subs, handler)) = self.accounts_subs {
-            let account_subs = account_subs
-                .iter()
-                .map(|v| (v.to_string(), None))
-                .collect::<HashMap<_, _>>();
-            subs.insert(WsSubscriptionType::Accounts, account_subs);
-            on_account_update = Some(handler);
+        self.subscribe(format!("account_all/{}", account_index.into()), true)
+            .await
+    }
+
+    /// Subscribes to the notifications of the account (e.g. liquidations), authenticating with
+    /// the signer's auth token. They can be acknowledged with
+    /// [`NotificationApi::ack`](crate::api::notification::NotificationApi::ack).
+    pub async fn subscribe_notifications(
+        &self,
+        account_index: impl Into<AccountIndex>,
+    ) -> Result<WsSubscription<NotificationUpdate>> {
+        // fail before connecting
+        self.auth.token()?;
+
+        self.subscribe(format!("notification/{}", account_index.into()), true)
+            .await
+    }
+
+    async fn subscribe<T>(&self, channel: String, auth: bool) -> Result<WsSubscription<T>> {
+        let commands = self.commands().await?;
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = mpsc::unbounded();
+        let (ack, ack_receiver) = oneshot::channel();
+        commands
+            .unbounded_send(Command::Subscribe {
+                id,
+                channel: channel.clone(),
+                auth,
+                sender,
+                ack,
+            })
+            .map_err(|_| connection_closed())?;
+
+        ack_receiver
+            .await
+            .map_err(|_| connection_closed())?
+            .inspect_err(|e| tracing::error!("unable to subscribe to `{channel}`: {e}"))?;
+
+        Ok(WsSubscription {
+            id,
+            channel,
+            receiver,
+            commands,
+            inspect: None,
+            _update: PhantomData,
+        })
+    }
+
+    /// Returns the commands of the running connection, connecting if there is none
+    async fn commands(&self) -> Result<mpsc::UnboundedSender<Command>> {
+        let mut connection = self.connection.lock().await;
+        if self.tasks.is_shut_down() {
+            return Err(LighterError::Generic("WebSocket client shut down".into()));
+        }
+        if let Some(commands) = connection.as_ref().filter(|v| !v.is_closed()) {
+            return Ok(commands.clone());
         }
 
-        Ok(WsClient {
-            stream: ws_stream,
-            subscriptions: subs,
-            on_order_book_update,
-            on_account_update,
+        let stream = connect(&self.ws_url, self.connect_timeout)
+            .await
+            .inspect_err(|e| tracing::error!("unable to connect to `{}`: {e}", self.ws_url))?;
+        let (commands, receiver) = mpsc::unbounded();
+        let task = Connection {
+            ws_url: self.ws_url.clone(),
+            connect_timeout: self.connect_timeout,
+            auth: self.auth.clone(),
+            reconnect: self.reconnect.clone(),
+            keepalive: self.keepalive.clone().map(Keepalive::new),
+            last_pong: self.last_pong.clone(),
+            stream,
+            subscriptions: HashMap::new(),
+            channels: HashMap::new(),
+            pending: VecDeque::new(),
+            renew_auth_at: None,
+        };
+        let shutdown = self.tasks.token().clone();
+        self.tasks.spawn(task.run(receiver, shutdown));
+
+        *connection = Some(commands.clone());
+        Ok(commands)
+    }
+}
+
+fn connection_closed() -> LighterError {
+    LighterError::Generic("WebSocket connection closed".into())
+}
+
+/// Called on each update of a subscription, e.g. to check the trade ids
+type Inspect<T> = Box<dyn FnMut(&T) + Send + Sync>;
+
+/// Updates of a channel. Dropping it unsubscribes from the channel.
+pub struct WsSubscription<T> {
+    id: u64,
+    channel: String,
+    receiver: mpsc::UnboundedReceiver<Frame>,
+    commands: mpsc::UnboundedSender<Command>,
+    inspect: Option<Inspect<T>>,
+    _update: PhantomData<fn() -> T>,
+}
+
+impl<T> std::fmt::Debug for WsSubscription<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WsSubscription")
+            .field("id", &self.id)
+            .field("channel", &self.channel)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> WsSubscription<T> {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Returns the channel, e.g. `order_book/0`
+    pub fn channel(&self) -> &str {
+        &self.channel
+    }
+
+    /// Subscribes again to the channel, so that its whole state is sent again (e.g. after an
+    /// order book gap)
+    pub fn resubscribe(&self) -> Result<()> {
+        self.commands
+            .unbounded_send(Command::Resubscribe { id: self.id })
+            .map_err(|_| connection_closed())
+    }
+}
+
+impl<T: DeserializeOwned> Stream for WsSubscription<T> {
+    type Item = Result<T>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        let channel = &this.channel;
+        let inspect = &mut this.inspect;
+        this.receiver.poll_next_unpin(cx).map(|frame| {
+            frame.map(|frame| {
+                frame
+                    .and_then(|v| {
+                        serde_json::from_value(v)
+                            .inspect_err(|e| {
+                                tracing::error!("unable to deserialize `{channel}` update: {e}")
+                            })
+                            .map_err(LighterError::from)
+                    })
+                    .inspect(|update| {
+                        if let Some(inspect) = inspect {
+                            inspect(update);
+                        }
+                    })
+            })
         })
     }
 }
 
-impl<F1, F2> WsClient<F1, F2>
-where
-    F1: Fn(String, Value) + Send + Sync + 'static,
-    F2: Fn(String, Value) + Send + Sync + 'static,
-{
-    pub async fn run(&mut self) -> Result<()> {
-        while let Some(msg) = self.stream.next().await {
-            let msg = msg.map_err(|e| {
-                tracing::error!("unable to get message: {e}");
-                LighterError::Generic("Unable to handle message".into())
-            })?;
-
-            self.handle_message(msg).await?;
+impl<T> Drop for WsSubscription<T> {
+    fn drop(&mut self) {
+        // the connection may be already gone
+        let _ = self
+            .commands
+            .unbounded_send(Command::Unsubscribe { id: self.id });
+    }
+}
+
+struct Subscription {
+    channel: String,
+    auth: bool,
+    sender: mpsc::UnboundedSender<Frame>,
+    ack: Option<oneshot::Sender<Result<()>>>, // until subscribed
+}
+
+/// Schedule of the keepalive pings of a connection
+struct Keepalive {
+    ping_interval: Duration,
+    pong_timeout: Duration,
+    next_ping_at: Instant,
+    pong_deadline: Option<Instant>, // while waiting for the pong
+}
+
+impl Keepalive {
+    fn new(config: KeepaliveConfig) -> Self {
+        let ping_interval = Duration::from_millis(config.ping_interval);
+        Self {
+            ping_interval,
+            pong_timeout: Duration::from_millis(config.pong_timeout),
+            next_ping_at: Instant::now() + ping_interval,
+            pong_deadline: None,
         }
+    }
 
-        Ok(())
+    /// Starts over, e.g. on a new connection
+    fn reset(&mut self) {
+        self.next_ping_at = Instant::now() + self.ping_interval;
+        self.pong_deadline = None;
     }
 
-    // TODO: complete
-    async fn handle_message(&mut self, msg: Message) -> Result<()> {
-        match msg {
-            Message::Text(data) => {
-                let msg = serde_json::from_str::<Value>(&data).map_err(|e| {
-                    tracing::error!("unable to deserialize msg: {e}");
-                    LighterError::Generic("Unable to deserialize json message".into())
-                })?;
-
-                if let Some(msg_type) = msg.get("type") {
-                    let api_msg = serde_json::from_value::<WsMessage>(msg_type.to_owned())
-                        .map_err(|e| {
-                            tracing::error!("unable to deserialize api msg: {e}");
-                            LighterError::Generic("Unable to deserialize api message".into())
-                        })?;
-
-                    match api_msg {
-                        WsMessage::Connected => self.handle_connected().await?,
-                        WsMessage::SubscribedOrderBook => {
-                            self.handle_subscribed_order_book(msg).await?
-                        }
-                        WsMessage::UpdateOrderBook => self.handle_update_order_book(msg).await?,
-                        WsMessage::SubscribedAccountAll => {
-                            self.handle_subscribed_account_all().await?
-                        }
-                        WsMessage::UpdateAccountAll => self.handle_update_account_all().await?,
-                        WsMessage::Ping => self
-                            .stream
-                            .send(Message::text(json!({"type": "pong"}).to_string()))
-                            .await
-                            .map_err(|e| {
-                                tracing::error!("unable to send `pong`");
-                                LighterError::Generic("Unable to send `pong`".into())
-                            })?,
+    /// When the next ping is due, or the pong is overdue
+    fn wake_at(&self) -> Instant {
+        self.pong_deadline.unwrap_or(self.next_ping_at)
+    }
+}
+
+/// Task owning the connection, it routes the frames to the subscriptions by channel
+struct Connection {
+    ws_url: String,
+    connect_timeout: Option<Duration>,
+    auth: Auth,
+    reconnect: Option<RetryConfig>,
+    keepalive: Option<Keepalive>,
+    last_pong: Arc<AtomicU64>,
+    stream: WsStream,
+    subscriptions: HashMap<u64, Subscription>,
+    channels: HashMap<String, u64>,
+    pending: VecDeque<u64>,         // waiting for the confirmation, in order
+    renew_auth_at: Option<Instant>, // when the authenticated channels need a new token
+}
+
+enum Event {
+    Command(Option<Command>),
+    Message(Option<std::result::Result<Message, tokio_tungstenite::tungstenite::Error>>),
+    RenewAuth,
+    Keepalive,
+}
+
+impl Connection {
+    /// Runs until the client and all the subscriptions are dropped, the connection is lost or
+    /// `shutdown` is cancelled
+    async fn run(
+        mut self,
+        commands: mpsc::UnboundedReceiver<Command>,
+        shutdown: CancellationToken,
+    ) {
+        if shutdown
+            .run_until_cancelled(self.serve(commands))
+            .await
+            .is_none()
+        {
+            tracing::debug!("ws client shut down, closing the connection");
+        }
+        let _ = self.stream.close(None).await;
+    }
+
+    async fn serve(&mut self, mut commands: mpsc::UnboundedReceiver<Command>) {
+        loop {
+            let renew_auth_at = self.renew_auth_at;
+            let keepalive_at = self.keepalive.as_ref().map(Keepalive::wake_at);
+            let event = tokio::select! {
+                command = commands.next() => Event::Command(command),
+                msg = self.stream.next() => Event::Message(msg),
+                _ = sleep_until(renew_auth_at), if renew_auth_at.is_some() => Event::RenewAuth,
+                _ = sleep_until(keepalive_at), if keepalive_at.is_some() => Event::Keepalive,
+            };
+
+            let reason = match event {
+                Event::Command(Some(command)) => {
+                    self.handle_command(command).await;
+                    continue;
+                }
+                Event::Command(None) => break,
+                Event::Message(Some(Ok(Message::Close(_)))) | Event::Message(None) => {
+                    "connection closed".to_string()
+                }
+                Event::Message(Some(Ok(Message::Pong(_)))) => {
+                    self.handle_pong();
+                    continue;
+                }
+                Event::Message(Some(Ok(msg))) => match self.handle_message(msg).await {
+                    Ok(()) => continue,
+                    Err(e) => e.to_string(),
+                },
+                Event::Message(Some(Err(e))) => e.to_string(),
+                Event::RenewAuth => match self.renew_auth().await {
+                    Ok(()) => continue,
+                    Err(e) => {
+                        tracing::warn!("unable to renew the auth of the ws channels: {e}");
+                        format!("auth renewal failed: {e}")
                     }
+                },
+                Event::Keepalive => match self.ping().await {
+                    Ok(()) => continue,
+                    Err(e) => e.to_string(),
+                },
+            };
+
+            if self.subscriptions.is_empty() || self.reconnect.is_none() {
+                tracing::warn!("ws connection lost: {reason}");
+                break;
+            }
+            if let Err(e) = self.reconnect(reason).await {
+                for subscription in self.subscriptions.values() {
+                    let _ = subscription
+                        .sender
+                        .unbounded_send(Err(LighterError::Generic(e.to_string())));
                 }
+                break;
+            }
+        }
+    }
+
+    async fn handle_command(&mut self, command: Command) {
+        match command {
+            Command::Subscribe {
+                id,
+                channel,
+                auth,
+                sender,
+                ack,
+            } => {
+                if self.channels.contains_key(&channel) {
+                    let _ = ack.send(Err(LighterError::Generic(format!(
+                        "Already subscribed to `{channel}`"
+                    ))));
+                    return;
+                }
+
+                self.channels.insert(channel.clone(), id);
+                self.subscriptions.insert(
+                    id,
+                    Subscription {
+                        channel,
+                        auth,
+                        sender,
+                        ack: Some(ack),
+                    },
+                );
+                if let Err(e) = self.send_subscribe(id).await {
+                    self.fail(id, e);
+                }
+            }
+            Command::Unsubscribe { id } => {
+                let Some(subscription) = self.remove(id) else {
+                    return;
+                };
+
+                let request = json!({"type": "unsubscribe", "channel": subscription.channel});
+                if let Err(e) = send(&mut self.stream, Message::text(request.to_string())).await {
+                    tracing::error!("unable to unsubscribe from `{}`: {e}", subscription.channel);
+                }
+            }
+            Command::Resubscribe { id } => {
+                let Some(subscription) = self.subscriptions.get(&id) else {
+                    return;
+                };
+
+                let request = json!({"type": "unsubscribe", "channel": subscription.channel});
+                let result = match send(&mut self.stream, Message::text(request.to_string())).await
+                {
+                    Ok(()) => self.send_subscribe(id).await,
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = result {
+                    self.fail(id, e);
+                }
+            }
+        }
+    }
+
+    /// Routes the frame; it fails only if the connection is not usable anymore
+    async fn handle_message(&mut self, msg: Message) -> Result<()> {
+        let Message::Text(data) = msg else {
+            return Ok(()); // the ws level pings are answered by tungstenite
+        };
+
+        let Ok(value) = serde_json::from_str::<Value>(&data)
+            .inspect_err(|e| tracing::error!("unable to deserialize ws message: {e}"))
+        else {
+            return Ok(());
+        };
+        let Ok(header) = FrameHeader::deserialize(&value)
+            .inspect_err(|e| tracing::error!("unable to deserialize ws message header: {e}"))
+        else {
+            return Ok(());
+        };
+
+        // the errors don't carry the channel, they answer the oldest pending subscription
+        if let Some(error) = header.error {
+            match self.pending.pop_front() {
+                Some(id) => self.fail(id, error.into()),
+                None => tracing::error!("ws error: [{}] {}", error.code, error.message),
+            }
+            return Ok(());
+        }
 
-                Ok(())
+        if header.kind == "ping" {
+            return send(
+                &mut self.stream,
+                Message::text(json!({"type": "pong"}).to_string()),
+            )
+            .await;
+        }
+
+        let Some(channel) = header.channel.map(|v| v.replacen(':', "/", 1)) else {
+            return Ok(());
+        };
+        let Some(subscription) = self
+            .channels
+            .get(&channel)
+            .and_then(|id| self.subscriptions.get_mut(id))
+        else {
+            return Ok(()); // e.g. unsubscribed in the meantime
+        };
+
+        if header.kind.starts_with("subscribed/") {
+            let id = self.channels[&channel];
+            self.pending.retain(|v| *v != id);
+            if let Some(ack) = subscription.ack.take() {
+                let _ = ack.send(Ok(()));
+            }
+        }
+        // if the subscription was dropped, the unsubscribe command is on its way
+        let _ = subscription.sender.unbounded_send(Ok(value));
+
+        Ok(())
+    }
+
+    /// Sends the keepalive ping when it's due; it fails if the previous one is still unanswered
+    /// after the timeout, or the connection is not usable
+    async fn ping(&mut self) -> Result<()> {
+        let Some(keepalive) = self.keepalive.as_mut() else {
+            return Ok(());
+        };
+        if keepalive.pong_deadline.is_some() {
+            return Err(LighterError::Generic(format!(
+                "No pong received within {:?}",
+                keepalive.pong_timeout
+            )));
+        }
+
+        let now = Instant::now();
+        keepalive.pong_deadline = Some(now + keepalive.pong_timeout);
+        keepalive.next_ping_at = now + keepalive.ping_interval;
+        send(&mut self.stream, Message::Ping(Default::default())).await
+    }
+
+    fn handle_pong(&mut self) {
+        if let Some(keepalive) = self.keepalive.as_mut() {
+            keepalive.pong_deadline = None;
+        }
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        self.last_pong
+            .store(now.as_millis() as u64, Ordering::Release);
+    }
+
+    async fn send_subscribe(&mut self, id: u64) -> Result<()> {
+        let Some(subscription) = self.subscriptions.get(&id) else {
+            return Ok(());
+        };
+
+        let mut request = json!({"type": "subscribe", "channel": subscription.channel});
+        if subscription.auth {
+            request["auth"] = self.auth.token()?.into();
+        }
+
+        send(&mut self.stream, Message::text(request.to_string())).await?;
+        self.pending.push_back(id);
+
+        if subscription.auth {
+            if let Some(renewal_in) = self.auth.renewal_in() {
+                let renew_at = Instant::now() + renewal_in;
+                self.renew_auth_at = Some(self.renew_auth_at.map_or(renew_at, |v| v.min(renew_at)));
             }
-            _ => Ok(()),
         }
+
+        Ok(())
+    }
+
+    /// Subscribes again to the authenticated channels with a new token, keeping the
+    /// subscriptions. It fails if the connection is not usable, or the token can't be created.
+    async fn renew_auth(&mut self) -> Result<()> {
+        self.renew_auth_at = None;
+
+        let mut ids = self
+            .subscriptions
+            .iter()
+            .filter(|(_, v)| v.auth && v.ack.is_none())
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        ids.sort();
+        tracing::debug!("renewing the auth of {} ws channels", ids.len());
+
+        for id in ids {
+            let channel = &self.subscriptions[&id].channel;
+            let request = json!({"type": "unsubscribe", "channel": channel});
+            send(&mut self.stream, Message::text(request.to_string())).await?;
+            self.send_subscribe(id).await?;
+        }
+
+        Ok(())
     }
 
-    async fn handle_connected(&mut self) -> Result<()> {
-        let order_book_subs = self.subscriptions.get(&WsSubscriptionType::OrderBooks);
-        let accounts_subs = self.subscriptions.get(&WsSubscriptionType::Accounts);
-
-        if let Some(order_books_subs) = order_book_subs {
-            for market_id in order_books_subs.keys() {
-                let resp =
-                    json!({"type": "subscribe", "channel": format!("order_book/{market_id}")});
-                self.stream
-                    .send(Message::text(resp.to_string()))
-                    .await
-                    .map_err(|e| {
-                        tracing::error!("unable to send `connected` response: {e}");
-                        LighterError::Generic("Unable to send `connected` response: {e}".into())
-                    })?;
+    /// Reconnects with a backoff and subscribes again to all the channels
+    async fn reconnect(&mut self, mut reason: String) -> Result<()> {
+        let policy = self
+            .reconnect
+            .as_ref()
+            .map(RetryConfig::backoff)
+            .ok_or_else(|| LighterError::Config("WS reconnect is disabled".into()))?;
+
+        let start_time = SystemTime::now();
+        let mut attempts = 0;
+        loop {
+            let delay = match policy.should_retry(start_time, attempts) {
+                RetryDecision::Retry { execute_after } => execute_after
+                    .duration_since(SystemTime::now())
+                    .unwrap_or_default(),
+                RetryDecision::DoNotRetry => {
+                    tracing::error!("unable to reconnect after {attempts} attempts: {reason}");
+                    return Err(LighterError::Generic(format!(
+                        "Unable to reconnect after {attempts} attempts: {reason}"
+                    )));
+                }
+            };
+
+            attempts += 1;
+            tracing::warn!(
+                "ws connection lost ({reason}), reconnecting in {delay:?} (attempt {attempts})"
+            );
+            tokio::time::sleep(delay).await;
+
+            // a server that accepts but never answers would hold the reconnect forever
+            match connect(&self.ws_url, self.connect_timeout).await {
+                Ok(stream) => {
+                    self.stream = stream;
+                    if let Some(keepalive) = self.keepalive.as_mut() {
+                        keepalive.reset();
+                    }
+                }
+                Err(e) => {
+                    reason = e.to_string();
+                    continue;
+                }
+            }
+
+            match self.resubscribe().await {
+                Ok(()) => {
+                    tracing::info!(
+                        "ws reconnected, {} subscriptions restored",
+                        self.subscriptions.len()
+                    );
+                    return Ok(());
+                }
+                Err(e) => reason = e.to_string(),
             }
         }
+    }
+
+    /// Subscribes again to all the channels, it fails only if the connection is not usable
+    async fn resubscribe(&mut self) -> Result<()> {
+        self.pending.clear();
+        self.renew_auth_at = None;
 
-        if let Some(accounts_subs) = accounts_subs {
-            for account_id in accounts_subs.keys() {
-                let resp =
-                    json!({"type": "subscribe", "channel": format!("account_all/{account_id}")});
-                self.stream
-                    .send(Message::text(resp.to_string()))
-                    .await
-                    .map_err(|e| {
-                        tracing::error!("unable to send `connected` response: {e}");
-                        LighterError::Generic("Unable to send `connected` response: {e}".into())
-                    })?;
+        let mut ids = self.subscriptions.keys().copied().collect::<Vec<_>>();
+        ids.sort();
+        for id in ids {
+            match self.send_subscribe(id).await {
+                Ok(()) => {}
+                Err(e @ LighterError::WebSocket(_)) => return Err(e),
+                Err(e) => self.fail(id, e),
             }
         }
 
         Ok(())
     }
 
-    async fn handle_subscribed_order_book(&mut self, msg: Value) -> Result<()> {
-        let channel = msg["channel"].to_string();
-        let parts: Vec<&str> = channel.split(':').collect();
-
-        let market_id = parts.get(1).ok_or_else(|| {
-            tracing::error!("Unable to get market_id");
-            LighterError::Generic("Unable to get market_id".into())
-        })?;
-
-        if let Some(subs) = self.subscriptions.get_mut(&WsSubscriptionType::OrderBooks) {
-            // set the state
-            let state_data = msg
-                .get("order_book")
-                .ok_or_else(|| {
-                    tracing::error!("unable to get order_book from message");
-                    LighterError::Generic("Unable to get `order_book` from message".into())
-                })?
-                .to_owned();
-            subs.insert(market_id.to_string(), Some(state_data.clone()));
-            if let Some(handler) = &self.on_order_book_update {
-                handler(market_id.to_string(), state_data)
+    fn remove(&mut self, id: u64) -> Option<Subscription> {
+        let subscription = self.subscriptions.remove(&id)?;
+        self.channels.remove(&subscription.channel);
+        self.pending.retain(|v| *v != id);
+        Some(subscription)
+    }
+
+    /// Ends the subscription with the error
+    fn fail(&mut self, id: u64, error: LighterError) {
+        let Some(subscription) = self.remove(id) else {
+            return;
+        };
+
+        tracing::error!("subscription to `{}` failed: {error}", subscription.channel);
+        match subscription.ack {
+            Some(ack) => {
+                let _ = ack.send(Err(error));
+            }
+            None => {
+                let _ = subscription.sender.unbounded_send(Err(error));
             }
         }
-        todo!()
     }
+}
 
-    async fn handle_update_order_book(&mut self, msg: Value) -> Result<()> {
-        todo!()
+/// Sleeps until `deadline`, forever without one
+async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
     }
+}
 
-    async fn handle_subscribed_account_all(&mut self) -> Result<()> {
-        todo!()
+/// Connects, waiting for the server to greet with `connected` within `timeout`
+async fn connect(ws_url: &str, timeout: Option<Duration>) -> Result<WsStream> {
+    match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, handshake(ws_url))
+            .await
+            .map_err(|_| {
+                LighterError::Generic(format!("Connection timed out after {timeout:?}"))
+            })?,
+        None => handshake(ws_url).await,
     }
+}
 
-    async fn handle_update_account_all(&mut self) -> Result<()> {
-        todo!()
+async fn handshake(ws_url: &str) -> Result<WsStream> {
+    let (mut stream, _) = connect_async(ws_url)
+        .await
+        .map_err(|e| LighterError::WebSocket(Box::new(e)))?;
+
+    while let Some(msg) = stream.next().await {
+        let msg = msg.map_err(|e| LighterError::WebSocket(Box::new(e)))?;
+        if let Message::Text(data) = msg {
+            let header = serde_json::from_str::<FrameHeader>(&data)?;
+            match header.kind.as_str() {
+                "connected" => return Ok(stream),
+                kind => tracing::debug!("ignoring `{kind}` message while connecting"),
+            }
+        }
     }
 
-    async fn handle_ping(&mut self) -> Result<()> {
-        todo!()
+    Err(LighterError::Generic(
+        "Connection closed before being connected".into(),
+    ))
+}
+
+async fn send(stream: &mut WsStream, msg: Message) -> Result<()> {
+    stream
+        .send(msg)
+        .await
+        .inspect_err(|e| tracing::error!("unable to send ws message: {e}"))
+        .map_err(|e| LighterError::WebSocket(Box::new(e)))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::accept_async;
+
+    pub(crate) fn mock_config(ws_url: String) -> LighterConfig {
+        LighterConfig {
+            ws_url,
+            ws_reconnect: None,
+            ..LighterConfig::new()
+                .with_api_key_private(
+                    "01db9eed031d59d6bd0ee00ee5a7dc1f62087bf217b51caea57eb6e17a02c49e0a748d2f155a2f60",
+                )
+                .with_account_index(28)
+                .with_api_key_index(2)
+        }
     }
 
-    fn update_order_book_state(&mut self, market_id: String, order_book: Value) -> Result<()> {
-        let existing_asks = self
-            .subscriptions
-            .get(&WsSubscriptionType::OrderBooks)
+    /// Serves a single connection, see `serve`
+    pub(crate) async fn mock_server(script: Vec<Value>) -> String {
+        mock_server_reconnect(vec![script]).await
+    }
+
+    /// Serves one connection for each script, then it stops listening. Each connection is
+    /// served in the background, so that a stalled one doesn't hold the next one back.
+    async fn mock_server_reconnect(connections: Vec<Vec<Value>>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            for script in connections {
+                let (socket, _) = listener.accept().await.unwrap();
+                tokio::spawn(serve(socket, script));
+            }
+        });
+
+        format!("ws://{addr}")
+    }
+
+    /// Sends `connected`, then runs the script: `{"expect": {..}}` reads a request and checks its
+    /// `type` and `channel`, `{"type": "ping"}` waits for the pong, `{"closed": true}` waits for
+    /// the client to close the connection, `{"pong": true}` answers a keepalive ping,
+    /// `{"stall": true}` stops reading (so the pings are never answered) and keeps the connection
+    /// open, anything else is sent as is.
+    async fn serve(socket: TcpStream, script: Vec<Value>) {
+        let mut stream = accept_async(socket).await.unwrap();
+        stream
+            .send(Message::text(json!({"type": "connected"}).to_string()))
+            .await
             .unwrap();
-        let existing_asks = existing_asks[&market_id].clone().unwrap();
-        let existing_asks = existing_asks.as_array().unwrap();
-        self.update_orders(
-            order_book["asks"].clone().as_array().unwrap(),
-            existing_asks,
+
+        for step in script {
+            if let Some(expected) = step.get("expect") {
+                let request = stream.next().await.unwrap().unwrap().into_text().unwrap();
+                let request = serde_json::from_str::<Value>(&request).unwrap();
+                assert_eq!(request["type"], expected["type"]);
+                assert_eq!(request["channel"], expected["channel"]);
+                if request["type"] == "subscribe"
+                    && request["channel"]
+                        .as_str()
+                        .unwrap()
+                        .starts_with("account_all")
+                {
+                    assert!(!request["auth"].as_str().unwrap().is_empty());
+                }
+            } else if step.get("stall").is_some() {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+                return;
+            } else if step.get("pong").is_some() {
+                loop {
+                    let msg = stream.next().await.unwrap().unwrap();
+                    if msg.is_ping() {
+                        break;
+                    }
+                }
+                // the pong is queued when reading the ping
+                stream.flush().await.unwrap();
+            } else if step.get("closed").is_some() {
+                let msg = stream.next().await;
+                assert!(matches!(msg, Some(Ok(Message::Close(_))) | None), "{msg:?}");
+                return;
+            } else if step["type"] == "ping" {
+                stream.send(Message::text(step.to_string())).await.unwrap();
+                let pong = stream.next().await.unwrap().unwrap().into_text().unwrap();
+                assert_eq!(
+                    serde_json::from_str::<Value>(&pong).unwrap()["type"],
+                    "pong"
+                );
+            } else {
+                stream.send(Message::text(step.to_string())).await.unwrap();
+            }
+        }
+        stream.close(None).await.unwrap();
+    }
+
+    pub(crate) fn expect(kind: &str, channel: &str) -> Value {
+        json!({"expect": {"type": kind, "channel": channel}})
+    }
+
+    #[cfg(feature = "signer")]
+    fn position(market_id: i32, position: &str) -> Value {
+        serde_json::to_value(AccountPosition {
+            market_id,
+            position: position.into(),
+            open_order_count: 2,
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    pub(crate) fn order_book(kind: &str, market_id: i32, price: &str, offset: i64) -> Value {
+        json!({
+            "type": format!("{kind}/order_book"),
+            "channel": format!("order_book:{market_id}"),
+            "order_book": {
+                "asks": [{"price": price, "size": "1.0"}],
+                "bids": [],
+                "offset": offset,
+            },
+        })
+    }
+
+    #[cfg(feature = "signer")]
+    #[tokio::test]
+    async fn test_subscribe_account() {
+        let ws_url = mock_server(vec![
+            expect("subscribe", "account_all/28"),
+            json!({
+                "type": "subscribed/account_all",
+                "channel": "account_all:28",
+                "account": 28,
+                "positions": {"0": position(0, "1.5"), "1": position(1, "0")},
+                "shares": [],
+            }),
+            json!({"type": "ping"}),
+            json!({
+                "type": "update/account_all",
+                "channel": "account_all:28",
+                "account": 28,
+                "positions": {"0": position(0, "2.0")},
+            }),
+        ])
+        .await;
+        let client = WsClient::new(&mock_config(ws_url)).unwrap();
+
+        let updates = client
+            .subscribe_account(28)
+            .await
+            .unwrap()
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(updates.len(), 2);
+
+        let snapshot = updates[0].as_ref().unwrap();
+        assert!(snapshot.snapshot);
+        assert_eq!(snapshot.account_index, 28);
+        assert_eq!(snapshot.positions.len(), 2);
+        assert_eq!(snapshot.positions[0].market_id, 0);
+        assert_eq!(snapshot.positions[0].position, "1.5");
+        assert_eq!(snapshot.positions[0].open_order_count, 2);
+        assert_eq!(snapshot.positions[1].market_id, 1);
+
+        let update = updates[1].as_ref().unwrap();
+        assert!(!update.snapshot);
+        assert_eq!(update.positions.len(), 1);
+        assert_eq!(update.positions[0].position, "2.0");
+    }
+
+    #[cfg(feature = "signer")]
+    #[tokio::test]
+    async fn test_subscribe_notifications() {
+        let ws_url = mock_server(vec![
+            expect("subscribe", "notification/28"),
+            json!({
+                "type": "subscribed/notification",
+                "channel": "notification:28",
+                "notifs": [{
+                    "id": "n1",
+                    "kind": "liquidation",
+                    "account_index": 28,
+                    "created_at": "2024-01-02T03:04:05Z",
+                    "content": {"market_index": 1},
+                    "ack": false,
+                }],
+            }),
+            json!({"type": "update/notification", "channel": "notification:28", "notifs": []}),
+        ])
+        .await;
+        let client = WsClient::new(&mock_config(ws_url)).unwrap();
+
+        let updates = client
+            .subscribe_notifications(28)
+            .await
+            .unwrap()
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(updates.len(), 2);
+
+        let snapshot = updates[0].as_ref().unwrap();
+        assert!(snapshot.snapshot);
+        assert_eq!(snapshot.account_index, 28);
+        assert_eq!(snapshot.notifications[0].id, "n1");
+        assert!(updates[1].as_ref().unwrap().notifications.is_empty());
+
+        // read only
+        let client = WsClient::new(&LighterConfig::new()).unwrap();
+        let res = client.subscribe_notifications(28).await;
+        assert!(matches!(res, Err(LighterError::Config(_))));
+    }
+
+    #[cfg(feature = "signer")]
+    #[tokio::test]
+    async fn test_subscribe_account_renews_auth() {
+        let snapshot = |position| {
+            json!({
+                "type": "subscribed/account_all",
+                "channel": "account_all:28",
+                "account": 28,
+                "positions": {"0": self::position(0, position)},
+            })
+        };
+        let ws_url = mock_server(vec![
+            expect("subscribe", "account_all/28"),
+            snapshot("1.5"),
+            // the token expires in 2s, it's renewed 1s before
+            expect("unsubscribe", "account_all/28"),
+            expect("subscribe", "account_all/28"),
+            snapshot("2.0"),
+            json!({
+                "type": "update/account_all",
+                "channel": "account_all:28",
+                "account": 28,
+                "positions": {"0": position(0, "2.5")},
+            }),
+        ])
+        .await;
+        let config = mock_config(ws_url)
+            .with_auth_token_ttl(2)
+            .with_auth_refresh_margin(1);
+        let client = WsClient::new(&config).unwrap();
+
+        let updates = client
+            .subscribe_account(28)
+            .await
+            .unwrap()
+            .map(|v| v.unwrap())
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(
+            updates
+                .iter()
+                .map(|v| (v.snapshot, v.positions[0].position.as_str()))
+                .collect::<Vec<_>>(),
+            vec![(true, "1.5"), (true, "2.0"), (false, "2.5")]
+        );
+    }
+
+    #[cfg(feature = "signer")]
+    #[tokio::test]
+    async fn test_subscribe_account_invalid_auth() {
+        let ws_url = mock_server(vec![
+            expect("subscribe", "account_all/28"),
+            json!({"error": {"code": 20013, "message": "invalid auth"}}),
+        ])
+        .await;
+        let client = WsClient::new(&mock_config(ws_url)).unwrap();
+
+        let err = client.subscribe_account(28).await.err().unwrap();
+        assert!(matches!(err, LighterError::Auth(_)), "{err}");
+    }
+
+    #[cfg(feature = "signer")]
+    #[tokio::test]
+    async fn test_subscribe_account_reconnect() {
+        let snapshot = |position| {
+            json!({
+                "type": "subscribed/account_all",
+                "channel": "account_all:28",
+                "account": 28,
+                "positions": {"0": self::position(0, position)},
+            })
+        };
+        let ws_url = mock_server_reconnect(vec![
+            vec![expect("subscribe", "account_all/28"), snapshot("1.5")],
+            vec![
+                expect("subscribe", "account_all/28"),
+                snapshot("2.0"),
+                json!({
+                    "type": "update/account_all",
+                    "channel": "account_all:28",
+                    "account": 28,
+                    "positions": {"0": position(0, "2.5")},
+                }),
+            ],
+        ])
+        .await;
+        let config = LighterConfig {
+            ws_reconnect: Some(RetryConfig {
+                max_retries: 2,
+                min_retry_interval: 10,
+                max_retry_interval: 50,
+                ..RetryConfig::default()
+            }),
+            ..mock_config(ws_url)
+        };
+        let client = WsClient::new(&config).unwrap();
+
+        let updates = client
+            .subscribe_account(28)
+            .await
+            .unwrap()
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(updates.len(), 4);
+
+        // the state is sent again after the reconnect
+        let positions = updates[..3]
+            .iter()
+            .map(|v| v.as_ref().unwrap().positions[0].position.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(positions, ["1.5", "2.0", "2.5"]);
+
+        // the server is gone
+        let err = updates[3].as_ref().err().unwrap();
+        assert!(
+            err.to_string()
+                .contains("Unable to reconnect after 2 attempts"),
+            "{err}"
         );
+    }
 
-        let existing_bids = self
-            .subscriptions
-            .get(&WsSubscriptionType::OrderBooks)
-            .unwrap();
-        let existing_bids = existing_bids[&market_id].clone().unwrap();
-        let existing_bids = existing_bids.as_array().unwrap();
-        self.update_orders(
-            order_book["bids"].clone().as_array().unwrap(),
-            existing_bids,
+    #[tokio::test]
+    async fn test_reconnect_times_out() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let ws_url = format!("ws://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            tokio::spawn(serve(
+                socket,
+                vec![
+                    expect("subscribe", "order_book/0"),
+                    order_book("subscribed", 0, "3000", 1),
+                ],
+            ));
+            // then accepted, but the handshake is never answered
+            let mut sockets = Vec::new();
+            loop {
+                sockets.push(listener.accept().await.unwrap());
+            }
+        });
+        let config = LighterConfig {
+            timeout_secs: Some(1),
+            ws_reconnect: Some(RetryConfig {
+                max_retries: 2,
+                min_retry_interval: 10,
+                max_retry_interval: 50,
+                ..RetryConfig::default()
+            }),
+            ..mock_config(ws_url)
+        };
+        let client = WsClient::new(&config).unwrap();
+
+        let updates = tokio::time::timeout(Duration::from_secs(10), async {
+            client
+                .subscribe_order_book(0)
+                .await
+                .unwrap()
+                .collect::<Vec<_>>()
+                .await
+        })
+        .await
+        .unwrap();
+        assert_eq!(updates.len(), 2);
+        assert!(updates[0].is_ok());
+        let err = updates[1].as_ref().err().unwrap().to_string();
+        assert!(
+            err.contains("Unable to reconnect after 2 attempts"),
+            "{err}"
         );
+        assert!(err.contains("timed out"), "{err}");
+    }
 
-        Ok(())
+    #[tokio::test]
+    async fn test_subscribe_order_books_single_connection() {
+        let ws_url = mock_server(vec![
+            expect("subscribe", "order_book/0"),
+            order_book("subscribed", 0, "3000", 1),
+            expect("subscribe", "order_book/1"),
+            order_book("subscribed", 1, "60000", 1),
+            order_book("update", 1, "60001", 2),
+            order_book("update", 0, "3001", 2),
+        ])
+        .await;
+        let client = WsClient::new(&mock_config(ws_url)).unwrap();
+
+        let first = client.subscribe_order_book(0).await.unwrap();
+        let second = client.subscribe_order_book(1).await.unwrap();
+        assert_eq!(first.channel(), "order_book/0");
+        assert_ne!(first.id(), second.id());
+
+        for (subscription, market_id, prices) in [
+            (first, 0, ["3000", "3001"]),
+            (second, 1, ["60000", "60001"]),
+        ] {
+            let updates = subscription.map(|v| v.unwrap()).collect::<Vec<_>>().await;
+            assert_eq!(updates.len(), 2);
+            assert!(updates.iter().all(|v| v.market_id == market_id));
+            assert!(updates[0].snapshot && !updates[1].snapshot);
+            assert_eq!(
+                updates
+                    .iter()
+                    .map(|v| v.order_book.asks[0].price.as_str())
+                    .collect::<Vec<_>>(),
+                prices
+            );
+        }
     }
 
-    fn update_orders(
-        &mut self,
-        new_orders: &Vec<Value>,
-        existing_orders: &Vec<Value>,
-    ) -> Result<()> {
-        todo!()
+    #[tokio::test]
+    async fn test_unsubscribe_on_drop() {
+        let ws_url = mock_server(vec![
+            expect("subscribe", "order_book/0"),
+            order_book("subscribed", 0, "3000", 1),
+            expect("subscribe", "trade/0"),
+            json!({"type": "subscribed/trade", "channel": "trade:0", "trades": []}),
+            expect("unsubscribe", "order_book/0"),
+            json!({
+                "type": "update/trade",
+                "channel": "trade:0",
+                "trades": [Trade { market_id: 0, price: "3000".into(), ..Default::default() }],
+            }),
+        ])
+        .await;
+        let client = WsClient::new(&mock_config(ws_url)).unwrap();
+
+        let order_book = client.subscribe_order_book(0).await.unwrap();
+        let trades = client.subscribe_trades(0).await.unwrap();
+
+        // already subscribed
+        assert!(client.subscribe_trades(0).await.is_err());
+
+        drop(order_book);
+        let updates = trades.map(|v| v.unwrap()).collect::<Vec<_>>().await;
+        assert_eq!(updates.len(), 2);
+        assert!(updates[0].trades.is_empty());
+        assert_eq!(updates[1].trades[0].price, "3000");
+    }
+
+    pub(crate) fn trades(kind: &str, market_id: i32, trade_ids: &[i64]) -> Value {
+        let trades = trade_ids
+            .iter()
+            .map(|&trade_id| Trade {
+                trade_id,
+                market_id,
+                price: "3000".into(),
+                size: "0.1".into(),
+                is_maker_ask: trade_id % 2 == 0,
+                timestamp: 1_700_000_000_000 + trade_id,
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        json!({
+            "type": format!("{kind}/trade"),
+            "channel": format!("trade:{market_id}"),
+            "trades": trades,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_trades() {
+        let script = vec![
+            trades("subscribed", 1, &[5, 4]),
+            trades("update", 1, &[6, 7]),
+            trades("update", 1, &[7, 8]),
+            trades("subscribed", 1, &[8, 3]), // sent again after a reconnect
+            trades("update", 1, &[9]),
+        ];
+
+        // decoded and checked as they are received
+        let mut trade_ids = TradeIds::default();
+        let out_of_order = script
+            .iter()
+            .map(|v| trade_ids.check(&serde_json::from_value(v.clone()).unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(out_of_order, vec![vec![], vec![], vec![7], vec![], vec![]]);
+
+        let ws_url = mock_server(
+            std::iter::once(expect("subscribe", "trade/1"))
+                .chain(script)
+                .collect(),
+        )
+        .await;
+        let client = WsClient::new(&mock_config(ws_url)).unwrap();
+
+        let updates = client
+            .subscribe_trades(1)
+            .await
+            .unwrap()
+            .map(|v| v.unwrap())
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(updates.len(), 5);
+        assert!(updates.iter().all(|v| v.market_id == 1));
+        assert!(updates[0].snapshot);
+
+        // the out of order trades are still yielded
+        let trade = &updates[2].trades[0];
+        assert_eq!(trade.trade_id, 7);
+        assert_eq!((trade.price.as_str(), trade.size.as_str()), ("3000", "0.1"));
+        assert_eq!(trade.timestamp, 1_700_000_000_007);
+        assert!(!trade.is_maker_ask);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown() {
+        let ws_url = mock_server(vec![
+            expect("subscribe", "order_book/0"),
+            order_book("subscribed", 0, "3000", 1),
+            json!({"closed": true}),
+        ])
+        .await;
+        let client = WsClient::new(&mock_config(ws_url)).unwrap();
+
+        let mut subscription = client.subscribe_order_book(0).await.unwrap();
+        assert!(subscription.next().await.unwrap().is_ok());
+
+        // the connection task ends, and so do the subscriptions
+        tokio::time::timeout(Duration::from_secs(1), client.clone().shutdown())
+            .await
+            .unwrap();
+        assert!(subscription.next().await.is_none());
+        let res = client.subscribe_order_book(1).await;
+        assert!(matches!(res, Err(LighterError::Generic(e)) if e.contains("shut down")));
+    }
+
+    #[tokio::test]
+    async fn test_keepalive_reconnects_dead_connection() {
+        // the first connection stops answering the pings, without being closed
+        let ws_url = mock_server_reconnect(vec![
+            vec![
+                expect("subscribe", "order_book/0"),
+                order_book("subscribed", 0, "3000", 1),
+                json!({"stall": true}),
+            ],
+            vec![
+                expect("subscribe", "order_book/0"),
+                order_book("subscribed", 0, "3001", 7),
+                json!({"stall": true}),
+            ],
+        ])
+        .await;
+        let config = LighterConfig {
+            ws_reconnect: Some(RetryConfig {
+                max_retries: 2,
+                min_retry_interval: 10,
+                max_retry_interval: 50,
+                ..RetryConfig::default()
+            }),
+            ..mock_config(ws_url)
+        }
+        .with_ws_keepalive(KeepaliveConfig {
+            ping_interval: 50,
+            pong_timeout: 100,
+        });
+        let client = WsClient::new(&config).unwrap();
+
+        let mut subscription = client.subscribe_order_book(0).await.unwrap();
+        let update = subscription.next().await.unwrap().unwrap();
+        assert_eq!(update.order_book.offset, 1);
+
+        // subscribed again on the new connection
+        let update = tokio::time::timeout(Duration::from_secs(5), subscription.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert!(update.snapshot);
+        assert_eq!(update.order_book.offset, 7);
+        assert_eq!(client.last_pong(), None);
+    }
+
+    #[tokio::test]
+    async fn test_keepalive_last_pong() {
+        let ws_url = mock_server(vec![
+            expect("subscribe", "order_book/0"),
+            order_book("subscribed", 0, "3000", 1),
+            json!({"pong": true}),
+            json!({"closed": true}),
+        ])
+        .await;
+        let config = mock_config(ws_url).with_ws_keepalive(KeepaliveConfig {
+            ping_interval: 50,
+            pong_timeout: 1000,
+        });
+        let client = WsClient::new(&config).unwrap();
+        assert_eq!(client.last_pong(), None);
+
+        let before = SystemTime::now();
+        let mut subscription = client.subscribe_order_book(0).await.unwrap();
+        assert!(subscription.next().await.unwrap().is_ok());
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while client.last_pong().is_none() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert!(client.last_pong().unwrap() >= before);
+
+        client.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_token() {
+        let token = CancellationToken::new();
+        let client = WsClient::new(&mock_config("ws://127.0.0.1:1".into()))
+            .unwrap()
+            .with_shutdown_token(token.clone());
+        token.cancel();
+        let res = client.subscribe_order_book(0).await;
+        assert!(matches!(res, Err(LighterError::Generic(e)) if e.contains("shut down")));
+    }
+
+    #[tokio::test]
+    async fn test_resubscribe() {
+        let ws_url = mock_server(vec![
+            expect("subscribe", "order_book/0"),
+            order_book("subscribed", 0, "3000", 1),
+            expect("unsubscribe", "order_book/0"),
+            expect("subscribe", "order_book/0"),
+            order_book("subscribed", 0, "3001", 7),
+        ])
+        .await;
+        let client = WsClient::new(&mock_config(ws_url)).unwrap();
+
+        let mut subscription = client.subscribe_order_book(0).await.unwrap();
+        assert_eq!(
+            subscription
+                .next()
+                .await
+                .unwrap()
+                .unwrap()
+                .order_book
+                .offset,
+            1
+        );
+
+        subscription.resubscribe().unwrap();
+        let snapshot = subscription.next().await.unwrap().unwrap();
+        assert!(snapshot.snapshot);
+        assert_eq!(snapshot.order_book.offset, 7);
     }
 }
@@ -0,0 +1,256 @@
+use std::{
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    apis::{self, configuration::Configuration},
+    client::tasks::BackgroundTasks,
+    config::LighterConfig,
+    models::Status,
+    Result,
+};
+
+/// Offset between the server clock and the local one, so that the deadlines sent to the server
+/// (order expiries, auth token expirations) don't depend on the local clock drift.
+///
+/// The offset is 0 until the clock gets synced, see [`crate::HttpClient::sync_time`].
+#[derive(Debug, Default)]
+pub struct ServerClock {
+    offset_ms: AtomicI64, // server time - local time
+    synced_at: Mutex<Option<Instant>>,
+}
+
+impl ServerClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the current time of the server
+    pub fn server_now(&self) -> DateTime<Utc> {
+        Utc::now() + self.offset()
+    }
+
+    pub fn offset(&self) -> chrono::Duration {
+        chrono::Duration::milliseconds(self.offset_ms.load(Ordering::Acquire))
+    }
+
+    pub fn set_offset(&self, offset: chrono::Duration) {
+        self.offset_ms
+            .store(offset.num_milliseconds(), Ordering::Release);
+        *self.synced_at.lock().unwrap_or_else(|e| e.into_inner()) = Some(Instant::now());
+    }
+
+    /// Returns `true` if the clock was never synced or it was synced more than `max_age` ago
+    pub fn is_stale(&self, max_age: Duration) -> bool {
+        self.synced_at
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_none_or(|synced_at| synced_at.elapsed() > max_age)
+    }
+}
+
+/// Syncs a `ServerClock` with the time returned by the status endpoint
+#[derive(Debug)]
+pub(crate) struct TimeSync {
+    config: Configuration,
+    clock: Arc<ServerClock>,
+    max_skew: Option<chrono::Duration>, // a warning is logged when the offset exceeds it
+}
+
+impl TimeSync {
+    #[cfg(test)]
+    pub fn new(config: &LighterConfig) -> Result<Self> {
+        Ok(Self::new_with_configuration(
+            config,
+            Configuration::try_from(config)?,
+        ))
+    }
+
+    /// Same as [`TimeSync::new`], fetching the server time with `configuration`
+    pub fn new_with_configuration(config: &LighterConfig, configuration: Configuration) -> Self {
+        Self {
+            config: configuration,
+            clock: config.server_clock.clone(),
+            max_skew: config
+                .max_clock_skew_ms
+                .map(|max_skew| chrono::Duration::milliseconds(max_skew as i64)),
+        }
+    }
+
+    pub fn warns_on_skew(&self) -> bool {
+        self.max_skew.is_some()
+    }
+
+    #[cfg(feature = "signer")]
+    pub fn clock(&self) -> &Arc<ServerClock> {
+        &self.clock
+    }
+
+    /// Fetches the server time and updates the offset
+    pub async fn sync(&self) -> Result<chrono::Duration> {
+        let offset = self.measure().await?;
+        self.clock.set_offset(offset);
+        tracing::debug!("server clock offset: {}ms", offset.num_milliseconds());
+
+        Ok(offset)
+    }
+
+    /// Fetches the server time and returns its offset from the local clock, halving the round
+    /// trip to account for the latency. The offset of the clock is left as is.
+    pub async fn measure(&self) -> Result<chrono::Duration> {
+        let sent_at = Utc::now();
+        let status = apis::root_api::status(&self.config)
+            .await
+            .inspect_err(|e| tracing::error!("unable to call `status`: {e}"))?;
+        let received_at = Utc::now();
+
+        let server_ms = status_timestamp_ms(&status);
+        let local_ms = sent_at.timestamp_millis()
+            + (received_at.timestamp_millis() - sent_at.timestamp_millis()) / 2;
+
+        let offset = chrono::Duration::milliseconds(server_ms - local_ms);
+        if let Some(max_skew) = self.max_skew {
+            if offset.abs() > max_skew {
+                tracing::warn!(
+                    "the local clock is {}ms off the server one, the auth tokens and the order \
+                     expiries may be rejected; sync it or call `HttpClient::sync_time`",
+                    offset.num_milliseconds()
+                );
+            }
+        }
+
+        Ok(offset)
+    }
+}
+
+/// Returns the server time of the status (ms)
+pub(crate) fn status_timestamp_ms(status: &Status) -> i64 {
+    // the timestamp is in seconds, but accept millis too
+    if status.timestamp < 10_000_000_000 {
+        status.timestamp * 1000
+    } else {
+        status.timestamp
+    }
+}
+
+/// Syncs the clock every `interval` in the background until the shutdown of `tasks`, failures
+/// are logged and retried at the next round
+pub(crate) fn spawn_sync(
+    sync: Arc<TimeSync>,
+    interval: Duration,
+    tasks: &BackgroundTasks,
+) -> tokio::task::JoinHandle<()> {
+    let shutdown = tasks.token().clone();
+    tasks.spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        shutdown
+            .run_until_cancelled(async {
+                loop {
+                    ticker.tick().await;
+                    if let Err(e) = sync.sync().await {
+                        tracing::warn!("unable to sync the server clock: {e}");
+                    }
+                }
+            })
+            .await;
+    })
+}
+
+/// Measures the clock skew once in the background, to log a warning if it exceeds the max
+/// skew of the config. Failures are logged too.
+pub(crate) fn spawn_skew_check(sync: Arc<TimeSync>, tasks: &BackgroundTasks) {
+    let shutdown = tasks.token().clone();
+    tasks.spawn(async move {
+        if let Some(Err(e)) = shutdown.run_until_cancelled(sync.measure()).await {
+            tracing::warn!("unable to check the clock skew: {e}");
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_clock_offset() {
+        let clock = ServerClock::new();
+        assert!(clock.is_stale(Duration::from_secs(60)));
+        assert_eq!(clock.offset(), chrono::Duration::zero());
+
+        clock.set_offset(chrono::Duration::minutes(10));
+        assert!(!clock.is_stale(Duration::from_secs(60)));
+        let drift = clock.server_now() - Utc::now() - chrono::Duration::minutes(10);
+        assert!(drift.num_milliseconds().abs() < 100);
+    }
+
+    #[tokio::test]
+    async fn test_time_sync() {
+        let mut server = mockito::Server::new_async().await;
+        let server_time = Utc::now() - chrono::Duration::minutes(5);
+        server
+            .mock("GET", "/")
+            .with_header("content-type", "application/json")
+            .with_body(format!(
+                r#"{{"status":200,"network_id":1,"timestamp":{}}}"#,
+                server_time.timestamp()
+            ))
+            .create_async()
+            .await;
+
+        let config = LighterConfig {
+            base_url: server.url(),
+            ..Default::default()
+        };
+        let sync = TimeSync::new(&config).unwrap();
+
+        let offset = sync.sync().await.unwrap();
+        // the server timestamp has a resolution of 1s
+        assert!(
+            (offset + chrono::Duration::minutes(5))
+                .num_milliseconds()
+                .abs()
+                < 1100
+        );
+        assert_eq!(config.server_clock.offset(), offset);
+    }
+
+    #[tokio::test]
+    async fn test_time_measure() {
+        let mut server = mockito::Server::new_async().await;
+        let server_time = Utc::now() + chrono::Duration::minutes(2);
+        server
+            .mock("GET", "/")
+            .with_header("content-type", "application/json")
+            .with_body(format!(
+                r#"{{"status":200,"network_id":1,"timestamp":{}}}"#,
+                server_time.timestamp()
+            ))
+            .create_async()
+            .await;
+
+        let config = LighterConfig {
+            base_url: server.url(),
+            ..Default::default()
+        }
+        .with_max_clock_skew(1000);
+        let sync = TimeSync::new(&config).unwrap();
+        assert!(sync.warns_on_skew());
+
+        let offset = sync.measure().await.unwrap();
+        assert!(
+            (offset - chrono::Duration::minutes(2))
+                .num_milliseconds()
+                .abs()
+                < 1100
+        );
+        // only measured
+        assert_eq!(config.server_clock.offset(), chrono::Duration::zero());
+        assert!(config.server_clock.is_stale(Duration::from_secs(60)));
+    }
+}
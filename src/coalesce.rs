@@ -0,0 +1,216 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use http::{Extensions, HeaderMap, HeaderName, HeaderValue, StatusCode, Version};
+use reqwest::{Method, Request, Response, ResponseBuilderExt};
+use reqwest_middleware::{Middleware, Next};
+use tokio::sync::OnceCell;
+use url::Url;
+
+use crate::circuit_breaker::CircuitOpen;
+
+/// URL and headers of a request: the requests sent with different credentials aren't coalesced
+type RequestKey = (String, Vec<(HeaderName, HeaderValue)>);
+
+type Outcome = std::result::Result<BufferedResponse, Arc<reqwest_middleware::Error>>;
+
+/// Shares a single in-flight request between the concurrent identical `GET` requests (same URL,
+/// query and headers): the first one is sent, the others wait for its response and get a copy of
+/// it. Nothing is cached, a request sent once the previous one completed goes to the network.
+///
+/// See [`LighterConfig::with_request_coalescing`](crate::LighterConfig::with_request_coalescing).
+#[derive(Debug, Default)]
+pub struct RequestCoalescer {
+    in_flight: Mutex<HashMap<RequestKey, Arc<OnceCell<Outcome>>>>,
+}
+
+impl RequestCoalescer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the cell of the in-flight request, creating it if there is none
+    fn join(&self, key: &RequestKey) -> Arc<OnceCell<Outcome>> {
+        let mut in_flight = self.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+        in_flight.entry(key.clone()).or_default().clone()
+    }
+
+    /// Forgets the completed request, the next identical one is sent again
+    fn complete(&self, key: &RequestKey, cell: &Arc<OnceCell<Outcome>>) {
+        let mut in_flight = self.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+        if in_flight.get(key).is_some_and(|v| Arc::ptr_eq(v, cell)) {
+            in_flight.remove(key);
+        }
+    }
+}
+
+/// Response read in full, so that it can be handed to every waiter
+#[derive(Debug)]
+struct BufferedResponse {
+    status: StatusCode,
+    version: Version,
+    url: Url,
+    headers: HeaderMap,
+    body: Vec<u8>,
+}
+
+impl BufferedResponse {
+    async fn read(resp: Response) -> reqwest_middleware::Result<Self> {
+        Ok(Self {
+            status: resp.status(),
+            version: resp.version(),
+            url: resp.url().clone(),
+            headers: resp.headers().clone(),
+            body: resp.bytes().await?.to_vec(),
+        })
+    }
+
+    fn to_response(&self) -> reqwest_middleware::Result<Response> {
+        let mut builder = http::Response::builder()
+            .status(self.status)
+            .version(self.version)
+            .url(self.url.clone());
+        if let Some(headers) = builder.headers_mut() {
+            headers.extend(self.headers.clone());
+        }
+
+        let resp = builder
+            .body(self.body.clone())
+            .map_err(|e| reqwest_middleware::Error::Middleware(e.into()))?;
+        Ok(resp.into())
+    }
+}
+
+/// Error of the shared request, given to each waiter. `CircuitOpen` is kept as is so that it's
+/// still converted into `LighterError::CircuitOpen`.
+fn shared_error(error: &Arc<reqwest_middleware::Error>) -> reqwest_middleware::Error {
+    if let reqwest_middleware::Error::Middleware(e) = &**error {
+        if let Some(open) = e.downcast_ref::<CircuitOpen>() {
+            return reqwest_middleware::Error::middleware(open.clone());
+        }
+    }
+    reqwest_middleware::Error::Middleware(anyhow::Error::new(error.clone()))
+}
+
+/// Middleware coalescing the identical `GET` requests, see [`RequestCoalescer`]. It must come
+/// before the observer and the retries, so that the shared request is observed and retried once.
+pub(crate) struct CoalesceMiddleware {
+    coalescer: Arc<RequestCoalescer>,
+}
+
+impl CoalesceMiddleware {
+    pub fn new(coalescer: Arc<RequestCoalescer>) -> Self {
+        Self { coalescer }
+    }
+}
+
+#[async_trait::async_trait]
+impl Middleware for CoalesceMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        if req.method() != Method::GET || req.body().is_some() {
+            return next.run(req, extensions).await;
+        }
+
+        let key = (
+            req.url().to_string(),
+            req.headers()
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect(),
+        );
+        let cell = self.coalescer.join(&key);
+        // if the future sending the request is dropped, one of the waiters sends its own
+        let outcome = cell
+            .get_or_init(|| async {
+                let outcome = match next.run(req, extensions).await {
+                    Ok(resp) => BufferedResponse::read(resp).await.map_err(Arc::new),
+                    Err(e) => Err(Arc::new(e)),
+                };
+                self.coalescer.complete(&key, &cell);
+                outcome
+            })
+            .await;
+
+        match outcome {
+            Ok(resp) => resp.to_response(),
+            Err(e) => Err(shared_error(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::{
+        apis::{self, configuration::Configuration},
+        config::LighterConfig,
+        LighterError,
+    };
+
+    fn slow_status(server: &mut mockito::Server, status: usize) -> mockito::Mock {
+        server
+            .mock("GET", "/")
+            .with_status(status)
+            .with_header("content-type", "application/json")
+            .with_chunked_body(|w| {
+                std::thread::sleep(Duration::from_millis(200));
+                w.write_all(br#"{"status":200,"network_id":1,"timestamp":1}"#)
+            })
+    }
+
+    #[tokio::test]
+    async fn test_request_coalescing() {
+        let mut server = mockito::Server::new_async().await;
+        let status = slow_status(&mut server, 200).expect(2).create_async().await;
+
+        let config = LighterConfig {
+            base_url: server.url(),
+            ..LighterConfig::new().with_request_coalescing(true)
+        };
+        // e.g. two different APIs
+        let first = Configuration::try_from(&config).unwrap();
+        let second = Configuration::try_from(&config).unwrap();
+
+        let results = futures::future::join_all((0..10).map(|i| {
+            let configuration = if i % 2 == 0 { &first } else { &second };
+            apis::root_api::status(configuration)
+        }))
+        .await;
+        for res in results {
+            assert_eq!(res.unwrap().network_id, 1);
+        }
+
+        // the completed request isn't reused
+        assert!(apis::root_api::status(&first).await.is_ok());
+        status.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_request_coalescing_shares_errors() {
+        let mut server = mockito::Server::new_async().await;
+        let unavailable = slow_status(&mut server, 503).expect(1).create_async().await;
+
+        let config = LighterConfig {
+            base_url: server.url(),
+            retry_config: None,
+            ..LighterConfig::new().with_request_coalescing(true)
+        };
+        let configuration = Configuration::try_from(&config).unwrap();
+
+        let results =
+            futures::future::join_all((0..5).map(|_| apis::root_api::status(&configuration))).await;
+        for res in results {
+            let error = LighterError::from(res.unwrap_err());
+            assert_eq!(error.http_status(), Some(503));
+        }
+        unavailable.assert_async().await;
+    }
+}
@@ -1,15 +1,26 @@
-use std::time::Duration;
+use std::{path::PathBuf, sync::Arc, time::Duration};
 
 use crate::{
     apis::configuration::Configuration,
+    circuit_breaker::{CircuitBreaker, CircuitBreakerMiddleware},
+    client::NonceStore,
+    clock::ServerClock,
+    coalesce::{CoalesceMiddleware, RequestCoalescer},
+    connection_stats::{ConnectionMetrics, ConnectionStatsMiddleware, CountConnectionsLayer},
     error::{LighterError, Result},
+    observer::{ObserverMiddleware, RequestObserver},
+    rate_limit::{RateLimitMiddleware, RateLimiter},
+    request::TimeoutMiddleware,
+    retry::{RetryMiddleware, RetryStrategy},
 };
-use reqwest::Client;
-use reqwest_middleware::ClientBuilder;
-use reqwest_retry::{
-    policies::ExponentialBackoff, Jitter, RetryTransientMiddleware, Retryable, RetryableStrategy,
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue, USER_AGENT},
+    Client, Proxy,
 };
-use secrecy::SecretString;
+use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
+use reqwest_retry::{policies::ExponentialBackoff, Jitter};
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use url::Url;
 
 static DEFAULT_MIN_RETRY_INTERVAL: u64 = 100; // 100ms
@@ -22,30 +33,32 @@ static DEFAULT_TCP_KEEPALIVE_DURATION: u64 = 60; // 60s
 static DEFAULT_TCP_NODELAY: bool = true;
 static DEFAULT_HTTPV1_ONLY: bool = true;
 static DEFAULT_CONNECTION_VERBOSE: bool = false;
+static DEFAULT_AUTH_TOKEN_TTL: u64 = 600; // 10m
+static DEFAULT_AUTH_REFRESH_MARGIN: u64 = 30; // 30s
+static DEFAULT_WS_PING_INTERVAL: u64 = 20000; // 20s
+static DEFAULT_WS_PONG_TIMEOUT: u64 = 10000; // 10s
+static DEFAULT_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+static REDACTED: &str = "[REDACTED]";
 
-/// Retries when the successfull response code is `429`.
-struct TooManyRequestsStrategy;
-impl RetryableStrategy for TooManyRequestsStrategy {
-    fn handle(
-        &self,
-        res: &std::result::Result<reqwest::Response, reqwest_middleware::Error>,
-    ) -> Option<reqwest_retry::Retryable> {
-        match res {
-            Ok(success) if success.status().as_u16() == 429 => Some(Retryable::Transient),
-            Ok(success) if success.status().is_server_error() => Some(Retryable::Transient),
-            Ok(_) => None, // do not retry in this case,
-            Err(error) => reqwest_retry::default_on_request_failure(error),
-        }
-    }
-}
-
-#[derive(Debug, Clone)]
+/// Configuration of the clients.
+///
+/// It can be loaded from a config file (e.g. TOML or JSON), the missing fields taking their
+/// default value; call `validate` afterwards. When serialized, the secrets (the keys, the proxy
+/// password and the values of `default_headers`, which often carry credentials) are redacted.
+/// The runtime objects (`nonce_store`, `http_client`, `request_observer`, `retry_strategy`,
+/// `rate_limiter`, `circuit_breaker`, `request_coalescer`, `server_clock` and
+/// `connection_metrics`) are neither serialized nor deserialized.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct LighterConfig {
     pub base_url: String,
     pub ws_url: String,
+    pub chain_id: Option<i32>, // if `None`, the mainnet one when `base_url` contains `mainnet`, the testnet one otherwise
     pub account_index: Option<i32>,
+    #[serde(serialize_with = "serialize_redacted")]
     pub eth_private_key: Option<SecretString>,
     pub api_key_index: Option<i32>,
+    #[serde(serialize_with = "serialize_redacted")]
     pub api_key_private: Option<SecretString>,
     pub timeout_secs: Option<u64>,
     pub pool_max_idle_per_host: Option<usize>,
@@ -55,14 +68,49 @@ pub struct LighterConfig {
     pub http1_only: bool,
     pub connection_verbose: bool,
     pub retry_config: Option<RetryConfig>,
+    #[serde(skip)]
+    pub retry_strategy: Option<Arc<dyn RetryStrategy>>, // used only with `retry_config`, `DefaultRetryStrategy` if `None`
     pub local_nonce: bool,
+    pub local_nonce_path: Option<PathBuf>, // used only with `local_nonce`
+    #[serde(skip)]
+    pub nonce_store: Option<Arc<dyn NonceStore>>, // used only with `local_nonce`, it takes precedence over `local_nonce_path`
+    pub auth_token_ttl_secs: u64,
+    pub auth_refresh_margin_secs: u64,
+    pub max_clock_skew_ms: Option<u64>, // if set, a warning is logged when the local clock is more off the server one
+    pub check_reduce_only: bool, // the reduce only orders are checked against the position, fetched for each of them
+    #[serde(skip)]
+    pub http_client: Option<ClientWithMiddleware>, // if set, all the other HTTP client options are ignored
+    pub proxy: Option<String>,
+    #[serde(serialize_with = "serialize_proxy_auth")]
+    pub proxy_auth: Option<(String, SecretString)>, // username and password
+    #[serde(skip)]
+    pub request_observer: Option<Arc<dyn RequestObserver>>,
+    #[serde(skip)]
+    pub rate_limiter: Option<Arc<RateLimiter>>, // shared by all the clients built from this config
+    #[serde(skip)]
+    pub circuit_breaker: Option<Arc<CircuitBreaker>>, // shared by all the clients built from this config
+    #[serde(skip)]
+    pub request_coalescer: Option<Arc<RequestCoalescer>>, // shared by all the clients built from this config
+    pub ws_reconnect: Option<RetryConfig>, // if `None`, the WS streams end when the connection drops
+    pub ws_keepalive: Option<KeepaliveConfig>, // if `None`, the WS connection is never pinged
+    #[serde(skip)]
+    pub server_clock: Arc<ServerClock>, // shared by all the clients built from this config
+    #[serde(skip)]
+    pub connection_metrics: Arc<ConnectionMetrics>, // shared by all the clients built from this config (not used with `http_client`)
+    #[serde(
+        serialize_with = "serialize_headers",
+        deserialize_with = "deserialize_headers"
+    )]
+    pub default_headers: HeaderMap, // sent with every HTTP request (not used with `http_client`)
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct RetryConfig {
     pub max_retries: u32,
     pub min_retry_interval: u64,
     pub max_retry_interval: u64,
+    pub jitter: RetryJitter,
 }
 
 impl Default for RetryConfig {
@@ -71,15 +119,169 @@ impl Default for RetryConfig {
             max_retries: DEFAULT_MAX_RETRIES,
             min_retry_interval: DEFAULT_MIN_RETRY_INTERVAL,
             max_retry_interval: DEFAULT_MAX_RETRY_INTERVAL,
+            jitter: RetryJitter::default(),
+        }
+    }
+}
+
+/// Randomization of the backoff intervals of a [`RetryConfig`].
+///
+/// `Bounded` keeps the intervals between `min_retry_interval` and the computed backoff, so the
+/// retries are never sent too early. `Full` draws them between 0 and the computed backoff: the
+/// retries of many clients failing at once (e.g. a fleet hitting the same outage) are spread
+/// more evenly, at the cost of some retries being sent almost immediately. `None` keeps the exact
+/// exponential intervals, which is only advisable for a single client.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RetryJitter {
+    None,
+    #[default]
+    Bounded,
+    Full,
+}
+
+impl From<RetryJitter> for Jitter {
+    fn from(val: RetryJitter) -> Self {
+        match val {
+            RetryJitter::None => Jitter::None,
+            RetryJitter::Bounded => Jitter::Bounded,
+            RetryJitter::Full => Jitter::Full,
+        }
+    }
+}
+
+/// Lighter deployment, setting the URLs and the chain id together, see
+/// [`LighterConfig::with_network`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Network {
+    Mainnet,
+    Testnet,
+}
+
+impl Network {
+    pub fn base_url(&self) -> &'static str {
+        match self {
+            Network::Mainnet => "https://mainnet.zklighter.elliot.ai",
+            Network::Testnet => "https://testnet.zklighter.elliot.ai",
+        }
+    }
+
+    pub fn ws_url(&self) -> &'static str {
+        match self {
+            Network::Mainnet => "wss://mainnet.zklighter.elliot.ai/stream",
+            Network::Testnet => "wss://testnet.zklighter.elliot.ai/stream",
+        }
+    }
+
+    /// Chain id the txs are signed for
+    pub fn chain_id(&self) -> i32 {
+        match self {
+            Network::Mainnet => 304,
+            Network::Testnet => 300,
+        }
+    }
+}
+
+/// Pings sent on the idle WS connection, see [`LighterConfig::with_ws_keepalive`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeepaliveConfig {
+    pub ping_interval: u64, // ms
+    pub pong_timeout: u64,  // ms
+}
+
+impl Default for KeepaliveConfig {
+    fn default() -> Self {
+        Self {
+            ping_interval: DEFAULT_WS_PING_INTERVAL,
+            pong_timeout: DEFAULT_WS_PONG_TIMEOUT,
         }
     }
 }
 
+impl RetryConfig {
+    /// Exponential backoff between `min_retry_interval` and `max_retry_interval` (ms), with the
+    /// `jitter` of the config
+    pub(crate) fn backoff(&self) -> ExponentialBackoff {
+        ExponentialBackoff::builder()
+            .retry_bounds(
+                Duration::from_millis(self.min_retry_interval),
+                Duration::from_millis(self.max_retry_interval),
+            )
+            .jitter(self.jitter.into())
+            .build_with_max_retries(self.max_retries)
+    }
+}
+
 impl LighterConfig {
     pub fn new() -> Self {
         Self::default()
     }
 
+    /// Checks up front the fields needed by what the config is set up for, reporting everything
+    /// that is missing or invalid at once:
+    /// - read only: valid URLs
+    /// - signing (`api_key_private` set): `account_index` and `api_key_index` too
+    /// - L1 txs (`eth_private_key` set): all the signing fields too
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = Vec::new();
+
+        if let Err(e) = Url::parse(&self.base_url) {
+            errors.push(format!("invalid `base_url`: {e}"));
+        }
+        if let Err(e) = Url::parse(&self.ws_url) {
+            errors.push(format!("invalid `ws_url`: {e}"));
+        }
+
+        let signing = self.api_key_private.is_some() || self.eth_private_key.is_some();
+        if signing {
+            if self.api_key_private.is_none() {
+                errors.push("`api_key_private` is required by `eth_private_key`".into());
+            }
+            if self.account_index.is_none() {
+                errors.push("`account_index` is required to sign txs".into());
+            }
+            if self.api_key_index.is_none() {
+                errors.push("`api_key_index` is required to sign txs".into());
+            }
+        }
+
+        if let Some(key) = &self.api_key_private {
+            if !is_hex(key.expose_secret(), None) {
+                errors.push("`api_key_private` is not a hex string".into());
+            }
+        }
+        if let Some(key) = &self.eth_private_key {
+            if !is_hex(key.expose_secret(), Some(64)) {
+                errors.push("`eth_private_key` is not a 32 bytes hex string".into());
+            }
+        }
+
+        if let Some(user_agent) = self.default_headers.get(USER_AGENT) {
+            if user_agent.to_str().is_err() {
+                errors.push("the `User-Agent` header is not a visible ASCII string".into());
+            }
+        }
+
+        if let Some(keepalive) = &self.ws_keepalive {
+            if keepalive.ping_interval == 0 || keepalive.pong_timeout == 0 {
+                errors.push("the `ws_keepalive` intervals must be greater than 0".into());
+            }
+        }
+
+        if self.auth_refresh_margin_secs >= self.auth_token_ttl_secs {
+            errors
+                .push("`auth_refresh_margin_secs` must be lower than `auth_token_ttl_secs`".into());
+        }
+
+        if !errors.is_empty() {
+            return Err(LighterError::Config(errors.join("; ")));
+        }
+
+        Ok(())
+    }
+
     pub fn with_account_index(mut self, account_index: i32) -> Self {
         self.account_index = Some(account_index);
         self
@@ -107,6 +309,32 @@ impl LighterConfig {
         Ok(self)
     }
 
+    /// Sets the URLs and the chain id of the network. Custom endpoints can still be set
+    /// afterwards with `with_base_url` and `with_ws_url`, the chain id is kept.
+    pub fn with_network(mut self, network: Network) -> Self {
+        self.base_url = network.base_url().to_string();
+        self.ws_url = network.ws_url().to_string();
+        self.chain_id = Some(network.chain_id());
+        self
+    }
+
+    /// Chain id the txs are signed for, e.g. for a custom endpoint whose URL doesn't tell the
+    /// network (see [`Network::chain_id`])
+    pub fn with_chain_id(mut self, chain_id: i32) -> Self {
+        self.chain_id = Some(chain_id);
+        self
+    }
+
+    /// Returns the chain id the txs are signed for, guessed from `base_url` if not set
+    pub fn chain_id(&self) -> i32 {
+        self.chain_id
+            .unwrap_or(if self.base_url.contains("mainnet") {
+                Network::Mainnet.chain_id()
+            } else {
+                Network::Testnet.chain_id()
+            })
+    }
+
     pub fn with_ws_url<S: AsRef<str>>(mut self, url: S) -> Result<Self> {
         self.ws_url = Url::parse(url.as_ref())
             .map_err(|e| LighterError::Config(format!("Invalid WebSocket URL: {}", e)))?
@@ -124,6 +352,28 @@ impl LighterConfig {
         self
     }
 
+    /// Decides which attempts are retried instead of `DefaultRetryStrategy`, e.g. to retry on
+    /// some Lighter error codes (not used without `retry_config` or with `with_http_client`)
+    pub fn with_retry_strategy(mut self, retry_strategy: Arc<dyn RetryStrategy>) -> Self {
+        self.retry_strategy = Some(retry_strategy);
+        self
+    }
+
+    /// Reconnects the dropped WS streams with an exponential backoff, giving up after
+    /// `max_retries` consecutive failed attempts
+    pub fn with_ws_reconnect(mut self, ws_reconnect: RetryConfig) -> Self {
+        self.ws_reconnect = Some(ws_reconnect);
+        self
+    }
+
+    /// Pings the WS connection every `ping_interval` (ms): if the pong doesn't come back within
+    /// `pong_timeout` (ms), the connection is considered dead and it reconnects (see
+    /// [`LighterConfig::with_ws_reconnect`])
+    pub fn with_ws_keepalive(mut self, ws_keepalive: KeepaliveConfig) -> Self {
+        self.ws_keepalive = Some(ws_keepalive);
+        self
+    }
+
     pub fn with_pool_max_idle_per_host(mut self, pool_max_idle_per_host: usize) -> Self {
         self.pool_max_idle_per_host = Some(pool_max_idle_per_host);
         self
@@ -153,13 +403,169 @@ impl LighterConfig {
         self.connection_verbose = connection_verbose;
         self
     }
+
+    pub fn with_local_nonce(mut self, local_nonce: bool) -> Self {
+        self.local_nonce = local_nonce;
+        self
+    }
+
+    /// Persists the local nonce in the file at `path`, so that it survives restarts
+    pub fn with_local_nonce_path<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.local_nonce_path = Some(path.into());
+        self
+    }
+
+    /// Persists the local nonce using a custom `NonceStore`
+    pub fn with_nonce_store(mut self, nonce_store: Arc<dyn NonceStore>) -> Self {
+        self.nonce_store = Some(nonce_store);
+        self
+    }
+
+    /// Routes all the HTTP requests through the given HTTP/HTTPS proxy
+    pub fn with_proxy<S: AsRef<str>>(mut self, url: S) -> Result<Self> {
+        let url = Url::parse(url.as_ref())
+            .map_err(|e| LighterError::Config(format!("Invalid proxy URL: {}", e)))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(LighterError::Config(format!(
+                "Invalid proxy URL: unsupported scheme `{}`",
+                url.scheme()
+            )));
+        }
+        self.proxy = Some(url.to_string());
+        Ok(self)
+    }
+
+    /// Basic auth credentials of the proxy, used only with `with_proxy`
+    pub fn with_proxy_auth<S: Into<String>>(mut self, username: S, password: S) -> Self {
+        self.proxy_auth = Some((username.into(), SecretString::from(password.into())));
+        self
+    }
+
+    /// Throttles the HTTP requests to `requests_per_second`, allowing bursts of `burst` requests.
+    /// The budget is shared by all the APIs built from this config (not used with `with_http_client`).
+    pub fn with_rate_limit(mut self, requests_per_second: u32, burst: u32) -> Result<Self> {
+        self.rate_limiter = Some(Arc::new(RateLimiter::new(requests_per_second, burst)?));
+        Ok(self)
+    }
+
+    /// Fails fast with `LighterError::CircuitOpen` for `cooldown` after `failure_threshold`
+    /// consecutive failures to a host, see [`CircuitBreaker`]. The state is shared by all the APIs
+    /// built from this config (not used with `with_http_client`).
+    pub fn with_circuit_breaker(
+        mut self,
+        failure_threshold: u32,
+        cooldown: Duration,
+    ) -> Result<Self> {
+        self.circuit_breaker = Some(Arc::new(CircuitBreaker::new(failure_threshold, cooldown)?));
+        Ok(self)
+    }
+
+    /// Makes the concurrent identical `GET` requests share a single in-flight request, see
+    /// [`RequestCoalescer`]. The in-flight requests are shared by all the APIs built from this
+    /// config (not used with `with_http_client`).
+    pub fn with_request_coalescing(mut self, request_coalescing: bool) -> Self {
+        self.request_coalescer = request_coalescing.then(|| Arc::new(RequestCoalescer::new()));
+        self
+    }
+
+    /// Sends the given headers with every HTTP request (not used with `with_http_client`), on top
+    /// of the ones already set. A `User-Agent` header replaces the default one of the SDK.
+    pub fn with_default_headers(mut self, headers: HeaderMap) -> Self {
+        self.default_headers.extend(headers);
+        self
+    }
+
+    /// Same as [`LighterConfig::with_default_headers`], for a single header
+    pub fn with_default_header<K: AsRef<str>, V: AsRef<str>>(
+        mut self,
+        name: K,
+        value: V,
+    ) -> Result<Self> {
+        let name = HeaderName::try_from(name.as_ref())
+            .map_err(|e| LighterError::Config(format!("Invalid header name: {e}")))?;
+        let value = HeaderValue::try_from(value.as_ref())
+            .map_err(|e| LighterError::Config(format!("Invalid value of header {name}: {e}")))?;
+        self.default_headers.insert(name, value);
+        Ok(self)
+    }
+
+    /// Identifies the application in the `User-Agent` header of the HTTP requests, replacing the
+    /// default one of the SDK (`lighter-rust/<version>`)
+    pub fn with_user_agent<S: AsRef<str>>(self, user_agent: S) -> Result<Self> {
+        self.with_default_header(USER_AGENT, user_agent)
+    }
+
+    /// Appends a tag (e.g. `my-bot/1.2`) to the current user agent, the default one of the SDK
+    /// unless set by [`LighterConfig::with_user_agent`]
+    pub fn with_user_agent_suffix<S: AsRef<str>>(self, suffix: S) -> Result<Self> {
+        let user_agent = match self.default_headers.get(USER_AGENT) {
+            Some(user_agent) => user_agent
+                .to_str()
+                .map_err(|_| LighterError::Config("Invalid `User-Agent` header".into()))?,
+            None => DEFAULT_USER_AGENT,
+        };
+        let user_agent = format!("{user_agent} {}", suffix.as_ref());
+        self.with_user_agent(user_agent)
+    }
+
+    /// Registers hooks invoked for every HTTP request (not used with `with_http_client`)
+    pub fn with_request_observer(mut self, request_observer: Arc<dyn RequestObserver>) -> Self {
+        self.request_observer = Some(request_observer);
+        self
+    }
+
+    /// Uses the given client verbatim instead of building a new one, so that an already existing
+    /// client (with its connection pool, middlewares, ...) can be shared.
+    /// The txs are sent with it too, as multipart forms that can't be cloned, so it must not
+    /// include a retry middleware (`retry_config` doesn't apply to it): every tx would fail.
+    pub fn with_http_client(mut self, http_client: ClientWithMiddleware) -> Self {
+        self.http_client = Some(http_client);
+        self
+    }
+
+    /// Computes the deadlines (order expiries, auth tokens) with the given clock, e.g. to share it
+    /// between configs
+    pub fn with_server_clock(mut self, server_clock: Arc<ServerClock>) -> Self {
+        self.server_clock = server_clock;
+        self
+    }
+
+    /// Lifetime of the generated auth tokens
+    pub fn with_auth_token_ttl(mut self, auth_token_ttl_secs: u64) -> Self {
+        self.auth_token_ttl_secs = auth_token_ttl_secs;
+        self
+    }
+
+    /// Auth tokens are regenerated when they expire in less than `auth_refresh_margin_secs`, so
+    /// that they don't expire while a request is in flight. It should be lower than the TTL.
+    pub fn with_auth_refresh_margin(mut self, auth_refresh_margin_secs: u64) -> Self {
+        self.auth_refresh_margin_secs = auth_refresh_margin_secs;
+        self
+    }
+
+    /// Logs a warning when the local clock is more than `max_clock_skew_ms` off the server one,
+    /// since the auth tokens and the order expiries are then likely to be rejected. The skew is
+    /// checked in the background when a `HttpClient` is built, and at every clock sync.
+    pub fn with_max_clock_skew(mut self, max_clock_skew_ms: u64) -> Self {
+        self.max_clock_skew_ms = Some(max_clock_skew_ms);
+        self
+    }
+
+    /// Checks the reduce only orders against the position of the account before placing them,
+    /// see [`CreateOrderData::validate_reduce_only`](crate::signer::data::CreateOrderData::validate_reduce_only).
+    /// The position is fetched for each of them, so it's off by default.
+    pub fn with_reduce_only_check(mut self, check_reduce_only: bool) -> Self {
+        self.check_reduce_only = check_reduce_only;
+        self
+    }
 }
 
 impl Default for LighterConfig {
     fn default() -> Self {
         Self {
-            base_url: "https://mainnet.zklighter.elliot.ai".to_string(),
-            ws_url: "wss://mainnet.zklighter.elliot.ai/stream".to_string(),
+            base_url: Network::Mainnet.base_url().to_string(),
+            ws_url: Network::Mainnet.ws_url().to_string(),
+            chain_id: None,
             account_index: None,
             eth_private_key: None,
             api_key_index: None,
@@ -172,7 +578,26 @@ impl Default for LighterConfig {
             http1_only: DEFAULT_HTTPV1_ONLY,
             connection_verbose: DEFAULT_CONNECTION_VERBOSE,
             retry_config: Some(RetryConfig::default()),
+            retry_strategy: None,
             local_nonce: true, // by default we have the nonce generation as local to avoid further API requests; if `false` it will use API nonce
+            local_nonce_path: None,
+            nonce_store: None,
+            auth_token_ttl_secs: DEFAULT_AUTH_TOKEN_TTL,
+            auth_refresh_margin_secs: DEFAULT_AUTH_REFRESH_MARGIN,
+            max_clock_skew_ms: None,
+            check_reduce_only: false,
+            http_client: None,
+            proxy: None,
+            proxy_auth: None,
+            request_observer: None,
+            rate_limiter: None,
+            circuit_breaker: None,
+            request_coalescer: None,
+            ws_reconnect: Some(RetryConfig::default()),
+            ws_keepalive: Some(KeepaliveConfig::default()),
+            server_clock: Arc::new(ServerClock::new()),
+            connection_metrics: Arc::new(ConnectionMetrics::new()),
+            default_headers: HeaderMap::new(),
         }
     }
 }
@@ -183,77 +608,561 @@ impl TryFrom<&LighterConfig> for Configuration {
     type Error = LighterError;
 
     fn try_from(config: &LighterConfig) -> std::result::Result<Self, Self::Error> {
-        // create the inner client
-        let mut builder = Client::builder();
+        let client = match &config.http_client {
+            Some(client) => client.clone(),
+            None => build_client(config)?,
+        };
 
-        // timeout
-        if let Some(timeout) = config.timeout_secs {
-            builder = builder.timeout(Duration::from_secs(timeout));
-        }
+        // the generated APIs set the user agent on every request, so a custom one must go there
+        let user_agent = match config.default_headers.get(USER_AGENT) {
+            Some(user_agent) => user_agent
+                .to_str()
+                .map_err(|_| LighterError::Config("Invalid `User-Agent` header".into()))?
+                .to_string(),
+            None => DEFAULT_USER_AGENT.to_string(),
+        };
 
-        // pool_max_idle_per_host
-        if let Some(pool_max_idle_per_host) = config.pool_max_idle_per_host {
-            builder = builder.pool_max_idle_per_host(pool_max_idle_per_host);
-        }
+        let openapi_config = Configuration {
+            base_path: config.base_url.to_string(),
+            user_agent: Some(user_agent),
+            client,
+            basic_auth: None,
+            oauth_access_token: None,
+            bearer_access_token: None,
+            api_key: None,
+        };
+
+        Ok(openapi_config)
+    }
+}
+
+fn build_client(config: &LighterConfig) -> Result<ClientWithMiddleware> {
+    // create the inner client
+    let mut builder = Client::builder();
+
+    // TLS backend, selected at compile time
+    #[cfg(feature = "native-tls")]
+    {
+        builder = builder.use_native_tls();
+    }
+    #[cfg(all(feature = "rustls-tls", not(feature = "native-tls")))]
+    {
+        builder = builder.use_rustls_tls();
+    }
+
+    // default headers
+    builder = builder.default_headers(config.default_headers.clone());
 
-        // pool_idle_timeout
-        if let Some(pool_idle_timeout) = config.pool_idle_timeout {
-            builder = builder.pool_idle_timeout(Duration::from_secs(pool_idle_timeout));
+    // timeout
+    if let Some(timeout) = config.timeout_secs {
+        builder = builder.timeout(Duration::from_secs(timeout));
+    }
+
+    // pool_max_idle_per_host
+    if let Some(pool_max_idle_per_host) = config.pool_max_idle_per_host {
+        builder = builder.pool_max_idle_per_host(pool_max_idle_per_host);
+    }
+
+    // pool_idle_timeout
+    if let Some(pool_idle_timeout) = config.pool_idle_timeout {
+        builder = builder.pool_idle_timeout(Duration::from_secs(pool_idle_timeout));
+    }
+
+    // tcp_keepalive
+    if let Some(tcp_keepalive) = config.tcp_keepalive_duration {
+        builder = builder.tcp_keepalive(Duration::from_secs(tcp_keepalive));
+    }
+
+    // tcp_nodelay
+    builder = builder.tcp_nodelay(config.tcp_nodelay);
+
+    // http1_only
+    if config.http1_only {
+        builder = builder.http1_only();
+    }
+
+    // connection_verbose
+    builder = builder.connection_verbose(config.connection_verbose);
+
+    // proxy
+    if let Some(proxy) = &config.proxy {
+        let mut proxy = Proxy::all(proxy).map_err(|e| {
+            tracing::error!("unable to create proxy: {e}");
+            LighterError::Config(format!("Invalid proxy URL: {e}"))
+        })?;
+        if let Some((username, password)) = &config.proxy_auth {
+            proxy = proxy.basic_auth(username, password.expose_secret());
         }
+        builder = builder.proxy(proxy);
+    }
+
+    // counts the connections opened, see `ConnectionMetrics`
+    builder = builder.connector_layer(CountConnectionsLayer::new(
+        config.connection_metrics.clone(),
+    ));
 
-        // tcp_keepalive
-        if let Some(tcp_keepalive) = config.tcp_keepalive_duration {
-            builder = builder.tcp_keepalive(Duration::from_secs(tcp_keepalive));
+    let client = builder.build().map_err(|e| {
+        tracing::error!("unable to create reqwest client: {e}");
+        LighterError::Config("Unable to create client".into())
+    })?;
+    // per call timeout, it must come before the retries so that they get it too
+    let mut middleware_builder = ClientBuilder::new(client).with(TimeoutMiddleware);
+
+    // coalescing, it must come before the observer and the retries so that a shared request is
+    // only observed and retried once
+    if let Some(coalescer) = &config.request_coalescer {
+        middleware_builder = middleware_builder.with(CoalesceMiddleware::new(coalescer.clone()));
+    }
+
+    // observer, it must come first to see the outcome of the retries
+    if let Some(observer) = &config.request_observer {
+        middleware_builder = middleware_builder.with(ObserverMiddleware::new(observer.clone()));
+    }
+
+    // retry strategy
+    if let Some(retry_config) = &config.retry_config {
+        let mut retry = RetryMiddleware::new(
+            retry_config.backoff(),
+            Duration::from_millis(retry_config.max_retry_interval),
+            config.request_observer.clone(),
+        );
+        if let Some(strategy) = &config.retry_strategy {
+            retry = retry.with_strategy(strategy.clone());
         }
+        middleware_builder = middleware_builder.with(retry);
+    }
+
+    // circuit breaker, it must come after the retries so that they stop once it opens, and before
+    // the rate limit so that the failing fast requests don't take a token
+    if let Some(circuit_breaker) = &config.circuit_breaker {
+        middleware_builder =
+            middleware_builder.with(CircuitBreakerMiddleware::new(circuit_breaker.clone()));
+    }
+
+    // rate limit, it must come after the retries so that they are throttled too
+    if let Some(rate_limiter) = &config.rate_limiter {
+        middleware_builder =
+            middleware_builder.with(RateLimitMiddleware::new(rate_limiter.clone()));
+    }
+
+    // request count, it must come last so that every attempt is counted
+    middleware_builder = middleware_builder.with(ConnectionStatsMiddleware::new(
+        config.connection_metrics.clone(),
+    ));
+
+    Ok(middleware_builder.build())
+}
+
+fn serialize_redacted<S: Serializer>(
+    secret: &Option<SecretString>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    secret.as_ref().map(|_| REDACTED).serialize(serializer)
+}
+
+fn serialize_proxy_auth<S: Serializer>(
+    proxy_auth: &Option<(String, SecretString)>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    proxy_auth
+        .as_ref()
+        .map(|(username, _)| (username, REDACTED))
+        .serialize(serializer)
+}
+
+fn serialize_headers<S: Serializer>(
+    headers: &HeaderMap,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    serializer.collect_map(headers.keys().map(|name| (name.as_str(), REDACTED)))
+}
+
+fn deserialize_headers<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<HeaderMap, D::Error> {
+    let headers = std::collections::BTreeMap::<String, String>::deserialize(deserializer)?;
+    headers
+        .into_iter()
+        .try_fold(LighterConfig::new(), |config, (name, value)| {
+            config.with_default_header(name, value)
+        })
+        .map(|config| config.default_headers)
+        .map_err(serde::de::Error::custom)
+}
 
-        // tcp_nodelay
-        builder = builder.tcp_nodelay(config.tcp_nodelay);
+/// Returns `true` if `value` (optionally `0x` prefixed) is a non empty hex string of `len` chars
+fn is_hex(value: &str, len: Option<usize>) -> bool {
+    let value = value.trim_start_matches("0x");
+    !value.is_empty()
+        && len.is_none_or(|len| value.len() == len)
+        && value.chars().all(|c| c.is_ascii_hexdigit())
+}
 
-        // http1_only
-        if config.http1_only {
-            builder = builder.http1_only();
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        assert!(LighterConfig::new().validate().is_ok());
+
+        let config = LighterConfig::new()
+            .with_api_key_private(
+                "01db9eed031d59d6bd0ee00ee5a7dc1f62087bf217b51caea57eb6e17a02c49e0a748d2f155a2f60",
+            )
+            .with_account_index(28)
+            .with_api_key_index(2);
+        assert!(config.validate().is_ok());
+        assert!(config
+            .clone()
+            .with_eth_private_key(
+                "0x4fd51c004ad02a003e321d5154d9b22c6bb89e1e5017bdc832c69ef28f65c04e"
+            )
+            .validate()
+            .is_ok());
+
+        // everything is reported at once
+        let config = LighterConfig {
+            base_url: "not a url".into(),
+            ..LighterConfig::new()
+                .with_eth_private_key("0x1234")
+                .with_ws_keepalive(KeepaliveConfig {
+                    ping_interval: 0,
+                    ..Default::default()
+                })
+        };
+        let Err(LighterError::Config(e)) = config.validate() else {
+            panic!("expected a config error");
+        };
+        for field in [
+            "base_url",
+            "ws_keepalive",
+            "api_key_private",
+            "account_index",
+            "api_key_index",
+            "eth_private_key",
+        ] {
+            assert!(e.contains(field), "{field} not in {e}");
         }
+    }
 
-        // connection_verbose
-        builder = builder.connection_verbose(config.connection_verbose);
+    #[test]
+    fn test_with_network() {
+        let config = LighterConfig::new().with_network(Network::Testnet);
+        assert_eq!(config.base_url, "https://testnet.zklighter.elliot.ai");
+        assert_eq!(config.ws_url, "wss://testnet.zklighter.elliot.ai/stream");
+        assert_eq!(config.chain_id(), 300);
+        config.validate().unwrap();
 
-        let client = builder.build().map_err(|e| {
-            tracing::error!("unable to create reqwest client: {e}");
-            LighterError::Config("Unable to create client".into())
-        })?;
-        let mut middleware_builder = ClientBuilder::new(client);
-
-        // retry strategy
-        if let Some(retry_config) = &config.retry_config {
-            let exp_backoff = ExponentialBackoff::builder()
-                .retry_bounds(
-                    Duration::from_millis(retry_config.min_retry_interval),
-                    Duration::from_millis(retry_config.max_retry_interval),
-                )
-                .jitter(Jitter::Bounded)
-                .build_with_max_retries(retry_config.max_retries);
-
-            middleware_builder =
-                middleware_builder.with(RetryTransientMiddleware::new_with_policy_and_strategy(
-                    exp_backoff,
-                    TooManyRequestsStrategy,
-                ));
+        // a custom endpoint keeps the chain id of the network
+        let config = config.with_base_url("https://mainnet-proxy.local").unwrap();
+        assert_eq!(config.chain_id(), 300);
+
+        let config = LighterConfig::new().with_network(Network::Mainnet);
+        assert_eq!(config.base_url, LighterConfig::new().base_url);
+        assert_eq!(config.ws_url, LighterConfig::new().ws_url);
+        assert_eq!(config.chain_id(), 304);
+
+        // guessed from the URL when not set
+        assert_eq!(LighterConfig::new().chain_id(), 304);
+        let config = LighterConfig::new()
+            .with_base_url("https://testnet.zklighter.elliot.ai")
+            .unwrap();
+        assert_eq!(config.chain_id(), 300);
+        assert_eq!(config.with_chain_id(304).chain_id(), 304);
+    }
+
+    #[test]
+    fn test_serde() {
+        let config: LighterConfig = serde_json::from_str(
+            r#"{
+                "base_url": "https://testnet.zklighter.elliot.ai",
+                "account_index": 28,
+                "api_key_index": 2,
+                "api_key_private": "01db9eed",
+                "retry_config": {"max_retries": 3, "jitter": "full"},
+                "local_nonce": false,
+                "proxy_auth": ["user", "pass"],
+                "default_headers": {"X-Api-Gateway-Key": "secret"}
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(config.base_url, "https://testnet.zklighter.elliot.ai");
+        assert_eq!(
+            config.api_key_private.as_ref().unwrap().expose_secret(),
+            "01db9eed"
+        );
+        assert_eq!(
+            config.proxy_auth.as_ref().unwrap().1.expose_secret(),
+            "pass"
+        );
+        assert_eq!(config.default_headers["x-api-gateway-key"], "secret");
+        assert_eq!(config.retry_config.as_ref().unwrap().max_retries, 3);
+        assert_eq!(
+            config.retry_config.as_ref().unwrap().backoff().jitter,
+            Jitter::Full
+        );
+        assert_eq!(
+            config.ws_reconnect.as_ref().unwrap().backoff().jitter,
+            Jitter::Bounded
+        );
+        // the missing fields take the default value
+        assert_eq!(
+            config.retry_config.as_ref().unwrap().max_retry_interval,
+            DEFAULT_MAX_RETRY_INTERVAL
+        );
+        assert_eq!(config.ws_url, LighterConfig::default().ws_url);
+        assert_eq!(config.timeout_secs, Some(DEFAUL_TIMEOUT));
+
+        let json = serde_json::to_value(&config).unwrap();
+        for secret in ["01db9eed", "pass", "secret"] {
+            assert!(
+                !json.to_string().contains(secret),
+                "{secret} leaked: {json}"
+            );
         }
+        assert_eq!(json["api_key_private"], REDACTED);
+        assert_eq!(json["eth_private_key"], serde_json::Value::Null);
+        assert_eq!(json["proxy_auth"], serde_json::json!(["user", REDACTED]));
 
-        let openapi_config = Configuration {
-            base_path: config.base_url.to_string(),
-            user_agent: Some(format!(
-                "{}/{}",
-                env!("CARGO_PKG_NAME"),
-                env!("CARGO_PKG_VERSION")
-            )),
-            client: middleware_builder.build(),
-            basic_auth: None,
-            oauth_access_token: None,
-            bearer_access_token: None,
-            api_key: None,
+        // the non secret fields round trip
+        let restored: LighterConfig = serde_json::from_value(json).unwrap();
+        assert_eq!(restored.base_url, config.base_url);
+        assert_eq!(restored.account_index, Some(28));
+        assert_eq!(restored.api_key_index, Some(2));
+        assert_eq!(restored.retry_config, config.retry_config);
+        assert_eq!(restored.ws_reconnect, config.ws_reconnect);
+        assert_eq!(restored.ws_keepalive, Some(KeepaliveConfig::default()));
+        assert!(!restored.local_nonce);
+
+        let res = serde_json::from_str::<LighterConfig>(r#"{"default_headers": {"a b": "1"}}"#);
+        assert!(res.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_with_http_client() {
+        let mut server = mockito::Server::new_async().await;
+        let status = server
+            .mock("GET", "/")
+            .match_header("x-custom", "1")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":200,"network_id":1,"timestamp":1}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert("x-custom", "1".parse().unwrap());
+        let client = Client::builder().default_headers(headers).build().unwrap();
+
+        let config = LighterConfig {
+            base_url: server.url(),
+            ..LighterConfig::new().with_http_client(ClientBuilder::new(client).build())
         };
+        let configuration = Configuration::try_from(&config).unwrap();
 
-        Ok(openapi_config)
+        crate::apis::root_api::status(&configuration).await.unwrap();
+        status.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_with_http_client_sends_txs() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/api/v1/sendTx")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":200,"tx_hash":"0x7","predicted_execution_time_ms":1}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let send_tx = |http_client| {
+            let config = LighterConfig {
+                base_url: server.url(),
+                ..LighterConfig::new().with_http_client(http_client)
+            };
+            async move {
+                crate::api::transaction::TransactionApi::new(&config)
+                    .unwrap()
+                    .send_tx(14, "{}", None)
+                    .await
+            }
+        };
+
+        let resp = send_tx(ClientBuilder::new(Client::new()).build())
+            .await
+            .unwrap();
+        assert_eq!(resp.tx_hash, "0x7");
+
+        // the retries can't clone the multipart forms, the tx isn't even sent
+        let retry = RetryMiddleware::new(
+            RetryConfig::default().backoff(),
+            Duration::from_secs(1),
+            None,
+        );
+        let res = send_tx(ClientBuilder::new(Client::new()).with(retry).build()).await;
+        assert!(
+            matches!(&res, Err(e) if e.to_string().contains("not cloneable")),
+            "{res:?}"
+        );
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_with_default_headers() {
+        let mut server = mockito::Server::new_async().await;
+        let status = server
+            .mock("GET", "/")
+            .match_header("x-api-gateway-key", "secret")
+            .match_header("x-region", "eu")
+            .match_header(
+                "user-agent",
+                concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")),
+            )
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":200,"network_id":1,"timestamp":1}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-region", HeaderValue::from_static("eu"));
+        let config = LighterConfig {
+            base_url: server.url(),
+            ..LighterConfig::new()
+                .with_default_header("X-Api-Gateway-Key", "secret")
+                .unwrap()
+                .with_default_headers(headers)
+        };
+        let configuration = Configuration::try_from(&config).unwrap();
+
+        crate::apis::root_api::status(&configuration).await.unwrap();
+        status.assert_async().await;
+
+        // a custom user agent replaces the default one
+        let config = config.with_default_header("User-Agent", "bot/1.0").unwrap();
+        assert_eq!(
+            Configuration::try_from(&config)
+                .unwrap()
+                .user_agent
+                .as_deref(),
+            Some("bot/1.0")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_with_user_agent() {
+        let mut server = mockito::Server::new_async().await;
+        let status = server
+            .mock("GET", "/")
+            .match_header("user-agent", "my-bot/1.2")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":200,"network_id":1,"timestamp":1}"#)
+            .expect(1)
+            .create_async()
+            .await;
+        let suffixed = server
+            .mock("GET", "/")
+            .match_header(
+                "user-agent",
+                concat!(
+                    env!("CARGO_PKG_NAME"),
+                    "/",
+                    env!("CARGO_PKG_VERSION"),
+                    " my-bot/1.2"
+                ),
+            )
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":200,"network_id":1,"timestamp":1}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let config = LighterConfig {
+            base_url: server.url(),
+            ..LighterConfig::new()
+        };
+        let custom = config.clone().with_user_agent("my-bot/1.2").unwrap();
+        crate::apis::root_api::status(&Configuration::try_from(&custom).unwrap())
+            .await
+            .unwrap();
+        status.assert_async().await;
+
+        let suffixed_config = config.with_user_agent_suffix("my-bot/1.2").unwrap();
+        crate::apis::root_api::status(&Configuration::try_from(&suffixed_config).unwrap())
+            .await
+            .unwrap();
+        suffixed.assert_async().await;
+
+        assert!(matches!(
+            LighterConfig::new().with_user_agent("my-bot\n1.2"),
+            Err(LighterError::Config(_))
+        ));
+        assert_eq!(
+            LighterConfig::new()
+                .with_user_agent("bot")
+                .unwrap()
+                .with_user_agent_suffix("v2")
+                .unwrap()
+                .default_headers[USER_AGENT],
+            "bot v2"
+        );
+    }
+
+    #[test]
+    fn test_with_default_header_invalid() {
+        assert!(matches!(
+            LighterConfig::new().with_default_header("invalid name", "1"),
+            Err(LighterError::Config(_))
+        ));
+        assert!(matches!(
+            LighterConfig::new().with_default_header("x-key", "line\nbreak"),
+            Err(LighterError::Config(_))
+        ));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(USER_AGENT, HeaderValue::from_bytes(b"caf\xe9").unwrap());
+        assert!(matches!(
+            LighterConfig::new()
+                .with_default_headers(headers)
+                .validate(),
+            Err(LighterError::Config(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_with_proxy() {
+        let mut proxy = mockito::Server::new_async().await;
+        // plain HTTP requests are forwarded to the proxy with the absolute URL
+        let status = proxy
+            .mock("GET", mockito::Matcher::Any)
+            .match_header("proxy-authorization", "Basic dXNlcjpwYXNz")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":200,"network_id":1,"timestamp":1}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let config = LighterConfig::new()
+            .with_base_url("http://lighter.invalid")
+            .unwrap()
+            .with_proxy(proxy.url())
+            .unwrap()
+            .with_proxy_auth("user", "pass");
+        let configuration = Configuration::try_from(&config).unwrap();
+
+        crate::apis::root_api::status(&configuration).await.unwrap();
+        status.assert_async().await;
+    }
+
+    #[test]
+    fn test_with_proxy_invalid_url() {
+        assert!(matches!(
+            LighterConfig::new().with_proxy("not a url"),
+            Err(LighterError::Config(_))
+        ));
+        assert!(matches!(
+            LighterConfig::new().with_proxy("ftp://localhost:21"),
+            Err(LighterError::Config(_))
+        ));
     }
 }
@@ -0,0 +1,263 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+
+use http::Extensions;
+use reqwest::{Request, Response};
+use reqwest_middleware::{Middleware, Next};
+
+/// Counters of the HTTP requests and of the connections opened to send them, shared by all the
+/// clients built from a config (see [`LighterConfig::connection_metrics`]).
+///
+/// The requests sent without opening a connection reused one of the pool, so a low reuse (see
+/// [`ConnectionStats::reused`]) means that `pool_max_idle_per_host` or `pool_idle_timeout` are too
+/// low for the request rate. The idle connections of the pool aren't exposed by `reqwest`, their
+/// number is at most `pool_max_idle_per_host`.
+///
+/// [`LighterConfig::connection_metrics`]: crate::LighterConfig::connection_metrics
+#[derive(Debug, Default)]
+pub struct ConnectionMetrics {
+    requests: AtomicU64,
+    opened: AtomicU64,
+    failed: AtomicU64,
+}
+
+/// Snapshot of the [`ConnectionMetrics`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConnectionStats {
+    /// Requests sent, each retry counting as a request
+    pub requests: u64,
+    /// Connections opened
+    pub opened: u64,
+    /// Connections that could not be opened (DNS, TCP or TLS failures)
+    pub connect_failures: u64,
+}
+
+impl ConnectionStats {
+    /// Requests sent on a connection already open
+    pub fn reused(&self) -> u64 {
+        self.requests.saturating_sub(self.opened)
+    }
+
+    /// Share of the requests sent on a connection already open, `None` before the first request
+    pub fn reuse_ratio(&self) -> Option<f64> {
+        (self.requests > 0).then(|| self.reused() as f64 / self.requests as f64)
+    }
+}
+
+impl ConnectionMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn snapshot(&self) -> ConnectionStats {
+        ConnectionStats {
+            requests: self.requests.load(Ordering::Relaxed),
+            opened: self.opened.load(Ordering::Relaxed),
+            connect_failures: self.failed.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Connector layer counting the connections opened by the `reqwest` client
+#[derive(Debug, Clone)]
+pub(crate) struct CountConnectionsLayer {
+    metrics: Arc<ConnectionMetrics>,
+}
+
+impl CountConnectionsLayer {
+    pub fn new(metrics: Arc<ConnectionMetrics>) -> Self {
+        Self { metrics }
+    }
+}
+
+impl<S> tower::Layer<S> for CountConnectionsLayer {
+    type Service = CountConnections<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CountConnections {
+            inner,
+            metrics: self.metrics.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct CountConnections<S> {
+    inner: S,
+    metrics: Arc<ConnectionMetrics>,
+}
+
+impl<S, R> tower::Service<R> for CountConnections<S>
+where
+    S: tower::Service<R>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = std::result::Result<S::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<std::result::Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: R) -> Self::Future {
+        let connect = self.inner.call(req);
+        let metrics = self.metrics.clone();
+        Box::pin(async move {
+            let res = connect.await;
+            let counter = match res {
+                Ok(_) => &metrics.opened,
+                Err(_) => &metrics.failed,
+            };
+            counter.fetch_add(1, Ordering::Relaxed);
+            res
+        })
+    }
+}
+
+/// Middleware counting the requests. It must come after the retry middleware, so that every
+/// attempt is counted.
+pub(crate) struct ConnectionStatsMiddleware {
+    metrics: Arc<ConnectionMetrics>,
+}
+
+impl ConnectionStatsMiddleware {
+    pub fn new(metrics: Arc<ConnectionMetrics>) -> Self {
+        Self { metrics }
+    }
+}
+
+#[async_trait::async_trait]
+impl Middleware for ConnectionStatsMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        self.metrics.requests.fetch_add(1, Ordering::Relaxed);
+        next.run(req, extensions).await
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use super::*;
+    use crate::{
+        apis::{self, configuration::Configuration},
+        config::LighterConfig,
+    };
+
+    /// Answers the status requests (and the txs sent, as accepted), keeping the connections alive
+    /// (unlike mockito)
+    pub(crate) async fn keep_alive_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                tokio::spawn(async move {
+                    let body = r#"{"status":200,"network_id":1,"timestamp":1,"code":200,"tx_hash":"0x1","predicted_execution_time_ms":1}"#;
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{body}",
+                        body.len()
+                    );
+                    let mut request = Vec::new();
+                    let mut buf = [0; 1024];
+                    while let Ok(n) = socket.read(&mut buf).await {
+                        if n == 0 {
+                            break;
+                        }
+                        request.extend_from_slice(&buf[..n]);
+                        // the requests of the txs come with a body, the others don't
+                        while let Some(end) = request.windows(4).position(|v| v == b"\r\n\r\n") {
+                            let len = content_length(&request[..end]);
+                            if request.len() < end + 4 + len {
+                                break;
+                            }
+                            request.drain(..end + 4 + len);
+                            socket.write_all(response.as_bytes()).await.unwrap();
+                        }
+                    }
+                });
+            }
+        });
+
+        format!("http://{addr}")
+    }
+
+    fn content_length(headers: &[u8]) -> usize {
+        String::from_utf8_lossy(headers)
+            .lines()
+            .find_map(|v| {
+                let (name, value) = v.split_once(':')?;
+                name.eq_ignore_ascii_case("content-length")
+                    .then(|| value.trim().parse().ok())?
+            })
+            .unwrap_or(0)
+    }
+
+    async fn send_status(config: LighterConfig, count: usize) -> ConnectionStats {
+        let config = LighterConfig {
+            base_url: keep_alive_server().await,
+            ..config
+        };
+        // e.g. two different APIs, they share the metrics but not the pool
+        let first = Configuration::try_from(&config).unwrap();
+        let second = Configuration::try_from(&config).unwrap();
+        for _ in 0..count {
+            apis::root_api::status(&first).await.unwrap();
+        }
+        apis::root_api::status(&second).await.unwrap();
+
+        config.connection_metrics.snapshot()
+    }
+
+    #[tokio::test]
+    async fn test_connection_stats_reused() {
+        let stats = send_status(LighterConfig::new(), 3).await;
+        assert_eq!(stats.requests, 4);
+        assert_eq!(stats.opened, 2);
+        assert_eq!(stats.connect_failures, 0);
+        assert_eq!(stats.reused(), 2);
+        assert_eq!(stats.reuse_ratio(), Some(0.5));
+    }
+
+    #[tokio::test]
+    async fn test_connection_stats_without_pool() {
+        let config = LighterConfig::new().with_pool_max_idle_per_host(0);
+        let stats = send_status(config, 3).await;
+        assert_eq!(stats.requests, 4);
+        assert_eq!(stats.opened, 4);
+        assert_eq!(stats.reused(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_connection_stats_connect_failure() {
+        let config = LighterConfig {
+            base_url: "http://127.0.0.1:1".into(),
+            retry_config: None,
+            ..LighterConfig::new()
+        };
+        let configuration = Configuration::try_from(&config).unwrap();
+        assert!(apis::root_api::status(&configuration).await.is_err());
+
+        let stats = config.connection_metrics.snapshot();
+        assert_eq!(stats.requests, 1);
+        assert_eq!(stats.opened, 0);
+        assert_eq!(stats.connect_failures, 1);
+        assert_eq!(ConnectionStats::default().reuse_ratio(), None);
+    }
+}
@@ -1,6 +1,6 @@
 use thiserror::Error;
 
-use crate::apis;
+use crate::{apis, circuit_breaker::CircuitOpen, models::ResultCode};
 
 #[derive(Error, Debug)]
 pub enum LighterError {
@@ -14,7 +14,7 @@ pub enum LighterError {
     IO(#[from] std::io::Error),
 
     #[error("Middleware error: {0}")]
-    Middleware(#[from] reqwest_middleware::Error),
+    Middleware(reqwest_middleware::Error),
 
     #[error("WebSocket error: {0}")]
     WebSocket(#[from] Box<tungstenite::Error>),
@@ -22,8 +22,12 @@ pub enum LighterError {
     #[error("Signing error: {0}")]
     Signing(String),
 
-    #[error("API error: {status} - {message}")]
-    Api { status: u16, message: String },
+    #[error("API error: {http_status} - {}{message}", code.map(|c| format!("[{c}] ")).unwrap_or_default())]
+    ApiError {
+        http_status: u16,
+        code: Option<i64>,
+        message: String,
+    },
 
     #[error("Invalid configuration: {0}")]
     Config(String),
@@ -34,6 +38,9 @@ pub enum LighterError {
     #[error("Rate limit exceeded")]
     RateLimit,
 
+    #[error("Circuit open for {0}, not sending the request")]
+    CircuitOpen(String),
+
     #[error("Invalid nonce: {0}")]
     Nonce(String),
 
@@ -43,9 +50,18 @@ pub enum LighterError {
     #[error("Invalid account state: {0}")]
     AccountState(String),
 
+    #[error("Account not found: {0}")]
+    AccountNotFound(String),
+
+    #[error("Market not found: {0}")]
+    MarketNotFound(String),
+
     #[error("Order validation failed: {0}")]
     OrderValidation(String),
 
+    #[error("Order book gap: expected offset {expected}, got {got}")]
+    OrderBookGap { expected: i64, got: i64 },
+
     #[error("Unknown error: {0}")]
     Unknown(String),
 
@@ -55,17 +71,136 @@ pub enum LighterError {
 
 pub type Result<T> = std::result::Result<T, LighterError>;
 
+/// Error codes returned by the Lighter API in the error body.
+///
+/// Codes not known by the SDK are kept in `Unknown` so that they can still be inspected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LighterErrorCode {
+    InvalidParam,
+    InvalidAuth,
+    AccountNotFound,
+    NonceTooLow,
+    InvalidSignature,
+    TxNotFound,
+    InsufficientMargin,
+    RateLimited,
+    Unknown(i64),
+}
+
+impl LighterErrorCode {
+    pub fn code(&self) -> i64 {
+        match self {
+            Self::InvalidParam => 20001,
+            Self::InvalidAuth => 20013,
+            Self::AccountNotFound => 21100,
+            Self::NonceTooLow => 21104,
+            Self::InvalidSignature => 21120,
+            Self::TxNotFound => 21500,
+            Self::InsufficientMargin => 21706,
+            Self::RateLimited => 23000,
+            Self::Unknown(code) => *code,
+        }
+    }
+}
+
+impl From<i64> for LighterErrorCode {
+    fn from(code: i64) -> Self {
+        match code {
+            20001 => Self::InvalidParam,
+            20013 => Self::InvalidAuth,
+            21100 => Self::AccountNotFound,
+            21104 => Self::NonceTooLow,
+            21120 => Self::InvalidSignature,
+            21500 => Self::TxNotFound,
+            21706 => Self::InsufficientMargin,
+            23000 => Self::RateLimited,
+            code => Self::Unknown(code),
+        }
+    }
+}
+
+impl LighterError {
+    /// Error returned by what needs the signer library when built without the `signer` feature
+    #[cfg(not(feature = "signer"))]
+    pub(crate) fn signer_disabled(what: &str) -> Self {
+        LighterError::Config(format!("{what} requires the `signer` feature"))
+    }
+
+    /// Returns the API error code, if the error comes from an API response carrying one
+    pub fn api_code(&self) -> Option<LighterErrorCode> {
+        match self {
+            LighterError::ApiError {
+                code: Some(code), ..
+            } => Some((*code).into()),
+            _ => None,
+        }
+    }
+
+    /// Returns the HTTP status of the response the error comes from, if there was one
+    pub fn http_status(&self) -> Option<u16> {
+        match self {
+            LighterError::ApiError { http_status, .. } => Some(*http_status),
+            LighterError::Http(e) => e.status().map(|s| s.as_u16()),
+            LighterError::Middleware(e) => e.status().map(|s| s.as_u16()),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the request failed before getting an answer (timeout, connection lost,
+    /// server error), so it may have been executed anyway
+    pub fn is_transient(&self) -> bool {
+        match self {
+            LighterError::Http(_) | LighterError::Middleware(_) => true,
+            LighterError::ApiError {
+                http_status,
+                code: None,
+                ..
+            } => *http_status >= 500,
+            _ => false,
+        }
+    }
+
+    /// Builds an `ApiError` from the HTTP status and the response body, parsing the
+    /// `{"code": .., "message": ..}` body returned by the API when possible
+    pub(crate) fn from_response(http_status: u16, content: String) -> Self {
+        match serde_json::from_str::<ResultCode>(&content) {
+            Ok(result) => LighterError::ApiError {
+                http_status,
+                code: Some(result.code as i64),
+                message: result.message.unwrap_or(content),
+            },
+            Err(_) => LighterError::ApiError {
+                http_status,
+                code: None,
+                message: content,
+            },
+        }
+    }
+}
+
+impl From<reqwest_middleware::Error> for LighterError {
+    fn from(error: reqwest_middleware::Error) -> Self {
+        match &error {
+            reqwest_middleware::Error::Middleware(e) => match e.downcast_ref::<CircuitOpen>() {
+                Some(open) => LighterError::CircuitOpen(open.host().to_string()),
+                None => LighterError::Middleware(error),
+            },
+            reqwest_middleware::Error::Reqwest(_) => LighterError::Middleware(error),
+        }
+    }
+}
+
 impl<T> From<apis::Error<T>> for LighterError {
     fn from(value: apis::Error<T>) -> Self {
         match value {
             apis::Error::Reqwest(error) => LighterError::Http(Box::new(error)),
-            apis::Error::Middleware(error) => LighterError::Middleware(error),
+            apis::Error::Middleware(error) => error.into(),
             apis::Error::Serde(error) => LighterError::Json(error),
             apis::Error::Io(error) => LighterError::IO(error),
-            apis::Error::ResponseError(response_content) => LighterError::Api {
-                status: response_content.status.into(),
-                message: response_content.content,
-            },
+            apis::Error::ResponseError(response_content) => LighterError::from_response(
+                response_content.status.into(),
+                response_content.content,
+            ),
         }
     }
 }
@@ -82,13 +217,102 @@ mod tests {
         let error = LighterError::RateLimit;
         assert_eq!(error.to_string(), "Rate limit exceeded");
 
-        let error = LighterError::Api {
-            status: 404,
+        let error = LighterError::ApiError {
+            http_status: 404,
+            code: None,
             message: "Order not found".to_string(),
         };
         assert_eq!(error.to_string(), "API error: 404 - Order not found");
     }
 
+    #[test]
+    fn test_api_error_display() {
+        let error = LighterError::ApiError {
+            http_status: 400,
+            code: Some(21100),
+            message: "account not found".to_string(),
+        };
+        assert_eq!(
+            error.to_string(),
+            "API error: 400 - [21100] account not found"
+        );
+
+        let error = LighterError::ApiError {
+            http_status: 502,
+            code: None,
+            message: "Bad Gateway".to_string(),
+        };
+        assert_eq!(error.to_string(), "API error: 502 - Bad Gateway");
+    }
+
+    #[test]
+    fn test_api_error_from_response() {
+        let error = LighterError::from_response(
+            400,
+            r#"{"code":21100,"message":"account not found"}"#.into(),
+        );
+        assert!(matches!(
+            error,
+            LighterError::ApiError {
+                http_status: 400,
+                code: Some(21100),
+                ref message,
+            } if message == "account not found"
+        ));
+        assert_eq!(error.api_code(), Some(LighterErrorCode::AccountNotFound));
+
+        let error = LighterError::from_response(502, "Bad Gateway".into());
+        assert!(matches!(
+            error,
+            LighterError::ApiError {
+                http_status: 502,
+                code: None,
+                ..
+            }
+        ));
+        assert_eq!(error.api_code(), None);
+    }
+
+    #[test]
+    fn test_error_http_status() {
+        let error = LighterError::from_response(404, "404 page not found".into());
+        assert_eq!(error.http_status(), Some(404));
+        assert_eq!(error.to_string(), "API error: 404 - 404 page not found");
+
+        let error = LighterError::from_response(
+            429,
+            r#"{"code":23000,"message":"too many requests"}"#.into(),
+        );
+        assert_eq!(error.http_status(), Some(429));
+
+        assert_eq!(LighterError::Config("".into()).http_status(), None);
+    }
+
+    #[test]
+    fn test_error_code_mapping() {
+        assert_eq!(LighterErrorCode::from(20013), LighterErrorCode::InvalidAuth);
+        assert_eq!(LighterErrorCode::from(21104), LighterErrorCode::NonceTooLow);
+        assert_eq!(LighterErrorCode::from(23000), LighterErrorCode::RateLimited);
+        assert_eq!(LighterErrorCode::from(1), LighterErrorCode::Unknown(1));
+
+        for code in [20001, 20013, 21100, 21104, 21120, 21500, 21706, 23000, 1] {
+            assert_eq!(LighterErrorCode::from(code).code(), code);
+        }
+    }
+
+    #[test]
+    fn test_error_is_transient() {
+        let error = |http_status, code| LighterError::ApiError {
+            http_status,
+            code,
+            message: String::new(),
+        };
+        assert!(error(503, None).is_transient());
+        assert!(!error(500, Some(21104)).is_transient());
+        assert!(!error(400, None).is_transient());
+        assert!(!LighterError::Config("".into()).is_transient());
+    }
+
     #[tokio::test]
     async fn test_error_from_reqwest() {
         // This tests the automatic conversion from reqwest::Error
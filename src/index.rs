@@ -0,0 +1,85 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// Index of a market (e.g. `0` for ETH).
+///
+/// The convenience methods take `impl Into<MarketIndex>` (and `impl Into<AccountIndex>`), so raw
+/// integers are still accepted, while passing the typed indexes makes a swapped argument a
+/// compile error.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct MarketIndex(pub i32);
+
+/// Index of an account (main account, sub account or public pool)
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct AccountIndex(pub i64);
+
+impl From<i32> for MarketIndex {
+    fn from(val: i32) -> Self {
+        Self(val)
+    }
+}
+
+impl From<MarketIndex> for i32 {
+    fn from(val: MarketIndex) -> Self {
+        val.0
+    }
+}
+
+impl fmt::Display for MarketIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl From<i64> for AccountIndex {
+    fn from(val: i64) -> Self {
+        Self(val)
+    }
+}
+
+impl From<i32> for AccountIndex {
+    fn from(val: i32) -> Self {
+        Self(val.into())
+    }
+}
+
+impl From<AccountIndex> for i64 {
+    fn from(val: AccountIndex) -> Self {
+        val.0
+    }
+}
+
+impl fmt::Display for AccountIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_conversions() {
+        assert_eq!(MarketIndex::from(3), MarketIndex(3));
+        assert_eq!(i32::from(MarketIndex(3)), 3);
+        assert_eq!(AccountIndex::from(28), AccountIndex(28));
+        assert_eq!(AccountIndex::from(28_i64), AccountIndex(28));
+        assert_eq!(i64::from(AccountIndex(28)), 28);
+        assert_eq!(format!("account/{}", AccountIndex(28)), "account/28");
+
+        // serialized as the raw integer
+        assert_eq!(serde_json::to_string(&MarketIndex(3)).unwrap(), "3");
+        assert_eq!(
+            serde_json::from_str::<AccountIndex>("28").unwrap(),
+            AccountIndex(28)
+        );
+    }
+}
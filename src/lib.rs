@@ -10,6 +10,19 @@
 //! - **Pluggable APIs** - Support for REST APIs exposed by the Lighter platform, which can be enabled all or only a specific subset of them.
 //! - **Ethereum signing** - Built-in support for Ethereum-compatible wallet signing
 //!
+//! ## Cargo features
+//!
+//! - `signer` (default) - Loads the native `lighter-signer` library on first use, needed to sign
+//!   the txs and to create the auth tokens. It's taken from the `libs` of the crate, unless
+//!   `LIGHTER_SIGNER_LIB` is set to its path at runtime. Without the feature only the endpoints
+//!   not requiring them are usable.
+//! - `blocking` - Synchronous facade over `HttpClient`, see [`blocking`].
+//! - `rustls-tls` (default) - TLS of the HTTP and WS clients through `rustls`, with bundled CA
+//!   certs, so that neither OpenSSL nor a system CA bundle is needed (e.g. in scratch images).
+//! - `native-tls` - TLS of the platform instead (OpenSSL on Linux, vendored). It takes precedence
+//!   over `rustls-tls` when both are enabled, so disable the default features to build a single
+//!   TLS stack. One of the two is required.
+//!
 //! ## Quick Start
 //!
 //! ```ignore
@@ -38,15 +51,47 @@
 //! }
 //! ```
 //!
+#[cfg(not(any(feature = "rustls-tls", feature = "native-tls")))]
+compile_error!("either the `rustls-tls` or the `native-tls` feature must be enabled");
+
 pub mod api; // implementation of the APIs interfaces
 mod apis; // openapi generated
+#[cfg(feature = "blocking")]
+pub mod blocking; // synchronous facade over the async client
+mod circuit_breaker;
 mod client; // module containing http/ws clients
+mod clock;
+mod coalesce;
 mod config;
+mod connection_stats;
+mod index;
 pub mod log;
 pub mod models; // openapi generated, needed for requests/responses
+mod observer;
+mod rate_limit;
+mod request;
+mod retry;
+#[cfg(feature = "signer")]
 pub mod signer; // module containing the interface to the `lighter-go` lib, used for signing
-pub use config::LighterConfig;
+pub use apis::configuration::Configuration;
+pub use circuit_breaker::CircuitBreaker;
+pub use clock::ServerClock;
+pub use coalesce::RequestCoalescer;
+pub use config::{KeepaliveConfig, LighterConfig, Network, RetryConfig, RetryJitter};
+pub use connection_stats::{ConnectionMetrics, ConnectionStats};
+pub use index::{AccountIndex, MarketIndex};
 mod error;
-pub use error::{LighterError, Result};
+pub use error::{LighterError, LighterErrorCode, Result};
+pub use observer::{RequestInfo, RequestObserver};
+pub use rate_limit::RateLimiter;
+pub use request::RequestOptions;
+pub use retry::{DefaultRetryStrategy, RetryContext, RetryStrategy};
 
-pub use crate::{client::HttpClient, signer::Signer};
+pub use crate::client::{
+    AccountInfo, AccountUpdate, CandlestickStream, ClientOrderIdGenerator, EventBus,
+    ExchangeMetadata, FileNonceStore, HttpClient, HttpClientBuilder, LighterEvent, MetadataCache,
+    NonceStore, NotificationUpdate, OrderBook, OrderBookUpdate, OverflowPolicy, TradeUpdate,
+    WsClient, WsOrderBook, WsSubscription,
+};
+#[cfg(feature = "signer")]
+pub use crate::{client::DeadMansSwitch, signer::Signer};
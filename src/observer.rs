@@ -0,0 +1,142 @@
+use std::{
+    fmt::Debug,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use http::Extensions;
+use reqwest::{Method, Request, Response};
+use reqwest_middleware::{Middleware, Next};
+
+/// Request being observed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestInfo {
+    pub method: Method,
+    pub path: String, // e.g. `/api/v1/account`
+}
+
+impl From<&Request> for RequestInfo {
+    fn from(req: &Request) -> Self {
+        Self {
+            method: req.method().clone(),
+            path: req.url().path().to_string(),
+        }
+    }
+}
+
+/// Hooks invoked for every HTTP request, e.g. for recording metrics.
+/// All of them are no-op by default.
+pub trait RequestObserver: Debug + Send + Sync {
+    /// Called before the request is sent (only once, even if it's retried)
+    fn on_request(&self, _request: &RequestInfo) {}
+
+    /// Called with the final outcome of the request, after any retry. `status` is `None` if no
+    /// response was received.
+    fn on_response(&self, _request: &RequestInfo, _status: Option<u16>, _elapsed: Duration) {}
+
+    /// Called before the `attempt`-th retry of the request, which is sent after `delay`
+    fn on_retry(&self, _request: &RequestInfo, _attempt: u32, _delay: Duration) {}
+}
+
+/// Middleware calling the `RequestObserver` hooks. It must be the outermost one, so that it sees
+/// the final outcome of the retried requests.
+pub(crate) struct ObserverMiddleware {
+    observer: Arc<dyn RequestObserver>,
+}
+
+impl ObserverMiddleware {
+    pub fn new(observer: Arc<dyn RequestObserver>) -> Self {
+        Self { observer }
+    }
+}
+
+#[async_trait::async_trait]
+impl Middleware for ObserverMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        let info = RequestInfo::from(&req);
+        self.observer.on_request(&info);
+
+        let start = Instant::now();
+        let result = next.run(req, extensions).await;
+        let status = result.as_ref().ok().map(|resp| resp.status().as_u16());
+        self.observer.on_response(&info, status, start.elapsed());
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::{
+        apis::{self, configuration::Configuration},
+        config::{LighterConfig, RetryConfig},
+    };
+
+    #[derive(Debug, Default)]
+    struct RecordingObserver {
+        events: Mutex<Vec<String>>,
+    }
+
+    impl RequestObserver for RecordingObserver {
+        fn on_request(&self, request: &RequestInfo) {
+            let event = format!("request {} {}", request.method, request.path);
+            self.events.lock().unwrap().push(event);
+        }
+
+        fn on_response(&self, request: &RequestInfo, status: Option<u16>, _elapsed: Duration) {
+            let event = format!("response {} {status:?}", request.path);
+            self.events.lock().unwrap().push(event);
+        }
+
+        fn on_retry(&self, request: &RequestInfo, attempt: u32, _delay: Duration) {
+            let event = format!("retry {} #{attempt}", request.path);
+            self.events.lock().unwrap().push(event);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_request_observer() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/")
+            .with_status(503)
+            .expect(1)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":200,"network_id":1,"timestamp":1}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let observer = Arc::new(RecordingObserver::default());
+        let config = LighterConfig {
+            base_url: server.url(),
+            ..LighterConfig::new()
+                .with_retry_config(RetryConfig {
+                    max_retries: 1,
+                    min_retry_interval: 10,
+                    max_retry_interval: 10,
+                    ..RetryConfig::default()
+                })
+                .with_request_observer(observer.clone())
+        };
+        let configuration = Configuration::try_from(&config).unwrap();
+
+        apis::root_api::status(&configuration).await.unwrap();
+        assert_eq!(
+            *observer.events.lock().unwrap(),
+            vec!["request GET /", "retry / #1", "response / Some(200)"]
+        );
+    }
+}
@@ -0,0 +1,155 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use http::Extensions;
+use reqwest::{Request, Response};
+use reqwest_middleware::{Middleware, Next};
+
+use crate::{LighterError, Result};
+
+/// Token bucket limiting the rate of the HTTP requests.
+///
+/// Requests exceeding the budget are not rejected, they wait for their turn (in order).
+#[derive(Debug)]
+pub struct RateLimiter {
+    rate: f64,  // tokens per second
+    burst: f64, // bucket capacity
+    state: Mutex<BucketState>,
+}
+
+#[derive(Debug)]
+struct BucketState {
+    tokens: f64, // it goes negative when requests are waiting
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(requests_per_second: u32, burst: u32) -> Result<Self> {
+        if requests_per_second == 0 || burst == 0 {
+            return Err(LighterError::Config(
+                "`requests_per_second` and `burst` must be greater than 0".into(),
+            ));
+        }
+
+        Ok(Self {
+            rate: requests_per_second as f64,
+            burst: burst as f64,
+            state: Mutex::new(BucketState {
+                tokens: burst as f64,
+                last_refill: Instant::now(),
+            }),
+        })
+    }
+
+    /// Takes a token, returning how long to wait before it can be used
+    fn reserve(&self) -> Duration {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+
+        let now = Instant::now();
+        let refill = now.duration_since(state.last_refill).as_secs_f64() * self.rate;
+        state.tokens = (state.tokens + refill).min(self.burst);
+        state.last_refill = now;
+
+        state.tokens -= 1.0;
+        if state.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-state.tokens / self.rate)
+        }
+    }
+
+    /// Waits until a request can be sent
+    pub async fn acquire(&self) {
+        let delay = self.reserve();
+        if !delay.is_zero() {
+            tracing::debug!("rate limited, waiting {delay:?}");
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+/// Middleware throttling the requests with a shared `RateLimiter`. It must come after the retry
+/// middleware, so that every attempt is throttled.
+pub(crate) struct RateLimitMiddleware {
+    limiter: Arc<RateLimiter>,
+}
+
+impl RateLimitMiddleware {
+    pub fn new(limiter: Arc<RateLimiter>) -> Self {
+        Self { limiter }
+    }
+}
+
+#[async_trait::async_trait]
+impl Middleware for RateLimitMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        self.limiter.acquire().await;
+        next.run(req, extensions).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        apis::{self, configuration::Configuration},
+        config::LighterConfig,
+    };
+
+    #[test]
+    fn test_rate_limiter_burst() {
+        let limiter = RateLimiter::new(10, 3).unwrap();
+        for _ in 0..3 {
+            assert!(limiter.reserve().is_zero());
+        }
+        // the following ones are spaced by 100ms each
+        let delay = limiter.reserve();
+        assert!(delay > Duration::from_millis(90) && delay <= Duration::from_millis(100));
+        let delay = limiter.reserve();
+        assert!(delay > Duration::from_millis(190) && delay <= Duration::from_millis(200));
+    }
+
+    #[test]
+    fn test_rate_limiter_invalid() {
+        assert!(RateLimiter::new(0, 1).is_err());
+        assert!(RateLimiter::new(1, 0).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_shared_across_configurations() {
+        let mut server = mockito::Server::new_async().await;
+        let status = server
+            .mock("GET", "/")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":200,"network_id":1,"timestamp":1}"#)
+            .expect(6)
+            .create_async()
+            .await;
+
+        let config = LighterConfig {
+            base_url: server.url(),
+            ..LighterConfig::new().with_rate_limit(10, 2).unwrap()
+        };
+        // e.g. two different APIs
+        let first = Configuration::try_from(&config).unwrap();
+        let second = Configuration::try_from(&config).unwrap();
+
+        let start = Instant::now();
+        for configuration in [&first, &second, &first, &second, &first, &second] {
+            apis::root_api::status(configuration).await.unwrap();
+        }
+        // 2 within the burst, then 1 every 100ms
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(390), "{elapsed:?}");
+        assert!(elapsed < Duration::from_millis(800), "{elapsed:?}");
+
+        status.assert_async().await;
+    }
+}
@@ -0,0 +1,99 @@
+use std::{future::Future, time::Duration};
+
+use http::Extensions;
+use reqwest::{Request, Response};
+use reqwest_middleware::{Middleware, Next};
+
+tokio::task_local! {
+    // timeout of the requests sent by the current call, see `RequestOptions::scope`
+    static REQUEST_TIMEOUT: Duration;
+}
+
+/// Options of a single call, overriding the ones of the `LighterConfig`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RequestOptions {
+    /// Timeout of each request (and of each of its retries), instead of `timeout_secs`
+    pub timeout: Option<Duration>,
+}
+
+impl RequestOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Runs `fut` applying the options to the requests it sends
+    pub(crate) async fn scope<F: Future>(&self, fut: F) -> F::Output {
+        match self.timeout {
+            Some(timeout) => REQUEST_TIMEOUT.scope(timeout, fut).await,
+            None => fut.await,
+        }
+    }
+}
+
+/// Middleware applying the timeout of the `RequestOptions` of the current call, if any. It must
+/// come before the retry middleware, so that the retries get it too.
+pub(crate) struct TimeoutMiddleware;
+
+#[async_trait::async_trait]
+impl Middleware for TimeoutMiddleware {
+    async fn handle(
+        &self,
+        mut req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        // the timeout of the request takes precedence over the one of the client
+        if let Ok(timeout) = REQUEST_TIMEOUT.try_with(|v| *v) {
+            *req.timeout_mut() = Some(timeout);
+        }
+        next.run(req, extensions).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        apis::{self, configuration::Configuration},
+        config::LighterConfig,
+    };
+
+    #[tokio::test]
+    async fn test_request_timeout_override() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/")
+            .with_header("content-type", "application/json")
+            .with_chunked_body(|w| {
+                std::thread::sleep(Duration::from_millis(1500));
+                w.write_all(br#"{"status":200,"network_id":1,"timestamp":1}"#)
+            })
+            .create_async()
+            .await;
+
+        let config = LighterConfig {
+            base_url: server.url(),
+            retry_config: None,
+            ..LighterConfig::new().with_timeout(1)
+        };
+        let configuration = Configuration::try_from(&config).unwrap();
+
+        // too slow for the config timeout
+        assert!(apis::root_api::status(&configuration).await.is_err());
+
+        // but not for the one of the call
+        let options = RequestOptions::new().with_timeout(Duration::from_secs(5));
+        let res = options.scope(apis::root_api::status(&configuration)).await;
+        assert!(res.is_ok());
+
+        // which can also be shorter
+        let options = RequestOptions::new().with_timeout(Duration::from_millis(100));
+        let res = options.scope(apis::root_api::status(&configuration)).await;
+        assert!(res.is_err());
+    }
+}
@@ -0,0 +1,493 @@
+use std::{
+    fmt::Debug,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
+};
+
+use anyhow::anyhow;
+use http::{Extensions, StatusCode};
+use reqwest::{
+    header::{HeaderMap, RETRY_AFTER},
+    Request, Response, ResponseBuilderExt,
+};
+use reqwest_middleware::{Middleware, Next};
+use reqwest_retry::{policies::ExponentialBackoff, RetryDecision, RetryPolicy, Retryable};
+use serde::Deserialize;
+use tracing::Instrument;
+
+use crate::observer::{RequestInfo, RequestObserver};
+
+/// Outcome of an attempt, given to a [`RetryStrategy`]
+#[derive(Debug)]
+pub struct RetryContext<'a> {
+    /// Status of the response, `None` if the request failed
+    pub status: Option<StatusCode>,
+    /// `code` of the JSON body of the response, if any, e.g. a
+    /// [`LighterErrorCode`](crate::LighterErrorCode). Only parsed if
+    /// [`RetryStrategy::needs_code`].
+    pub code: Option<i64>,
+    /// Why the request failed (e.g. a timeout or a connection reset), `None` if there is a
+    /// response
+    pub error: Option<&'a reqwest_middleware::Error>,
+}
+
+/// Decides which attempts are retried, see
+/// [`LighterConfig::with_retry_strategy`](crate::LighterConfig::with_retry_strategy). The
+/// retries still follow the backoff and the limits of the `retry_config`.
+pub trait RetryStrategy: Debug + Send + Sync {
+    /// Returns `true` to retry the attempt
+    fn should_retry(&self, context: &RetryContext<'_>) -> bool;
+
+    /// Returns `false` if [`RetryContext::code`] isn't needed, so that the response body isn't
+    /// buffered to parse it
+    fn needs_code(&self) -> bool {
+        true
+    }
+}
+
+/// Retries on `429`, on the server errors and on the transient request failures (e.g. timeouts
+/// or connection resets)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultRetryStrategy;
+
+impl RetryStrategy for DefaultRetryStrategy {
+    fn should_retry(&self, context: &RetryContext<'_>) -> bool {
+        match (context.status, context.error) {
+            (Some(status), _) => {
+                status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+            }
+            (None, Some(error)) => matches!(
+                reqwest_retry::default_on_request_failure(error),
+                Some(Retryable::Transient)
+            ),
+            (None, None) => false,
+        }
+    }
+
+    fn needs_code(&self) -> bool {
+        false
+    }
+}
+
+/// Body of the responses, only the code is parsed
+#[derive(Deserialize)]
+struct BodyCode {
+    code: i64,
+}
+
+/// Reads the body of the response to parse its code, returning an identical response
+async fn parse_code(resp: Response) -> reqwest_middleware::Result<(Response, Option<i64>)> {
+    let mut builder = http::Response::builder()
+        .status(resp.status())
+        .version(resp.version())
+        .url(resp.url().clone());
+    if let Some(headers) = builder.headers_mut() {
+        headers.extend(resp.headers().clone());
+    }
+
+    let body = resp.bytes().await?;
+    let code = serde_json::from_slice::<BodyCode>(&body)
+        .ok()
+        .map(|v| v.code);
+    let resp = builder
+        .body(body)
+        .map_err(|e| reqwest_middleware::Error::Middleware(e.into()))?;
+
+    Ok((resp.into(), code))
+}
+
+/// Retries the attempts selected by the `RetryStrategy` (`DefaultRetryStrategy` by default)
+/// with an exponential backoff.
+/// When the response has a `Retry-After` header, the delay it requests is used instead, capped at
+/// `max_retry_interval`.
+///
+/// Every request runs in an `http_request` span, recording the number of `attempts`, the total
+/// `elapsed_ms` (retries included) and the final `status` (if there was a response) once it
+/// completes.
+pub(crate) struct RetryMiddleware {
+    policy: ExponentialBackoff,
+    max_retry_interval: Duration,
+    observer: Option<Arc<dyn RequestObserver>>,
+    strategy: Arc<dyn RetryStrategy>,
+}
+
+impl RetryMiddleware {
+    pub fn new(
+        policy: ExponentialBackoff,
+        max_retry_interval: Duration,
+        observer: Option<Arc<dyn RequestObserver>>,
+    ) -> Self {
+        Self {
+            policy,
+            max_retry_interval,
+            observer,
+            strategy: Arc::new(DefaultRetryStrategy),
+        }
+    }
+
+    pub fn with_strategy(mut self, strategy: Arc<dyn RetryStrategy>) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    async fn should_retry(
+        &self,
+        result: reqwest_middleware::Result<Response>,
+    ) -> (reqwest_middleware::Result<Response>, bool) {
+        let (result, code) = match result {
+            Ok(resp) if self.strategy.needs_code() => match parse_code(resp).await {
+                Ok((resp, code)) => (Ok(resp), code),
+                Err(e) => (Err(e), None),
+            },
+            result => (result, None),
+        };
+
+        let context = RetryContext {
+            status: result.as_ref().ok().map(Response::status),
+            code,
+            error: result.as_ref().err(),
+        };
+        let retry = self.strategy.should_retry(&context);
+        (result, retry)
+    }
+
+    /// Sends the request, retrying it when needed. Returns the outcome with the number of attempts.
+    async fn send(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> (reqwest_middleware::Result<Response>, u32) {
+        let start_time = SystemTime::now();
+        let mut n_past_retries = 0;
+        loop {
+            let Some(duplicate_request) = req.try_clone() else {
+                let error = reqwest_middleware::Error::Middleware(anyhow!(
+                    "Request object is not cloneable. Are you passing a streaming body?"
+                ));
+                return (Err(error), n_past_retries + 1);
+            };
+
+            let result = next.clone().run(duplicate_request, extensions).await;
+
+            let (result, retry) = self.should_retry(result).await;
+            if retry {
+                if let RetryDecision::Retry { execute_after } =
+                    self.policy.should_retry(start_time, n_past_retries)
+                {
+                    let delay = result
+                        .as_ref()
+                        .ok()
+                        .and_then(|resp| retry_after(resp.headers()))
+                        .map(|delay| delay.min(self.max_retry_interval))
+                        .unwrap_or_else(|| {
+                            execute_after
+                                .duration_since(SystemTime::now())
+                                .unwrap_or_default()
+                        });
+                    tracing::warn!(
+                        "retry attempt #{n_past_retries}, sleeping {delay:?} before the next attempt"
+                    );
+                    if let Some(observer) = &self.observer {
+                        observer.on_retry(&RequestInfo::from(&req), n_past_retries + 1, delay);
+                    }
+                    tokio::time::sleep(delay).await;
+
+                    n_past_retries += 1;
+                    continue;
+                }
+            }
+
+            return (result, n_past_retries + 1);
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Middleware for RetryMiddleware {
+    async fn handle(
+        &self,
+        req: Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<Response> {
+        let span = tracing::debug_span!(
+            "http_request",
+            method = %req.method(),
+            path = req.url().path(),
+            attempts = tracing::field::Empty,
+            elapsed_ms = tracing::field::Empty,
+            status = tracing::field::Empty,
+        );
+
+        let start = Instant::now();
+        let (result, attempts) = self
+            .send(req, extensions, next)
+            .instrument(span.clone())
+            .await;
+        span.record("attempts", attempts);
+        span.record("elapsed_ms", start.elapsed().as_millis() as u64);
+        if let Ok(resp) = &result {
+            span.record("status", resp.status().as_u16());
+        }
+
+        result
+    }
+}
+
+/// Parses the `Retry-After` header, either in seconds or as an HTTP date
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(date.duration_since(SystemTime::now()).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+    use crate::{
+        apis::{self, configuration::Configuration},
+        config::{LighterConfig, RetryConfig},
+    };
+
+    fn headers(retry_after: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, retry_after.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_retry_after_parsing() {
+        assert_eq!(retry_after(&headers("2")), Some(Duration::from_secs(2)));
+
+        let date = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(30));
+        let delay = retry_after(&headers(&date)).unwrap();
+        assert!(delay > Duration::from_secs(28) && delay <= Duration::from_secs(30));
+
+        // already passed
+        let date = httpdate::fmt_http_date(SystemTime::now() - Duration::from_secs(30));
+        assert_eq!(retry_after(&headers(&date)), Some(Duration::ZERO));
+
+        assert_eq!(retry_after(&headers("soon")), None);
+        assert_eq!(retry_after(&HeaderMap::new()), None);
+    }
+
+    #[tokio::test]
+    async fn test_retry_after_honored() {
+        let mut server = mockito::Server::new_async().await;
+        let rate_limited = server
+            .mock("GET", "/")
+            .with_status(429)
+            .with_header("retry-after", "2")
+            .expect(1)
+            .create_async()
+            .await;
+        let status = server
+            .mock("GET", "/")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":200,"network_id":1,"timestamp":1}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        // the backoff alone would wait way longer
+        let config = LighterConfig {
+            base_url: server.url(),
+            ..LighterConfig::new().with_retry_config(RetryConfig {
+                max_retries: 1,
+                min_retry_interval: 8000,
+                max_retry_interval: 10000,
+                ..RetryConfig::default()
+            })
+        };
+        let configuration = Configuration::try_from(&config).unwrap();
+
+        let start = Instant::now();
+        apis::root_api::status(&configuration).await.unwrap();
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_secs(2), "{elapsed:?}");
+        assert!(elapsed < Duration::from_secs(4), "{elapsed:?}");
+
+        rate_limited.assert_async().await;
+        status.assert_async().await;
+    }
+
+    /// Collects the `attempts` recorded on the spans
+    #[derive(Debug, Clone, Default)]
+    struct AttemptsLayer(Arc<std::sync::Mutex<Vec<u64>>>);
+
+    impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for AttemptsLayer {
+        fn on_record(
+            &self,
+            _span: &tracing::span::Id,
+            values: &tracing::span::Record<'_>,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            struct Visitor<'a>(&'a std::sync::Mutex<Vec<u64>>);
+
+            impl tracing::field::Visit for Visitor<'_> {
+                fn record_u64(&mut self, field: &tracing::field::Field, value: u64) {
+                    if field.name() == "attempts" {
+                        self.0.lock().unwrap().push(value);
+                    }
+                }
+
+                fn record_debug(&mut self, _: &tracing::field::Field, _: &dyn std::fmt::Debug) {}
+            }
+
+            values.record(&mut Visitor(&self.0));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_attempts_span() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/")
+            .with_status(503)
+            .expect(2)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":200,"network_id":1,"timestamp":1}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let config = LighterConfig {
+            base_url: server.url(),
+            ..LighterConfig::new().with_retry_config(RetryConfig {
+                max_retries: 3,
+                min_retry_interval: 10,
+                max_retry_interval: 20,
+                ..RetryConfig::default()
+            })
+        };
+        let configuration = Configuration::try_from(&config).unwrap();
+
+        let layer = AttemptsLayer::default();
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(layer.clone()));
+        apis::root_api::status(&configuration).await.unwrap();
+
+        assert_eq!(*layer.0.lock().unwrap(), vec![3]);
+    }
+
+    /// Retries the rate limited requests, whatever their status, and nothing else
+    #[derive(Debug)]
+    struct RateLimitedStrategy;
+
+    impl RetryStrategy for RateLimitedStrategy {
+        fn should_retry(&self, context: &RetryContext<'_>) -> bool {
+            context.code == Some(crate::LighterErrorCode::RateLimited.code())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_strategy() {
+        let mut server = mockito::Server::new_async().await;
+        let rate_limited = server
+            .mock("GET", "/")
+            .with_status(400)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"code":23000,"message":"too many requests"}"#)
+            .expect(2)
+            .create_async()
+            .await;
+        let status = server
+            .mock("GET", "/")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":200,"network_id":1,"timestamp":1}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let config = LighterConfig {
+            base_url: server.url(),
+            ..LighterConfig::new()
+                .with_retry_config(RetryConfig {
+                    max_retries: 3,
+                    min_retry_interval: 10,
+                    max_retry_interval: 20,
+                    ..RetryConfig::default()
+                })
+                .with_retry_strategy(Arc::new(RateLimitedStrategy))
+        };
+        let configuration = Configuration::try_from(&config).unwrap();
+
+        // the body is still read by the API once buffered
+        let resp = apis::root_api::status(&configuration).await.unwrap();
+        assert_eq!(resp.network_id, 1);
+        rate_limited.assert_async().await;
+        status.assert_async().await;
+
+        // the server errors aren't retried anymore
+        server.reset();
+        let unavailable = server
+            .mock("GET", "/")
+            .with_status(503)
+            .expect(1)
+            .create_async()
+            .await;
+        assert!(apis::root_api::status(&configuration).await.is_err());
+        unavailable.assert_async().await;
+    }
+
+    #[test]
+    fn test_default_retry_strategy() {
+        let context = |status| RetryContext {
+            status: Some(status),
+            code: None,
+            error: None,
+        };
+        assert!(DefaultRetryStrategy.should_retry(&context(StatusCode::TOO_MANY_REQUESTS)));
+        assert!(DefaultRetryStrategy.should_retry(&context(StatusCode::BAD_GATEWAY)));
+        assert!(!DefaultRetryStrategy.should_retry(&context(StatusCode::BAD_REQUEST)));
+        assert!(!DefaultRetryStrategy.should_retry(&context(StatusCode::OK)));
+        assert!(!DefaultRetryStrategy.needs_code());
+    }
+
+    #[tokio::test]
+    async fn test_retry_after_capped() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/")
+            .with_status(429)
+            .with_header("retry-after", "60")
+            .expect(1)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/")
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"status":200,"network_id":1,"timestamp":1}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let config = LighterConfig {
+            base_url: server.url(),
+            ..LighterConfig::new().with_retry_config(RetryConfig {
+                max_retries: 1,
+                min_retry_interval: 100,
+                max_retry_interval: 500,
+                ..RetryConfig::default()
+            })
+        };
+        let configuration = Configuration::try_from(&config).unwrap();
+
+        let start = Instant::now();
+        apis::root_api::status(&configuration).await.unwrap();
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+}
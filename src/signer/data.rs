@@ -1,9 +1,119 @@
-use crate::{api::order::GroupingType, signer::ffi::ffisigner};
+use std::{str::FromStr, time::Duration};
+
+use alloy::primitives::{Address, Signature};
+use base64::{prelude::BASE64_STANDARD, Engine};
+use chrono::{DateTime, Utc};
+use secrecy::SecretString;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{
+    api::{
+        account::{MarginDirection, MarginMode, PoolStatus, Position, PositionSide},
+        info::MarketInfo,
+        order::GroupingType,
+    },
+    clock::ServerClock,
+    signer::ffi::ffisigner,
+    LighterError, Result,
+};
+
+pub use ffisigner::CreateOrderTxReq;
 
 #[derive(Debug)]
 pub struct TxInfo {
+    pub tx_type: TxType,
     pub data: Option<TxInfoData>,
-    pub payload: String, // tx_info
+    pub tx: SignedTx,
+    pub payload: String, // tx_info, serialized `tx`
+}
+
+impl TxInfo {
+    pub fn new(tx_type: TxType, data: Option<TxInfoData>, tx: SignedTx) -> Result<Self> {
+        Ok(Self {
+            tx_type,
+            data,
+            payload: serde_json::to_string(&tx)?,
+            tx,
+        })
+    }
+
+    /// Returns `true` if the tx carries an L1 signature made by `expected`, e.g. to check the L1
+    /// key before submitting; `false` if it's signed by another key or not signed with it at all.
+    /// Fails only if the signature is malformed.
+    pub fn verify_signer(&self, expected: Address) -> Result<bool> {
+        let Some(data) = &self.data else {
+            return Ok(false);
+        };
+
+        let signature = Signature::from_str(&data.signature)
+            .map_err(|e| LighterError::Signing(format!("Invalid L1 signature: {e}")))?;
+        let address = signature
+            .recover_address_from_msg(&data.message)
+            .map_err(|e| LighterError::Signing(format!("Invalid L1 signature: {e}")))?;
+
+        Ok(address == expected)
+    }
+}
+
+/// Tx signed by the signer library. Only the fields common to all the tx types are typed, the
+/// others (e.g. `MarketIndex`) are kept in `fields`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SignedTx {
+    #[serde(rename = "ApiKeyIndex")]
+    pub api_key_index: i32,
+    #[serde(rename = "Nonce")]
+    pub nonce: i64,
+    #[serde(rename = "ExpiredAt")]
+    pub expired_at: i64, // ms
+    #[serde(rename = "Sig", default, skip_serializing_if = "Option::is_none")]
+    pub sig: Option<String>,
+    /// Message to sign with the L1 key, it's not sent
+    #[serde(
+        rename = "MessageToSign",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub message_to_sign: Option<String>,
+    #[serde(rename = "L1Sig", default, skip_serializing_if = "Option::is_none")]
+    pub l1_sig: Option<String>,
+    #[serde(flatten)]
+    pub fields: Map<String, Value>,
+}
+
+/// Transaction types, as expected by `send_tx`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(i32)]
+pub enum TxType {
+    ChangePubKey = 8,
+    CreateSubAccount = 9,
+    CreatePublicPool = 10,
+    UpdatePublicPool = 11,
+    Transfer = 12,
+    Withdraw = 13,
+    CreateOrder = 14,
+    CancelOrder = 15,
+    CancelAllOrders = 16,
+    ModifyOrder = 17,
+    MintShares = 18,
+    BurnShares = 19,
+    UpdateLeverage = 20,
+    CreateGroupedOrders = 28,
+    UpdateMargin = 29,
+}
+
+impl From<TxType> for i32 {
+    fn from(val: TxType) -> Self {
+        val as i32
+    }
+}
+
+impl TxType {
+    /// Returns `true` if the tx must be signed with the L1 key (`eth_private_key`) too. Only
+    /// `ChangePubKey` and `Transfer` are, the others are signed with the API key alone.
+    pub fn requires_l1_signature(&self) -> bool {
+        matches!(self, TxType::ChangePubKey | TxType::Transfer)
+    }
 }
 
 #[derive(Debug)]
@@ -15,7 +125,6 @@ pub struct TxInfoData {
 #[derive(Debug)]
 pub enum TxData {
     ChangePubKey(ChangePubKeyData),
-    //SwitchApiKey(SwitchApiKeyData), // I don't think it's strictly necessary to have it in. Leaving it out for now.
     CreateOrder(CreateOrderData),
     SignCreateGroupedOrders(SignCreateGroupedOrdersData),
     SignCancelOrder(SignCancelOrderData),
@@ -32,6 +141,34 @@ pub enum TxData {
     SignUpdateMargin(SignUpdateMarginData),
 }
 
+impl TxData {
+    pub fn tx_type(&self) -> TxType {
+        match self {
+            TxData::ChangePubKey(_) => TxType::ChangePubKey,
+            TxData::CreateOrder(_) => TxType::CreateOrder,
+            TxData::SignCreateGroupedOrders(_) => TxType::CreateGroupedOrders,
+            TxData::SignCancelOrder(_) => TxType::CancelOrder,
+            TxData::SignWithdraw(_) => TxType::Withdraw,
+            TxData::SignCreateSubaccount => TxType::CreateSubAccount,
+            TxData::SignCancelAllOrders(_) => TxType::CancelAllOrders,
+            TxData::SignModifyOrder(_) => TxType::ModifyOrder,
+            TxData::SignTransfer(_) => TxType::Transfer,
+            TxData::SignCreatePublicPool(_) => TxType::CreatePublicPool,
+            TxData::SignUpdatePublicPool(_) => TxType::UpdatePublicPool,
+            TxData::SignMintShares(_) => TxType::MintShares,
+            TxData::SignBurnShares(_) => TxType::BurnShares,
+            TxData::SignUpdateLeverage(_) => TxType::UpdateLeverage,
+            TxData::SignUpdateMargin(_) => TxType::UpdateMargin,
+        }
+    }
+
+    /// Returns `true` if the tx must be signed with the L1 key too, see
+    /// [`TxType::requires_l1_signature`]
+    pub fn requires_l1_signature(&self) -> bool {
+        self.tx_type().requires_l1_signature()
+    }
+}
+
 // ------------------ Requests data structs -------------------
 
 #[derive(Debug)]
@@ -39,7 +176,15 @@ pub struct ChangePubKeyData {
     pub new_pubk: String,
 }
 
+/// Switching API key is not a tx: it only changes the key used locally to sign the following txs,
+/// see [`crate::signer::Signer::switch_api_key`]
 #[derive(Debug)]
+pub struct SwitchApiKeyData {
+    pub api_key_index: i32,
+    pub api_key_private: SecretString,
+}
+
+#[derive(Debug, Clone)]
 pub struct CreateOrderData {
     pub market_index: i32,
     pub client_order_index: i64,
@@ -50,13 +195,330 @@ pub struct CreateOrderData {
     pub time_in_force: u8,
     pub reduce_only: bool,
     pub trigger_price: i32,
-    pub order_expiry: i64,
+    pub order_expiry: i64, // ms, see `with_expires_in`
 }
 
-#[derive(Debug)]
+impl CreateOrderData {
+    /// `client_order_index` to have one assigned by `OrderApi::place_order`
+    pub const AUTO_CLIENT_ORDER_INDEX: i64 = 0;
+
+    /// Max relative distance of a limit price from the last trade price, see
+    /// [`CreateOrderData::validate`]
+    pub const MAX_LIMIT_PRICE_DEVIATION: f64 = 0.5;
+
+    /// `order_expiry` of an order kept until cancelled (or until the max expiry of 28 days)
+    pub const GOOD_TILL_CANCELLED: i64 = -1;
+
+    pub fn good_till_cancelled(mut self) -> Self {
+        self.order_expiry = Self::GOOD_TILL_CANCELLED;
+        self
+    }
+
+    /// Checks that the order type and the time in force go together: the orders executed at the
+    /// market (market, stop loss, take profit) are immediate or cancel, only the orders resting
+    /// in the book can be post only, and the stop loss and take profit orders need a trigger
+    /// price. The TWAP sub orders and the liquidations are created by the venue only.
+    pub fn validate_type(&self) -> Result<()> {
+        use crate::models::order::{TimeInForce, Type};
+
+        let order_type = Type::try_from(self.order_type)?;
+        let time_in_force = TimeInForce::try_from(self.time_in_force)?;
+        let invalid = |reason: &str| {
+            Err(LighterError::OrderValidation(format!(
+                "{order_type:?} order with {time_in_force:?}: {reason}"
+            )))
+        };
+
+        let (at_market, triggered) = match order_type {
+            Type::Limit => (false, false),
+            Type::Market => (true, false),
+            Type::StopLoss | Type::TakeProfit => (true, true),
+            Type::StopLossLimit | Type::TakeProfitLimit => (false, true),
+            Type::Twap => (false, false),
+            Type::TwapSub | Type::Liquidation => {
+                return invalid("the order type can't be placed");
+            }
+        };
+
+        match time_in_force {
+            TimeInForce::Unknown => return invalid("the time in force is not valid"),
+            TimeInForce::ImmediateOrCancel if order_type == Type::Twap => {
+                return invalid("a TWAP order is executed over time")
+            }
+            TimeInForce::GoodTillTime | TimeInForce::PostOnly if at_market => {
+                return invalid("an order executed at the market must be immediate or cancel")
+            }
+            TimeInForce::PostOnly if order_type == Type::Twap => {
+                return invalid("a TWAP order can't be post only")
+            }
+            _ => {}
+        }
+
+        if triggered && self.trigger_price <= 0 {
+            return invalid("the trigger price must be positive");
+        }
+        if !triggered && self.trigger_price != 0 {
+            return invalid("only the stop loss and take profit orders have a trigger price");
+        }
+
+        Ok(())
+    }
+
+    /// Checks the order against the constraints of the market: the size must be a multiple of
+    /// the lot size and at least the min amounts, the prices must be on the tick grid, and a
+    /// limit price must be within [`Self::MAX_LIMIT_PRICE_DEVIATION`] of the last trade price.
+    /// The type and the time in force are checked too, see [`Self::validate_type`]. The venue
+    /// may still reject it, e.g. on tighter price bands.
+    pub fn validate(&self, market: &MarketInfo) -> Result<()> {
+        self.validate_type()?;
+
+        let invalid = |reason: String| {
+            Err(LighterError::OrderValidation(format!(
+                "{reason} on market {} ({})",
+                market.market_index, market.symbol
+            )))
+        };
+
+        if self.market_index != market.market_index {
+            return invalid(format!("Order of market {}", self.market_index));
+        }
+        if !market.active {
+            return invalid("Market not active".into());
+        }
+
+        let lot_size = market.lot_size();
+        if self.base_amount <= 0 || self.base_amount % lot_size != 0 {
+            return invalid(format!(
+                "Base amount {} is not a positive multiple of the lot size {lot_size}",
+                self.base_amount
+            ));
+        }
+        let min_base_amount = market.to_base_amount(market.min_base_amount);
+        if self.base_amount < min_base_amount {
+            return invalid(format!(
+                "Base amount {} is below the min {min_base_amount}",
+                self.base_amount
+            ));
+        }
+
+        let tick = market.price_tick();
+        if self.price <= 0 || i64::from(self.price) % tick != 0 {
+            return invalid(format!(
+                "Price {} is not a positive multiple of the tick {tick}",
+                self.price
+            ));
+        }
+        if self.trigger_price != 0 && i64::from(self.trigger_price) % tick != 0 {
+            return invalid(format!(
+                "Trigger price {} is not a multiple of the tick {tick}",
+                self.trigger_price
+            ));
+        }
+
+        let price = market.int_price_to_price(self.price.into());
+        let quote_amount = market.base_amount_to_size(self.base_amount) * price;
+        if quote_amount < market.min_quote_amount {
+            return invalid(format!(
+                "Quote amount {quote_amount} is below the min {}",
+                market.min_quote_amount
+            ));
+        }
+
+        let last_price = market.last_trade_price;
+        let is_limit = self.order_type == u8::from(crate::models::order::Type::Limit);
+        if is_limit
+            && last_price > 0.0
+            && (price - last_price).abs() / last_price > Self::MAX_LIMIT_PRICE_DEVIATION
+        {
+            return invalid(format!(
+                "Limit price {price} is too far from the last trade price {last_price}"
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Checks that a reduce only order actually reduces `position`, the position of the account
+    /// on the market of the order: it fails if there is no position or if the order is on the
+    /// same side. A size larger than the position is only logged, the venue caps it to the
+    /// position. Orders without `reduce_only` are not checked.
+    pub fn validate_reduce_only(
+        &self,
+        position: Option<&Position>,
+        market: &MarketInfo,
+    ) -> Result<()> {
+        if !self.reduce_only {
+            return Ok(());
+        }
+        let invalid = |reason: &str| {
+            Err(LighterError::OrderValidation(format!(
+                "Reduce only order on market {} ({}): {reason}",
+                market.market_index, market.symbol
+            )))
+        };
+
+        let Some(position) = position.filter(|v| v.side.is_some()) else {
+            return invalid("there is no position to reduce");
+        };
+        let reduces = match position.side {
+            Some(PositionSide::Long) => self.is_ask,
+            _ => !self.is_ask,
+        };
+        if !reduces {
+            return invalid(&format!(
+                "it would increase the {:?} position",
+                position.side.unwrap_or(PositionSide::Long)
+            ));
+        }
+
+        let size = market.base_amount_to_size(self.base_amount);
+        if size > position.size {
+            tracing::warn!(
+                "reduce only order of {size} on market {} larger than the position of {}",
+                market.market_index,
+                position.size
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Expires the order `expires_in` from now, on the server time
+    pub fn with_expires_in(self, expires_in: Duration, clock: &ServerClock) -> Result<Self> {
+        let expires_in = chrono::Duration::from_std(expires_in)
+            .map_err(|e| LighterError::OrderValidation(format!("Invalid expiry: {e}")))?;
+        self.with_expiry_at(clock.server_now() + expires_in, clock)
+    }
+
+    /// Expires the order at `expiry`, which must be in the future on the server time
+    pub fn with_expiry_at(mut self, expiry: DateTime<Utc>, clock: &ServerClock) -> Result<Self> {
+        if expiry <= clock.server_now() {
+            return Err(LighterError::OrderValidation(format!(
+                "Invalid expiry: {expiry} is in the past"
+            )));
+        }
+
+        self.order_expiry = expiry.timestamp_millis();
+        Ok(self)
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct SignCreateGroupedOrdersData {
     pub grouping_type: GroupingType,
-    pub orders: Vec<ffisigner::CreateOrderTxReq>,
+    pub orders: Vec<CreateOrderTxReq>,
+}
+
+impl SignCreateGroupedOrdersData {
+    /// Checks the orders against the grouping type before they are signed:
+    /// - `OneTriggersOther`: an order, then the stop loss or take profit it triggers
+    /// - `OneCancelsOther`: a stop loss and a take profit (in any order) on the same side
+    /// - `OneTriggersAndOneCancelsOther`: an order, then the stop loss and the take profit it
+    ///   triggers
+    ///
+    /// All the orders must be on the same market, the triggered ones on the opposite side of the
+    /// order triggering them, and the non-zero client order indexes must be unique (0 is none).
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |reason: String| {
+            Err(LighterError::OrderValidation(format!(
+                "Invalid {:?} group: {reason}",
+                self.grouping_type
+            )))
+        };
+
+        let expected = match self.grouping_type {
+            GroupingType::OneTriggersOther | GroupingType::OneCancelsOther => 2,
+            GroupingType::OneTriggersAndOneCancelsOther => 3,
+        };
+        if self.orders.len() != expected {
+            return invalid(format!(
+                "it has {} orders, expected {expected}",
+                self.orders.len()
+            ));
+        }
+
+        let first = &self.orders[0];
+        if let Some(order) = self
+            .orders
+            .iter()
+            .find(|v| v.MarketIndex != first.MarketIndex)
+        {
+            return invalid(format!(
+                "it mixes the markets {} and {}",
+                first.MarketIndex, order.MarketIndex
+            ));
+        }
+
+        let mut client_order_indexes = std::collections::HashSet::new();
+        for order in &self.orders {
+            if order.ClientOrderIndex != 0 && !client_order_indexes.insert(order.ClientOrderIndex) {
+                return invalid(format!(
+                    "the client order index {} is used more than once",
+                    order.ClientOrderIndex
+                ));
+            }
+        }
+
+        // the orders triggered or cancelled by the others
+        let conditionals = match self.grouping_type {
+            GroupingType::OneCancelsOther => &self.orders[..],
+            _ => {
+                if trigger_kind(first).is_some() {
+                    return invalid(
+                        "the first order must not be a stop loss or a take profit".into(),
+                    );
+                }
+                if let Some(order) = self.orders[1..].iter().find(|v| v.IsAsk == first.IsAsk) {
+                    return invalid(format!(
+                        "the triggered order of type {} must be on the opposite side of the first one",
+                        order.Type
+                    ));
+                }
+                &self.orders[1..]
+            }
+        };
+
+        let mut kinds = Vec::with_capacity(conditionals.len());
+        for order in conditionals {
+            match trigger_kind(order) {
+                Some(kind) => kinds.push(kind),
+                None => {
+                    return invalid(format!(
+                        "the order of type {} must be a stop loss or a take profit",
+                        order.Type
+                    ))
+                }
+            }
+        }
+        if kinds.len() == 2 {
+            if kinds[0] == kinds[1] {
+                return invalid("it needs a stop loss and a take profit, not two of a kind".into());
+            }
+            if conditionals[0].IsAsk != conditionals[1].IsAsk {
+                return invalid(
+                    "the stop loss and the take profit must be on the same side".into(),
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// `Some(true)` for a stop loss, `Some(false)` for a take profit, `None` otherwise
+fn trigger_kind(order: &CreateOrderTxReq) -> Option<bool> {
+    use crate::models::order::Type;
+
+    let order_type = order.Type;
+    if order_type == u8::from(Type::StopLoss) || order_type == u8::from(Type::StopLossLimit) {
+        Some(true)
+    } else if order_type == u8::from(Type::TakeProfit)
+        || order_type == u8::from(Type::TakeProfitLimit)
+    {
+        Some(false)
+    } else {
+        None
+    }
 }
 
 #[derive(Debug)]
@@ -65,6 +527,8 @@ pub struct SignCancelOrderData {
     pub order_index: i64,
 }
 
+/// Withdrawal of USDC to the L1 address of the account. The signer library (v0.1.3) takes no
+/// route type, the withdrawal always leaves from the signing account.
 #[derive(Debug)]
 pub struct SignWithdrawData {
     pub usdc_amount: i64,
@@ -85,7 +549,10 @@ pub struct SignModifyOrderData {
     pub trigger_price: i64,
 }
 
-#[derive(Debug)]
+/// Transfer of USDC from the signing account to `to_account_index`, e.g. between the main
+/// account and a sub account. The signer library (v0.1.3) takes no route types, only the
+/// destination account.
+#[derive(Debug, Clone)]
 pub struct SignTransferData {
     pub to_account_index: i64,
     pub usdc_amount: i64,
@@ -93,6 +560,109 @@ pub struct SignTransferData {
     pub memo: [u8; 32],
 }
 
+impl SignTransferData {
+    /// Transfer without memo
+    pub fn new(to_account_index: i64, usdc_amount: i64, fee: i64) -> Self {
+        Self {
+            to_account_index,
+            usdc_amount,
+            fee,
+            memo: Memo::default().into(),
+        }
+    }
+
+    pub fn with_memo(mut self, memo: Memo) -> Self {
+        self.memo = memo.into();
+        self
+    }
+}
+
+/// Memo attached to a transfer: up to 32 bytes, right-padded with spaces. Any bytes but zero can
+/// be set (e.g. an id encoded in hex), the memo doesn't need to be valid UTF-8.
+///
+/// The signer library reads the memo as a C string of exactly 32 bytes, so a zero byte can't be
+/// signed: the shorter memos are padded with spaces instead, and the default memo is blank.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Memo([u8; Memo::LEN]);
+
+impl Memo {
+    pub const LEN: usize = 32;
+    pub const PADDING: u8 = b' ';
+
+    pub const fn from_bytes(bytes: [u8; Memo::LEN]) -> Self {
+        Self(bytes)
+    }
+
+    /// Decodes up to 32 bytes from hex, with or without the `0x` prefix
+    pub fn from_hex(s: &str) -> Result<Self> {
+        let bytes = hex::decode(s.strip_prefix("0x").unwrap_or(s))
+            .map_err(|e| LighterError::Generic(format!("Invalid hex memo: {e}")))?;
+        Self::from_slice(&bytes)
+    }
+
+    /// Decodes up to 32 bytes from base64 (standard alphabet, padded)
+    pub fn from_base64(s: &str) -> Result<Self> {
+        let bytes = BASE64_STANDARD
+            .decode(s)
+            .map_err(|e| LighterError::Generic(format!("Invalid base64 memo: {e}")))?;
+        Self::from_slice(&bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; Memo::LEN] {
+        &self.0
+    }
+
+    /// Returns the 32 bytes in hex, with the `0x` prefix
+    pub fn to_hex(&self) -> String {
+        format!("0x{}", hex::encode(self.0))
+    }
+
+    fn from_slice(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() > Self::LEN {
+            return Err(LighterError::Generic(format!(
+                "Invalid memo: {} bytes, at most {} allowed",
+                bytes.len(),
+                Self::LEN
+            )));
+        }
+        if bytes.contains(&0) {
+            return Err(LighterError::Generic(
+                "Invalid memo: zero bytes are not supported by the signer library".into(),
+            ));
+        }
+
+        let mut memo = [Self::PADDING; Self::LEN];
+        memo[..bytes.len()].copy_from_slice(bytes);
+        Ok(Self(memo))
+    }
+}
+
+impl Default for Memo {
+    fn default() -> Self {
+        Self([Self::PADDING; Self::LEN])
+    }
+}
+
+impl FromStr for Memo {
+    type Err = LighterError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Self::from_slice(s.as_bytes())
+    }
+}
+
+impl From<[u8; Memo::LEN]> for Memo {
+    fn from(val: [u8; Memo::LEN]) -> Self {
+        Self(val)
+    }
+}
+
+impl From<Memo> for [u8; Memo::LEN] {
+    fn from(val: Memo) -> Self {
+        val.0
+    }
+}
+
 #[derive(Debug)]
 pub struct SignCreatePublicPoolData {
     pub operator_fee: i64,
@@ -103,11 +673,27 @@ pub struct SignCreatePublicPoolData {
 #[derive(Debug)]
 pub struct SignUpdatePublicPoolData {
     pub public_pool_index: i64,
-    pub status: i32,
+    pub status: i32, // see `PoolStatus`
     pub operator_fee: i64,
     pub min_operator_share_rate: i64,
 }
 
+impl SignUpdatePublicPoolData {
+    pub fn new(
+        public_pool_index: i64,
+        status: PoolStatus,
+        operator_fee: i64,
+        min_operator_share_rate: i64,
+    ) -> Self {
+        Self {
+            public_pool_index,
+            status: status.into(),
+            operator_fee,
+            min_operator_share_rate,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct SignMintSharesData {
     pub public_pool_index: i64,
@@ -124,12 +710,580 @@ pub struct SignBurnSharesData {
 pub struct SignUpdateLeverageData {
     pub market_index: i32,
     pub initial_margin_fraction: i32,
-    pub margin_mode: i32,
+    pub margin_mode: i32, // see `MarginMode`
+}
+
+impl SignUpdateLeverageData {
+    pub fn new(market_index: i32, initial_margin_fraction: i32, margin_mode: MarginMode) -> Self {
+        Self {
+            market_index,
+            initial_margin_fraction,
+            margin_mode: margin_mode.into(),
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct SignUpdateMarginData {
     pub market_index: i32,
     pub usdc_amount: i64,
-    pub direction: i32,
+    pub direction: i32, // see `MarginDirection`
+}
+
+impl SignUpdateMarginData {
+    pub fn new(market_index: i32, usdc_amount: i64, direction: MarginDirection) -> Self {
+        Self {
+            market_index,
+            usdc_amount,
+            direction: direction.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signed_tx_serde() {
+        let body = r#"{"AccountIndex":28,"ApiKeyIndex":2,"ExpiredAt":1791970810612,"L1Sig":"","MessageToSign":"Register Lighter Account","Nonce":1,"Sig":"abc"}"#;
+        let mut tx = serde_json::from_str::<SignedTx>(body).unwrap();
+        assert_eq!(tx.api_key_index, 2);
+        assert_eq!(tx.nonce, 1);
+        assert_eq!(
+            tx.message_to_sign.as_deref(),
+            Some("Register Lighter Account")
+        );
+        assert_eq!(tx.fields["AccountIndex"], 28);
+
+        tx.message_to_sign = None;
+        tx.l1_sig = Some("0x01".into());
+        let info = TxInfo::new(TxType::ChangePubKey, None, tx).unwrap();
+        let payload = serde_json::from_str::<Value>(&info.payload).unwrap();
+        assert!(payload.get("MessageToSign").is_none());
+        assert_eq!(payload["L1Sig"], "0x01");
+        assert_eq!(payload["AccountIndex"], 28);
+        assert_eq!(payload["Sig"], "abc");
+    }
+
+    fn create_order_data() -> CreateOrderData {
+        CreateOrderData {
+            market_index: 0,
+            client_order_index: 1,
+            base_amount: 1,
+            price: 1,
+            is_ask: true,
+            order_type: 0,
+            time_in_force: 1,
+            reduce_only: false,
+            trigger_price: 0,
+            order_expiry: 0,
+        }
+    }
+
+    #[test]
+    fn test_order_expiry() {
+        let clock = ServerClock::new();
+
+        let order = create_order_data().good_till_cancelled();
+        assert_eq!(order.order_expiry, CreateOrderData::GOOD_TILL_CANCELLED);
+
+        let expected = (Utc::now() + chrono::Duration::minutes(5)).timestamp_millis();
+        let order = create_order_data()
+            .with_expires_in(Duration::from_secs(300), &clock)
+            .unwrap();
+        assert!((order.order_expiry - expected).abs() < 1000); // ms
+
+        // shifted by the server clock offset
+        clock.set_offset(chrono::Duration::hours(1));
+        let order = create_order_data()
+            .with_expires_in(Duration::from_secs(300), &clock)
+            .unwrap();
+        assert!((order.order_expiry - expected - 3_600_000).abs() < 1000);
+
+        // in the future locally, but not for the server
+        let res =
+            create_order_data().with_expiry_at(Utc::now() + chrono::Duration::minutes(5), &clock);
+        assert!(matches!(res, Err(LighterError::OrderValidation(_))));
+        let res = create_order_data().with_expiry_at(
+            Utc::now() - chrono::Duration::seconds(1),
+            &ServerClock::new(),
+        );
+        assert!(matches!(res, Err(LighterError::OrderValidation(_))));
+    }
+
+    fn market() -> MarketInfo {
+        MarketInfo {
+            market_index: 0,
+            symbol: "ETH".into(),
+            active: true,
+            size_decimals: 4,
+            price_decimals: 2,
+            supported_size_decimals: 3,
+            supported_price_decimals: 1,
+            min_base_amount: 0.01,
+            min_quote_amount: 10.0,
+            min_initial_margin_fraction: 500,
+            last_trade_price: 3000.0,
+            open_interest: 0.0,
+            daily_base_volume: 0.0,
+            daily_quote_volume: 0.0,
+            daily_price_change: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_validate_order() {
+        // 0.01 at 3000.0
+        let order = CreateOrderData {
+            base_amount: 100,
+            price: 300_000,
+            ..create_order_data()
+        };
+        order.validate(&market()).unwrap();
+
+        let invalid = |order: CreateOrderData, reason: &str| {
+            let res = order.validate(&market());
+            assert!(
+                matches!(&res, Err(LighterError::OrderValidation(e)) if e.contains(reason)),
+                "{res:?}"
+            );
+        };
+        invalid(
+            CreateOrderData {
+                market_index: 1,
+                ..order.clone()
+            },
+            "Order of market 1",
+        );
+        invalid(
+            CreateOrderData {
+                base_amount: 105,
+                ..order.clone()
+            },
+            "lot size",
+        );
+        invalid(
+            CreateOrderData {
+                base_amount: 90,
+                ..order.clone()
+            },
+            "below the min 100",
+        );
+        invalid(
+            CreateOrderData {
+                price: 300_005,
+                ..order.clone()
+            },
+            "tick",
+        );
+        // off the tick grid, on an order type that takes a trigger price
+        invalid(
+            CreateOrderData {
+                order_type: crate::models::order::Type::StopLossLimit.into(),
+                time_in_force: crate::models::order::TimeInForce::GoodTillTime.into(),
+                trigger_price: 300_001,
+                ..order.clone()
+            },
+            "Trigger price",
+        );
+        // 0.01 at 900.0
+        invalid(
+            CreateOrderData {
+                price: 90_000,
+                ..order.clone()
+            },
+            "Quote amount",
+        );
+        // 0.01 at 5000.0
+        invalid(
+            CreateOrderData {
+                price: 500_000,
+                ..order.clone()
+            },
+            "too far",
+        );
+        // a market order can have any worst price
+        CreateOrderData {
+            price: 500_000,
+            order_type: 1,
+            time_in_force: 0,
+            ..order.clone()
+        }
+        .validate(&market())
+        .unwrap();
+
+        let res = order.validate(&MarketInfo {
+            active: false,
+            ..market()
+        });
+        assert!(matches!(res, Err(LighterError::OrderValidation(_))));
+    }
+
+    #[test]
+    fn test_validate_order_type() {
+        use crate::models::order::{TimeInForce, Type};
+
+        let order = |order_type: Type, time_in_force: TimeInForce, trigger_price| CreateOrderData {
+            order_type: order_type.into(),
+            time_in_force: time_in_force.into(),
+            trigger_price,
+            ..create_order_data()
+        };
+        // (type, time in force, trigger price, rejection reason)
+        let cases = [
+            (Type::Limit, TimeInForce::GoodTillTime, 0, None),
+            (Type::Limit, TimeInForce::PostOnly, 0, None),
+            (Type::Limit, TimeInForce::ImmediateOrCancel, 0, None),
+            (
+                Type::Limit,
+                TimeInForce::GoodTillTime,
+                100,
+                Some("trigger price"),
+            ),
+            (Type::Limit, TimeInForce::Unknown, 0, Some("not valid")),
+            (Type::Market, TimeInForce::ImmediateOrCancel, 0, None),
+            (
+                Type::Market,
+                TimeInForce::PostOnly,
+                0,
+                Some("immediate or cancel"),
+            ),
+            (
+                Type::Market,
+                TimeInForce::GoodTillTime,
+                0,
+                Some("immediate or cancel"),
+            ),
+            (Type::StopLoss, TimeInForce::ImmediateOrCancel, 100, None),
+            (
+                Type::StopLoss,
+                TimeInForce::ImmediateOrCancel,
+                0,
+                Some("positive"),
+            ),
+            (
+                Type::StopLoss,
+                TimeInForce::PostOnly,
+                100,
+                Some("immediate or cancel"),
+            ),
+            (Type::TakeProfit, TimeInForce::ImmediateOrCancel, 100, None),
+            (
+                Type::TakeProfit,
+                TimeInForce::ImmediateOrCancel,
+                -1,
+                Some("positive"),
+            ),
+            (Type::StopLossLimit, TimeInForce::GoodTillTime, 100, None),
+            (Type::StopLossLimit, TimeInForce::PostOnly, 100, None),
+            (
+                Type::StopLossLimit,
+                TimeInForce::GoodTillTime,
+                0,
+                Some("positive"),
+            ),
+            (Type::TakeProfitLimit, TimeInForce::GoodTillTime, 100, None),
+            (
+                Type::TakeProfitLimit,
+                TimeInForce::GoodTillTime,
+                0,
+                Some("positive"),
+            ),
+            (Type::Twap, TimeInForce::GoodTillTime, 0, None),
+            (
+                Type::Twap,
+                TimeInForce::ImmediateOrCancel,
+                0,
+                Some("over time"),
+            ),
+            (Type::Twap, TimeInForce::PostOnly, 0, Some("post only")),
+            (
+                Type::TwapSub,
+                TimeInForce::GoodTillTime,
+                0,
+                Some("can't be placed"),
+            ),
+            (
+                Type::Liquidation,
+                TimeInForce::ImmediateOrCancel,
+                0,
+                Some("can't be placed"),
+            ),
+        ];
+        for (order_type, time_in_force, trigger_price, reason) in cases {
+            let res = order(order_type, time_in_force, trigger_price).validate_type();
+            match reason {
+                None => assert!(res.is_ok(), "{order_type:?} {time_in_force:?}: {res:?}"),
+                Some(reason) => assert!(
+                    matches!(&res, Err(LighterError::OrderValidation(e)) if e.contains(reason)),
+                    "{order_type:?} {time_in_force:?}: {res:?}"
+                ),
+            }
+        }
+
+        let res = CreateOrderData {
+            order_type: 9,
+            ..create_order_data()
+        }
+        .validate_type();
+        assert!(matches!(res, Err(LighterError::OrderValidation(e)) if e.contains("order type 9")));
+        let res = CreateOrderData {
+            time_in_force: 4,
+            ..create_order_data()
+        }
+        .validate_type();
+        assert!(
+            matches!(res, Err(LighterError::OrderValidation(e)) if e.contains("time in force 4"))
+        );
+    }
+
+    #[test]
+    fn test_validate_reduce_only() {
+        let position = |side, size| Position {
+            market_index: 0,
+            symbol: "ETH".into(),
+            side,
+            size,
+            avg_entry_price: 3000.0,
+            position_value: 0.0,
+            unrealized_pnl: 0.0,
+            realized_pnl: 0.0,
+            liquidation_price: 0.0,
+            initial_margin_fraction: 0.0,
+            margin_mode: MarginMode::Cross,
+            allocated_margin: 0.0,
+            total_funding_paid_out: None,
+            open_order_count: 0,
+        };
+        let long = position(Some(PositionSide::Long), 0.5);
+        let short = position(Some(PositionSide::Short), 0.5);
+        // 0.01
+        let sell = CreateOrderData {
+            base_amount: 100,
+            reduce_only: true,
+            is_ask: true,
+            ..create_order_data()
+        };
+        let buy = CreateOrderData {
+            is_ask: false,
+            ..sell.clone()
+        };
+
+        sell.validate_reduce_only(Some(&long), &market()).unwrap();
+        buy.validate_reduce_only(Some(&short), &market()).unwrap();
+        // larger than the position, capped by the venue
+        CreateOrderData {
+            base_amount: 10_000,
+            ..sell.clone()
+        }
+        .validate_reduce_only(Some(&long), &market())
+        .unwrap();
+
+        let invalid = |order: &CreateOrderData, position: Option<&Position>, reason: &str| {
+            let res = order.validate_reduce_only(position, &market());
+            assert!(
+                matches!(&res, Err(LighterError::OrderValidation(e)) if e.contains(reason)),
+                "{res:?}"
+            );
+        };
+        invalid(&buy, Some(&long), "increase the Long position");
+        invalid(&sell, Some(&short), "increase the Short position");
+        invalid(&sell, None, "no position");
+        invalid(&sell, Some(&position(None, 0.0)), "no position");
+
+        // not reduce only
+        CreateOrderData {
+            reduce_only: false,
+            ..buy
+        }
+        .validate_reduce_only(Some(&long), &market())
+        .unwrap();
+    }
+
+    #[test]
+    fn test_update_leverage_and_margin_data() {
+        let data = SignUpdateLeverageData::new(1, 500, MarginMode::Isolated);
+        assert_eq!((data.market_index, data.initial_margin_fraction), (1, 500));
+        assert_eq!(data.margin_mode, 1);
+
+        let data = SignUpdateMarginData::new(1, 10, MarginDirection::Remove);
+        assert_eq!((data.market_index, data.usdc_amount), (1, 10));
+        assert_eq!(data.direction, 0);
+    }
+
+    #[test]
+    fn test_memo_from_str() {
+        let memo = Memo::from_str("gm").unwrap();
+        assert_eq!(&memo.as_bytes()[..2], b"gm");
+        assert!(memo.as_bytes()[2..].iter().all(|v| *v == Memo::PADDING));
+
+        let msg = "Hal Finney was `Running Bitcoin`";
+        assert_eq!(Memo::from_str(msg).unwrap().as_bytes(), msg.as_bytes());
+
+        assert_eq!(Memo::from_str("").unwrap(), Memo::default());
+        assert!(Memo::from_str(&"a".repeat(33)).is_err());
+        assert!(Memo::from_str("g\0m").is_err());
+    }
+
+    #[test]
+    fn test_memo_from_hex() {
+        let bytes: [u8; Memo::LEN] = std::array::from_fn(|i| 0xe0 + i as u8); // not UTF-8
+        let memo = Memo::from_bytes(bytes);
+        assert_eq!(memo.as_bytes(), &bytes);
+        assert!(std::str::from_utf8(memo.as_bytes()).is_err());
+
+        let hex = memo.to_hex();
+        assert!(hex.starts_with("0xe0e1e2"));
+        assert_eq!(Memo::from_hex(&hex).unwrap(), memo);
+        assert_eq!(Memo::from_hex(&hex[2..]).unwrap(), memo);
+        assert_eq!(<[u8; Memo::LEN]>::from(memo), bytes);
+
+        // shorter, right-padded
+        let memo = Memo::from_hex("0xdeadbeef").unwrap();
+        assert_eq!(&memo.as_bytes()[..4], &[0xde, 0xad, 0xbe, 0xef]);
+        assert!(memo.as_bytes()[4..].iter().all(|v| *v == Memo::PADDING));
+
+        assert!(Memo::from_hex("0xab00").is_err());
+        assert!(Memo::from_hex("0xabc").is_err());
+        assert!(Memo::from_hex("0xzz").is_err());
+        assert!(Memo::from_hex(&"ab".repeat(33)).is_err());
+    }
+
+    #[test]
+    fn test_memo_from_base64() {
+        let bytes: [u8; Memo::LEN] = std::array::from_fn(|i| 0xff - i as u8);
+        let memo = Memo::from_base64(&BASE64_STANDARD.encode(bytes)).unwrap();
+        assert_eq!(memo.as_bytes(), &bytes);
+
+        let memo = Memo::from_base64("Z20=").unwrap();
+        assert_eq!(memo, Memo::from_str("gm").unwrap());
+
+        assert!(Memo::from_base64("Z20").is_err());
+        assert!(Memo::from_base64(&BASE64_STANDARD.encode([1u8; 33])).is_err());
+    }
+
+    fn grouped_order(order_type: crate::models::order::Type, is_ask: bool) -> CreateOrderTxReq {
+        CreateOrderTxReq {
+            MarketIndex: 1,
+            ClientOrderIndex: 0,
+            BaseAmount: 100,
+            Price: 100,
+            IsAsk: is_ask as u8,
+            Type: order_type.into(),
+            TimeInForce: crate::models::order::TimeInForce::GoodTillTime.into(),
+            ReduceOnly: 0,
+            TriggerPrice: 0,
+            OrderExpiry: 0,
+        }
+    }
+
+    #[test]
+    fn test_validate_grouped_orders() {
+        use crate::models::order::Type;
+
+        let group = |grouping_type, orders| SignCreateGroupedOrdersData {
+            grouping_type,
+            orders,
+        };
+        let limit = grouped_order(Type::Limit, false);
+        let stop_loss = grouped_order(Type::StopLoss, true);
+        let take_profit = grouped_order(Type::TakeProfitLimit, true);
+
+        // valid
+        for data in [
+            group(GroupingType::OneCancelsOther, vec![stop_loss, take_profit]),
+            group(GroupingType::OneCancelsOther, vec![take_profit, stop_loss]),
+            group(GroupingType::OneTriggersOther, vec![limit, stop_loss]),
+            group(
+                GroupingType::OneTriggersAndOneCancelsOther,
+                vec![limit, take_profit, stop_loss],
+            ),
+        ] {
+            data.validate().unwrap();
+        }
+
+        let numbered = |mut order: CreateOrderTxReq, index| {
+            order.ClientOrderIndex = index;
+            order
+        };
+        let on_market = |mut order: CreateOrderTxReq, market| {
+            order.MarketIndex = market;
+            order
+        };
+        for (data, reason) in [
+            (group(GroupingType::OneCancelsOther, vec![]), "0 orders"),
+            (
+                group(GroupingType::OneCancelsOther, vec![stop_loss]),
+                "1 orders",
+            ),
+            (
+                group(
+                    GroupingType::OneTriggersAndOneCancelsOther,
+                    vec![limit, stop_loss],
+                ),
+                "expected 3",
+            ),
+            (
+                group(
+                    GroupingType::OneCancelsOther,
+                    vec![numbered(stop_loss, 7), numbered(take_profit, 7)],
+                ),
+                "client order index 7",
+            ),
+            (
+                group(
+                    GroupingType::OneCancelsOther,
+                    vec![stop_loss, on_market(take_profit, 2)],
+                ),
+                "markets 1 and 2",
+            ),
+            (
+                group(GroupingType::OneCancelsOther, vec![stop_loss, stop_loss]),
+                "two of a kind",
+            ),
+            (
+                group(GroupingType::OneCancelsOther, vec![limit, stop_loss]),
+                "must be a stop loss",
+            ),
+            (
+                group(
+                    GroupingType::OneCancelsOther,
+                    vec![stop_loss, grouped_order(Type::TakeProfit, false)],
+                ),
+                "same side",
+            ),
+            (
+                group(GroupingType::OneTriggersOther, vec![stop_loss, take_profit]),
+                "first order",
+            ),
+            (
+                group(
+                    GroupingType::OneTriggersOther,
+                    vec![limit, grouped_order(Type::StopLoss, false)],
+                ),
+                "opposite side",
+            ),
+            (
+                group(
+                    GroupingType::OneTriggersOther,
+                    vec![limit, grouped_order(Type::Market, true)],
+                ),
+                "must be a stop loss",
+            ),
+            (
+                group(
+                    GroupingType::OneTriggersAndOneCancelsOther,
+                    vec![limit, take_profit, take_profit],
+                ),
+                "two of a kind",
+            ),
+        ] {
+            let res = data.validate();
+            assert!(
+                matches!(&res, Err(LighterError::OrderValidation(e)) if e.contains(reason)),
+                "{reason}: {res:?}"
+            );
+        }
+    }
 }
@@ -1,16 +1,41 @@
-use chrono::{Duration, Utc};
+use chrono::Duration;
 use secrecy::{ExposeSecret, SecretString};
 
+use crate::clock::ServerClock;
 use crate::error::{LighterError, Result};
 use crate::signer::data::TxData;
-use std::ffi::{c_int, c_longlong, CStr, CString};
-use std::sync::{Arc, RwLock};
+use std::ffi::{c_char, c_int, c_longlong, CStr, CString};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock, PoisonError, RwLock};
 
 pub mod ffisigner {
     #![allow(warnings)]
     include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
 }
 
+/// The `lighter-signer` lib, loaded on first use
+static LIBRARY: OnceLock<std::result::Result<ffisigner::LighterSigner, String>> = OnceLock::new();
+
+/// Returns the `lighter-signer` lib, loaded from `LIGHTER_SIGNER_LIB` if set, otherwise from the
+/// `libs` of the crate. A lib that can't be loaded (missing, or built for another arch) fails
+/// all the signing calls with the same error, instead of aborting the process at startup.
+fn library() -> Result<&'static ffisigner::LighterSigner> {
+    LIBRARY
+        .get_or_init(|| {
+            let path = std::env::var("LIGHTER_SIGNER_LIB")
+                .unwrap_or_else(|_| env!("LIGHTER_SIGNER_DEFAULT_LIB").to_string());
+            load_library(&path)
+        })
+        .as_ref()
+        .map_err(|e| LighterError::Signing(e.clone()))
+}
+
+fn load_library(path: &str) -> std::result::Result<ffisigner::LighterSigner, String> {
+    // it runs the initialization of the Go runtime of the lib, as linking it did at startup
+    unsafe { ffisigner::LighterSigner::new(path) }
+        .map_err(|e| format!("Unable to load the signer library `{path}`: {e}"))
+        .inspect_err(|e| tracing::error!("{e}"))
+}
+
 #[derive(Debug, Clone)]
 pub struct AuthToken {
     pub token: String,
@@ -18,25 +43,88 @@ pub struct AuthToken {
 }
 
 impl AuthToken {
-    pub fn is_expired(&self) -> bool {
-        Utc::now().timestamp() >= self.expiration
+    /// Returns `true` if the token expires in less than `margin_secs` from `now` (secs)
+    pub fn expires_within(&self, margin_secs: i64, now: i64) -> bool {
+        now + margin_secs >= self.expiration
     }
 }
 
-#[derive(Debug)]
-pub struct FFISigner {
+static DEFAULT_AUTH_TOKEN_TTL: i64 = 600; // 10m
+static DEFAULT_AUTH_REFRESH_MARGIN: i64 = 30; // 30s
+
+/// Client the native library currently signs with. The library holds it in a process-wide
+/// state, so all the calls go through this lock, and the client is created again whenever
+/// another signer than the last one calls it. `None` if unknown, e.g. after a failure.
+static CURRENT_CLIENT: Mutex<Option<NativeClient>> = Mutex::new(None);
+
+/// Arguments of `CreateClient`, identifying a client of the native library
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct NativeClient {
     url: String,
     private_key: String,
     chain_id: c_int,
     api_key_index: c_int,
     account_index: c_int,
+}
+
+impl NativeClient {
+    /// Creates the client, which becomes the one the library signs with
+    fn create(&self) -> Result<()> {
+        let c_url = CString::new(self.url.as_str())
+            .map_err(|_| LighterError::Signing("Invalid URL".to_string()))?;
+        let c_key = CString::new(self.private_key.as_str())
+            .map_err(|_| LighterError::Signing("Invalid key".to_string()))?;
+
+        let lib = library()?;
+        let res = unsafe {
+            lib.CreateClient(
+                c_url.as_ptr() as *mut i8,
+                c_key.as_ptr() as *mut i8,
+                self.chain_id,
+                self.api_key_index,
+                self.account_index as c_longlong,
+            )
+        };
+        match unsafe { take_c_string(res) } {
+            Some(err_str) => Err(LighterError::Signing(err_str)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// API key the txs are signed with, switched by [`FFISigner::switch_api_key`]
+#[derive(Debug)]
+struct ApiKey {
+    private_key: String,
+    index: c_int,
+}
+
+/// Signer of the txs and the auth tokens of an API key. The clones share the cached auth token
+/// and the API key, so the APIs of a client create the token only once, and switching the key
+/// switches it for all of them.
+///
+/// The native library signs with a single client for the whole process, so all the signers of
+/// the process are serialized, and the client is created again when a signer of another API key
+/// or account signs in between. Using one signer per process (e.g. the one of
+/// [`crate::HttpClient`]) avoids it.
+#[derive(Debug, Clone)]
+pub struct FFISigner {
+    url: String,
+    chain_id: c_int,
+    account_index: c_int,
+    api_key: Arc<RwLock<ApiKey>>,
     // We should expect more reads than actual writes since the token will most likely have a long expiration
     // In case this does not happen, the implementation could be changed to have a Mutex
     // By using the Arc we ensure to have interior mutability
     auth_token: Arc<RwLock<Option<AuthToken>>>,
+    auth_token_ttl: i64,      // seconds
+    auth_refresh_margin: i64, // seconds, the token is regenerated when it's this close to expire
+    clock: Arc<ServerClock>,  // the expirations are checked against the server time
 }
 
 impl FFISigner {
+    /// Creates a signer for the chain of `url`: mainnet if it contains `mainnet`, testnet
+    /// otherwise. See [`FFISigner::new_with_chain_id`] to set it explicitly.
     pub fn new(
         url: &str,
         private_key: SecretString,
@@ -44,30 +132,63 @@ impl FFISigner {
         account_index: i32,
     ) -> Result<Self> {
         let chain_id = if url.contains("mainnet") { 304 } else { 300 };
+        Self::new_with_chain_id(url, private_key, api_key_index, account_index, chain_id)
+    }
+
+    pub fn new_with_chain_id(
+        url: &str,
+        private_key: SecretString,
+        api_key_index: i32,
+        account_index: i32,
+        chain_id: i32,
+    ) -> Result<Self> {
         let clean_key = private_key.expose_secret().trim_start_matches("0x");
 
         let signer = Self {
             url: url.to_string(),
-            private_key: clean_key.to_string(),
             chain_id: chain_id as c_int,
-            api_key_index: api_key_index as c_int,
             account_index: account_index as c_int,
+            api_key: Arc::new(RwLock::new(ApiKey {
+                private_key: clean_key.to_string(),
+                index: api_key_index as c_int,
+            })),
             auth_token: Arc::new(RwLock::new(None)),
+            auth_token_ttl: DEFAULT_AUTH_TOKEN_TTL,
+            auth_refresh_margin: DEFAULT_AUTH_REFRESH_MARGIN,
+            clock: Arc::new(ServerClock::new()),
         };
 
-        signer.create_client()?;
+        // fails early on an invalid key
+        drop(signer.lock_client()?);
         Ok(signer)
     }
 
+    pub fn with_auth_token_ttl(mut self, ttl_secs: u64) -> Self {
+        self.auth_token_ttl = ttl_secs as i64;
+        self
+    }
+
+    pub fn with_auth_refresh_margin(mut self, margin_secs: u64) -> Self {
+        self.auth_refresh_margin = margin_secs as i64;
+        self
+    }
+
+    pub fn with_server_clock(mut self, clock: Arc<ServerClock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
     pub fn get_tx_data(&self, data: TxData, nonce: i64) -> Result<String> {
+        let lib = library()?;
+        let _client = self.lock_client()?;
         let res = match data {
             TxData::ChangePubKey(data) => {
                 let c_pubkey = CString::new(data.new_pubk.as_str())
                     .map_err(|_| LighterError::Signing("Invalid key".to_string()))?;
-                unsafe { ffisigner::SignChangePubKey(c_pubkey.as_ptr() as *mut i8, nonce) }
+                unsafe { lib.SignChangePubKey(c_pubkey.as_ptr() as *mut i8, nonce) }
             }
             TxData::CreateOrder(data) => unsafe {
-                ffisigner::SignCreateOrder(
+                lib.SignCreateOrder(
                     data.market_index,
                     data.client_order_index,
                     data.base_amount,
@@ -78,14 +199,14 @@ impl FFISigner {
                     data.reduce_only as c_int,
                     data.trigger_price,
                     data.order_expiry as c_longlong,
-                    1,
+                    nonce,
                 )
             },
             TxData::SignCreateGroupedOrders(mut data) => {
                 let orders_len = data.orders.len();
                 let orders_ptr = data.orders.as_mut_ptr();
                 unsafe {
-                    ffisigner::SignCreateGroupedOrders(
+                    lib.SignCreateGroupedOrders(
                         data.grouping_type as u8,
                         orders_ptr,
                         orders_len as i32,
@@ -94,17 +215,15 @@ impl FFISigner {
                 }
             }
             TxData::SignCancelOrder(data) => unsafe {
-                ffisigner::SignCancelOrder(data.market_index, data.order_index, nonce)
-            },
-            TxData::SignWithdraw(data) => unsafe {
-                ffisigner::SignWithdraw(data.usdc_amount, nonce)
+                lib.SignCancelOrder(data.market_index, data.order_index, nonce)
             },
-            TxData::SignCreateSubaccount => unsafe { ffisigner::SignCreateSubAccount(nonce) },
+            TxData::SignWithdraw(data) => unsafe { lib.SignWithdraw(data.usdc_amount, nonce) },
+            TxData::SignCreateSubaccount => unsafe { lib.SignCreateSubAccount(nonce) },
             TxData::SignCancelAllOrders(data) => unsafe {
-                ffisigner::SignCancelAllOrders(data.time_in_force as c_int, data.time, nonce)
+                lib.SignCancelAllOrders(data.time_in_force as c_int, data.time, nonce)
             },
             TxData::SignModifyOrder(data) => unsafe {
-                ffisigner::SignModifyOrder(
+                lib.SignModifyOrder(
                     data.market_index,
                     data.order_index,
                     data.amount,
@@ -113,21 +232,26 @@ impl FFISigner {
                     nonce,
                 )
             },
-            TxData::SignTransfer(data) => unsafe {
-                let memo = str::from_utf8(&data.memo)
-                    .map_err(|_| LighterError::Generic("Invalid memo (non UTF-8)".to_string()))?;
-                let memo = CString::new(memo)
-                    .map_err(|_| LighterError::Signing("Invalid memo".to_string()))?;
-                ffisigner::SignTransfer(
-                    data.to_account_index,
-                    data.usdc_amount,
-                    data.fee,
-                    memo.as_ptr() as *mut i8,
-                    nonce,
-                )
-            },
+            TxData::SignTransfer(data) => {
+                // the memo is passed as raw bytes, not necessarily UTF-8, but the signer library reads
+                // it as a C string of exactly 32 bytes, so it can't carry any zero byte (see `Memo`)
+                let memo = CString::new(data.memo).map_err(|_| {
+                    LighterError::Signing(
+                        "Invalid memo: zero bytes are not supported by the signer library".into(),
+                    )
+                })?;
+                unsafe {
+                    lib.SignTransfer(
+                        data.to_account_index,
+                        data.usdc_amount,
+                        data.fee,
+                        memo.as_ptr() as *mut i8,
+                        nonce,
+                    )
+                }
+            }
             TxData::SignCreatePublicPool(data) => unsafe {
-                ffisigner::SignCreatePublicPool(
+                lib.SignCreatePublicPool(
                     data.operator_fee,
                     data.initial_total_shares,
                     data.min_operator_share_rate,
@@ -135,7 +259,7 @@ impl FFISigner {
                 )
             },
             TxData::SignUpdatePublicPool(data) => unsafe {
-                ffisigner::SignUpdatePublicPool(
+                lib.SignUpdatePublicPool(
                     data.public_pool_index,
                     data.status,
                     data.operator_fee,
@@ -144,13 +268,13 @@ impl FFISigner {
                 )
             },
             TxData::SignMintShares(data) => unsafe {
-                ffisigner::SignMintShares(data.public_pool_index, data.share_amount, nonce)
+                lib.SignMintShares(data.public_pool_index, data.share_amount, nonce)
             },
             TxData::SignBurnShares(data) => unsafe {
-                ffisigner::SignBurnShares(data.public_pool_index, data.share_amount, nonce)
+                lib.SignBurnShares(data.public_pool_index, data.share_amount, nonce)
             },
             TxData::SignUpdateLeverage(data) => unsafe {
-                ffisigner::SignUpdateLeverage(
+                lib.SignUpdateLeverage(
                     data.market_index,
                     data.initial_margin_fraction,
                     data.margin_mode,
@@ -158,18 +282,16 @@ impl FFISigner {
                 )
             },
             TxData::SignUpdateMargin(data) => unsafe {
-                ffisigner::SignUpdateMargin(
-                    data.market_index,
-                    data.usdc_amount,
-                    data.direction,
-                    nonce,
-                )
+                lib.SignUpdateMargin(data.market_index, data.usdc_amount, data.direction, nonce)
             },
         };
 
         self.parse_result(res)
     }
 
+    /// Returns an auth token valid until at least `expiration_timestamp` (secs), by default for
+    /// the configured TTL. The cached token is handed out as long as it's valid long enough,
+    /// otherwise a new one is created with the requested expiration and cached instead.
     pub fn get_auth_token(&self, expiration_timestamp: Option<i64>) -> Result<String> {
         {
             let guard = self.auth_token.read().map_err(|e| {
@@ -177,7 +299,10 @@ impl FFISigner {
                 LighterError::Generic("Unable to get auth token".into())
             })?;
             if let Some(auth_token) = &*guard {
-                if !auth_token.is_expired() {
+                // refresh it a bit earlier, so that it doesn't expire while a request is in flight
+                let now = self.clock.server_now().timestamp();
+                let long_enough = expiration_timestamp.is_none_or(|v| v <= auth_token.expiration);
+                if long_enough && !auth_token.expires_within(self.auth_refresh_margin, now) {
                     return Ok(auth_token.token.clone());
                 }
             }
@@ -195,74 +320,184 @@ impl FFISigner {
         Ok(token_str)
     }
 
+    /// Returns how long `get_auth_token` keeps handing out the cached token before creating a
+    /// new one, `None` if there is no token yet
+    pub fn auth_token_renewal_in(&self) -> Option<std::time::Duration> {
+        let guard = self.auth_token.read().ok()?;
+        let renew_at = (guard.as_ref()?.expiration - self.auth_refresh_margin) * 1000;
+        let now = self.clock.server_now().timestamp_millis();
+        Some(std::time::Duration::from_millis(
+            (renew_at - now).max(0) as u64
+        ))
+    }
+
     fn create_auth_token_with_expiry(&self, deadline: Option<i64>) -> Result<AuthToken> {
-        unsafe {
-            let deadline =
-                deadline.unwrap_or((chrono::Utc::now() + Duration::minutes(10)).timestamp());
+        let deadline = deadline.unwrap_or(
+            (self.clock.server_now() + Duration::seconds(self.auth_token_ttl)).timestamp(),
+        );
+
+        let lib = library()?;
+        let _client = self.lock_client()?;
+        let result = unsafe { lib.CreateAuthToken(deadline) };
+        let token = self.parse_result(result)?;
+
+        Ok(AuthToken {
+            token,
+            expiration: deadline,
+        })
+    }
+
+    /// Registers the API key `api_key_index` and uses it to sign all the following txs, for all
+    /// the clones. In case of error, the current API key is kept.
+    pub fn switch_api_key(&self, api_key_index: i32, private_key: SecretString) -> Result<()> {
+        let clean_key = private_key.expose_secret().trim_start_matches("0x");
+        let client = NativeClient {
+            private_key: clean_key.to_string(),
+            api_key_index: api_key_index as c_int,
+            ..self.native_client()?
+        };
+
+        // switched under the lock, so that no other call signs in between with the previous key
+        let mut current = CURRENT_CLIENT
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        *current = None;
+        client.create()?;
+        *self.api_key.write().map_err(|e| {
+            tracing::error!("unable to get API key write lock: {e}");
+            LighterError::Generic("Unable to switch API key".into())
+        })? = ApiKey {
+            private_key: client.private_key.clone(),
+            index: client.api_key_index,
+        };
+        *current = Some(client);
+        drop(current);
+
+        // the auth token is bound to the API key
+        let mut guard = self.auth_token.write().map_err(|e| {
+            tracing::error!("unable to get token write lock: {e}");
+            LighterError::Generic("Unable to reset auth token".into())
+        })?;
+        *guard = None;
+
+        Ok(())
+    }
 
-            let result = ffisigner::CreateAuthToken(deadline);
-            let token = self.parse_result(result)?;
+    /// Generates a new API key, derived from `seed` if any, otherwise random. Returns the private
+    /// key and the public key, to be registered with a `ChangePubKey` tx before signing with it.
+    pub fn generate_api_key(seed: Option<&str>) -> Result<(SecretString, String)> {
+        let c_seed = CString::new(seed.unwrap_or_default())
+            .map_err(|_| LighterError::Signing("Invalid seed".to_string()))?;
 
-            Ok(AuthToken {
-                token,
-                expiration: deadline,
-            })
+        // all the returned strings are ours to free, even on error
+        let lib = library()?;
+        let (private_key, public_key, err) = unsafe {
+            let res = lib.GenerateAPIKey(c_seed.as_ptr() as *mut c_char);
+            (
+                take_secret_c_string(res.privateKey),
+                take_c_string(res.publicKey),
+                take_c_string(res.err),
+            )
+        };
+        if let Some(err) = err {
+            return Err(LighterError::Signing(err));
         }
+
+        private_key
+            .zip(public_key)
+            .ok_or_else(|| LighterError::Signing("Null API key".to_string()))
     }
 
-    fn create_client(&self) -> Result<()> {
-        unsafe {
-            let c_url = CString::new(self.url.as_str())
-                .map_err(|_| LighterError::Signing("Invalid URL".to_string()))?;
-            let c_key = CString::new(self.private_key.as_str())
-                .map_err(|_| LighterError::Signing("Invalid key".to_string()))?;
+    pub fn api_key_index(&self) -> i32 {
+        self.api_key
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .index
+    }
 
-            let res = ffisigner::CreateClient(
-                c_url.as_ptr() as *mut i8,
-                c_key.as_ptr() as *mut i8,
-                self.chain_id as c_int,
-                self.api_key_index as c_int,
-                self.account_index as c_longlong,
-            );
+    pub fn chain_id(&self) -> i32 {
+        self.chain_id
+    }
 
-            if !res.is_null() {
-                let err_str = CStr::from_ptr(res).to_string_lossy().to_string();
-                libc::free(res as *mut libc::c_void);
-                return Err(LighterError::Signing(err_str));
-            }
+    fn native_client(&self) -> Result<NativeClient> {
+        let api_key = self.api_key.read().map_err(|e| {
+            tracing::error!("unable to get API key read lock: {e}");
+            LighterError::Generic("Unable to get API key".into())
+        })?;
+        Ok(NativeClient {
+            url: self.url.clone(),
+            private_key: api_key.private_key.clone(),
+            chain_id: self.chain_id,
+            api_key_index: api_key.index,
+            account_index: self.account_index,
+        })
+    }
 
-            Ok(())
+    /// Locks the native library, with the client of this signer as the current one. The lock
+    /// must be held until the result of the call is parsed.
+    fn lock_client(&self) -> Result<MutexGuard<'static, Option<NativeClient>>> {
+        // the state is reset before any call that could leave it unknown, so it's still valid
+        let mut current = CURRENT_CLIENT
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let client = self.native_client()?;
+        if current.as_ref() != Some(&client) {
+            *current = None;
+            client.create()?;
+            *current = Some(client);
         }
+        Ok(current)
     }
 
     fn parse_result(&self, result: ffisigner::StrOrErr) -> Result<String> {
-        unsafe {
-            if !result.err.is_null() {
-                let error_str = CStr::from_ptr(result.err).to_string_lossy().to_string();
-                libc::free(result.err as *mut libc::c_void);
-                if !result.str_.is_null() {
-                    libc::free(result.str_ as *mut libc::c_void);
-                }
-                return Err(LighterError::Signing(error_str));
-            }
+        // both are freed, whatever the outcome
+        let (value, err) = unsafe { (take_c_string(result.str_), take_c_string(result.err)) };
+        if let Some(err) = err {
+            return Err(LighterError::Signing(err));
+        }
 
-            if result.str_.is_null() {
-                return Err(LighterError::Signing("Null result".to_string()));
-            }
+        value.ok_or_else(|| LighterError::Signing("Null result".to_string()))
+    }
+}
 
-            let value_str = CStr::from_ptr(result.str_).to_string_lossy().to_string();
-            libc::free(result.str_ as *mut libc::c_void);
+/// Copies and frees a string allocated by the library, returning `None` if `ptr` is null
+///
+/// # Safety
+/// `ptr` must be null or a nul terminated string allocated with `malloc`, not used afterwards
+unsafe fn take_c_string(ptr: *mut c_char) -> Option<String> {
+    if ptr.is_null() {
+        return None;
+    }
 
-            Ok(value_str)
-        }
+    let value = CStr::from_ptr(ptr).to_string_lossy().to_string();
+    libc::free(ptr as *mut libc::c_void);
+    Some(value)
+}
+
+/// Same as [`take_c_string`], wiping the string before freeing it
+unsafe fn take_secret_c_string(ptr: *mut c_char) -> Option<SecretString> {
+    if ptr.is_null() {
+        return None;
     }
+
+    let len = CStr::from_ptr(ptr).to_bytes().len();
+    let value = SecretString::from(CStr::from_ptr(ptr).to_string_lossy().to_string());
+    std::ptr::write_bytes(ptr, 0, len);
+    libc::free(ptr as *mut libc::c_void);
+    Some(value)
 }
 
 #[cfg(test)]
 mod tests {
-    use secrecy::SecretString;
+    use chrono::Utc;
+    use secrecy::{ExposeSecret, SecretString};
+
+    use std::sync::Arc;
 
-    use crate::signer::ffi::FFISigner;
+    use crate::{
+        clock::ServerClock,
+        signer::ffi::{AuthToken, FFISigner},
+    };
 
     #[test]
     fn test_create_auth_token_testnet() {
@@ -295,4 +530,146 @@ mod tests {
         let token = signer.create_auth_token_with_expiry(None).unwrap();
         println!("Token: {token:?}");
     }
+
+    #[test]
+    fn test_load_missing_library() {
+        let path = "/nonexistent/liblighter-signer.so";
+        let err = super::load_library(path).err().unwrap();
+        assert!(err.contains(path), "{err}");
+    }
+
+    #[test]
+    fn test_generate_api_key() {
+        let is_hex = |v: &str| {
+            v.strip_prefix("0x")
+                .is_some_and(|v| !v.is_empty() && v.chars().all(|c| c.is_ascii_hexdigit()))
+        };
+
+        let (private_key, public_key) = FFISigner::generate_api_key(None).unwrap();
+        assert!(is_hex(private_key.expose_secret()));
+        assert!(is_hex(&public_key));
+
+        let (other, _) = FFISigner::generate_api_key(None).unwrap();
+        assert_ne!(other.expose_secret(), private_key.expose_secret());
+
+        assert!(FFISigner::generate_api_key(Some("in\0valid")).is_err());
+    }
+
+    #[test]
+    fn test_auth_token_refreshed_near_expiry() {
+        let signer = FFISigner::new(
+            "https://testnet.zklighter.elliot.ai",
+            SecretString::from(
+                "12345678123456781234567812345678123456781234567812345678123456781234567812345678",
+            ),
+            3,
+            2,
+        )
+        .unwrap()
+        .with_auth_token_ttl(300)
+        .with_auth_refresh_margin(60);
+
+        assert_eq!(signer.auth_token_renewal_in(), None);
+        let token = signer.get_auth_token(None).unwrap();
+        let renewal_in = signer.auth_token_renewal_in().unwrap();
+        assert!(renewal_in > std::time::Duration::from_secs(230));
+        let expiration = signer
+            .auth_token
+            .read()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .expiration;
+        assert!(expiration > Utc::now().timestamp() + 240);
+        // still valid
+        assert_eq!(signer.get_auth_token(None).unwrap(), token);
+
+        // not expired yet, but within the refresh margin
+        *signer.auth_token.write().unwrap() = Some(AuthToken {
+            token: token.clone(),
+            expiration: Utc::now().timestamp() + 10,
+        });
+        let new_token = signer.get_auth_token(None).unwrap();
+        assert_ne!(new_token, token);
+        assert!(!signer
+            .auth_token
+            .read()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .expires_within(60, Utc::now().timestamp()));
+    }
+
+    #[test]
+    fn test_auth_token_longer_expiry_requested() {
+        let signer = FFISigner::new(
+            "https://testnet.zklighter.elliot.ai",
+            SecretString::from(
+                "12345678123456781234567812345678123456781234567812345678123456781234567812345678",
+            ),
+            3,
+            2,
+        )
+        .unwrap();
+        let expiration = || {
+            signer
+                .auth_token
+                .read()
+                .unwrap()
+                .as_ref()
+                .unwrap()
+                .expiration
+        };
+
+        // 10 minutes by default
+        let short = signer.get_auth_token(None).unwrap();
+        assert!(expiration() <= Utc::now().timestamp() + 600);
+
+        // the cached token expires too early, so a new one is issued
+        let deadline = Utc::now().timestamp() + 3600;
+        let long = signer.get_auth_token(Some(deadline)).unwrap();
+        assert_ne!(long, short);
+        assert_eq!(expiration(), deadline);
+
+        // it's valid long enough for the shorter requests
+        assert_eq!(signer.get_auth_token(None).unwrap(), long);
+        assert_eq!(signer.get_auth_token(Some(deadline - 60)).unwrap(), long);
+        assert_eq!(signer.get_auth_token(Some(deadline)).unwrap(), long);
+    }
+
+    #[test]
+    fn test_auth_token_expiry_uses_server_clock() {
+        let clock = Arc::new(ServerClock::new());
+        clock.set_offset(chrono::Duration::hours(1)); // server ahead of the local clock
+        let signer = FFISigner::new(
+            "https://testnet.zklighter.elliot.ai",
+            SecretString::from(
+                "12345678123456781234567812345678123456781234567812345678123456781234567812345678",
+            ),
+            3,
+            2,
+        )
+        .unwrap()
+        .with_auth_token_ttl(300)
+        .with_server_clock(clock.clone());
+
+        let token = signer.create_auth_token_with_expiry(None).unwrap();
+        let expected = (clock.server_now() + chrono::Duration::seconds(300)).timestamp();
+        assert!((token.expiration - expected).abs() <= 1);
+
+        // valid on the local clock, but already within the refresh margin on the server one
+        *signer.auth_token.write().unwrap() = Some(AuthToken {
+            token: token.token.clone(),
+            expiration: Utc::now().timestamp() + 600,
+        });
+        signer.get_auth_token(None).unwrap();
+        let expiration = signer
+            .auth_token
+            .read()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .expiration;
+        assert!(expiration > Utc::now().timestamp() + 3600);
+    }
 }
@@ -1,14 +1,14 @@
 pub mod data;
 mod ffi;
 
-use std::str::FromStr;
+use std::{str::FromStr, sync::Arc};
 
+pub use alloy::primitives::Address; // e.g. for `TxInfo::verify_signer`
 use alloy::{
     primitives::eip191_hash_message, signers::local::PrivateKeySigner, signers::SignerSync,
 };
 pub use ffi::FFISigner;
 use secrecy::ExposeSecret;
-use serde_json::Value;
 
 use crate::{
     config::LighterConfig,
@@ -16,15 +16,21 @@ use crate::{
         ChangePubKeyData, CreateOrderData, SignBurnSharesData, SignCancelAllOrdersData,
         SignCancelOrderData, SignCreateGroupedOrdersData, SignCreatePublicPoolData,
         SignMintSharesData, SignModifyOrderData, SignTransferData, SignUpdateLeverageData,
-        SignUpdateMarginData, SignUpdatePublicPoolData, SignWithdrawData, TxData, TxInfo,
-        TxInfoData,
+        SignUpdateMarginData, SignUpdatePublicPoolData, SignWithdrawData, SignedTx,
+        SwitchApiKeyData, TxData, TxInfo, TxInfoData,
     },
     LighterError, Result,
 };
 
-#[derive(Debug)]
+/// Signs the txs with the API key, and with the L1 key those that require it.
+///
+/// Cloning is cheap: the clones share the same FFI signer (its API key and cached auth token), so
+/// a single signer can be handed to a pool of tasks. It's `Send` and `Sync`, and signing only
+/// takes `&self`. The native library signs with a process-wide client though, so the calls of
+/// all the signers are serialized, see [`FFISigner`].
+#[derive(Debug, Clone)]
 pub struct Signer {
-    ffi: FFISigner,
+    ffi: Arc<FFISigner>,
     eth: Option<PrivateKeySigner>, // we might not need an eth signer if we just need to have read only access to the APIs
 }
 
@@ -32,17 +38,7 @@ impl TryFrom<&LighterConfig> for Signer {
     type Error = crate::LighterError;
 
     fn try_from(config: &LighterConfig) -> Result<Self> {
-        let ffi = FFISigner::try_from(config)?;
-
-        if config.eth_private_key.is_some() {
-            let eth = PrivateKeySigner::try_from(config)?;
-            return Ok(Self {
-                ffi,
-                eth: Some(eth),
-            });
-        }
-
-        Ok(Self { ffi, eth: None })
+        Self::from_ffi(config, FFISigner::try_from(config)?)
     }
 }
 
@@ -78,29 +74,71 @@ impl TryFrom<&LighterConfig> for FFISigner {
         let account_index = config
             .account_index
             .ok_or_else(|| LighterError::Generic("Account Index is not initialized".into()))?;
-        FFISigner::new(
+        Ok(FFISigner::new_with_chain_id(
             &config.base_url,
             api_key_private.clone(),
             api_key_index,
             account_index,
-        )
+            config.chain_id(),
+        )?
+        .with_auth_token_ttl(config.auth_token_ttl_secs)
+        .with_auth_refresh_margin(config.auth_refresh_margin_secs)
+        .with_server_clock(config.server_clock.clone()))
     }
 }
 
 impl Signer {
+    /// Creates a signer on top of `ffi`, e.g. one shared with the APIs, instead of creating
+    /// another client of the native library. The L1 key is taken from `config`, if set.
+    pub fn from_ffi(config: &LighterConfig, ffi: FFISigner) -> Result<Self> {
+        let ffi = Arc::new(ffi);
+
+        if config.eth_private_key.is_some() {
+            let eth = PrivateKeySigner::try_from(config)?;
+            return Ok(Self {
+                ffi,
+                eth: Some(eth),
+            });
+        }
+
+        Ok(Self { ffi, eth: None })
+    }
+
+    #[cfg(test)]
+    pub(crate) fn ffi(&self) -> &FFISigner {
+        &self.ffi
+    }
+
     pub fn sign_change_pubkey(&self, data: ChangePubKeyData, nonce: i64) -> Result<TxInfo> {
         self.sign_tx_data(TxData::ChangePubKey(data), nonce)
     }
 
+    /// Switches the API key used to sign the following txs, for all the clones. It's not a tx,
+    /// so the key must have already been registered for the account (see
+    /// [`Signer::sign_change_pubkey`]).
+    pub fn switch_api_key(&self, data: SwitchApiKeyData) -> Result<()> {
+        self.ffi
+            .switch_api_key(data.api_key_index, data.api_key_private)
+    }
+
+    /// Index of the API key the txs are signed with
+    pub fn api_key_index(&self) -> i32 {
+        self.ffi.api_key_index()
+    }
+
+    /// Signs the order, once checked with [`CreateOrderData::validate_type`]
     pub fn sign_create_order(&self, data: CreateOrderData, nonce: i64) -> Result<TxInfo> {
+        data.validate_type()?;
         self.sign_tx_data(TxData::CreateOrder(data), nonce)
     }
 
+    /// Signs the grouped orders, once checked with [`SignCreateGroupedOrdersData::validate`]
     pub fn sign_create_grouped_orders(
         &self,
         data: SignCreateGroupedOrdersData,
         nonce: i64,
     ) -> Result<TxInfo> {
+        data.validate()?;
         self.sign_tx_data(TxData::SignCreateGroupedOrders(data), nonce)
     }
 
@@ -132,6 +170,26 @@ impl Signer {
         self.sign_tx_data(TxData::SignTransfer(data), nonce)
     }
 
+    /// Signs the transfers with the given nonces, one per transfer and in the same order
+    pub fn sign_batch_transfer(
+        &self,
+        data: Vec<SignTransferData>,
+        nonces: &[i64],
+    ) -> Result<Vec<TxInfo>> {
+        if data.len() != nonces.len() {
+            return Err(LighterError::Signing(format!(
+                "{} transfers but {} nonces",
+                data.len(),
+                nonces.len()
+            )));
+        }
+
+        data.into_iter()
+            .zip(nonces)
+            .map(|(data, nonce)| self.sign_transfer(data, *nonce))
+            .collect()
+    }
+
     pub fn sign_create_public_pool(
         &self,
         data: SignCreatePublicPoolData,
@@ -164,35 +222,34 @@ impl Signer {
         self.sign_tx_data(TxData::SignUpdateMargin(data), nonce)
     }
 
+    /// Returns `true` if the L1 key is set, so the txs that require it can be signed (see
+    /// [`TxData::requires_l1_signature`])
+    pub fn has_l1_key(&self) -> bool {
+        self.eth.is_some()
+    }
+
     fn sign_tx_data(&self, tx_data: TxData, nonce: i64) -> Result<TxInfo> {
+        let tx_type = tx_data.tx_type();
+        if tx_data.requires_l1_signature() && !self.has_l1_key() {
+            return Err(LighterError::Config(format!(
+                "`eth_private_key` is not set, it's required to sign {tx_type:?} txs"
+            )));
+        }
         let tx_body = self.ffi.get_tx_data(tx_data, nonce)?;
-        let tx_json = serde_json::from_str::<Value>(&tx_body).unwrap();
-
-        let mut tx_info = TxInfo {
-            data: None,
-            payload: serde_json::to_string(&tx_json).unwrap(),
-        };
-
-        // check we actually have something to sign
-        if let Some(msg) = tx_json["MessageToSign"].as_str() {
-            // sign
-            let sig = self.sign_message(msg)?;
-
-            // update the data
-            let mut tx_json = tx_json.clone();
-            if let Some(obj) = tx_json.as_object_mut() {
-                obj.remove("MessageToSign");
-                obj.insert("L1Sig".into(), Value::String(sig.clone()));
+        let mut tx = serde_json::from_str::<SignedTx>(&tx_body)
+            .inspect_err(|e| tracing::error!("unable to deserialize signed tx: {e}"))?;
+
+        // some txs must be signed with the L1 key too
+        let data = match tx.message_to_sign.take() {
+            Some(message) => {
+                let signature = self.sign_message(&message)?;
+                tx.l1_sig = Some(signature.clone());
+                Some(TxInfoData { message, signature })
             }
+            None => None,
+        };
 
-            tx_info.data = Some(TxInfoData {
-                message: msg.into(),
-                signature: sig,
-            });
-            tx_info.payload = serde_json::to_string(&tx_json).unwrap();
-        }
-
-        Ok(tx_info)
+        TxInfo::new(tx_type, data, tx)
     }
 
     fn sign_message(&self, message: &str) -> Result<String> {
@@ -213,17 +270,17 @@ mod tests {
         models,
         signer::{
             data::{
-                ChangePubKeyData, CreateOrderData, SignBurnSharesData, SignCancelAllOrdersData,
-                SignCancelOrderData, SignCreateGroupedOrdersData, SignCreatePublicPoolData,
-                SignMintSharesData, SignModifyOrderData, SignTransferData, SignUpdateLeverageData,
-                SignUpdateMarginData, SignUpdatePublicPoolData, SignWithdrawData,
+                ChangePubKeyData, CreateOrderData, Memo, SignBurnSharesData,
+                SignCancelAllOrdersData, SignCancelOrderData, SignCreateGroupedOrdersData,
+                SignCreatePublicPoolData, SignMintSharesData, SignModifyOrderData,
+                SignTransferData, SignUpdateLeverageData, SignUpdateMarginData,
+                SignUpdatePublicPoolData, SignWithdrawData, SwitchApiKeyData,
             },
             ffi::ffisigner::CreateOrderTxReq,
         },
     };
 
     use super::*;
-    use alloy::primitives::Signature;
     use chrono::Utc;
 
     static TEST_API_KEY_PRIVATE: &str =
@@ -233,6 +290,8 @@ mod tests {
     static TEST_PRIVATE_KEY: &str =
         "0x4fd51c004ad02a003e321d5154d9b22c6bb89e1e5017bdc832c69ef28f65c04e";
     static TEST_ACCOUNT_ADDRESS: &str = "0x2b8a17334f9474ceE44CdeD230dc6fE537eda02E";
+    // the order indexes assigned by the venue start at 2^48
+    const MIN_ORDER_INDEX: i64 = 1 << 48;
 
     #[test]
     fn test_sign_change_pubkey() {
@@ -252,11 +311,119 @@ mod tests {
         let signer = Signer::try_from(&config).unwrap();
 
         let tx_sign = signer.sign_change_pubkey(tx_data, 1).unwrap();
+        assert!(tx_sign
+            .verify_signer(Address::from_str(TEST_ACCOUNT_ADDRESS).unwrap())
+            .unwrap());
+        assert!(!tx_sign.verify_signer(Address::ZERO).unwrap());
 
         let data = tx_sign.data.unwrap();
-        let sign = Signature::from_str(&data.signature).unwrap();
-        let address = sign.recover_address_from_msg(&data.message).unwrap();
-        assert_eq!(TEST_ACCOUNT_ADDRESS, address.to_string());
+
+        // the message is replaced by its L1 signature
+        assert_eq!(tx_sign.tx.l1_sig, Some(data.signature));
+        assert!(!tx_sign.payload.contains("MessageToSign"));
+        assert_eq!(tx_sign.tx.nonce, 1);
+    }
+
+    #[test]
+    fn test_switch_api_key() {
+        let config = LighterConfig::new()
+            .with_base_url("https://testnet.zklighter.elliot.ai")
+            .unwrap()
+            .with_api_key_private(TEST_API_KEY_PRIVATE)
+            .with_account_index(TEST_ACCOUNT_INDEX.parse().unwrap())
+            .with_api_key_index(TEST_API_KEY_INDEX)
+            .with_eth_private_key(TEST_PRIVATE_KEY);
+        let signer = Signer::try_from(&config).unwrap();
+
+        signer
+            .switch_api_key(SwitchApiKeyData {
+                api_key_index: 3,
+                api_key_private: "0x12345678123456781234567812345678123456781234567812345678123456781234567812345678".into(),
+            })
+            .unwrap();
+        assert_eq!(signer.ffi.api_key_index(), 3);
+
+        let res = signer.switch_api_key(SwitchApiKeyData {
+            api_key_index: 4,
+            api_key_private: "invalid".into(),
+        });
+        assert!(matches!(res, Err(LighterError::Signing(_))));
+        assert_eq!(signer.ffi.api_key_index(), 3);
+
+        // switched for the clones too
+        let clone = signer.clone();
+        signer
+            .switch_api_key(SwitchApiKeyData {
+                api_key_index: 2,
+                api_key_private: TEST_API_KEY_PRIVATE.into(),
+            })
+            .unwrap();
+        assert_eq!(clone.api_key_index(), 2);
+        let cancel = SignCancelOrderData {
+            market_index: 1,
+            order_index: MIN_ORDER_INDEX,
+        };
+        assert_eq!(
+            clone.sign_cancel_order(cancel, 1).unwrap().tx.api_key_index,
+            2
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_sign_concurrently_through_clones() {
+        let config = LighterConfig::new()
+            .with_base_url("https://testnet.zklighter.elliot.ai")
+            .unwrap()
+            .with_api_key_private(TEST_API_KEY_PRIVATE)
+            .with_account_index(TEST_ACCOUNT_INDEX.parse().unwrap())
+            .with_api_key_index(TEST_API_KEY_INDEX)
+            .with_eth_private_key(TEST_PRIVATE_KEY);
+        // another account signing in between, with its own client of the native library
+        let other = LighterConfig::new()
+            .with_base_url("https://testnet.zklighter.elliot.ai")
+            .unwrap()
+            .with_api_key_private(
+                "12345678123456781234567812345678123456781234567812345678123456781234567812345678",
+            )
+            .with_account_index(29)
+            .with_api_key_index(3);
+        let signers = [
+            Signer::try_from(&config).unwrap(),
+            Signer::try_from(&other).unwrap(),
+        ];
+
+        let tasks = (0..8)
+            .map(|i| {
+                let signer = signers[i % 2].clone();
+                tokio::spawn(async move {
+                    (0..10)
+                        .map(|j| {
+                            let nonce = (i * 10 + j) as i64;
+                            let cancel = SignCancelOrderData {
+                                market_index: 1,
+                                order_index: MIN_ORDER_INDEX + nonce,
+                            };
+                            (i, signer.sign_cancel_order(cancel, nonce).unwrap().tx)
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut nonces = Vec::new();
+        for task in tasks {
+            for (i, tx) in task.await.unwrap() {
+                // signed by the client of the signer, whatever the other signer did meanwhile
+                let (account_index, api_key_index) = if i % 2 == 0 { (28, 2) } else { (29, 3) };
+                assert_eq!(tx.fields["AccountIndex"], account_index);
+                assert_eq!(tx.api_key_index, api_key_index);
+                assert_eq!(tx.fields["Index"], MIN_ORDER_INDEX + tx.nonce);
+                assert!(tx.sig.is_some_and(|v| !v.is_empty()));
+                nonces.push(tx.nonce);
+            }
+        }
+        nonces.sort();
+        assert_eq!(nonces, (0..80).collect::<Vec<_>>());
     }
 
     #[test]
@@ -430,16 +597,73 @@ mod tests {
 
     #[test]
     fn test_sign_transfer() {
-        let msg = "Hal Finney was `Running Bitcoin`"; // must be 32 bytes!
-        let mut memo = [0u8; 32];
-        memo.copy_from_slice(msg.as_bytes());
+        let memo = Memo::from_str("Hal Finney was `Running Bitcoin`").unwrap(); // the signer library requires 32 bytes
+        let tx_data = SignTransferData::new(1, 100, 2).with_memo(memo);
+
+        let config = LighterConfig::new()
+            .with_base_url("https://testnet.zklighter.elliot.ai")
+            .unwrap()
+            .with_api_key_private(TEST_API_KEY_PRIVATE)
+            .with_account_index(TEST_ACCOUNT_INDEX.parse().unwrap())
+            .with_api_key_index(TEST_API_KEY_INDEX)
+            .with_eth_private_key(TEST_PRIVATE_KEY);
+        let signer = Signer::try_from(&config).unwrap();
+
+        let mut tx_sign = signer.sign_transfer(tx_data, 1).unwrap();
+        let address = Address::from_str(TEST_ACCOUNT_ADDRESS).unwrap();
+        assert!(tx_sign.verify_signer(address).unwrap());
+
+        tx_sign.data.as_mut().unwrap().signature = "0x1234".into();
+        assert!(matches!(
+            tx_sign.verify_signer(address),
+            Err(LighterError::Signing(_))
+        ));
+        tx_sign.data = None;
+        assert!(!tx_sign.verify_signer(address).unwrap());
+    }
+
+    #[test]
+    fn test_sign_transfer_raw_memo() {
+        let config = LighterConfig::new()
+            .with_base_url("https://testnet.zklighter.elliot.ai")
+            .unwrap()
+            .with_api_key_private(TEST_API_KEY_PRIVATE)
+            .with_account_index(TEST_ACCOUNT_INDEX.parse().unwrap())
+            .with_api_key_index(TEST_API_KEY_INDEX)
+            .with_eth_private_key(TEST_PRIVATE_KEY);
+        let signer = Signer::try_from(&config).unwrap();
+
+        // not UTF-8
+        let memo = Memo::from_bytes(std::array::from_fn(|i| 0x80 + i as u8));
+        let tx_data = SignTransferData::new(1, 100, 2).with_memo(memo);
+        let tx_sign = signer.sign_transfer(tx_data, 1).unwrap();
+        let address = Address::from_str(TEST_ACCOUNT_ADDRESS).unwrap();
+        assert!(tx_sign.verify_signer(address).unwrap());
+
+        let memo = Memo::from_hex(&"ab".repeat(Memo::LEN)).unwrap();
+        let tx_data = SignTransferData::new(1, 100, 2).with_memo(memo);
+        signer.sign_transfer(tx_data, 2).unwrap();
+
+        // right-padded
+        let memo = Memo::from_hex("0xabcd").unwrap();
+        let tx_data = SignTransferData::new(1, 100, 2).with_memo(memo);
+        signer.sign_transfer(tx_data, 3).unwrap();
+
+        // zero bytes can't be passed to the signer library
+        let mut memo = [0xab; Memo::LEN];
+        memo[Memo::LEN - 1] = 0;
         let tx_data = SignTransferData {
-            to_account_index: 1,
-            usdc_amount: 100,
-            fee: 2,
             memo,
+            ..SignTransferData::new(1, 100, 2)
         };
+        assert!(matches!(
+            signer.sign_transfer(tx_data, 4),
+            Err(LighterError::Signing(e)) if e.contains("zero bytes")
+        ));
+    }
 
+    #[test]
+    fn test_sign_transfer_memo_round_trip() {
         let config = LighterConfig::new()
             .with_base_url("https://testnet.zklighter.elliot.ai")
             .unwrap()
@@ -449,11 +673,107 @@ mod tests {
             .with_eth_private_key(TEST_PRIVATE_KEY);
         let signer = Signer::try_from(&config).unwrap();
 
-        let tx_sign = signer.sign_transfer(tx_data, 1).unwrap();
-        let data = tx_sign.data.unwrap();
-        let sign = Signature::from_str(&data.signature).unwrap();
-        let address = sign.recover_address_from_msg(&data.message).unwrap();
-        assert_eq!(TEST_ACCOUNT_ADDRESS, address.to_string());
+        // the short memos are signed padded, and the default one blank
+        let memos = [
+            Memo::from_str("gm").unwrap(),
+            Memo::default(),
+            Memo::from_hex("0xdeadbeef").unwrap(),
+            Memo::from_bytes(std::array::from_fn(|i| 0xe0 + i as u8)),
+        ];
+        for (nonce, memo) in memos.into_iter().enumerate() {
+            let tx_data = SignTransferData::new(1, 100, 2).with_memo(memo);
+            let tx_sign = signer.sign_transfer(tx_data, nonce as i64).unwrap();
+            let signed = serde_json::from_value::<Vec<u8>>(tx_sign.tx.fields["Memo"].clone());
+            assert_eq!(&signed.unwrap()[..], memo.as_bytes());
+        }
+    }
+
+    #[test]
+    fn test_sign_batch_transfer() {
+        let memo = Memo::from_str("Hal Finney was `Running Bitcoin`").unwrap();
+        let transfers = (1..=3)
+            .map(|i| SignTransferData::new(i, 100, 2).with_memo(memo))
+            .collect::<Vec<_>>();
+
+        let config = LighterConfig::new()
+            .with_base_url("https://testnet.zklighter.elliot.ai")
+            .unwrap()
+            .with_api_key_private(TEST_API_KEY_PRIVATE)
+            .with_account_index(TEST_ACCOUNT_INDEX.parse().unwrap())
+            .with_api_key_index(TEST_API_KEY_INDEX)
+            .with_eth_private_key(TEST_PRIVATE_KEY);
+        let signer = Signer::try_from(&config).unwrap();
+
+        let txs = signer
+            .sign_batch_transfer(transfers.clone(), &[5, 6, 7])
+            .unwrap();
+        assert_eq!(
+            txs.iter().map(|v| v.tx.nonce).collect::<Vec<_>>(),
+            vec![5, 6, 7]
+        );
+
+        let res = signer.sign_batch_transfer(transfers, &[5, 6]);
+        assert!(matches!(res, Err(LighterError::Signing(_))));
+    }
+
+    #[test]
+    fn test_sign_requires_l1_key() {
+        let config = LighterConfig::new()
+            .with_base_url("https://testnet.zklighter.elliot.ai")
+            .unwrap()
+            .with_api_key_private(TEST_API_KEY_PRIVATE)
+            .with_account_index(TEST_ACCOUNT_INDEX.parse().unwrap())
+            .with_api_key_index(TEST_API_KEY_INDEX);
+        let signer = Signer::try_from(&config).unwrap();
+        assert!(!signer.has_l1_key());
+
+        let memo = Memo::from_str("Hal Finney was `Running Bitcoin`").unwrap();
+        let transfer = TxData::SignTransfer(SignTransferData::new(1, 100, 2).with_memo(memo));
+        assert!(transfer.requires_l1_signature());
+        let res = signer.sign_transfer(SignTransferData::new(1, 100, 2).with_memo(memo), 1);
+        assert!(
+            matches!(&res, Err(LighterError::Config(e)) if e.contains("Transfer")),
+            "{res:?}"
+        );
+        let res = signer.sign_change_pubkey(
+            ChangePubKeyData {
+                new_pubk: "0x01".into(),
+            },
+            1,
+        );
+        assert!(matches!(res, Err(LighterError::Config(e)) if e.contains("ChangePubKey")));
+
+        // signed with the API key alone
+        let cancel = SignCancelOrderData {
+            market_index: 1,
+            order_index: 1,
+        };
+        assert!(!TxData::SignCancelOrder(cancel).requires_l1_signature());
+        let cancel = SignCancelOrderData {
+            market_index: 1,
+            order_index: 1,
+        };
+        assert!(signer.sign_cancel_order(cancel, 1).unwrap().data.is_none());
+    }
+
+    #[test]
+    fn test_sign_transfer_padded_memo() {
+        let tx_data = SignTransferData::new(1, 100, 2).with_memo(Memo::from_str("gm").unwrap());
+
+        let config = LighterConfig::new()
+            .with_base_url("https://testnet.zklighter.elliot.ai")
+            .unwrap()
+            .with_api_key_private(TEST_API_KEY_PRIVATE)
+            .with_account_index(TEST_ACCOUNT_INDEX.parse().unwrap())
+            .with_api_key_index(TEST_API_KEY_INDEX)
+            .with_eth_private_key(TEST_PRIVATE_KEY);
+        let signer = Signer::try_from(&config).unwrap();
+
+        signer.sign_transfer(tx_data, 1).unwrap();
+        // without memo
+        signer
+            .sign_transfer(SignTransferData::new(1, 100, 2), 2)
+            .unwrap();
     }
 
     #[test]